@@ -1,4 +1,46 @@
+const PROTO: &str = "jelly-fpga-server/protos/jelly_fpga_control.proto";
+
+/// Package and `major.minor` version of a `.proto`
+///
+/// The version comes from a `// version: major.minor` line, else from a
+/// `vN` package suffix (`N.0`); protos without either count as `1.0`.
+fn proto_version(proto: &str) -> (String, String) {
+    let mut package = String::new();
+    let mut version = None;
+    for line in proto.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("package ") {
+            package = name.trim_end_matches(';').trim().to_string();
+        } else if let Some(v) = line.strip_prefix("//").map(str::trim)
+            && let Some(v) = v.strip_prefix("version:")
+        {
+            version = Some(v.trim().to_string());
+        }
+    }
+    let version = version.unwrap_or_else(|| {
+        package
+            .rsplit('.')
+            .next()
+            .and_then(|last| last.strip_prefix('v'))
+            .filter(|major| major.parse::<u32>().is_ok())
+            .map_or_else(|| "1.0".to_string(), |major| format!("{}.0", major))
+    });
+    (package, version)
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_prost_build::compile_protos("jelly-fpga-server/protos/jelly_fpga_control.proto")?;
+    // the HTTP/2 transport (and the generated `connect`) is not available on wasm32
+    let wasm = std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("wasm32");
+    tonic_prost_build::configure()
+        .build_server(false)
+        .build_transport(!wasm)
+        .type_attribute(
+            ".",
+            "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize))]",
+        )
+        .compile_protos(&[PROTO], &["jelly-fpga-server/protos"])?;
+    // `raw::PROTO_PACKAGE` / `raw::PROTO_VERSION`, checked against the server on connect
+    let (package, version) = proto_version(&std::fs::read_to_string(PROTO)?);
+    println!("cargo:rustc-env=JELLY_FPGA_PROTO_PACKAGE={}", package);
+    println!("cargo:rustc-env=JELLY_FPGA_PROTO_VERSION={}", version);
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,111 @@
+//! Board profiles
+//!
+//! Stored as TOML in `$JELLY_FPGA_CONFIG`, or
+//! `$XDG_CONFIG_HOME/jelly-fpga/config.toml` (`~/.config/...` by default):
+//!
+//! ```toml
+//! [boards.kv260-lab1]
+//! addr = "http://192.168.1.10:8051"
+//! default_firmware = "k26-starter-kits"
+//!
+//! [boards.kv260-lab2]
+//! addr = "http://kv260-lab2.local:8051"
+//! alt_addrs = ["http://192.168.1.11:8051", "http://[fe80::2%eth0]:8051"]
+//!
+//! [boards.kr260-remote]
+//! addr = "https://lab.example.com:8051"
+//! tls = true
+//!
+//! [boards.kv260-lab3]
+//! addr = "http://10.0.0.3:8051"
+//! ssh = { jump = "me@gateway.example.com", key = "~/.ssh/id_ed25519" }
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Server address used without `--addr` or `--board`
+pub const DEFAULT_ADDR: &str = "http://127.0.0.1:8051";
+
+/// Connection settings of one board
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardProfile {
+    /// Server URL
+    pub addr: String,
+    /// Other addresses of the same board, raced with `addr`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_addrs: Vec<String>,
+    /// Connect with TLS (native root certificates)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tls: bool,
+    /// Firmware loaded by `restore-default`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_firmware: Option<String>,
+    /// Reach `addr` through an SSH jump host (`alt_addrs` are not used then)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<SshProfile>,
+}
+
+/// SSH jump host of a board (needs the `ssh-tunnel` feature)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshProfile {
+    /// Jump host as `[user@]host[:port]`
+    pub jump: String,
+    /// Private key file
+    pub key: PathBuf,
+    /// Accept a jump host key missing from `~/.ssh/known_hosts`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub accept_unknown_host: bool,
+}
+
+impl BoardProfile {
+    /// Plain connection to `addr`
+    pub fn with_addr(addr: &str) -> Self {
+        BoardProfile {
+            addr: addr.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Contents of the config file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// Board profiles by name
+    #[serde(default)]
+    pub boards: BTreeMap<String, BoardProfile>,
+}
+
+/// Config file location when `--config` is not given
+pub fn default_path() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("jelly-fpga").join("config.toml")
+}
+
+impl Config {
+    /// Read the config file; a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+        }
+    }
+
+    /// Write the config file, creating its directory
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
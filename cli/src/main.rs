@@ -0,0 +1,563 @@
+//! `jelly-fpga` command line tool
+
+mod config;
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use jelly_fpga_client::bench::{self, BenchDir};
+use jelly_fpga_client::cache::DiskCache;
+use jelly_fpga_client::codegen;
+use jelly_fpga_client::deploy::DeployManifest;
+use jelly_fpga_client::personality::Personality;
+use jelly_fpga_client::regmap::RegisterMap;
+use jelly_fpga_client::report::OperationReport;
+#[cfg(feature = "ssh-tunnel")]
+use jelly_fpga_client::ssh::SshTunnel;
+use jelly_fpga_client::tuning::Http2Tuning;
+use jelly_fpga_client::{AccessSize, Accessor, JellyFpgaClient};
+
+use config::{BoardProfile, Config, SshProfile};
+
+#[derive(Parser)]
+#[command(
+    name = "jelly-fpga",
+    version,
+    about = "Command line tool for Jelly FPGA Server"
+)]
+struct Cli {
+    /// Board profile from the config file
+    #[arg(long, short, global = true, env = "JELLY_FPGA_BOARD")]
+    board: Option<String>,
+
+    /// Server address (takes precedence over --board; default http://127.0.0.1:8051)
+    #[arg(long, global = true, env = "JELLY_FPGA_ADDR")]
+    addr: Option<String>,
+
+    /// Config file (default ~/.config/jelly-fpga/config.toml)
+    #[arg(long, global = true, env = "JELLY_FPGA_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Use the bulk transfer HTTP/2 preset (large flow-control windows for high-latency links)
+    #[arg(long, global = true)]
+    bulk_tuning: bool,
+
+    /// Probe the server instead of using the capabilities cached in ~/.cache/jelly-fpga
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Record every RPC and write a trace for Perfetto / chrome://tracing to FILE
+    #[arg(long, global = true, value_name = "FILE")]
+    trace: Option<PathBuf>,
+
+    /// Skip the protocol version check
+    #[arg(long, global = true)]
+    no_version_check: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the server version
+    Version,
+    /// Load firmware and print its slot
+    Load {
+        /// Firmware name
+        name: String,
+    },
+    /// Unload all firmware
+    UnloadAll,
+    /// Unload all firmware and load NAME or the board's default firmware
+    RestoreDefault {
+        /// Firmware name
+        name: Option<String>,
+    },
+    /// Upload, convert and load a design described by a TOML manifest, then initialize registers
+    Deploy {
+        /// Manifest file (paths inside are relative to it)
+        manifest: PathBuf,
+        /// Validate the manifest (files, overlay, DTS compile on the server) and
+        /// print the planned steps without changing the board
+        #[arg(long)]
+        dry_run: bool,
+        /// Write the operation report as JSON (also when a step fails)
+        #[arg(long)]
+        report: Option<PathBuf>,
+        /// Independent steps (uploads) run at once (default from the manifest, else 2)
+        #[arg(long, short)]
+        jobs: Option<usize>,
+    },
+    /// Manage board profiles
+    #[command(subcommand)]
+    Boards(BoardsCommand),
+    /// Measure register latency or transfer throughput
+    #[command(subcommand)]
+    Bench(BenchCommand),
+    /// Generate a Rust driver from a register map or a personality TOML file
+    Codegen {
+        /// Register map (text format) or personality (`.toml`)
+        input: PathBuf,
+        /// Driver struct name for a register map (default: file stem)
+        #[arg(long)]
+        name: Option<String>,
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BoardsCommand {
+    /// List board profiles
+    List,
+    /// Add or replace a board profile
+    Add {
+        /// Profile name
+        name: String,
+        /// Server URL
+        addr: String,
+        /// Other address of the board, raced with ADDR (repeatable)
+        #[arg(long = "alt-addr")]
+        alt_addrs: Vec<String>,
+        /// Connect with TLS
+        #[arg(long)]
+        tls: bool,
+        /// Firmware loaded by `restore-default`
+        #[arg(long)]
+        default_firmware: Option<String>,
+    },
+    /// Remove a board profile
+    Remove {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BenchCommand {
+    /// Register access latency
+    Reg {
+        #[command(flatten)]
+        target: BenchTarget,
+        /// Register index
+        #[arg(long, default_value = "0", value_parser = parse_size)]
+        reg: u64,
+        /// Register size in bytes
+        #[arg(long, default_value_t = 4)]
+        size: u64,
+        /// Accesses per measurement
+        #[arg(long, default_value_t = 1000)]
+        iters: usize,
+        /// Also time writes of VALUE to the register
+        #[arg(long, value_parser = parse_size)]
+        write: Option<u64>,
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Memory transfer throughput
+    Dma {
+        #[command(flatten)]
+        target: BenchTarget,
+        /// Bytes to transfer (K, M and G suffixes)
+        #[arg(long, default_value = "1M", value_parser = parse_size)]
+        size: u64,
+        /// Direction: write, read or both (write, read back and compare)
+        #[arg(long, default_value = "both")]
+        dir: BenchDir,
+        /// Bytes per transfer call
+        #[arg(long, default_value = "1M", value_parser = parse_size)]
+        chunk: u64,
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Device a benchmark runs on
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct BenchTarget {
+    /// UIO device name
+    #[arg(long)]
+    uio: Option<String>,
+    /// udmabuf device name
+    #[arg(long)]
+    udmabuf: Option<String>,
+    /// Physical address to map through /dev/mem
+    #[arg(long, value_parser = parse_size)]
+    mmap: Option<u64>,
+}
+
+/// Decimal or `0x` hexadecimal number with an optional K, M or G (binary) suffix
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 30),
+        _ => (s, 0),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .map_err(|e| format!("invalid number {:?}: {}", s, e))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("{} is too large", s))
+}
+
+/// Profile selected by `--addr`, `--board` or the default address
+fn select_profile(cli: &Cli, config: &Config) -> Result<BoardProfile, Box<dyn Error>> {
+    match (&cli.addr, &cli.board) {
+        (Some(addr), _) => Ok(BoardProfile::with_addr(addr)),
+        (None, Some(name)) => {
+            config.boards.get(name).cloned().ok_or_else(|| {
+                format!("unknown board {} (see `jelly-fpga boards list`)", name).into()
+            })
+        }
+        (None, None) => Ok(BoardProfile::with_addr(config::DEFAULT_ADDR)),
+    }
+}
+
+async fn connect(
+    profile: &BoardProfile,
+    bulk_tuning: bool,
+    cache: Option<&DiskCache>,
+    check_version: bool,
+) -> Result<JellyFpgaClient, Box<dyn Error>> {
+    let tuning = if bulk_tuning {
+        Http2Tuning::bulk_transfer()
+    } else {
+        Http2Tuning::default()
+    };
+    let mut endpoints = Vec::new();
+    for addr in std::iter::once(&profile.addr).chain(&profile.alt_addrs) {
+        let mut endpoint = tonic::transport::Endpoint::from_shared(addr.clone())?;
+        if profile.tls {
+            endpoint = endpoint
+                .tls_config(tonic::transport::ClientTlsConfig::new().with_native_roots())?;
+        }
+        endpoints.push(tuning.apply(endpoint));
+    }
+    let client = if let Some(ssh) = &profile.ssh {
+        connect_via_ssh(endpoints.remove(0), ssh).await?
+    } else if endpoints.len() == 1 {
+        match cache {
+            Some(cache) => JellyFpgaClient::connect_cached(endpoints.remove(0), cache).await?,
+            None => JellyFpgaClient::connect(endpoints.remove(0)).await?,
+        }
+    } else {
+        JellyFpgaClient::connect_any(endpoints).await?
+    };
+    let mut client = client.with_tuning(&tuning);
+    // other probe errors show up on the first call as before
+    if check_version && let Err(e) = client.check_compatibility().await {
+        let e = jelly_fpga_client::error::Error::from(e);
+        if matches!(
+            e,
+            jelly_fpga_client::error::Error::IncompatibleServer { .. }
+        ) {
+            return Err(e.into());
+        }
+    }
+    if let Some(name) = &profile.default_firmware {
+        client.set_default_firmware(name);
+    }
+    Ok(client)
+}
+
+#[cfg(feature = "ssh-tunnel")]
+async fn connect_via_ssh(
+    endpoint: tonic::transport::Endpoint,
+    ssh: &SshProfile,
+) -> Result<JellyFpgaClient, Box<dyn Error>> {
+    let tunnel =
+        SshTunnel::new(&ssh.jump, &ssh.key).with_accept_unknown_host(ssh.accept_unknown_host);
+    Ok(JellyFpgaClient::connect_via_ssh(endpoint, &tunnel).await?)
+}
+
+#[cfg(not(feature = "ssh-tunnel"))]
+async fn connect_via_ssh(
+    _endpoint: tonic::transport::Endpoint,
+    ssh: &SshProfile,
+) -> Result<JellyFpgaClient, Box<dyn Error>> {
+    Err(format!(
+        "board profile uses ssh jump host {} but jelly-fpga was built without the ssh-tunnel feature",
+        ssh.jump
+    )
+    .into())
+}
+
+fn boards(command: &BoardsCommand, config: &mut Config, path: &Path) -> Result<(), Box<dyn Error>> {
+    match command {
+        BoardsCommand::List => {
+            for (name, profile) in &config.boards {
+                print!("{}\t{}", name, profile.addr);
+                for addr in &profile.alt_addrs {
+                    print!(",{}", addr);
+                }
+                if profile.tls {
+                    print!("\ttls");
+                }
+                if let Some(firmware) = &profile.default_firmware {
+                    print!("\tdefault_firmware={}", firmware);
+                }
+                if let Some(ssh) = &profile.ssh {
+                    print!("\tssh={}", ssh.jump);
+                }
+                println!();
+            }
+        }
+        BoardsCommand::Add {
+            name,
+            addr,
+            alt_addrs,
+            tls,
+            default_firmware,
+        } => {
+            let profile = BoardProfile {
+                addr: addr.clone(),
+                alt_addrs: alt_addrs.clone(),
+                tls: *tls,
+                default_firmware: default_firmware.clone(),
+                ..Default::default()
+            };
+            config.boards.insert(name.clone(), profile);
+            config.save(path)?;
+        }
+        BoardsCommand::Remove { name } => {
+            if config.boards.remove(name).is_none() {
+                return Err(format!("unknown board {}", name).into());
+            }
+            config.save(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_manifest(path: &Path) -> Result<DeployManifest, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let manifest: DeployManifest =
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    Ok(manifest.with_base_dir(dir))
+}
+
+/// Open the benchmark target; `/dev/mem` mappings cover `map_size` bytes
+async fn open_target(
+    client: &mut JellyFpgaClient,
+    target: &BenchTarget,
+    map_size: u64,
+    unit: u64,
+) -> Result<Accessor, Box<dyn Error>> {
+    let (result, id) = if let Some(name) = &target.uio {
+        client.open_uio(name, unit).await?
+    } else if let Some(name) = &target.udmabuf {
+        client.open_udmabuf(name, false, unit).await?
+    } else if let Some(addr) = target.mmap {
+        client.open_mmap("/dev/mem", addr, map_size, unit).await?
+    } else {
+        return Err("no benchmark target".into());
+    };
+    if !result {
+        return Err("failed to open the benchmark target".into());
+    }
+    Ok(client.accessor(id))
+}
+
+async fn bench(client: &mut JellyFpgaClient, command: &BenchCommand) -> Result<(), Box<dyn Error>> {
+    let (report, json) = match command {
+        BenchCommand::Reg {
+            target,
+            reg,
+            size,
+            iters,
+            write,
+            json,
+        } => {
+            let access = AccessSize::from_bytes(*size)
+                .ok_or_else(|| format!("invalid register size {}", size))?;
+            let map_size = (reg + 1) * size;
+            let mut accessor = open_target(client, target, map_size.max(0x1000), *size).await?;
+            let report = async {
+                let mut report = bench::bench_reg_read(&mut accessor, *reg, access, *iters).await?;
+                if let Some(value) = write {
+                    report.extend(
+                        bench::bench_reg_write(&mut accessor, *reg, *value, access, *iters).await?,
+                    );
+                }
+                Ok::<_, tonic::Status>(report)
+            }
+            .await;
+            accessor.close().await?;
+            (report?, *json)
+        }
+        BenchCommand::Dma {
+            target,
+            size,
+            dir,
+            chunk,
+            json,
+        } => {
+            let mut accessor = open_target(client, target, *size, 1).await?;
+            let report = bench::bench_dma(&mut accessor, *size, *dir, *chunk as usize).await;
+            accessor.close().await?;
+            (report?, *json)
+        }
+    };
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+    Ok(())
+}
+
+fn codegen(input: &Path, name: Option<&str>, out: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let text = std::fs::read_to_string(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let source = if input.extension().is_some_and(|e| e == "toml") {
+        let personality: Personality =
+            toml::from_str(&text).map_err(|e| format!("{}: {}", input.display(), e))?;
+        let personality = personality.with_base_dir(input.parent().unwrap_or(Path::new(".")));
+        let regmaps = codegen::personality_regmaps(&personality)?;
+        codegen::generate_personality(&personality, &regmaps)
+    } else {
+        let map = RegisterMap::parse(&text)
+            .map_err(|e| format!("{}: {}", input.display(), e.message()))?;
+        let stem = input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("driver");
+        codegen::generate_driver(name.unwrap_or(stem), &map)
+    };
+    match out {
+        Some(path) => std::fs::write(path, source)?,
+        None => print!("{}", source),
+    }
+    Ok(())
+}
+
+async fn deploy(
+    client: &mut JellyFpgaClient,
+    manifest: &DeployManifest,
+    report_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let bar = ProgressBar::new(manifest.steps().len() as u64);
+    bar.set_style(ProgressStyle::with_template(
+        "{spinner} [{pos}/{len}] {elapsed:>4} {msg}",
+    )?);
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar.set_message(format!("deploying {}", manifest.name));
+    let mut report = OperationReport::new("deploy");
+    let result = client
+        .deploy_recorded(manifest, &mut report, |record| {
+            if record.error.is_none() {
+                bar.set_message(format!("done: {}", record.detail));
+                bar.inc(1);
+            }
+        })
+        .await;
+    if let Err(e) = &result {
+        match report.steps.iter().find(|s| s.error.is_some()) {
+            Some(failed) => bar.abandon_with_message(format!("{} failed", failed.detail)),
+            None => bar.abandon_with_message(e.message().to_string()),
+        }
+    } else {
+        bar.finish_with_message(format!(
+            "deployed {} ({} bytes in {:.1} s)",
+            manifest.name,
+            report.bytes(),
+            report.duration().as_secs_f64()
+        ));
+    }
+    if let Some(path) = report_path {
+        std::fs::write(path, report.to_json())?;
+    }
+    Ok(result?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let config_path = cli.config.clone().unwrap_or_else(config::default_path);
+    let mut config = Config::load(&config_path)?;
+
+    if let Command::Boards(command) = &cli.command {
+        return boards(command, &mut config, &config_path);
+    }
+    if let Command::Codegen { input, name, out } = &cli.command {
+        return codegen(input, name.as_deref(), out.as_deref());
+    }
+
+    let profile = select_profile(&cli, &config)?;
+    let cache = (!cli.no_cache).then(DiskCache::open_default);
+    let mut client = connect(
+        &profile,
+        cli.bulk_tuning,
+        cache.as_ref(),
+        !cli.no_version_check,
+    )
+    .await?;
+    if cli.trace.is_some() {
+        client = client.with_call_timing();
+    }
+    let result = run(&cli, &mut client).await;
+    // also written when the command failed, to see where it stopped
+    if let Some(path) = &cli.trace
+        && let Some(calls) = client.take_call_report()
+    {
+        std::fs::write(path, calls.to_chrome_trace())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    result
+}
+
+async fn run(cli: &Cli, client: &mut JellyFpgaClient) -> Result<(), Box<dyn Error>> {
+    match &cli.command {
+        Command::Version => println!("{}", client.get_version().await?),
+        Command::Load { name } => {
+            let (result, slot) = client.load(name).await?;
+            if !result {
+                return Err(format!("failed to load {}", name).into());
+            }
+            println!("{}", slot);
+        }
+        Command::UnloadAll => {
+            if !client.unload_all().await? {
+                return Err("failed to unload firmware".into());
+            }
+        }
+        Command::RestoreDefault { name } => {
+            println!("{}", client.restore_default(name.as_deref()).await?);
+        }
+        Command::Deploy {
+            manifest,
+            dry_run: true,
+            ..
+        } => print!(
+            "{}",
+            client.dry_run_deploy(&read_manifest(manifest)?).await?
+        ),
+        Command::Deploy {
+            manifest,
+            report,
+            jobs,
+            ..
+        } => {
+            let mut manifest = read_manifest(manifest)?;
+            if let Some(jobs) = jobs {
+                manifest.parallelism = *jobs;
+            }
+            deploy(client, &manifest, report.as_deref()).await?
+        }
+        Command::Bench(command) => bench(client, command).await?,
+        Command::Boards(_) | Command::Codegen { .. } => unreachable!(),
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Derive macros for jelly-fpga-client
+//!
+//! `#[derive(MemLayout)]` implements `jelly_fpga_client::layout::MemLayout`
+//! for a `#[repr(C)]` struct with named fields. Each field is placed at its
+//! `repr(C)` offset and converted with the byte order given by
+//! `#[mem_layout(big_endian)]` / `#[mem_layout(little_endian)]` on the
+//! field, else on the struct, else little-endian.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Fields, parse_macro_input};
+
+#[proc_macro_derive(MemLayout, attributes(mem_layout))]
+pub fn derive_mem_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Byte order from `#[mem_layout(...)]`, if given
+fn endian(attrs: &[Attribute]) -> syn::Result<Option<TokenStream2>> {
+    let mut endian = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("mem_layout")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("big_endian") {
+                endian = Some(quote!(::jelly_fpga_client::Endian::Big));
+                Ok(())
+            } else if meta.path.is_ident("little_endian") {
+                endian = Some(quote!(::jelly_fpga_client::Endian::Little));
+                Ok(())
+            } else {
+                Err(meta.error("expected `big_endian` or `little_endian`"))
+            }
+        })?;
+    }
+    Ok(endian)
+}
+
+fn is_repr_c(attrs: &[Attribute]) -> bool {
+    let mut repr_c = false;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("repr")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            repr_c |= meta.path.is_ident("C");
+            Ok(())
+        });
+    }
+    repr_c
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "MemLayout does not support generic structs",
+        ));
+    }
+    if !is_repr_c(&input.attrs) {
+        return Err(syn::Error::new_spanned(
+            name,
+            "MemLayout requires #[repr(C)]",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "MemLayout requires named fields",
+                ));
+            }
+        },
+        _ => return Err(syn::Error::new_spanned(name, "MemLayout requires a struct")),
+    };
+    let default = endian(&input.attrs)?.unwrap_or(quote!(::jelly_fpga_client::Endian::Little));
+
+    let mut checks = Vec::new();
+    let mut puts = Vec::new();
+    let mut gets = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let endian = endian(&field.attrs)?.unwrap_or_else(|| default.clone());
+        let field_trait = quote!(<#ty as ::jelly_fpga_client::layout::LayoutField>);
+        let range = quote! {
+            ::core::mem::offset_of!(Self, #ident)
+                ..::core::mem::offset_of!(Self, #ident) + #field_trait::BYTES
+        };
+        checks.push(quote! {
+            assert!(
+                #field_trait::BYTES == ::core::mem::size_of::<#ty>(),
+                concat!("MemLayout field `", stringify!(#ident), "` is not stored in its own size")
+            );
+        });
+        puts.push(quote!(#field_trait::put(&self.#ident, #endian, &mut buf[#range]);));
+        gets.push(quote!(#ident: #field_trait::get(#endian, &buf[#range]),));
+    }
+
+    Ok(quote! {
+        const _: () = {
+            #(#checks)*
+        };
+
+        impl ::jelly_fpga_client::layout::MemLayout for #name {
+            const SIZE: usize = ::core::mem::size_of::<Self>();
+
+            fn encode(&self, buf: &mut [u8]) {
+                #(#puts)*
+            }
+
+            fn decode(buf: &[u8]) -> Self {
+                Self {
+                    #(#gets)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,45 @@
+use jelly_fpga_client::capture::{Capture, CaptureConfig, VideoFormatRegularizer};
+use jelly_fpga_client::video::VideoWriteDma;
+use jelly_fpga_client::JellyFpgaClient;
+use std::env;
+use tokio_stream::StreamExt;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Get server address from command line or use default
+    let server_addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "http://[::1]:8051".to_string());
+
+    println!("Connecting to Jelly FPGA Server at: {}", server_addr);
+    let mut client = JellyFpgaClient::connect(server_addr).await?;
+
+    // Register blocks of the capture pipeline (addresses depend on the design)
+    let (_, fmtreg_id) = client.open_mmap("/dev/mem", 0xa010_0000, 0x800, 8).await?;
+    let (_, wdma_id) = client.open_mmap("/dev/mem", 0xa021_0000, 0x800, 8).await?;
+    let (_, buf_id) = client.open_udmabuf("udmabuf-jelly-vram0", false, 1).await?;
+
+    let config = CaptureConfig::new(640, 480, 4);
+    let mut capture = Capture::new(
+        VideoFormatRegularizer::new(client.accessor(fmtreg_id)),
+        VideoWriteDma::new(client.accessor(wdma_id)),
+        client.accessor(buf_id),
+        config,
+    );
+    capture.setup().await?;
+
+    // Grab 4 frames and stream them back to the host
+    let mut frames = capture.into_stream(4);
+    let mut n = 0;
+    while let Some(frame) = frames.next().await {
+        let frame = frame?;
+        std::fs::write(format!("frame{}.raw", n), &frame)?;
+        println!("frame {}: {} bytes", n, frame.len());
+        n += 1;
+    }
+
+    client.close(buf_id).await?;
+    client.close(wdma_id).await?;
+    client.close(fmtreg_id).await?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use jelly_fpga_client::JellyFpgaClient;
+use jelly_fpga_client::regmap::{RegDump, RegisterMap, dump_regmap};
+use std::env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // regdump <server> <uio name> <register unit> <map file> [previous dump]
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 5 {
+        eprintln!("usage: regdump <server> <uio name> <register unit> <map file> [previous dump]");
+        std::process::exit(1);
+    }
+    let unit: u64 = args[3].parse()?;
+    let map = RegisterMap::parse(&std::fs::read_to_string(&args[4])?)?;
+
+    let mut client = JellyFpgaClient::connect(args[1].clone()).await?;
+    let (result, id) = client.open_uio(&args[2], unit).await?;
+    if !result {
+        return Err(format!("failed to open {}", args[2]).into());
+    }
+    let mut accessor = client.accessor(id);
+    let dump = dump_regmap(&mut accessor, &map).await?;
+    accessor.close().await?;
+
+    match args.get(5) {
+        // compare with a dump saved from a previous run
+        Some(path) => {
+            let previous = RegDump::parse(&std::fs::read_to_string(path)?)?;
+            print!("{}", previous.diff(&dump));
+        }
+        None => print!("{}", dump),
+    }
+    Ok(())
+}
@@ -129,11 +129,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Remove bin result: {}", remove_bin_result);
 
     // 元の設定に戻す
-    let final_unload_result = fpga_ctl.unload_all().await?;
-    println!("Final unload result: {}", final_unload_result);
-    
-    let (load_starter_result, _slot) = fpga_ctl.load("k26-starter-kits").await?;
-    println!("Load k26-starter-kits result: {}", load_starter_result);
+    let slot = fpga_ctl.restore_default(None).await?;
+    println!("Restored {} in slot {}", fpga_ctl.default_firmware(), slot);
 
     println!("Blinking LED test completed successfully!");
     Ok(())
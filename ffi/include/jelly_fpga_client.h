@@ -0,0 +1,140 @@
+#ifndef JELLY_FPGA_CLIENT_H
+#define JELLY_FPGA_CLIENT_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Client handle (opaque to C)
+typedef struct JellyFpgaClient JellyFpgaClient;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message of the last failed call on this thread (valid until the next failure)
+const char *jelly_fpga_last_error(void);
+
+// Connect to a server (e.g. "http://192.168.1.10:8051"); returns NULL on failure
+//
+// # Safety
+// `url` must be a NUL terminated string.
+JellyFpgaClient *jelly_fpga_connect(const char *url);
+
+// Disconnect and free the handle (NULL is ignored)
+//
+// # Safety
+// `handle` must come from `jelly_fpga_connect` and not be used afterwards.
+void jelly_fpga_free(JellyFpgaClient *handle);
+
+// Load firmware; stores the slot in `slot` if not NULL
+//
+// # Safety
+// `handle` must be valid, `name` NUL terminated, `slot` NULL or writable.
+int32_t jelly_fpga_load(JellyFpgaClient *handle, const char *name, int32_t *slot);
+
+// Unload firmware from a slot
+//
+// # Safety
+// `handle` must be valid.
+int32_t jelly_fpga_unload(JellyFpgaClient *handle, int32_t slot);
+
+// Open a memory map
+//
+// # Safety
+// `handle` must be valid, `path` NUL terminated, `id` writable.
+int32_t jelly_fpga_open_mmap(JellyFpgaClient *handle,
+                             const char *path,
+                             uint64_t offset,
+                             uint64_t size,
+                             uint64_t unit,
+                             uint32_t *id);
+
+// Open a UIO device
+//
+// # Safety
+// `handle` must be valid, `name` NUL terminated, `id` writable.
+int32_t jelly_fpga_open_uio(JellyFpgaClient *handle, const char *name, uint64_t unit, uint32_t *id);
+
+// Open a udmabuf device
+//
+// # Safety
+// `handle` must be valid, `name` NUL terminated, `id` writable.
+int32_t jelly_fpga_open_udmabuf(JellyFpgaClient *handle,
+                                const char *name,
+                                bool cache_enable,
+                                uint64_t unit,
+                                uint32_t *id);
+
+// Close an id
+//
+// # Safety
+// `handle` must be valid.
+int32_t jelly_fpga_close(JellyFpgaClient *handle, uint32_t id);
+
+// Write an unsigned integer of `size` bytes to memory
+//
+// # Safety
+// `handle` must be valid.
+int32_t jelly_fpga_write_mem_u(JellyFpgaClient *handle,
+                               uint32_t id,
+                               uint64_t offset,
+                               uint64_t data,
+                               uint64_t size);
+
+// Read an unsigned integer of `size` bytes from memory
+//
+// # Safety
+// `handle` must be valid, `data` writable.
+int32_t jelly_fpga_read_mem_u(JellyFpgaClient *handle,
+                              uint32_t id,
+                              uint64_t offset,
+                              uint64_t size,
+                              uint64_t *data);
+
+// Write an unsigned integer of `size` bytes to a register
+//
+// # Safety
+// `handle` must be valid.
+int32_t jelly_fpga_write_reg_u(JellyFpgaClient *handle,
+                               uint32_t id,
+                               uint64_t reg,
+                               uint64_t data,
+                               uint64_t size);
+
+// Read an unsigned integer of `size` bytes from a register
+//
+// # Safety
+// `handle` must be valid, `data` writable.
+int32_t jelly_fpga_read_reg_u(JellyFpgaClient *handle,
+                              uint32_t id,
+                              uint64_t reg,
+                              uint64_t size,
+                              uint64_t *data);
+
+// Copy `len` bytes from `data` to device memory
+//
+// # Safety
+// `handle` must be valid, `data` readable for `len` bytes.
+int32_t jelly_fpga_mem_copy_to(JellyFpgaClient *handle,
+                               uint32_t id,
+                               uint64_t offset,
+                               const uint8_t *data,
+                               uintptr_t len);
+
+// Copy `len` bytes from device memory into `buf`
+//
+// # Safety
+// `handle` must be valid, `buf` writable for `len` bytes.
+int32_t jelly_fpga_mem_copy_from(JellyFpgaClient *handle,
+                                 uint32_t id,
+                                 uint64_t offset,
+                                 uint8_t *buf,
+                                 uintptr_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* JELLY_FPGA_CLIENT_H */
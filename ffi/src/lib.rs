@@ -0,0 +1,351 @@
+//! C API for jelly-fpga-client
+//!
+//! Every function blocks on an internal single-threaded tokio runtime owned
+//! by the handle. Functions returning `int` give 0 on success and -1 on
+//! failure; `jelly_fpga_last_error()` then describes the error. A server
+//! side `result=false` is reported as a failure (the client runs in strict
+//! mode).
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::ptr;
+
+use jelly_fpga_client::JellyFpgaClient;
+use tokio::runtime::Runtime;
+
+/// Client handle (opaque to C)
+pub struct Handle {
+    rt: Runtime,
+    client: JellyFpgaClient,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_error(msg: impl std::fmt::Display) {
+    let msg = CString::new(msg.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = msg);
+}
+
+fn status(result: Result<(), String>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(msg) => {
+            set_error(msg);
+            -1
+        }
+    }
+}
+
+/// Run `f` with the handle and map errors to -1
+fn with_handle<F>(handle: *mut Handle, f: F) -> i32
+where
+    F: FnOnce(&mut Handle) -> Result<(), tonic::Status>,
+{
+    // SAFETY: the caller passes a handle from `jelly_fpga_connect` (or NULL)
+    let Some(handle) = (unsafe { handle.as_mut() }) else {
+        return status(Err("null handle".to_string()));
+    };
+    status(f(handle).map_err(|e| e.message().to_string()))
+}
+
+/// Borrow a C string argument
+fn arg<'a>(s: *const c_char) -> Result<&'a str, tonic::Status> {
+    if s.is_null() {
+        return Err(tonic::Status::invalid_argument("null string"));
+    }
+    // SAFETY: non-null, caller passes a NUL terminated string
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| tonic::Status::invalid_argument("string is not UTF-8"))
+}
+
+/// Message of the last failed call on this thread (valid until the next failure)
+#[unsafe(no_mangle)]
+pub extern "C" fn jelly_fpga_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
+
+/// Connect to a server (e.g. "http://192.168.1.10:8051"); returns NULL on failure
+///
+/// # Safety
+/// `url` must be a NUL terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_connect(url: *const c_char) -> *mut Handle {
+    let result = (|| {
+        let url = arg(url).map_err(|e| e.message().to_string())?.to_string();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut client = rt
+            .block_on(JellyFpgaClient::connect(url))
+            .map_err(|e| e.to_string())?;
+        client.set_strict(true);
+        Ok::<_, String>(Handle { rt, client })
+    })();
+    match result {
+        Ok(handle) => Box::into_raw(Box::new(handle)),
+        Err(msg) => {
+            set_error(msg);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Disconnect and free the handle (NULL is ignored)
+///
+/// # Safety
+/// `handle` must come from `jelly_fpga_connect` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_free(handle: *mut Handle) {
+    if !handle.is_null() {
+        // SAFETY: ownership returns from `jelly_fpga_connect`
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Load firmware; stores the slot in `slot` if not NULL
+///
+/// # Safety
+/// `handle` must be valid, `name` NUL terminated, `slot` NULL or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_load(
+    handle: *mut Handle,
+    name: *const c_char,
+    slot: *mut i32,
+) -> i32 {
+    with_handle(handle, |h| {
+        let (_, s) = h.rt.block_on(h.client.load(arg(name)?))?;
+        if !slot.is_null() {
+            // SAFETY: caller provides a writable pointer
+            unsafe { *slot = s };
+        }
+        Ok(())
+    })
+}
+
+/// Unload firmware from a slot
+///
+/// # Safety
+/// `handle` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_unload(handle: *mut Handle, slot: i32) -> i32 {
+    with_handle(handle, |h| h.rt.block_on(h.client.unload(slot)).map(|_| ()))
+}
+
+/// Store an id through an out pointer
+fn put_id(out: *mut u32, id: u32) -> Result<(), tonic::Status> {
+    if out.is_null() {
+        return Err(tonic::Status::invalid_argument("null id pointer"));
+    }
+    // SAFETY: non-null, caller provides a writable pointer
+    unsafe { *out = id };
+    Ok(())
+}
+
+/// Open a memory map
+///
+/// # Safety
+/// `handle` must be valid, `path` NUL terminated, `id` writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_open_mmap(
+    handle: *mut Handle,
+    path: *const c_char,
+    offset: u64,
+    size: u64,
+    unit: u64,
+    id: *mut u32,
+) -> i32 {
+    with_handle(handle, |h| {
+        let (_, i) = h
+            .rt
+            .block_on(h.client.open_mmap(arg(path)?, offset, size, unit))?;
+        put_id(id, i)
+    })
+}
+
+/// Open a UIO device
+///
+/// # Safety
+/// `handle` must be valid, `name` NUL terminated, `id` writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_open_uio(
+    handle: *mut Handle,
+    name: *const c_char,
+    unit: u64,
+    id: *mut u32,
+) -> i32 {
+    with_handle(handle, |h| {
+        let (_, i) = h.rt.block_on(h.client.open_uio(arg(name)?, unit))?;
+        put_id(id, i)
+    })
+}
+
+/// Open a udmabuf device
+///
+/// # Safety
+/// `handle` must be valid, `name` NUL terminated, `id` writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_open_udmabuf(
+    handle: *mut Handle,
+    name: *const c_char,
+    cache_enable: bool,
+    unit: u64,
+    id: *mut u32,
+) -> i32 {
+    with_handle(handle, |h| {
+        let (_, i) = h
+            .rt
+            .block_on(h.client.open_udmabuf(arg(name)?, cache_enable, unit))?;
+        put_id(id, i)
+    })
+}
+
+/// Close an id
+///
+/// # Safety
+/// `handle` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_close(handle: *mut Handle, id: u32) -> i32 {
+    with_handle(handle, |h| h.rt.block_on(h.client.close(id)).map(|_| ()))
+}
+
+/// Write an unsigned integer of `size` bytes to memory
+///
+/// # Safety
+/// `handle` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_write_mem_u(
+    handle: *mut Handle,
+    id: u32,
+    offset: u64,
+    data: u64,
+    size: u64,
+) -> i32 {
+    with_handle(handle, |h| {
+        h.rt.block_on(h.client.write_mem_u(id, offset, data, size))
+            .map(|_| ())
+    })
+}
+
+/// Read an unsigned integer of `size` bytes from memory
+///
+/// # Safety
+/// `handle` must be valid, `data` writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_read_mem_u(
+    handle: *mut Handle,
+    id: u32,
+    offset: u64,
+    size: u64,
+    data: *mut u64,
+) -> i32 {
+    with_handle(handle, |h| {
+        let (_, v) = h.rt.block_on(h.client.read_mem_u(id, offset, size))?;
+        put_u64(data, v)
+    })
+}
+
+/// Write an unsigned integer of `size` bytes to a register
+///
+/// # Safety
+/// `handle` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_write_reg_u(
+    handle: *mut Handle,
+    id: u32,
+    reg: u64,
+    data: u64,
+    size: u64,
+) -> i32 {
+    with_handle(handle, |h| {
+        h.rt.block_on(h.client.write_reg_u(id, reg, data, size))
+            .map(|_| ())
+    })
+}
+
+/// Read an unsigned integer of `size` bytes from a register
+///
+/// # Safety
+/// `handle` must be valid, `data` writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_read_reg_u(
+    handle: *mut Handle,
+    id: u32,
+    reg: u64,
+    size: u64,
+    data: *mut u64,
+) -> i32 {
+    with_handle(handle, |h| {
+        let (_, v) = h.rt.block_on(h.client.read_reg_u(id, reg, size))?;
+        put_u64(data, v)
+    })
+}
+
+fn put_u64(out: *mut u64, value: u64) -> Result<(), tonic::Status> {
+    if out.is_null() {
+        return Err(tonic::Status::invalid_argument("null data pointer"));
+    }
+    // SAFETY: non-null, caller provides a writable pointer
+    unsafe { *out = value };
+    Ok(())
+}
+
+/// Copy `len` bytes from `data` to device memory
+///
+/// # Safety
+/// `handle` must be valid, `data` readable for `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_mem_copy_to(
+    handle: *mut Handle,
+    id: u32,
+    offset: u64,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    with_handle(handle, |h| {
+        if data.is_null() && len > 0 {
+            return Err(tonic::Status::invalid_argument("null data pointer"));
+        }
+        let bytes = if len == 0 {
+            Vec::new()
+        } else {
+            // SAFETY: caller guarantees `len` readable bytes
+            unsafe { std::slice::from_raw_parts(data, len) }.to_vec()
+        };
+        h.rt.block_on(h.client.mem_copy_to(id, offset, bytes))
+            .map(|_| ())
+    })
+}
+
+/// Copy `len` bytes from device memory into `buf`
+///
+/// # Safety
+/// `handle` must be valid, `buf` writable for `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jelly_fpga_mem_copy_from(
+    handle: *mut Handle,
+    id: u32,
+    offset: u64,
+    buf: *mut u8,
+    len: usize,
+) -> i32 {
+    with_handle(handle, |h| {
+        if buf.is_null() && len > 0 {
+            return Err(tonic::Status::invalid_argument("null buffer pointer"));
+        }
+        let (_, data) = h
+            .rt
+            .block_on(h.client.mem_copy_from(id, offset, len as u64))?;
+        if data.len() != len {
+            return Err(tonic::Status::internal("short read"));
+        }
+        if len > 0 {
+            // SAFETY: caller guarantees `len` writable bytes
+            unsafe { ptr::copy_nonoverlapping(data.as_ptr(), buf, len) };
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,323 @@
+//! Python bindings for jelly-fpga-client
+//!
+//! Exposes a blocking `jelly_fpga_client.JellyFpgaClient` class backed by the
+//! Rust client. Each instance owns a single-threaded tokio runtime and
+//! releases the GIL while a request is in flight. The client runs in strict
+//! mode, so a server side `result=false` raises `JellyFpgaError` instead of
+//! returning `False`; methods return only the payload (slot, id, value, ...).
+
+use std::borrow::Cow;
+
+use jelly_fpga_client::JellyFpgaClient;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use tokio::runtime::Runtime;
+
+create_exception!(jelly_fpga_client, JellyFpgaError, PyException);
+
+fn err(status: tonic::Status) -> PyErr {
+    JellyFpgaError::new_err(status.message().to_string())
+}
+
+/// Blocking client for the Jelly FPGA Server
+#[pyclass(name = "JellyFpgaClient", module = "jelly_fpga_client")]
+pub struct PyClient {
+    rt: Runtime,
+    client: JellyFpgaClient,
+}
+
+impl PyClient {
+    /// Block on an RPC with the GIL released
+    fn run<T, F>(&mut self, py: Python<'_>, f: F) -> PyResult<T>
+    where
+        T: Send,
+        F: AsyncFnOnce(&mut JellyFpgaClient) -> Result<T, tonic::Status> + Send,
+    {
+        let PyClient { rt, client } = self;
+        py.allow_threads(|| rt.block_on(f(client))).map_err(err)
+    }
+}
+
+#[pymethods]
+impl PyClient {
+    /// Connect to a server (e.g. "http://192.168.1.10:8051")
+    #[new]
+    fn new(py: Python<'_>, url: String) -> PyResult<Self> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| JellyFpgaError::new_err(e.to_string()))?;
+        let mut client = py
+            .allow_threads(|| rt.block_on(JellyFpgaClient::connect(url)))
+            .map_err(|e| JellyFpgaError::new_err(e.to_string()))?;
+        client.set_strict(true);
+        Ok(PyClient { rt, client })
+    }
+
+    /// Server version
+    fn get_version(&mut self, py: Python<'_>) -> PyResult<String> {
+        self.run(py, async |c| c.get_version().await)
+    }
+
+    /// Reset the server
+    fn reset(&mut self, py: Python<'_>) -> PyResult<()> {
+        self.run(py, async |c| c.reset().await.map(|_| ()))
+    }
+
+    /// Load firmware; returns the slot
+    fn load(&mut self, py: Python<'_>, name: &str) -> PyResult<i32> {
+        self.run(py, async |c| c.load(name).await.map(|(_, slot)| slot))
+    }
+
+    /// Unload firmware from a slot
+    fn unload(&mut self, py: Python<'_>, slot: i32) -> PyResult<()> {
+        self.run(py, async |c| c.unload(slot).await.map(|_| ()))
+    }
+
+    /// Unload all firmware
+    fn unload_all(&mut self, py: Python<'_>) -> PyResult<()> {
+        self.run(py, async |c| c.unload_all().await.map(|_| ()))
+    }
+
+    /// Upload firmware data
+    fn upload_firmware(&mut self, py: Python<'_>, name: &str, data: Vec<u8>) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.upload_firmware(name, data).await.map(|_| ())
+        })
+    }
+
+    /// Upload firmware from a local file
+    fn upload_firmware_file(
+        &mut self,
+        py: Python<'_>,
+        name: &str,
+        file_path: &str,
+    ) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.upload_firmware_file(name, file_path).await.map(|_| ())
+        })
+    }
+
+    /// Remove firmware
+    fn remove_firmware(&mut self, py: Python<'_>, name: &str) -> PyResult<()> {
+        self.run(py, async |c| c.remove_firmware(name).await.map(|_| ()))
+    }
+
+    /// Load bitstream
+    fn load_bitstream(&mut self, py: Python<'_>, name: &str) -> PyResult<()> {
+        self.run(py, async |c| c.load_bitstream(name).await.map(|_| ()))
+    }
+
+    /// Load device tree overlay
+    fn load_dtbo(&mut self, py: Python<'_>, name: &str) -> PyResult<()> {
+        self.run(py, async |c| c.load_dtbo(name).await.map(|_| ()))
+    }
+
+    /// Compile DTS source; returns the DTB bytes
+    fn dts_to_dtb(&mut self, py: Python<'_>, dts: &str) -> PyResult<Cow<'static, [u8]>> {
+        self.run(py, async |c| {
+            c.dts_to_dtb(dts).await.map(|(_, dtb)| Cow::Owned(dtb))
+        })
+    }
+
+    /// Convert bitstream to bin
+    fn bitstream_to_bin(
+        &mut self,
+        py: Python<'_>,
+        bitstream_name: &str,
+        bin_name: &str,
+        arch: &str,
+    ) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.bitstream_to_bin(bitstream_name, bin_name, arch)
+                .await
+                .map(|_| ())
+        })
+    }
+
+    /// Open a memory map; returns the id
+    #[pyo3(signature = (path, offset, size, unit = 0))]
+    fn open_mmap(
+        &mut self,
+        py: Python<'_>,
+        path: &str,
+        offset: u64,
+        size: u64,
+        unit: u64,
+    ) -> PyResult<u32> {
+        self.run(py, async |c| {
+            c.open_mmap(path, offset, size, unit)
+                .await
+                .map(|(_, id)| id)
+        })
+    }
+
+    /// Open a UIO device; returns the id
+    #[pyo3(signature = (name, unit = 0))]
+    fn open_uio(&mut self, py: Python<'_>, name: &str, unit: u64) -> PyResult<u32> {
+        self.run(py, async |c| c.open_uio(name, unit).await.map(|(_, id)| id))
+    }
+
+    /// Open a udmabuf device; returns the id
+    #[pyo3(signature = (name, cache_enable = false, unit = 0))]
+    fn open_udmabuf(
+        &mut self,
+        py: Python<'_>,
+        name: &str,
+        cache_enable: bool,
+        unit: u64,
+    ) -> PyResult<u32> {
+        self.run(py, async |c| {
+            c.open_udmabuf(name, cache_enable, unit)
+                .await
+                .map(|(_, id)| id)
+        })
+    }
+
+    /// Close an id
+    fn close(&mut self, py: Python<'_>, id: u32) -> PyResult<()> {
+        self.run(py, async |c| c.close(id).await.map(|_| ()))
+    }
+
+    /// Close every id opened by this client
+    fn close_all(&mut self, py: Python<'_>) -> PyResult<()> {
+        self.run(py, async |c| c.close_all().await.map(|_| ()))
+    }
+
+    /// Virtual address of an id
+    fn get_addr(&mut self, py: Python<'_>, id: u32) -> PyResult<u64> {
+        self.run(py, async |c| c.get_addr(id).await.map(|(_, v)| v))
+    }
+
+    /// Size of an id
+    fn get_size(&mut self, py: Python<'_>, id: u32) -> PyResult<u64> {
+        self.run(py, async |c| c.get_size(id).await.map(|(_, v)| v))
+    }
+
+    /// Physical address of an id
+    fn get_phys_addr(&mut self, py: Python<'_>, id: u32) -> PyResult<u64> {
+        self.run(py, async |c| c.get_phys_addr(id).await.map(|(_, v)| v))
+    }
+
+    /// Write an unsigned integer of `size` bytes to memory
+    #[pyo3(signature = (id, offset, data, size = 8))]
+    fn write_mem_u(
+        &mut self,
+        py: Python<'_>,
+        id: u32,
+        offset: u64,
+        data: u64,
+        size: u64,
+    ) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.write_mem_u(id, offset, data, size).await.map(|_| ())
+        })
+    }
+
+    /// Write a signed integer of `size` bytes to memory
+    #[pyo3(signature = (id, offset, data, size = 8))]
+    fn write_mem_i(
+        &mut self,
+        py: Python<'_>,
+        id: u32,
+        offset: u64,
+        data: i64,
+        size: u64,
+    ) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.write_mem_i(id, offset, data, size).await.map(|_| ())
+        })
+    }
+
+    /// Read an unsigned integer of `size` bytes from memory
+    #[pyo3(signature = (id, offset, size = 8))]
+    fn read_mem_u(&mut self, py: Python<'_>, id: u32, offset: u64, size: u64) -> PyResult<u64> {
+        self.run(py, async |c| {
+            c.read_mem_u(id, offset, size).await.map(|(_, v)| v)
+        })
+    }
+
+    /// Read a signed integer of `size` bytes from memory
+    #[pyo3(signature = (id, offset, size = 8))]
+    fn read_mem_i(&mut self, py: Python<'_>, id: u32, offset: u64, size: u64) -> PyResult<i64> {
+        self.run(py, async |c| {
+            c.read_mem_i(id, offset, size).await.map(|(_, v)| v)
+        })
+    }
+
+    /// Write an unsigned integer of `size` bytes to a register
+    #[pyo3(signature = (id, reg, data, size = 8))]
+    fn write_reg_u(
+        &mut self,
+        py: Python<'_>,
+        id: u32,
+        reg: u64,
+        data: u64,
+        size: u64,
+    ) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.write_reg_u(id, reg, data, size).await.map(|_| ())
+        })
+    }
+
+    /// Write a signed integer of `size` bytes to a register
+    #[pyo3(signature = (id, reg, data, size = 8))]
+    fn write_reg_i(
+        &mut self,
+        py: Python<'_>,
+        id: u32,
+        reg: u64,
+        data: i64,
+        size: u64,
+    ) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.write_reg_i(id, reg, data, size).await.map(|_| ())
+        })
+    }
+
+    /// Read an unsigned integer of `size` bytes from a register
+    #[pyo3(signature = (id, reg, size = 8))]
+    fn read_reg_u(&mut self, py: Python<'_>, id: u32, reg: u64, size: u64) -> PyResult<u64> {
+        self.run(py, async |c| {
+            c.read_reg_u(id, reg, size).await.map(|(_, v)| v)
+        })
+    }
+
+    /// Read a signed integer of `size` bytes from a register
+    #[pyo3(signature = (id, reg, size = 8))]
+    fn read_reg_i(&mut self, py: Python<'_>, id: u32, reg: u64, size: u64) -> PyResult<i64> {
+        self.run(py, async |c| {
+            c.read_reg_i(id, reg, size).await.map(|(_, v)| v)
+        })
+    }
+
+    /// Copy bytes to device memory
+    fn mem_copy_to(&mut self, py: Python<'_>, id: u32, offset: u64, data: Vec<u8>) -> PyResult<()> {
+        self.run(py, async |c| {
+            c.mem_copy_to(id, offset, data).await.map(|_| ())
+        })
+    }
+
+    /// Copy `size` bytes from device memory
+    fn mem_copy_from(
+        &mut self,
+        py: Python<'_>,
+        id: u32,
+        offset: u64,
+        size: u64,
+    ) -> PyResult<Cow<'static, [u8]>> {
+        self.run(py, async |c| {
+            c.mem_copy_from(id, offset, size)
+                .await
+                .map(|(_, data)| Cow::Owned(data))
+        })
+    }
+}
+
+#[pymodule]
+fn jelly_fpga_client(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    m.add("JellyFpgaError", m.py().get_type::<JellyFpgaError>())?;
+    Ok(())
+}
@@ -0,0 +1,163 @@
+//! Registered accelerator listing
+//!
+//! The server cannot list accelerators, so `register_accel` and
+//! `unregister_accel` keep a registry in a reserved firmware file (one
+//! `name bin dtbo json` line per accelerator, tab separated). Accelerators
+//! registered by other clients or tools are not listed. If the registry
+//! cannot be updated, the registering call fails even though the server
+//! registered the accelerator. JSON metadata is
+//! read back from the board when its size is known from the firmware
+//! manifest.
+
+use crate::JellyFpgaClient;
+use crate::firmware::FIRMWARE_DIR;
+
+/// Firmware name of the accelerator registry
+pub const ACCELS_FIRMWARE_NAME: &str = "jelly-fpga-client.accels";
+/// Registry file size (entries padded with zeros so it can be memory mapped)
+pub const ACCELS_FILE_SIZE: u64 = 16 * 1024;
+
+/// Registered accelerator package
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccelInfo {
+    /// Accelerator name
+    pub name: String,
+    /// Bitstream bin firmware name
+    pub bin_file: String,
+    /// Device tree overlay firmware name
+    pub dtbo_file: String,
+    /// JSON metadata firmware name
+    pub json_file: Option<String>,
+    /// JSON metadata contents (only filled by `accel_info`)
+    pub metadata: Option<String>,
+}
+
+/// Parse registry lines (trailing zero padding ignored)
+pub(crate) fn parse_accels(data: &[u8]) -> Vec<AccelInfo> {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end])
+        .lines()
+        .filter_map(|line| {
+            let mut it = line.split('\t');
+            let name = it.next()?.to_string();
+            let bin_file = it.next()?.to_string();
+            let dtbo_file = it.next()?.to_string();
+            let json_file = it.next().filter(|s| !s.is_empty()).map(str::to_string);
+            Some(AccelInfo {
+                name,
+                bin_file,
+                dtbo_file,
+                json_file,
+                metadata: None,
+            })
+        })
+        .collect()
+}
+
+/// Format registry entries, padded to [`ACCELS_FILE_SIZE`]
+pub(crate) fn format_accels(accels: &[AccelInfo]) -> Result<Vec<u8>, tonic::Status> {
+    let mut data: Vec<u8> = accels
+        .iter()
+        .map(|a| {
+            format!(
+                "{}\t{}\t{}\t{}\n",
+                a.name,
+                a.bin_file,
+                a.dtbo_file,
+                a.json_file.as_deref().unwrap_or("")
+            )
+        })
+        .collect::<String>()
+        .into_bytes();
+    if data.len() as u64 > ACCELS_FILE_SIZE {
+        return Err(tonic::Status::resource_exhausted(
+            "accelerator registry is full",
+        ));
+    }
+    data.resize(ACCELS_FILE_SIZE as usize, 0);
+    Ok(data)
+}
+
+impl JellyFpgaClient {
+    /// Names of accelerators registered through this library
+    pub async fn list_accels(&mut self) -> Result<Vec<String>, tonic::Status> {
+        Ok(self.accels().await?.into_iter().map(|a| a.name).collect())
+    }
+
+    /// Package of a registered accelerator, including its JSON metadata if readable
+    pub async fn accel_info(&mut self, name: &str) -> Result<Option<AccelInfo>, tonic::Status> {
+        let Some(mut info) = self.accels().await?.into_iter().find(|a| a.name == name) else {
+            return Ok(None);
+        };
+        if let Some(json_file) = &info.json_file
+            && let Some(size) = self
+                .firmware_stat(json_file)
+                .await?
+                .and_then(|stat| stat.size)
+        {
+            let path = format!("{}/{}", FIRMWARE_DIR, json_file);
+            info.metadata = self
+                .read_board_file(&path, size)
+                .await?
+                .map(|data| String::from_utf8_lossy(&data).into_owned());
+        }
+        Ok(Some(info))
+    }
+
+    async fn accels(&mut self) -> Result<Vec<AccelInfo>, tonic::Status> {
+        let path = format!("{}/{}", FIRMWARE_DIR, ACCELS_FIRMWARE_NAME);
+        Ok(self
+            .read_board_file(&path, ACCELS_FILE_SIZE)
+            .await?
+            .map(|data| parse_accels(&data))
+            .unwrap_or_default())
+    }
+
+    /// Add or drop a registry entry
+    ///
+    /// Serialized between clones like the firmware manifest. A failure is
+    /// returned to the caller of `register_accel` / `unregister_accel`,
+    /// although the server already applied the change.
+    pub(crate) async fn update_accels(
+        &mut self,
+        name: &str,
+        info: Option<AccelInfo>,
+    ) -> Result<(), tonic::Status> {
+        let board_files = self.board_files.clone();
+        let _guard = board_files.lock().await;
+        let mut accels = self.accels().await?;
+        accels.retain(|a| a.name != name);
+        accels.extend(info);
+        let data = format_accels(&accels)?;
+        self.write_board_firmware(ACCELS_FIRMWARE_NAME, data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accels_roundtrip() {
+        let accels = vec![
+            AccelInfo {
+                name: "blinking_led".to_string(),
+                bin_file: "blinking_led.bit.bin".to_string(),
+                dtbo_file: "blinking_led.dtbo".to_string(),
+                json_file: Some("shell.json".to_string()),
+                metadata: None,
+            },
+            AccelInfo {
+                name: "camera".to_string(),
+                bin_file: "camera.bit.bin".to_string(),
+                dtbo_file: "camera.dtbo".to_string(),
+                json_file: None,
+                metadata: None,
+            },
+        ];
+        let data = format_accels(&accels).unwrap();
+        assert_eq!(data.len() as u64, ACCELS_FILE_SIZE);
+        assert_eq!(parse_accels(&data), accels);
+    }
+}
@@ -0,0 +1,880 @@
+//! Accessor bound to an opened device id
+
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::JellyFpgaClient;
+use crate::addr::{AccessSize, ByteLen, PhysAddr, RegOffset};
+use crate::endian::Endian;
+use crate::error::with_rpc;
+use crate::field::Field;
+use crate::guard::{AddressGuard, AddressSpace};
+use crate::regmap::RegisterMap;
+use crate::shadow::Shadow;
+
+/// Convert a server side `result` flag into an error
+pub(crate) fn check(result: bool, op: &str) -> Result<(), tonic::Status> {
+    if result {
+        Ok(())
+    } else {
+        Err(with_rpc(
+            tonic::Status::internal(format!("{} failed", op)),
+            op,
+        ))
+    }
+}
+
+/// Memory/register accessor for an opened device
+///
+/// Wraps a client together with the id returned by `open_mmap`, `open_uio`,
+/// `open_udmabuf` or `subclone`, and turns a `false` result into an error so
+/// drivers can be written with `?`.
+///
+/// Clones share an ordering queue: each operation is issued while holding
+/// it, and [`lock`](Accessor::lock) holds it across a group of operations so
+/// tasks sharing the accessor cannot interleave with the group.
+pub struct Accessor {
+    client: JellyFpgaClient,
+    id: u32,
+    guard: Option<Guard>,
+    endian: Endian,
+    queue: Arc<Mutex<()>>,
+    pacing: Arc<std::sync::Mutex<Pacing>>,
+    shadow: Option<Arc<Shadow>>,
+    /// Set on the accessor inside an [`AccessorLock`]
+    held: bool,
+}
+
+impl Clone for Accessor {
+    fn clone(&self) -> Self {
+        Accessor {
+            client: self.client.clone(),
+            id: self.id,
+            guard: self.guard.clone(),
+            endian: self.endian,
+            queue: self.queue.clone(),
+            pacing: self.pacing.clone(),
+            shadow: self.shadow.clone(),
+            // a clone escaping an `AccessorLock` must queue again
+            held: false,
+        }
+    }
+}
+
+/// Exclusive use of an accessor; other clones wait until this is dropped
+///
+/// Dereferences to the accessor. Using another clone of the same accessor
+/// while holding the lock in the same task deadlocks.
+pub struct AccessorLock {
+    accessor: Accessor,
+    _turn: OwnedMutexGuard<()>,
+}
+
+/// Accessor for a sequence that must not interleave
+pub(crate) enum LockOrSelf<'a> {
+    Held(&'a mut Accessor),
+    Locked(Box<AccessorLock>),
+}
+
+impl Deref for LockOrSelf<'_> {
+    type Target = Accessor;
+
+    fn deref(&self) -> &Accessor {
+        match self {
+            LockOrSelf::Held(a) => a,
+            LockOrSelf::Locked(l) => l,
+        }
+    }
+}
+
+impl DerefMut for LockOrSelf<'_> {
+    fn deref_mut(&mut self) -> &mut Accessor {
+        match self {
+            LockOrSelf::Held(a) => a,
+            LockOrSelf::Locked(l) => l,
+        }
+    }
+}
+
+impl Deref for AccessorLock {
+    type Target = Accessor;
+
+    fn deref(&self) -> &Accessor {
+        &self.accessor
+    }
+}
+
+impl DerefMut for AccessorLock {
+    fn deref_mut(&mut self) -> &mut Accessor {
+        &mut self.accessor
+    }
+}
+
+/// Minimum spacing between operations, shared by clones
+#[derive(Default)]
+struct Pacing {
+    min_interval: Duration,
+    #[cfg(not(feature = "wasm"))]
+    last: Option<tokio::time::Instant>,
+}
+
+/// Guard bound to an accessor
+#[derive(Clone)]
+struct Guard {
+    guard: Arc<AddressGuard>,
+    /// Added to accessor offsets to get guard addresses
+    base: u64,
+    /// Register unit used to turn register indices into offsets
+    unit: u64,
+}
+
+impl Accessor {
+    /// Create an accessor for an already opened id
+    pub fn new(client: JellyFpgaClient, id: u32) -> Self {
+        Accessor {
+            client,
+            id,
+            guard: None,
+            endian: Endian::Little,
+            queue: Arc::new(Mutex::new(())),
+            pacing: Arc::default(),
+            shadow: None,
+            held: false,
+        }
+    }
+
+    /// Hold the ordering queue until the returned guard is dropped
+    ///
+    /// Operations issued through the guard run back to back; operations on
+    /// other clones of this accessor wait. Subclones have their own queue.
+    pub async fn lock(&self) -> AccessorLock {
+        let turn = self.queue.clone().lock_owned().await;
+        let mut accessor = self.clone();
+        accessor.held = true;
+        AccessorLock {
+            accessor,
+            _turn: turn,
+        }
+    }
+
+    /// Lock for a read-modify-write sequence, reusing the lock if already held
+    pub(crate) async fn lock_unless_held(&mut self) -> LockOrSelf<'_> {
+        if self.held {
+            LockOrSelf::Held(self)
+        } else {
+            LockOrSelf::Locked(Box::new(self.lock().await))
+        }
+    }
+
+    /// Wait for this operation's turn in the queue (no-op inside a lock) and the pacing interval
+    async fn turn(&self) -> Option<OwnedMutexGuard<()>> {
+        let turn = if self.held {
+            None
+        } else {
+            Some(self.queue.clone().lock_owned().await)
+        };
+        #[cfg(not(feature = "wasm"))]
+        {
+            let wait = {
+                let pacing = self.pacing.lock().unwrap();
+                pacing.last.map(|last| last + pacing.min_interval)
+            };
+            if let Some(at) = wait {
+                tokio::time::sleep_until(at).await;
+            }
+            self.pacing.lock().unwrap().last = Some(tokio::time::Instant::now());
+        }
+        turn
+    }
+
+    /// Keep at least `interval` between the start of consecutive operations
+    ///
+    /// For IPs that misbehave when accessed faster than their clock domain
+    /// crossing absorbs. Shared by clones; subclones start unpaced.
+    #[cfg(not(feature = "wasm"))]
+    pub fn set_min_interval(&mut self, interval: Duration) {
+        self.pacing.lock().unwrap().min_interval = interval;
+    }
+
+    /// Builder form of [`set_min_interval`](Self::set_min_interval)
+    #[cfg(not(feature = "wasm"))]
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.set_min_interval(interval);
+        self
+    }
+
+    /// Minimum spacing between operations
+    pub fn min_interval(&self) -> Duration {
+        self.pacing.lock().unwrap().min_interval
+    }
+
+    /// Set byte order for `write/read_mem_u*` and `write/read_reg_u*` (inherited by subclones)
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    /// Byte order
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// Reject writes not permitted by `guard`
+    ///
+    /// `unit` is the register unit the id was opened with, used to check
+    /// register writes. Subclones inherit the guard.
+    pub async fn set_guard(&mut self, guard: AddressGuard, unit: u64) -> Result<(), tonic::Status> {
+        let base = match guard.space() {
+            AddressSpace::Offset => 0,
+            AddressSpace::Physical => self.phys_addr().await?.0,
+        };
+        self.guard = Some(Guard {
+            guard: Arc::new(guard),
+            base,
+            unit,
+        });
+        Ok(())
+    }
+
+    /// Remove the guard
+    pub fn clear_guard(&mut self) {
+        self.guard = None;
+    }
+
+    /// Serve register reads from a shadow according to the access kinds of `map`
+    ///
+    /// Reads of write-only registers return the last written value (an
+    /// error before the first write) and static registers are read from
+    /// the hardware once; volatile registers and registers not in `map`
+    /// are always read. Only `write_reg_*` / `read_reg_*` and the field
+    /// methods use the shadow; memory accesses and `read_regs` bypass it.
+    /// Shared by clones; subclones start without a shadow.
+    pub fn set_shadow(&mut self, map: &RegisterMap) {
+        self.shadow = Some(Arc::new(Shadow::new(map)));
+    }
+
+    /// Builder form of [`set_shadow`](Self::set_shadow)
+    pub fn with_shadow(mut self, map: &RegisterMap) -> Self {
+        self.set_shadow(map);
+        self
+    }
+
+    /// Remove the shadow
+    pub fn clear_shadow(&mut self) {
+        self.shadow = None;
+    }
+
+    /// Forget the shadowed values (e.g. after the IP was reset)
+    pub fn invalidate_shadow(&mut self) {
+        if let Some(shadow) = &self.shadow {
+            shadow.invalidate();
+        }
+    }
+
+    /// Shadowed value of register `reg`, if any
+    pub fn shadow_value(&self, reg: u64) -> Option<u64> {
+        self.shadow.as_ref()?.get(reg)
+    }
+
+    /// Turn `result=false` into an error naming the operation, id and arguments
+    fn check_at(
+        &self,
+        result: bool,
+        op: &str,
+        args: impl std::fmt::Display,
+    ) -> Result<(), tonic::Status> {
+        if result {
+            Ok(())
+        } else {
+            let status = tonic::Status::internal(format!("{} failed (id={}{})", op, self.id, args));
+            Err(with_rpc(status, op))
+        }
+    }
+
+    fn check_write(&self, offset: u64, len: u64) -> Result<(), tonic::Status> {
+        match &self.guard {
+            Some(g) => g.guard.check(g.base.wrapping_add(offset), len),
+            None => Ok(()),
+        }
+    }
+
+    /// Device id
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Underlying client
+    pub fn client(&mut self) -> &mut JellyFpgaClient {
+        &mut self.client
+    }
+
+    /// Accessor on a [`high_priority`](JellyFpgaClient::high_priority) client
+    ///
+    /// Has its own ordering queue, so it does not wait for operations (such
+    /// as chunked copies) queued on this accessor; endianness, pacing and
+    /// the guard are kept.
+    pub fn high_priority(&self) -> Accessor {
+        Accessor {
+            client: self.client.high_priority(),
+            id: self.id,
+            guard: self.guard.clone(),
+            endian: self.endian,
+            queue: Arc::new(Mutex::new(())),
+            pacing: self.pacing.clone(),
+            shadow: self.shadow.clone(),
+            held: false,
+        }
+    }
+
+    /// Get physical address
+    pub async fn phys_addr(&mut self) -> Result<PhysAddr, tonic::Status> {
+        let _turn = self.turn().await;
+        let (result, addr) = self.client.get_phys_addr(self.id).await?;
+        self.check_at(result, "get_phys_addr", "")?;
+        Ok(PhysAddr(addr))
+    }
+
+    /// Get size
+    pub async fn size(&mut self) -> Result<u64, tonic::Status> {
+        let _turn = self.turn().await;
+        let (result, size) = self.client.get_size(self.id).await?;
+        self.check_at(result, "get_size", "")?;
+        Ok(size)
+    }
+
+    /// Wait up to `timeout` for the device's interrupt; `false` on timeout
+    ///
+    /// Needs local access to a UIO device, see
+    /// [`JellyFpgaClient::wait_irq`].
+    pub async fn wait_irq(&mut self, timeout: Duration) -> Result<bool, tonic::Status> {
+        self.client.wait_irq(self.id, timeout).await
+    }
+
+    /// Create accessor for a sub region
+    pub async fn subclone(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        size: impl Into<ByteLen>,
+        unit: u64,
+    ) -> Result<Accessor, tonic::Status> {
+        let offset = offset.into().0;
+        let size = size.into().0;
+        let _turn = self.turn().await;
+        let (result, id) = self.client.subclone(self.id, offset, size, unit).await?;
+        self.check_at(
+            result,
+            "subclone",
+            format_args!(" offset=0x{:x} size={}", offset, size),
+        )?;
+        let mut accessor = Accessor::new(self.client.clone(), id);
+        accessor.endian = self.endian;
+        accessor.guard = self.guard.as_ref().map(|g| Guard {
+            guard: g.guard.clone(),
+            base: g.base.wrapping_add(offset),
+            unit,
+        });
+        Ok(accessor)
+    }
+
+    /// Write unsigned integer to memory
+    pub async fn write_mem_u(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: u64,
+        size: AccessSize,
+    ) -> Result<(), tonic::Status> {
+        self.write_mem_u_raw(offset, data, size.bytes()).await
+    }
+
+    /// Read unsigned integer from memory
+    pub async fn read_mem_u(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        size: AccessSize,
+    ) -> Result<u64, tonic::Status> {
+        self.read_mem_u_raw(offset, size.bytes()).await
+    }
+
+    /// Write signed integer to memory (truncated to `size`)
+    pub async fn write_mem_i(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: i64,
+        size: AccessSize,
+    ) -> Result<(), tonic::Status> {
+        self.write_mem_u(offset, data as u64 & size.mask(), size)
+            .await
+    }
+
+    /// Read signed integer from memory (sign-extended from `size`)
+    pub async fn read_mem_i(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        size: AccessSize,
+    ) -> Result<i64, tonic::Status> {
+        Ok(size.sign_extend(self.read_mem_u(offset, size).await?))
+    }
+
+    /// Write unsigned integer of any `size` the server accepts to memory
+    pub async fn write_mem_u_raw(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: u64,
+        size: u64,
+    ) -> Result<(), tonic::Status> {
+        let offset = offset.into().0;
+        self.check_write(offset, size)?;
+        let data = self.endian.convert(data, size);
+        let _turn = self.turn().await;
+        let result = self.client.write_mem_u(self.id, offset, data, size).await?;
+        self.check_at(
+            result,
+            "write_mem_u",
+            format_args!(" offset=0x{:x} size={}", offset, size),
+        )
+    }
+
+    /// Read unsigned integer of any `size` the server accepts from memory
+    pub async fn read_mem_u_raw(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        size: u64,
+    ) -> Result<u64, tonic::Status> {
+        let offset = offset.into().0;
+        let _turn = self.turn().await;
+        let (result, data) = self.client.read_mem_u(self.id, offset, size).await?;
+        self.check_at(
+            result,
+            "read_mem_u",
+            format_args!(" offset=0x{:x} size={}", offset, size),
+        )?;
+        Ok(self.endian.convert(data, size))
+    }
+
+    /// Write 32-bit unsigned integer to memory
+    pub async fn write_mem_u32(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: u32,
+    ) -> Result<(), tonic::Status> {
+        self.write_mem_u(offset, data as u64, AccessSize::U32).await
+    }
+
+    /// Read 32-bit unsigned integer from memory
+    pub async fn read_mem_u32(
+        &mut self,
+        offset: impl Into<RegOffset>,
+    ) -> Result<u32, tonic::Status> {
+        Ok(self.read_mem_u(offset, AccessSize::U32).await? as u32)
+    }
+
+    /// Write 64-bit unsigned integer to memory
+    pub async fn write_mem_u64(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: u64,
+    ) -> Result<(), tonic::Status> {
+        self.write_mem_u(offset, data, AccessSize::U64).await
+    }
+
+    /// Read 64-bit unsigned integer from memory
+    pub async fn read_mem_u64(
+        &mut self,
+        offset: impl Into<RegOffset>,
+    ) -> Result<u64, tonic::Status> {
+        self.read_mem_u(offset, AccessSize::U64).await
+    }
+
+    /// Write 16-bit unsigned integer to memory in big-endian order
+    pub async fn write_mem_u16_be(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: u16,
+    ) -> Result<(), tonic::Status> {
+        let data = self.endian.convert(Endian::Big.convert(data as u64, 2), 2);
+        self.write_mem_u(offset, data, AccessSize::U16).await
+    }
+
+    /// Read 16-bit unsigned integer from memory in big-endian order
+    pub async fn read_mem_u16_be(
+        &mut self,
+        offset: impl Into<RegOffset>,
+    ) -> Result<u16, tonic::Status> {
+        let data = self
+            .endian
+            .convert(self.read_mem_u(offset, AccessSize::U16).await?, 2);
+        Ok(Endian::Big.convert(data, 2) as u16)
+    }
+
+    /// Write 32-bit unsigned integer to memory in big-endian order
+    pub async fn write_mem_u32_be(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: u32,
+    ) -> Result<(), tonic::Status> {
+        let data = self.endian.convert(Endian::Big.convert(data as u64, 4), 4);
+        self.write_mem_u(offset, data, AccessSize::U32).await
+    }
+
+    /// Read 32-bit unsigned integer from memory in big-endian order
+    pub async fn read_mem_u32_be(
+        &mut self,
+        offset: impl Into<RegOffset>,
+    ) -> Result<u32, tonic::Status> {
+        let data = self
+            .endian
+            .convert(self.read_mem_u(offset, AccessSize::U32).await?, 4);
+        Ok(Endian::Big.convert(data, 4) as u32)
+    }
+
+    /// Write 64-bit unsigned integer to memory in big-endian order
+    pub async fn write_mem_u64_be(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: u64,
+    ) -> Result<(), tonic::Status> {
+        let data = self.endian.convert(Endian::Big.convert(data, 8), 8);
+        self.write_mem_u(offset, data, AccessSize::U64).await
+    }
+
+    /// Read 64-bit unsigned integer from memory in big-endian order
+    pub async fn read_mem_u64_be(
+        &mut self,
+        offset: impl Into<RegOffset>,
+    ) -> Result<u64, tonic::Status> {
+        let data = self
+            .endian
+            .convert(self.read_mem_u(offset, AccessSize::U64).await?, 8);
+        Ok(Endian::Big.convert(data, 8))
+    }
+
+    /// Write unsigned integer to register
+    pub async fn write_reg_u(
+        &mut self,
+        reg: u64,
+        data: u64,
+        size: AccessSize,
+    ) -> Result<(), tonic::Status> {
+        self.write_reg_u_raw(reg, data, size.bytes()).await
+    }
+
+    /// Read unsigned integer from register
+    pub async fn read_reg_u(&mut self, reg: u64, size: AccessSize) -> Result<u64, tonic::Status> {
+        self.read_reg_u_raw(reg, size.bytes()).await
+    }
+
+    /// Write signed integer to register (truncated to `size`)
+    pub async fn write_reg_i(
+        &mut self,
+        reg: u64,
+        data: i64,
+        size: AccessSize,
+    ) -> Result<(), tonic::Status> {
+        self.write_reg_u(reg, data as u64 & size.mask(), size).await
+    }
+
+    /// Read signed integer from register (sign-extended from `size`)
+    pub async fn read_reg_i(&mut self, reg: u64, size: AccessSize) -> Result<i64, tonic::Status> {
+        Ok(size.sign_extend(self.read_reg_u(reg, size).await?))
+    }
+
+    /// Write unsigned integer of any `size` the server accepts to register
+    pub async fn write_reg_u_raw(
+        &mut self,
+        reg: u64,
+        data: u64,
+        size: u64,
+    ) -> Result<(), tonic::Status> {
+        if let Some(g) = &self.guard {
+            self.check_write(reg * g.unit, size)?;
+        }
+        let value = data;
+        let data = self.endian.convert(data, size);
+        let _turn = self.turn().await;
+        let result = self.client.write_reg_u(self.id, reg, data, size).await?;
+        self.check_at(
+            result,
+            "write_reg_u",
+            format_args!(" reg=0x{:x} size={}", reg, size),
+        )?;
+        if let Some(shadow) = &self.shadow {
+            shadow.written(reg, size, value);
+        }
+        Ok(())
+    }
+
+    /// Read unsigned integer of any `size` the server accepts from register
+    pub async fn read_reg_u_raw(&mut self, reg: u64, size: u64) -> Result<u64, tonic::Status> {
+        if let Some(shadow) = &self.shadow
+            && let Some(value) = shadow.read(reg, size)?
+        {
+            return Ok(value);
+        }
+        let _turn = self.turn().await;
+        let (result, data) = self.client.read_reg_u(self.id, reg, size).await?;
+        self.check_at(
+            result,
+            "read_reg_u",
+            format_args!(" reg=0x{:x} size={}", reg, size),
+        )?;
+        let value = self.endian.convert(data, size);
+        if let Some(shadow) = &self.shadow {
+            shadow.fetched(reg, size, value);
+        }
+        Ok(value)
+    }
+
+    /// Write 32-bit unsigned integer to register
+    pub async fn write_reg_u32(&mut self, reg: u64, data: u32) -> Result<(), tonic::Status> {
+        self.write_reg_u(reg, data as u64, AccessSize::U32).await
+    }
+
+    /// Read 32-bit unsigned integer from register
+    pub async fn read_reg_u32(&mut self, reg: u64) -> Result<u32, tonic::Status> {
+        Ok(self.read_reg_u(reg, AccessSize::U32).await? as u32)
+    }
+
+    /// Write 64-bit unsigned integer to register
+    pub async fn write_reg_u64(&mut self, reg: u64, data: u64) -> Result<(), tonic::Status> {
+        self.write_reg_u(reg, data, AccessSize::U64).await
+    }
+
+    /// Read 64-bit unsigned integer from register
+    pub async fn read_reg_u64(&mut self, reg: u64) -> Result<u64, tonic::Status> {
+        self.read_reg_u(reg, AccessSize::U64).await
+    }
+
+    /// Read registers concurrently (see [`JellyFpgaClient::read_regs`]); results are in `regs` order
+    ///
+    /// The batch takes one turn in the ordering queue. With a minimum
+    /// interval set the registers are read one by one instead.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn read_regs(
+        &mut self,
+        regs: &[u64],
+        size: AccessSize,
+    ) -> Result<Vec<u64>, tonic::Status> {
+        if !self.min_interval().is_zero() {
+            let mut values = Vec::with_capacity(regs.len());
+            for &reg in regs {
+                values.push(self.read_reg_u(reg, size).await?);
+            }
+            return Ok(values);
+        }
+        let _turn = self.turn().await;
+        let values = self.client.read_regs(self.id, regs, size.bytes()).await?;
+        Ok(values
+            .into_iter()
+            .map(|v| self.endian.convert(v, size.bytes()))
+            .collect())
+    }
+
+    /// Read an unsigned bitfield
+    pub async fn read_field(&mut self, field: &Field) -> Result<u64, tonic::Status> {
+        Ok(field.extract(self.read_reg_u(field.reg, field.access_size()).await?))
+    }
+
+    /// Read a signed bitfield
+    pub async fn read_field_signed(&mut self, field: &Field) -> Result<i64, tonic::Status> {
+        Ok(field.extract_signed(self.read_reg_u(field.reg, field.access_size()).await?))
+    }
+
+    /// Read a bitfield as an enum (or any type convertible from `u64`)
+    pub async fn read_field_as<T: TryFrom<u64>>(
+        &mut self,
+        field: &Field,
+    ) -> Result<T, tonic::Status> {
+        let value = self.read_field(field).await?;
+        T::try_from(value).map_err(|_| {
+            tonic::Status::out_of_range(format!(
+                "invalid value {} in field (reg=0x{:x} shift={} width={})",
+                value, field.reg, field.shift, field.width
+            ))
+        })
+    }
+
+    /// Write an unsigned bitfield (read-modify-write)
+    pub async fn write_field(&mut self, field: &Field, value: u64) -> Result<(), tonic::Status> {
+        let mut this = self.lock_unless_held().await;
+        let reg_value = this.read_reg_u(field.reg, field.access_size()).await?;
+        let reg_value = field.insert(reg_value, value)?;
+        this.write_reg_u(field.reg, reg_value, field.access_size())
+            .await
+    }
+
+    /// Write a signed bitfield (read-modify-write)
+    pub async fn write_field_signed(
+        &mut self,
+        field: &Field,
+        value: i64,
+    ) -> Result<(), tonic::Status> {
+        let mut this = self.lock_unless_held().await;
+        let reg_value = this.read_reg_u(field.reg, field.access_size()).await?;
+        let reg_value = field.insert_signed(reg_value, value)?;
+        this.write_reg_u(field.reg, reg_value, field.access_size())
+            .await
+    }
+
+    /// Write a bitfield from an enum (or any type convertible into `u64`)
+    pub async fn write_field_as<T: Into<u64>>(
+        &mut self,
+        field: &Field,
+        value: T,
+    ) -> Result<(), tonic::Status> {
+        self.write_field(field, value.into()).await
+    }
+
+    /// Copy data to memory
+    ///
+    /// Falls back to word writes if the server has no `mem_copy_to`
+    /// (see [`Capabilities`](crate::Capabilities)).
+    pub async fn mem_copy_to(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: Vec<u8>,
+    ) -> Result<(), tonic::Status> {
+        let offset = offset.into().0;
+        let len = data.len();
+        self.check_write(offset, len as u64)?;
+        let mem_copy = self.client.supports(|c| c.mem_copy).await?;
+        let _turn = self.turn().await;
+        let result = if mem_copy {
+            self.client.mem_copy_to(self.id, offset, data).await?
+        } else {
+            self.client.write_words(self.id, offset, &data).await?
+        };
+        self.check_at(
+            result,
+            "mem_copy_to",
+            format_args!(" offset=0x{:x} len={}", offset, len),
+        )
+    }
+
+    /// Copy data from memory
+    ///
+    /// Falls back to word reads if the server has no `mem_copy_from`.
+    pub async fn mem_copy_from(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        size: impl Into<ByteLen>,
+    ) -> Result<Vec<u8>, tonic::Status> {
+        let offset = offset.into().0;
+        let size = size.into().0;
+        let mem_copy = self.client.supports(|c| c.mem_copy).await?;
+        let _turn = self.turn().await;
+        let (result, data) = if mem_copy {
+            self.client.mem_copy_from(self.id, offset, size).await?
+        } else {
+            self.client.read_words(self.id, offset, size).await?
+        };
+        self.check_at(
+            result,
+            "mem_copy_from",
+            format_args!(" offset=0x{:x} size={}", offset, size),
+        )?;
+        Ok(data)
+    }
+
+    /// Write a byte slice to memory in chunks of at most `chunk_size` bytes
+    pub async fn write_bytes_chunked(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> Result<(), tonic::Status> {
+        let offset = offset.into().0;
+        let chunk_size = chunk_size.max(1);
+        for (i, chunk) in data.chunks(chunk_size).enumerate() {
+            let chunk_offset = offset + (i * chunk_size) as u64;
+            self.mem_copy_to(chunk_offset, chunk.to_vec()).await?;
+        }
+        Ok(())
+    }
+
+    /// Read `size` bytes from memory in chunks of at most `chunk_size` bytes
+    pub async fn read_bytes_chunked(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        size: impl Into<ByteLen>,
+        chunk_size: usize,
+    ) -> Result<Vec<u8>, tonic::Status> {
+        let offset = offset.into().0;
+        let size = size.into().0;
+        let chunk_size = chunk_size.max(1) as u64;
+        let mut data = Vec::with_capacity(size as usize);
+        let mut pos = 0;
+        while pos < size {
+            let len = std::cmp::min(chunk_size, size - pos);
+            data.extend(self.mem_copy_from(offset + pos, len).await?);
+            pos += len;
+        }
+        Ok(data)
+    }
+
+    /// Poll a 32-bit memory word until `(value & mask) == expected`
+    ///
+    /// Returns the last value read, or `DeadlineExceeded` on timeout.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn wait_mem_u32(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        mask: u32,
+        expected: u32,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Result<u32, tonic::Status> {
+        let offset = offset.into().0;
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let value = self.read_mem_u32(offset).await?;
+            if value & mask == expected {
+                return Ok(value);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded(format!(
+                    "timeout waiting for offset 0x{:x} (value=0x{:08x} mask=0x{:08x} expected=0x{:08x})",
+                    offset, value, mask, expected
+                )));
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Poll a register until `(value & mask) == expected`
+    ///
+    /// Returns the last value read, or `DeadlineExceeded` on timeout.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn wait_reg_u(
+        &mut self,
+        reg: u64,
+        size: AccessSize,
+        mask: u64,
+        expected: u64,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Result<u64, tonic::Status> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let value = self.read_reg_u(reg, size).await?;
+            if value & mask == expected {
+                return Ok(value);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded(format!(
+                    "timeout waiting for reg 0x{:x} (value=0x{:x} mask=0x{:x} expected=0x{:x})",
+                    reg, value, mask, expected
+                )));
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Close the device
+    pub async fn close(mut self) -> Result<(), tonic::Status> {
+        let _turn = self.turn().await;
+        let result = self.client.close(self.id).await?;
+        self.check_at(result, "close", "")
+    }
+}
@@ -0,0 +1,201 @@
+//! Address newtypes and access sizes
+//!
+//! The [`Accessor`](crate::Accessor) API takes offsets relative to the
+//! opened mapping, while DMA engines and address guards work with absolute
+//! bus addresses. [`PhysAddr`], [`RegOffset`] and [`ByteLen`] keep them
+//! apart: accessor methods accept `impl Into<RegOffset>` / `impl
+//! Into<ByteLen>`, so plain `u64` values still work but a `PhysAddr`
+//! (e.g. from [`Accessor::phys_addr`](crate::Accessor::phys_addr)) passed
+//! as an offset is a compile error. The `JellyFpgaClient` RPC wrappers keep
+//! the raw `u64` fields of the protocol.
+//!
+//! [`AccessSize`] likewise replaces the free-form `size: u64` of integer
+//! accesses, so widths like 3 or 16 cannot be requested; the accessor's
+//! `_raw` methods keep a `u64` size for servers with exotic widths.
+
+use std::fmt;
+use std::ops::{Add, Sub};
+
+/// Absolute physical (bus) address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysAddr(pub u64);
+
+/// Byte offset inside an opened mapping
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegOffset(pub u64);
+
+/// Length in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ByteLen(pub u64);
+
+macro_rules! newtype_conversions {
+    ($($t:ident),*) => {$(
+        impl From<u64> for $t {
+            fn from(value: u64) -> Self {
+                $t(value)
+            }
+        }
+
+        impl From<$t> for u64 {
+            fn from(value: $t) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "0x{:x}", self.0)
+            }
+        }
+
+        impl fmt::LowerHex for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::LowerHex::fmt(&self.0, f)
+            }
+        }
+    )*};
+}
+
+newtype_conversions!(PhysAddr, RegOffset, ByteLen);
+
+/// Address `offset` bytes into a mapping at `self`
+impl Add<RegOffset> for PhysAddr {
+    type Output = PhysAddr;
+
+    fn add(self, offset: RegOffset) -> PhysAddr {
+        PhysAddr(self.0 + offset.0)
+    }
+}
+
+/// Address `offset` bytes after `self`
+impl Add<u64> for PhysAddr {
+    type Output = PhysAddr;
+
+    fn add(self, offset: u64) -> PhysAddr {
+        PhysAddr(self.0 + offset)
+    }
+}
+
+/// Offset of `self` inside a mapping at `base`
+impl Sub for PhysAddr {
+    type Output = RegOffset;
+
+    fn sub(self, base: PhysAddr) -> RegOffset {
+        RegOffset(self.0 - base.0)
+    }
+}
+
+/// Offset `len` bytes further
+impl Add<ByteLen> for RegOffset {
+    type Output = RegOffset;
+
+    fn add(self, len: ByteLen) -> RegOffset {
+        RegOffset(self.0 + len.0)
+    }
+}
+
+/// Width of an integer memory or register access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccessSize {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl AccessSize {
+    /// Size for `bytes` (1, 2, 4 or 8)
+    pub const fn from_bytes(bytes: u64) -> Option<Self> {
+        match bytes {
+            1 => Some(AccessSize::U8),
+            2 => Some(AccessSize::U16),
+            4 => Some(AccessSize::U32),
+            8 => Some(AccessSize::U64),
+            _ => None,
+        }
+    }
+
+    /// Size in bytes, as sent to the server
+    pub const fn bytes(self) -> u64 {
+        match self {
+            AccessSize::U8 => 1,
+            AccessSize::U16 => 2,
+            AccessSize::U32 => 4,
+            AccessSize::U64 => 8,
+        }
+    }
+
+    /// Size in bits
+    pub const fn bits(self) -> u32 {
+        self.bytes() as u32 * 8
+    }
+
+    /// Mask of the value bits
+    pub const fn mask(self) -> u64 {
+        match self {
+            AccessSize::U64 => !0,
+            _ => (1 << self.bits()) - 1,
+        }
+    }
+
+    /// Sign-extend the low `bits()` bits of `value`
+    pub const fn sign_extend(self, value: u64) -> i64 {
+        let shift = 64 - self.bits();
+        ((value << shift) as i64) >> shift
+    }
+}
+
+impl TryFrom<u64> for AccessSize {
+    type Error = tonic::Status;
+
+    fn try_from(bytes: u64) -> Result<Self, tonic::Status> {
+        AccessSize::from_bytes(bytes).ok_or_else(|| {
+            tonic::Status::invalid_argument(format!(
+                "access size must be 1, 2, 4 or 8 bytes (got {})",
+                bytes
+            ))
+        })
+    }
+}
+
+impl From<AccessSize> for u64 {
+    fn from(size: AccessSize) -> u64 {
+        size.bytes()
+    }
+}
+
+impl fmt::Display for AccessSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "u{}", self.bits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addr_arithmetic() {
+        let base = PhysAddr(0xa000_0000);
+        let reg = RegOffset(0x10) + ByteLen(8);
+        assert_eq!(base + reg, PhysAddr(0xa000_0018));
+        assert_eq!(PhysAddr(0xa000_0018) - base, reg);
+        assert_eq!(u64::from(base + 4), 0xa000_0004);
+        assert_eq!(format!("{}", reg), "0x18");
+    }
+
+    #[test]
+    fn test_access_size() {
+        assert_eq!(AccessSize::try_from(4).unwrap(), AccessSize::U32);
+        assert!(AccessSize::try_from(3).is_err());
+        assert!(AccessSize::from_bytes(16).is_none());
+        assert_eq!(AccessSize::U16.mask(), 0xffff);
+        assert_eq!(AccessSize::U64.mask(), !0);
+        assert_eq!(AccessSize::U8.sign_extend(0x80), -128);
+        assert_eq!(AccessSize::U32.sign_extend(0x7fff_ffff), 0x7fff_ffff);
+    }
+}
@@ -0,0 +1,296 @@
+//! Address map import from Vivado/Vitis hardware exports
+//!
+//! Fills the region registry (see [`region`](crate::region)) from the
+//! hardware project's own metadata, so `open_region("axi_gpio_0")` works
+//! without copying addresses by hand. Supported inputs:
+//!
+//! - `.hwh` hardware handoff XML: every `MEMRANGE` becomes a region named
+//!   after its `INSTANCE`
+//! - `.xsa` archives (with the `xsa` feature): the `.hwh` files inside
+//! - `.csv` address editor exports: a header row naming the columns; the
+//!   name is taken from `name`, `instance`, `cell` or `slave segment`, the
+//!   base from `base`, `base address`, `offset address` or `address`, and
+//!   the size from `size` or `range`, or computed from `high address`.
+//!   Numbers may be hex (`0xA000_0000`) or decimal with a `K`/`M`/`G`
+//!   suffix (`64K`).
+//!
+//! An instance with several address ranges gets `instance/interface` names
+//! for the ranges after the first; ranges seen again through another
+//! master are skipped.
+
+use std::path::Path;
+
+use crate::JellyFpgaClient;
+use crate::region::Region;
+use crate::regmap::parse_num;
+
+fn invalid(msg: String) -> tonic::Status {
+    tonic::Status::invalid_argument(msg)
+}
+
+/// Parse `0xA000_0000`, `4096` or `64K`
+fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim().replace('_', "");
+    if let Some(v) = parse_num(&s) {
+        return Some(v);
+    }
+    let (digits, shift) = match s.chars().last()?.to_ascii_uppercase() {
+        'K' => (&s[..s.len() - 1], 10),
+        'M' => (&s[..s.len() - 1], 20),
+        'G' => (&s[..s.len() - 1], 30),
+        'T' => (&s[..s.len() - 1], 40),
+        _ => return None,
+    };
+    parse_num(digits.trim())?.checked_mul(1 << shift)
+}
+
+/// Add `region` unless the same range was seen, renaming clashes
+fn push_region(regions: &mut Vec<Region>, instance: &str, interface: &str, addr: u64, size: u64) {
+    let taken = |regions: &[Region], name: &str| regions.iter().any(|r| r.name == name);
+    if regions
+        .iter()
+        .any(|r| r.addr == addr && r.size == size && r.name.split('/').next() == Some(instance))
+    {
+        return;
+    }
+    let mut name = instance.to_string();
+    if taken(regions, &name) && !interface.is_empty() {
+        name = format!("{}/{}", instance, interface);
+    }
+    let mut n = 1;
+    let base = name.clone();
+    while taken(regions, &name) {
+        n += 1;
+        name = format!("{}#{}", base, n);
+    }
+    regions.push(Region::new(&name, addr, size));
+}
+
+/// Value of attribute `name` in the tag text `tag`
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(name) {
+        let before = rest[..pos].chars().last();
+        let after = rest[pos + name.len()..].trim_start();
+        rest = &rest[pos + name.len()..];
+        if !matches!(before, Some(c) if c.is_whitespace()) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let end = value[1..].find(quote)?;
+        return Some(&value[1..1 + end]);
+    }
+    None
+}
+
+/// Regions of the `MEMRANGE` elements of a `.hwh` file
+pub fn parse_hwh(text: &str) -> Result<Vec<Region>, tonic::Status> {
+    let mut regions = Vec::new();
+    for (i, chunk) in text.split("<MEMRANGE").skip(1).enumerate() {
+        let tag = &chunk[..chunk.find('>').unwrap_or(chunk.len())];
+        let get = |name: &str| {
+            attribute(tag, name)
+                .ok_or_else(|| invalid(format!("MEMRANGE {} has no {}", i + 1, name)))
+        };
+        let instance = get("INSTANCE")?;
+        let base = get("BASEVALUE")?;
+        let high = get("HIGHVALUE")?;
+        let (Some(addr), Some(high)) = (parse_size(base), parse_size(high)) else {
+            return Err(invalid(format!(
+                "MEMRANGE {}: bad range {}..{}",
+                instance, base, high
+            )));
+        };
+        if high < addr {
+            return Err(invalid(format!("MEMRANGE {}: high below base", instance)));
+        }
+        let interface = attribute(tag, "SLAVEBUSINTERFACE").unwrap_or("");
+        push_region(&mut regions, instance, interface, addr, high - addr + 1);
+    }
+    Ok(regions)
+}
+
+/// Split one CSV line, honoring double quotes
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Regions of an address editor CSV export
+pub fn parse_address_csv(text: &str) -> Result<Vec<Region>, tonic::Status> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'));
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<String> = csv_fields(header)
+        .into_iter()
+        .map(|h| h.to_ascii_lowercase())
+        .collect();
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|h| h == name))
+    };
+    let name_col = column(&["name", "instance", "cell", "slave segment"])
+        .ok_or_else(|| invalid("CSV has no name/instance/cell column".to_string()))?;
+    let base_col = column(&["base", "base address", "offset address", "address", "addr"])
+        .ok_or_else(|| invalid("CSV has no base address column".to_string()))?;
+    let size_col = column(&["size", "range"]);
+    let high_col = column(&["high address", "high"]);
+    if size_col.is_none() && high_col.is_none() {
+        return Err(invalid(
+            "CSV has no size/range/high address column".to_string(),
+        ));
+    }
+    let interface_col = column(&["slave interface", "interface"]);
+
+    let mut regions = Vec::new();
+    for (line, text) in lines {
+        let fields = csv_fields(text);
+        let field = |col: usize| fields.get(col).map(String::as_str).unwrap_or("");
+        let bad = |what: &str, value: &str| {
+            invalid(format!("line {}: bad {} {:?}", line + 1, what, value))
+        };
+        let instance = field(name_col).trim_start_matches('/');
+        let addr = parse_size(field(base_col)).ok_or_else(|| bad("address", field(base_col)))?;
+        let size = match (size_col, high_col) {
+            (Some(col), _) => parse_size(field(col)).ok_or_else(|| bad("size", field(col)))?,
+            (None, Some(col)) => match parse_size(field(col)) {
+                Some(high) if high >= addr => high - addr + 1,
+                _ => return Err(bad("high address", field(col))),
+            },
+            (None, None) => unreachable!(),
+        };
+        if instance.is_empty() {
+            return Err(invalid(format!("line {}: empty name", line + 1)));
+        }
+        let interface = interface_col.map_or("", field);
+        push_region(&mut regions, instance, interface, addr, size);
+    }
+    Ok(regions)
+}
+
+/// Regions of the `.hwh` files inside an `.xsa` archive
+#[cfg(feature = "xsa")]
+pub fn parse_xsa(data: &[u8]) -> Result<Vec<Region>, tonic::Status> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))
+        .map_err(|e| invalid(format!("not an XSA archive: {}", e)))?;
+    let names: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.ends_with(".hwh"))
+        .map(str::to_string)
+        .collect();
+    if names.is_empty() {
+        return Err(invalid("XSA archive has no .hwh file".to_string()));
+    }
+    let mut regions: Vec<Region> = Vec::new();
+    for name in names {
+        let mut text = String::new();
+        archive
+            .by_name(&name)
+            .and_then(|mut file| Ok(file.read_to_string(&mut text)?))
+            .map_err(|e| invalid(format!("{}: {}", name, e)))?;
+        for region in parse_hwh(&text)? {
+            if !regions
+                .iter()
+                .any(|r| r.addr == region.addr && r.size == region.size)
+            {
+                let (instance, interface) =
+                    region.name.split_once('/').unwrap_or((&region.name, ""));
+                push_region(&mut regions, instance, interface, region.addr, region.size);
+            }
+        }
+    }
+    Ok(regions)
+}
+
+/// Read an address map, choosing the format by extension (`.hwh`, `.csv`, `.xsa`)
+pub async fn load_address_map(path: &str) -> Result<Vec<Region>, tonic::Status> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "hwh" => parse_hwh(&crate::fs::read_to_string(path).await?),
+        "csv" => parse_address_csv(&crate::fs::read_to_string(path).await?),
+        #[cfg(feature = "xsa")]
+        "xsa" => parse_xsa(&crate::fs::read(path).await?),
+        #[cfg(not(feature = "xsa"))]
+        "xsa" => Err(tonic::Status::unimplemented(
+            "reading .xsa archives requires the xsa feature (or extract the .hwh file)",
+        )),
+        _ => Err(invalid(format!(
+            "{}: unknown address map format (expected .hwh, .xsa or .csv)",
+            path
+        ))),
+    }
+}
+
+impl JellyFpgaClient {
+    /// Define the regions of an exported address map; returns their names
+    ///
+    /// See the [module documentation](self) for the formats. Existing
+    /// regions of the same name are replaced.
+    pub async fn import_address_map(&mut self, path: &str) -> Result<Vec<String>, tonic::Status> {
+        let regions = load_address_map(path).await?;
+        let names = regions.iter().map(|r| r.name.clone()).collect();
+        self.add_regions(regions);
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_address_maps() {
+        let hwh = r#"<MEMORYMAP>
+  <MEMRANGE ADDRESSBLOCK="Reg" BASENAME="C_BASEADDR" BASEVALUE="0xA0000000" HIGHNAME="C_HIGHADDR" HIGHVALUE="0xA000FFFF" INSTANCE="axi_gpio_0" IS_DATA="TRUE" MASTERBUSINTERFACE="M_AXI_HPM0_FPD" MEMTYPE="REGISTER" SLAVEBUSINTERFACE="S_AXI"/>
+  <MEMRANGE BASEVALUE="0xA0010000" HIGHVALUE="0xA0010FFF" INSTANCE="axi_dma_0" SLAVEBUSINTERFACE="S_AXI_LITE"/>
+  <MEMRANGE BASEVALUE="0xA0000000" HIGHVALUE="0xA000FFFF" INSTANCE="axi_gpio_0" MASTERBUSINTERFACE="M_AXI_HPM1_FPD" SLAVEBUSINTERFACE="S_AXI"/>
+</MEMORYMAP>"#;
+        let regions = parse_hwh(hwh).unwrap();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0], Region::new("axi_gpio_0", 0xa000_0000, 0x1_0000));
+        assert_eq!(regions[1].size, 0x1000);
+
+        let csv = "Cell,Slave Interface,Base Name,Offset Address,Range,High Address\n\
+                   /axi_gpio_0,S_AXI,Reg,0xA000_0000,64K,0xA000_FFFF\n\
+                   /axi_bram_ctrl_0,S_AXI,Mem0,0xA010_0000,8K,0xA010_1FFF\n\
+                   /axi_bram_ctrl_0,S_AXI_ECC,Reg,0xA011_0000,4K,0xA011_0FFF\n";
+        let regions = parse_address_csv(csv).unwrap();
+        assert_eq!(regions[0], Region::new("axi_gpio_0", 0xa000_0000, 0x1_0000));
+        assert_eq!(regions[1].size, 0x2000);
+        assert_eq!(regions[2].name, "axi_bram_ctrl_0/S_AXI_ECC");
+        assert!(parse_address_csv("name,base\nled,0x0\n").is_err());
+    }
+}
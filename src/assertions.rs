@@ -0,0 +1,147 @@
+//! Assertions for hardware tests
+//!
+//! Each helper reads the hardware and returns `Ok(())` or an `internal`
+//! error (`deadline_exceeded` for the timed ones) whose message carries the
+//! read-back value, so a hardware test can `?` through a sequence of
+//! checks instead of matching on every result.
+
+use std::fmt::Write;
+
+use crate::JellyFpgaClient;
+use crate::accessor::check;
+
+/// Bytes shown around the first mismatch of `assert_mem_eq`
+const CONTEXT: usize = 16;
+
+fn failed(message: String) -> tonic::Status {
+    tonic::Status::internal(format!("assertion failed: {}", message))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            s.push(' ');
+        }
+        let _ = write!(s, "{:02x}", b);
+    }
+    s
+}
+
+/// Describe how `actual` differs from `expected` (same length), or `None` if equal
+fn mem_mismatch(offset: u64, expected: &[u8], actual: &[u8]) -> Option<String> {
+    let first = expected.iter().zip(actual).position(|(e, a)| e != a)?;
+    let count = expected.iter().zip(actual).filter(|(e, a)| e != a).count();
+    let end = (first + CONTEXT).min(expected.len());
+    Some(format!(
+        "{} of {} bytes differ, first at offset 0x{:x}: expected [{}], read [{}]",
+        count,
+        expected.len(),
+        offset + first as u64,
+        hex(&expected[first..end]),
+        hex(&actual[first..end])
+    ))
+}
+
+impl JellyFpgaClient {
+    /// Fail unless register `reg` of `id` reads `expected`
+    pub async fn assert_reg_eq(
+        &mut self,
+        id: u32,
+        reg: u64,
+        expected: u64,
+        size: u64,
+    ) -> Result<(), tonic::Status> {
+        let (result, value) = self.read_reg_u(id, reg, size).await?;
+        check(result, "read_reg_u")?;
+        if value == expected {
+            return Ok(());
+        }
+        Err(failed(format!(
+            "reg 0x{:x} of id {}: expected 0x{:x}, read 0x{:x} (differing bits 0x{:x})",
+            reg,
+            id,
+            expected,
+            value,
+            value ^ expected
+        )))
+    }
+
+    /// Fail unless memory of `id` at `offset` holds `expected`
+    ///
+    /// The error names the number of differing bytes and shows the first
+    /// mismatch with the bytes following it.
+    pub async fn assert_mem_eq(
+        &mut self,
+        id: u32,
+        offset: u64,
+        expected: &[u8],
+    ) -> Result<(), tonic::Status> {
+        let actual = self
+            .accessor(id)
+            .mem_copy_from(offset, expected.len() as u64)
+            .await?;
+        if actual.len() != expected.len() {
+            return Err(failed(format!(
+                "memory at offset 0x{:x} of id {}: expected {} bytes, read {}",
+                offset,
+                id,
+                expected.len(),
+                actual.len()
+            )));
+        }
+        match mem_mismatch(offset, expected, &actual) {
+            None => Ok(()),
+            Some(m) => Err(failed(format!("memory of id {}: {}", id, m))),
+        }
+    }
+
+    /// Poll register `reg` of `id` until all bits of `mask` are set
+    ///
+    /// Reads 64 bits if `mask` has bits above 31, 32 otherwise. Fails with
+    /// `deadline_exceeded` and the last value read after `timeout`.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn expect_bit_set_within(
+        &mut self,
+        id: u32,
+        reg: u64,
+        mask: u64,
+        timeout: std::time::Duration,
+    ) -> Result<(), tonic::Status> {
+        let size = if mask >> 32 != 0 { 8 } else { 4 };
+        let start = tokio::time::Instant::now();
+        loop {
+            let (result, value) = self.read_reg_u(id, reg, size).await?;
+            check(result, "read_reg_u")?;
+            if value & mask == mask {
+                return Ok(());
+            }
+            if start.elapsed() >= timeout {
+                return Err(tonic::Status::deadline_exceeded(format!(
+                    "assertion failed: reg 0x{:x} of id {}: bits 0x{:x} not set within {:?} (last read 0x{:x}, missing 0x{:x})",
+                    reg,
+                    id,
+                    mask,
+                    timeout,
+                    value,
+                    mask & !value
+                )));
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mem_mismatch() {
+        assert_eq!(mem_mismatch(0, &[1, 2, 3], &[1, 2, 3]), None);
+        assert_eq!(
+            mem_mismatch(0x100, &[1, 2, 3, 4], &[1, 9, 3, 8]).unwrap(),
+            "2 of 4 bytes differ, first at offset 0x101: expected [02 03 04], read [09 03 08]"
+        );
+    }
+}
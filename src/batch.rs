@@ -0,0 +1,54 @@
+//! Concurrent batched reads
+//!
+//! Reads are issued on clones of the client, which share one HTTP/2
+//! connection, so up to [`MAX_IN_FLIGHT`] requests overlap instead of paying
+//! one round trip per register.
+
+use tokio::task::JoinSet;
+
+use crate::JellyFpgaClient;
+
+/// Maximum number of concurrent requests of a batch
+pub const MAX_IN_FLIGHT: usize = 16;
+
+impl JellyFpgaClient {
+    /// Read registers `regs` of `size` bytes concurrently; results are in `regs` order
+    ///
+    /// Fails on the first RPC error or `result=false`, regardless of strict mode.
+    pub async fn read_regs(
+        &mut self,
+        id: u32,
+        regs: &[u64],
+        size: u64,
+    ) -> Result<Vec<u64>, tonic::Status> {
+        let mut values = vec![0; regs.len()];
+        let mut tasks = JoinSet::new();
+        let mut pending = regs.iter().copied().enumerate();
+        loop {
+            while tasks.len() < MAX_IN_FLIGHT
+                && let Some((index, reg)) = pending.next()
+            {
+                let mut client = self.clone();
+                tasks.spawn(async move {
+                    let (result, value) = client.read_reg_u(id, reg, size).await?;
+                    if !result {
+                        return Err(crate::error::with_rpc(
+                            tonic::Status::internal(format!(
+                                "read_reg_u failed (id={} reg=0x{:x} size={})",
+                                id, reg, size
+                            )),
+                            "read_reg_u",
+                        ));
+                    }
+                    Ok((index, value))
+                });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let (index, value) = joined.map_err(|e| tonic::Status::internal(e.to_string()))??;
+            values[index] = value;
+        }
+        Ok(values)
+    }
+}
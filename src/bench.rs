@@ -0,0 +1,314 @@
+//! Link and board benchmarks
+//!
+//! [`bench_reg_read`] / [`bench_reg_write`] time single register accesses
+//! (one RPC each, so mostly network and server latency) and
+//! [`bench_dma`] times `mem_copy_to` / `mem_copy_from` of a buffer in
+//! chunks (link throughput). Results are collected in a [`BenchReport`]
+//! that prints as a table or serializes to JSON; the `jelly-fpga bench`
+//! command runs them from the command line.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::accessor::Accessor;
+use crate::addr::AccessSize;
+use crate::display::format_size;
+use crate::stats::Summary;
+
+/// Transfer direction of [`bench_dma`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BenchDir {
+    /// Host to device (`mem_copy_to`)
+    Write,
+    /// Device to host (`mem_copy_from`)
+    Read,
+    /// Write, then read back and compare
+    Both,
+}
+
+impl FromStr for BenchDir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "write" => Ok(BenchDir::Write),
+            "read" => Ok(BenchDir::Read),
+            "both" => Ok(BenchDir::Both),
+            _ => Err(format!("unknown direction {:?} (write, read or both)", s)),
+        }
+    }
+}
+
+/// Latency of a repeated operation
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LatencyResult {
+    /// Operation
+    pub op: String,
+    /// Total time of all iterations
+    pub total: Duration,
+    /// Per-operation latency in microseconds
+    pub latency_us: Summary,
+}
+
+impl LatencyResult {
+    /// Operations per second
+    pub fn ops_per_sec(&self) -> f64 {
+        self.latency_us.count as f64 / self.total.as_secs_f64()
+    }
+}
+
+/// Throughput of a transfer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThroughputResult {
+    /// Operation
+    pub op: String,
+    /// Bytes transferred
+    pub bytes: u64,
+    /// Chunk size in bytes
+    pub chunk_size: u64,
+    /// Duration of the transfer
+    pub elapsed: Duration,
+}
+
+impl ThroughputResult {
+    /// Throughput in MB/s (10^6 bytes per second)
+    pub fn mb_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64() / 1e6
+    }
+}
+
+/// Results of one or more benchmarks
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BenchReport {
+    /// Latency results
+    pub latency: Vec<LatencyResult>,
+    /// Throughput results
+    pub throughput: Vec<ThroughputResult>,
+}
+
+impl BenchReport {
+    /// Append the results of `other`
+    pub fn extend(&mut self, other: BenchReport) {
+        self.latency.extend(other.latency);
+        self.throughput.extend(other.throughput);
+    }
+
+    /// JSON object on one line
+    pub fn to_json(&self) -> String {
+        let latency: Vec<String> = self
+            .latency
+            .iter()
+            .map(|r| {
+                let s = &r.latency_us;
+                format!(
+                    "{{\"op\":{:?},\"iters\":{},\"total_s\":{},\"ops_per_sec\":{},\"min_us\":{},\"mean_us\":{},\"p50_us\":{},\"p90_us\":{},\"p99_us\":{},\"max_us\":{}}}",
+                    r.op,
+                    s.count,
+                    r.total.as_secs_f64(),
+                    r.ops_per_sec(),
+                    s.min,
+                    s.mean,
+                    s.p50,
+                    s.p90,
+                    s.p99,
+                    s.max
+                )
+            })
+            .collect();
+        let throughput: Vec<String> = self
+            .throughput
+            .iter()
+            .map(|r| {
+                format!(
+                    "{{\"op\":{:?},\"bytes\":{},\"chunk_size\":{},\"elapsed_s\":{},\"mb_per_sec\":{}}}",
+                    r.op,
+                    r.bytes,
+                    r.chunk_size,
+                    r.elapsed.as_secs_f64(),
+                    r.mb_per_sec()
+                )
+            })
+            .collect();
+        format!(
+            "{{\"latency\":[{}],\"throughput\":[{}]}}",
+            latency.join(","),
+            throughput.join(",")
+        )
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.latency.is_empty() {
+            writeln!(
+                f,
+                "{:<12} {:>8} {:>10} {:>9} {:>9} {:>9} {:>9} {:>9}",
+                "op", "iters", "ops/s", "min us", "mean us", "p50 us", "p99 us", "max us"
+            )?;
+            for r in &self.latency {
+                let s = &r.latency_us;
+                writeln!(
+                    f,
+                    "{:<12} {:>8} {:>10.0} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+                    r.op,
+                    s.count,
+                    r.ops_per_sec(),
+                    s.min,
+                    s.mean,
+                    s.p50,
+                    s.p99,
+                    s.max
+                )?;
+            }
+        }
+        if !self.throughput.is_empty() {
+            if !self.latency.is_empty() {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "{:<12} {:>12} {:>10} {:>10} {:>10}",
+                "op", "size", "chunk", "seconds", "MB/s"
+            )?;
+            for r in &self.throughput {
+                writeln!(
+                    f,
+                    "{:<12} {:>12} {:>10} {:>10.3} {:>10.1}",
+                    r.op,
+                    format_size(r.bytes),
+                    format_size(r.chunk_size),
+                    r.elapsed.as_secs_f64(),
+                    r.mb_per_sec()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn latency(op: &str, total: Duration, samples: &[f64]) -> Result<LatencyResult, tonic::Status> {
+    let latency_us = Summary::of(samples)
+        .ok_or_else(|| tonic::Status::invalid_argument("benchmark needs at least one iteration"))?;
+    Ok(LatencyResult {
+        op: op.to_string(),
+        total,
+        latency_us,
+    })
+}
+
+/// Time `iters` reads of register `reg`
+pub async fn bench_reg_read(
+    accessor: &mut Accessor,
+    reg: u64,
+    size: AccessSize,
+    iters: usize,
+) -> Result<BenchReport, tonic::Status> {
+    let mut samples = Vec::with_capacity(iters);
+    let start = Instant::now();
+    for _ in 0..iters {
+        let t = Instant::now();
+        accessor.read_reg_u(reg, size).await?;
+        samples.push(t.elapsed().as_secs_f64() * 1e6);
+    }
+    Ok(BenchReport {
+        latency: vec![latency("reg read", start.elapsed(), &samples)?],
+        throughput: Vec::new(),
+    })
+}
+
+/// Time `iters` writes of `value` to register `reg`
+pub async fn bench_reg_write(
+    accessor: &mut Accessor,
+    reg: u64,
+    value: u64,
+    size: AccessSize,
+    iters: usize,
+) -> Result<BenchReport, tonic::Status> {
+    let mut samples = Vec::with_capacity(iters);
+    let start = Instant::now();
+    for _ in 0..iters {
+        let t = Instant::now();
+        accessor.write_reg_u(reg, value, size).await?;
+        samples.push(t.elapsed().as_secs_f64() * 1e6);
+    }
+    Ok(BenchReport {
+        latency: vec![latency("reg write", start.elapsed(), &samples)?],
+        throughput: Vec::new(),
+    })
+}
+
+/// Transfer `size` bytes at offset 0 of `accessor` in `chunk_size` chunks
+///
+/// Writes a counting byte pattern. With [`BenchDir::Both`] the data read
+/// back must match it, else the result is `data_loss`.
+pub async fn bench_dma(
+    accessor: &mut Accessor,
+    size: u64,
+    dir: BenchDir,
+    chunk_size: usize,
+) -> Result<BenchReport, tonic::Status> {
+    let chunk_size = chunk_size.max(1);
+    let mut report = BenchReport::default();
+    let data: Vec<u8> = (0..size).map(|i| i as u8).collect();
+    if dir != BenchDir::Read {
+        let start = Instant::now();
+        accessor.write_bytes_chunked(0, &data, chunk_size).await?;
+        report.throughput.push(ThroughputResult {
+            op: "dma write".to_string(),
+            bytes: size,
+            chunk_size: chunk_size as u64,
+            elapsed: start.elapsed(),
+        });
+    }
+    if dir != BenchDir::Write {
+        let start = Instant::now();
+        let back = accessor.read_bytes_chunked(0, size, chunk_size).await?;
+        report.throughput.push(ThroughputResult {
+            op: "dma read".to_string(),
+            bytes: size,
+            chunk_size: chunk_size as u64,
+            elapsed: start.elapsed(),
+        });
+        if dir == BenchDir::Both && back != data {
+            let pos = back
+                .iter()
+                .zip(&data)
+                .position(|(a, b)| a != b)
+                .unwrap_or(back.len().min(data.len()));
+            return Err(tonic::Status::data_loss(format!(
+                "dma benchmark read-back differs at byte {}",
+                pos
+            )));
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_report() {
+        let report = BenchReport {
+            latency: vec![latency("reg read", Duration::from_secs(1), &[100.0, 300.0]).unwrap()],
+            throughput: vec![ThroughputResult {
+                op: "dma write".to_string(),
+                bytes: 2_000_000,
+                chunk_size: 1 << 20,
+                elapsed: Duration::from_millis(500),
+            }],
+        };
+        assert_eq!(report.latency[0].ops_per_sec(), 2.0);
+        assert_eq!(report.throughput[0].mb_per_sec(), 4.0);
+        assert!(report.to_json().contains("\"mean_us\":200,"));
+        assert!(report.to_string().contains("dma write"));
+        assert!(report.to_string().contains("1 MiB"));
+        assert_eq!("both".parse(), Ok(BenchDir::Both));
+    }
+}
@@ -0,0 +1,142 @@
+//! Bulk writes from generated data
+//!
+//! [`JellyFpgaClient::write_mem_iter`] and
+//! [`write_mem_stream`](JellyFpgaClient::write_mem_stream) take 32-bit
+//! words from an iterator or stream and write them as consecutive
+//! `mem_copy_to` calls of at most one chunk each, so large test patterns
+//! (ramps, PRBS) never exist in memory as a whole. Only one chunk is
+//! buffered: the next one is filled after the previous call returned.
+//! Servers without `mem_copy` get word writes as in
+//! [`Accessor::mem_copy_to`](crate::accessor::Accessor::mem_copy_to).
+
+use futures_core::stream::Stream;
+use tokio_stream::StreamExt;
+
+use crate::JellyFpgaClient;
+use crate::accessor::check;
+
+/// Default chunk size of the iterator writes in bytes
+pub const DEFAULT_ITER_CHUNK_SIZE: usize = 64 * 1024;
+
+impl JellyFpgaClient {
+    /// Write the words of `words` little-endian from byte `offset` of `id`
+    ///
+    /// Returns the number of bytes written. Fails on the first rejected chunk.
+    pub async fn write_mem_iter<I>(
+        &mut self,
+        id: u32,
+        offset: u64,
+        words: I,
+    ) -> Result<u64, tonic::Status>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        self.write_mem_iter_chunked(id, offset, words, DEFAULT_ITER_CHUNK_SIZE)
+            .await
+    }
+
+    /// [`write_mem_iter`](Self::write_mem_iter) with chunks of at most `chunk_size` bytes
+    ///
+    /// `chunk_size` is rounded down to whole words (at least one).
+    pub async fn write_mem_iter_chunked<I>(
+        &mut self,
+        id: u32,
+        offset: u64,
+        words: I,
+        chunk_size: usize,
+    ) -> Result<u64, tonic::Status>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let per_chunk = (chunk_size / 4).max(1);
+        let mut words = words.into_iter();
+        let mut written = 0;
+        loop {
+            let chunk: Vec<u8> = words
+                .by_ref()
+                .take(per_chunk)
+                .flat_map(u32::to_le_bytes)
+                .collect();
+            if chunk.is_empty() {
+                return Ok(written);
+            }
+            written += self.write_chunk(id, offset + written, chunk).await?;
+        }
+    }
+
+    /// Write the words of `words` little-endian from byte `offset` of `id`
+    ///
+    /// A chunk is written when it is full or the stream ends, so a slow
+    /// producer delays the writes rather than growing a buffer.
+    pub async fn write_mem_stream<S>(
+        &mut self,
+        id: u32,
+        offset: u64,
+        words: S,
+    ) -> Result<u64, tonic::Status>
+    where
+        S: Stream<Item = u32> + Unpin,
+    {
+        self.write_mem_stream_chunked(id, offset, words, DEFAULT_ITER_CHUNK_SIZE)
+            .await
+    }
+
+    /// [`write_mem_stream`](Self::write_mem_stream) with chunks of at most `chunk_size` bytes
+    pub async fn write_mem_stream_chunked<S>(
+        &mut self,
+        id: u32,
+        offset: u64,
+        mut words: S,
+        chunk_size: usize,
+    ) -> Result<u64, tonic::Status>
+    where
+        S: Stream<Item = u32> + Unpin,
+    {
+        let chunk_bytes = (chunk_size / 4).max(1) * 4;
+        let mut chunk = Vec::with_capacity(chunk_bytes);
+        let mut written = 0;
+        while let Some(word) = words.next().await {
+            chunk.extend_from_slice(&word.to_le_bytes());
+            if chunk.len() == chunk_bytes {
+                let full = std::mem::replace(&mut chunk, Vec::with_capacity(chunk_bytes));
+                written += self.write_chunk(id, offset + written, full).await?;
+            }
+        }
+        if !chunk.is_empty() {
+            written += self.write_chunk(id, offset + written, chunk).await?;
+        }
+        Ok(written)
+    }
+
+    pub(crate) async fn write_chunk(
+        &mut self,
+        id: u32,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<u64, tonic::Status> {
+        let len = data.len() as u64;
+        let result = if self.supports(|c| c.mem_copy).await? {
+            self.mem_copy_to(id, offset, data).await?
+        } else {
+            self.write_words(id, offset, &data).await?
+        };
+        check(result, "mem_copy_to")?;
+        Ok(len)
+    }
+
+    /// `mem_copy_from`, or word reads if the server has no `mem_copy`
+    pub(crate) async fn read_chunk(
+        &mut self,
+        id: u32,
+        offset: u64,
+        size: u64,
+    ) -> Result<Vec<u8>, tonic::Status> {
+        let (result, data) = if self.supports(|c| c.mem_copy).await? {
+            self.mem_copy_from(id, offset, size).await?
+        } else {
+            self.read_words(id, offset, size).await?
+        };
+        check(result, "mem_copy_from")?;
+        Ok(data)
+    }
+}
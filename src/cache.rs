@@ -0,0 +1,306 @@
+//! On-disk cache for short-lived tools
+//!
+//! A command line tool connects anew on every invocation, probing the
+//! server [`Capabilities`] and re-parsing `.hwh`/`.xsa` address maps each
+//! time. A [`DiskCache`] (by default under `~/.cache/jelly-fpga/`) keeps
+//! both between runs:
+//!
+//! - capabilities per server address, reused while the server reports the
+//!   same version, so connecting costs one `get_version` instead of the
+//!   probes; the entry is dropped whenever firmware is loaded, unloaded or
+//!   the FPGA is reset through a client using the cache
+//! - parsed address maps keyed by the SHA-256 of the file, so a rebuilt
+//!   design is parsed again
+//!
+//! Entries are small text files written atomically; a missing, stale or
+//! unreadable entry simply counts as a miss.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::capabilities::{Capabilities, parse_features};
+use crate::firmware::sha256;
+use crate::region::Region;
+use crate::{JellyFpgaClient, addrmap, raw};
+
+/// Environment variable overriding the cache directory
+pub const CACHE_ENV: &str = "JELLY_FPGA_CACHE";
+
+/// `$JELLY_FPGA_CACHE`, else `$XDG_CACHE_HOME/jelly-fpga` (`~/.cache/jelly-fpga` by default)
+pub fn default_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(CACHE_ENV) {
+        return PathBuf::from(dir);
+    }
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("jelly-fpga")
+}
+
+/// Cache directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Cache in `dir` (created on first write)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskCache { dir: dir.into() }
+    }
+
+    /// Cache in [`default_dir`]
+    pub fn open_default() -> Self {
+        DiskCache::new(default_dir())
+    }
+
+    /// Cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn server_path(&self, server: &str) -> PathBuf {
+        self.dir
+            .join("servers")
+            .join(&sha256(server.as_bytes())[..32])
+    }
+
+    /// Cached capabilities of `server` if it still reports `version`
+    pub async fn capabilities(&self, server: &str, version: &str) -> Option<Capabilities> {
+        let text = tokio::fs::read_to_string(self.server_path(server))
+            .await
+            .ok()?;
+        let capabilities = parse_capabilities(&text)?;
+        (capabilities.version == version).then_some(capabilities)
+    }
+
+    /// Store the capabilities of `server`
+    pub async fn store_capabilities(
+        &self,
+        server: &str,
+        capabilities: &Capabilities,
+    ) -> Result<(), tonic::Status> {
+        let features: Vec<&str> = capabilities.features.iter().map(String::as_str).collect();
+        let text = format!(
+            "server {}\nversion {}\nmem_copy {}\ndts_to_dtb {}\nfeatures {}\nproto {}\n",
+            server,
+            capabilities.version,
+            capabilities.mem_copy,
+            capabilities.dts_to_dtb,
+            features.join(","),
+            capabilities.proto_version.as_deref().unwrap_or_default()
+        );
+        self.write(&self.server_path(server), text).await
+    }
+
+    /// Drop the entry of `server`
+    pub async fn invalidate_server(&self, server: &str) {
+        let _ = tokio::fs::remove_file(self.server_path(server)).await;
+    }
+
+    /// Regions of the address map at `path`, parsed only if the file is not cached
+    ///
+    /// Same formats as [`load_address_map`](addrmap::load_address_map).
+    pub async fn address_map(&self, path: &str) -> Result<Vec<Region>, tonic::Status> {
+        // the format follows the extension, so it is part of the key
+        let mut key = crate::fs::read(path).await?;
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        key.extend_from_slice(extension.as_bytes());
+        let entry = self.dir.join("addrmaps").join(&sha256(&key)[..32]);
+        if let Ok(text) = tokio::fs::read_to_string(&entry).await
+            && let Some(regions) = parse_regions(&text)
+        {
+            return Ok(regions);
+        }
+        let regions = addrmap::load_address_map(path).await?;
+        let _ = self.write(&entry, format_regions(&regions)).await;
+        Ok(regions)
+    }
+
+    /// Remove all entries
+    pub async fn clear(&self) -> Result<(), tonic::Status> {
+        match tokio::fs::remove_dir_all(&self.dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(tonic::Status::internal(
+                format!("Failed to remove {}: {}", self.dir.display(), e),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write through a temporary file so concurrent readers never see half an entry
+    async fn write(&self, path: &Path, text: String) -> Result<(), tonic::Status> {
+        let io = |e: std::io::Error| {
+            tonic::Status::internal(format!("Failed to write {}: {}", path.display(), e))
+        };
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await.map_err(io)?;
+        }
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        tokio::fs::write(&tmp, text).await.map_err(io)?;
+        tokio::fs::rename(&tmp, path).await.map_err(io)
+    }
+}
+
+fn parse_capabilities(text: &str) -> Option<Capabilities> {
+    let mut capabilities = Capabilities::default();
+    let mut version = None;
+    // entries written before the protocol version was recorded are misses
+    let mut proto = None;
+    for line in text.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "version" => version = Some(value.to_string()),
+            "mem_copy" => capabilities.mem_copy = value.parse().ok()?,
+            "dts_to_dtb" => capabilities.dts_to_dtb = value.parse().ok()?,
+            "features" => capabilities.features = parse_features(value),
+            "proto" => proto = Some(value),
+            _ => {}
+        }
+    }
+    capabilities.version = version?;
+    let proto = proto?;
+    capabilities.proto_version = (!proto.is_empty()).then(|| proto.to_string());
+    Some(capabilities)
+}
+
+/// One region per line: name, address, size, device, unit (tab separated)
+fn format_regions(regions: &[Region]) -> String {
+    regions
+        .iter()
+        .map(|r| {
+            format!(
+                "{}\t0x{:x}\t0x{:x}\t{}\t{}\n",
+                r.name, r.addr, r.size, r.device, r.unit
+            )
+        })
+        .collect()
+}
+
+fn parse_regions(text: &str) -> Option<Vec<Region>> {
+    let hex = |s: &str| u64::from_str_radix(s.strip_prefix("0x")?, 16).ok();
+    text.lines()
+        .map(|line| {
+            let mut columns = line.split('\t');
+            let name = columns.next()?;
+            let addr = hex(columns.next()?)?;
+            let size = hex(columns.next()?)?;
+            let device = columns.next()?;
+            let unit = columns.next()?.parse().ok()?;
+            Some(
+                Region::new(name, addr, size)
+                    .with_device(device)
+                    .with_unit(unit),
+            )
+        })
+        .collect()
+}
+
+/// Cache and server key of a client
+pub(crate) struct CacheSlot {
+    cache: DiskCache,
+    server: String,
+}
+
+impl CacheSlot {
+    /// Drop the capabilities entry after firmware changes
+    pub(crate) async fn invalidate(&self) {
+        self.cache.invalidate_server(&self.server).await;
+    }
+}
+
+impl JellyFpgaClient {
+    /// Connect to `dst`, taking the capabilities from `cache` when the server version matches
+    ///
+    /// The server is identified by its address. Loading or unloading
+    /// firmware and resetting through this client (or its clones) drops the
+    /// cache entry.
+    pub async fn connect_cached<D>(
+        dst: D,
+        cache: &DiskCache,
+    ) -> Result<Self, tonic::transport::Error>
+    where
+        D: std::convert::TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let endpoint = tonic::transport::Endpoint::new(dst)?;
+        let server = endpoint.uri().to_string();
+        let channel = endpoint.connect().await?;
+        let mut client = Self::from_raw(raw::JellyFpgaControlClient::new(channel))
+            .with_disk_cache(cache.clone(), &server);
+        let _ = client.capabilities().await;
+        Ok(client)
+    }
+
+    /// Use `cache` for the capabilities of this client, identified as `server`
+    ///
+    /// Only has an effect before the capabilities are first probed, so
+    /// apply it to a client from [`from_raw`](Self::from_raw) or
+    /// [`connect_lazy`](Self::connect_lazy).
+    pub fn with_disk_cache(mut self, cache: DiskCache, server: &str) -> Self {
+        self.disk_cache = Some(Arc::new(CacheSlot {
+            cache,
+            server: server.to_string(),
+        }));
+        self
+    }
+
+    /// Define the regions of an address map, parsing it only if not in `cache`
+    pub async fn import_address_map_cached(
+        &mut self,
+        cache: &DiskCache,
+        path: &str,
+    ) -> Result<Vec<String>, tonic::Status> {
+        let regions = cache.address_map(path).await?;
+        let names = regions.iter().map(|r| r.name.clone()).collect();
+        self.add_regions(regions);
+        Ok(names)
+    }
+
+    /// Capabilities from the disk cache if configured and current, else probed (and stored)
+    pub(crate) async fn cached_capabilities(&mut self) -> Result<Capabilities, tonic::Status> {
+        let Some(slot) = self.disk_cache.clone() else {
+            return self.detect_capabilities().await;
+        };
+        let version = self.get_version().await?;
+        if let Some(capabilities) = slot.cache.capabilities(&slot.server, &version).await {
+            return Ok(capabilities);
+        }
+        let capabilities = self.detect_capabilities().await?;
+        let _ = slot
+            .cache
+            .store_capabilities(&slot.server, &capabilities)
+            .await;
+        Ok(capabilities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_entries() {
+        let text = "server http://board:8051/\nversion 1.2 (jelly)\nmem_copy true\ndts_to_dtb false\nfeatures crc32,logs\nproto 1.2\n";
+        let capabilities = parse_capabilities(text).unwrap();
+        assert_eq!(capabilities.version, "1.2 (jelly)");
+        assert!(capabilities.mem_copy && !capabilities.dts_to_dtb);
+        assert!(capabilities.has("logs"));
+        assert_eq!(capabilities.proto_version.as_deref(), Some("1.2"));
+        assert!(parse_capabilities("mem_copy true\n").is_none());
+        assert!(parse_capabilities("version 1.2 (jelly)\nmem_copy true\n").is_none());
+
+        let regions = vec![
+            Region::new("axi_gpio_0", 0xa000_0000, 0x1_0000),
+            Region::new("udmabuf", 0, 0x40_0000)
+                .with_device("/dev/udmabuf0")
+                .with_unit(1),
+        ];
+        assert_eq!(parse_regions(&format_regions(&regions)), Some(regions));
+        assert!(parse_regions("axi_gpio_0\t0xa0000000\n").is_none());
+    }
+}
@@ -0,0 +1,243 @@
+//! Server feature detection
+//!
+//! Older servers lack some RPCs. [`Capabilities`] is probed once per
+//! connection (clones share it): RPCs answering `unimplemented` are marked
+//! missing, and newer servers may list extra features in the
+//! [`CAPABILITIES_KEY`] header of the `get_version` response. Helpers pick a
+//! fallback built from the basic RPCs where one exists, e.g.
+//! [`Accessor::mem_copy_to`](crate::Accessor::mem_copy_to) writes word by
+//! word on a server without `mem_copy_to`.
+//!
+//! Servers also report the version of their protos in
+//! [`PROTO_VERSION_KEY`]; [`JellyFpgaClient::check_compatibility`] compares
+//! it with [`PROTO_VERSION`] of the generated types. Servers predating the
+//! header are assumed compatible.
+
+use std::collections::BTreeSet;
+
+use crate::JellyFpgaClient;
+use crate::error::{incompatible_server, with_rpc};
+use crate::jelly_fpga_control::{DtsToDtbRequest, Empty, MemCopyFromRequest};
+use crate::raw::PROTO_VERSION;
+
+/// Response metadata key listing optional server features, comma separated
+pub const CAPABILITIES_KEY: &str = "x-jelly-capabilities";
+/// Response metadata key with the server's protocol version (`major.minor`)
+pub const PROTO_VERSION_KEY: &str = "x-jelly-proto-version";
+
+/// Features of the connected server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    /// Server version string
+    pub version: String,
+    /// `mem_copy_to` / `mem_copy_from` (otherwise word accesses are used)
+    pub mem_copy: bool,
+    /// `dts_to_dtb` (otherwise overlays must be uploaded as prebuilt `.dtbo`)
+    pub dts_to_dtb: bool,
+    /// Features advertised in [`CAPABILITIES_KEY`]
+    pub features: BTreeSet<String>,
+    /// Protocol version from [`PROTO_VERSION_KEY`] (`None` for servers predating it)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub proto_version: Option<String>,
+}
+
+impl Capabilities {
+    /// Whether the server advertised `feature`
+    pub fn has(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+}
+
+/// Whether protocol versions `client` and `server` can talk (same major version)
+pub fn proto_compatible(client: &str, server: &str) -> bool {
+    let major = |v: &str| v.trim().split('.').next().map(str::to_string);
+    major(client) == major(server)
+}
+
+/// Parse the [`CAPABILITIES_KEY`] header value
+pub(crate) fn parse_features(value: &str) -> BTreeSet<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Naturally aligned accesses of 1/2/4/8 bytes covering `len` bytes at `offset`
+///
+/// Returns `(position, size)` pairs with positions relative to `offset`.
+pub(crate) fn word_accesses(offset: u64, len: u64) -> Vec<(u64, u64)> {
+    let mut accesses = Vec::new();
+    let mut pos = 0;
+    while pos < len {
+        let mut size = 8;
+        while size > 1 && (!(offset + pos).is_multiple_of(size) || pos + size > len) {
+            size /= 2;
+        }
+        accesses.push((pos, size));
+        pos += size;
+    }
+    accesses
+}
+
+/// `Ok(false)` for `unimplemented`, other errors are passed through
+fn implemented<T>(response: Result<T, tonic::Status>) -> Result<bool, tonic::Status> {
+    match response {
+        Ok(_) => Ok(true),
+        Err(e) if e.code() == tonic::Code::Unimplemented => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+impl JellyFpgaClient {
+    /// Features of the server, probed on first use (done by `connect`)
+    ///
+    /// Taken from the [disk cache](crate::cache) if the client has one.
+    pub async fn capabilities(&mut self) -> Result<Capabilities, tonic::Status> {
+        let cell = self.capabilities.clone();
+        #[cfg(not(feature = "wasm"))]
+        let capabilities = cell.get_or_try_init(|| self.cached_capabilities()).await?;
+        #[cfg(feature = "wasm")]
+        let capabilities = cell.get_or_try_init(|| self.detect_capabilities()).await?;
+        Ok(capabilities.clone())
+    }
+
+    /// Probe the server without using the cached [`Capabilities`]
+    ///
+    /// The probes are harmless: `mem_copy_from` of zero bytes from an
+    /// invalid id and `dts_to_dtb` of an empty source.
+    pub async fn detect_capabilities(&mut self) -> Result<Capabilities, tonic::Status> {
+        let response = self
+            .limiter
+            .run(
+                "get_version",
+                self.client.get_version(self.request(Empty {})),
+            )
+            .await
+            .map_err(|e| with_rpc(e, "get_version"))?;
+        let header = |key| {
+            response
+                .metadata()
+                .get(key)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let features = header(CAPABILITIES_KEY)
+            .map(|v| parse_features(&v))
+            .unwrap_or_default();
+        let proto_version = header(PROTO_VERSION_KEY);
+        let version = response.into_inner().version;
+        let request = self.request(MemCopyFromRequest {
+            id: u32::MAX,
+            offset: 0,
+            size: 0,
+        });
+        let mem_copy = implemented(
+            self.limiter
+                .run("mem_copy_from", self.bulk_client().mem_copy_from(request))
+                .await,
+        )
+        .map_err(|e| with_rpc(e, "mem_copy_from"))?;
+        let request = self.request(DtsToDtbRequest { dts: String::new() });
+        let dts_to_dtb = implemented(
+            self.limiter
+                .run("dts_to_dtb", self.client.dts_to_dtb(request))
+                .await,
+        )
+        .map_err(|e| with_rpc(e, "dts_to_dtb"))?;
+        Ok(Capabilities {
+            version,
+            mem_copy,
+            dts_to_dtb,
+            features,
+            proto_version,
+        })
+    }
+
+    /// Fail if the server reports a protocol version this client cannot speak
+    ///
+    /// The generated types are only wire compatible within a major version;
+    /// otherwise calls would fail with confusing decode errors or silently
+    /// misread renumbered fields. The error converts to
+    /// [`Error::IncompatibleServer`](crate::error::Error::IncompatibleServer).
+    /// Servers not reporting a version (all releases predating
+    /// [`PROTO_VERSION_KEY`]) are accepted. Run by
+    /// [`connect_checked`](Self::connect_checked).
+    pub async fn check_compatibility(&mut self) -> Result<(), tonic::Status> {
+        match self.capabilities().await?.proto_version {
+            Some(server) if !proto_compatible(PROTO_VERSION, &server) => {
+                Err(incompatible_server(&server))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluate `f` on the capabilities without cloning them
+    pub(crate) async fn supports<F>(&mut self, f: F) -> Result<bool, tonic::Status>
+    where
+        F: FnOnce(&Capabilities) -> bool,
+    {
+        if let Some(capabilities) = self.capabilities.get() {
+            return Ok(f(capabilities));
+        }
+        Ok(f(&self.capabilities().await?))
+    }
+
+    /// `mem_copy_to` fallback: little-endian word writes
+    pub(crate) async fn write_words(
+        &mut self,
+        id: u32,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<bool, tonic::Status> {
+        for (pos, size) in word_accesses(offset, data.len() as u64) {
+            let mut word = [0u8; 8];
+            word[..size as usize].copy_from_slice(&data[pos as usize..(pos + size) as usize]);
+            if !self
+                .write_mem_u(id, offset + pos, u64::from_le_bytes(word), size)
+                .await?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// `mem_copy_from` fallback: little-endian word reads
+    pub(crate) async fn read_words(
+        &mut self,
+        id: u32,
+        offset: u64,
+        size: u64,
+    ) -> Result<(bool, Vec<u8>), tonic::Status> {
+        let mut data = Vec::with_capacity(size as usize);
+        for (pos, len) in word_accesses(offset, size) {
+            let (result, word) = self.read_mem_u(id, offset + pos, len).await?;
+            if !result {
+                return Ok((false, data));
+            }
+            data.extend_from_slice(&word.to_le_bytes()[..len as usize]);
+        }
+        Ok((true, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_accesses() {
+        assert_eq!(
+            word_accesses(0x13, 14),
+            vec![(0, 1), (1, 4), (5, 8), (13, 1)]
+        );
+        assert_eq!(word_accesses(0x10, 16), vec![(0, 8), (8, 8)]);
+        assert!(word_accesses(0x10, 0).is_empty());
+        let features = parse_features("write_batch, mem_copy_stream,");
+        assert!(features.contains("mem_copy_stream"));
+        assert_eq!(features.len(), 2);
+    }
+}
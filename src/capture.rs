@@ -0,0 +1,351 @@
+//! Camera capture pipeline (format regularizer -> FIFO -> video write-DMA -> udmabuf)
+//!
+//! Only the format regularizer and the write-DMA are driven here. The FIFO
+//! between them is a plain AXI4-Stream FIFO (`jelly2_axi4s_fifo` or the
+//! Xilinx AXI4-Stream Data FIFO) with no register interface, so there is
+//! nothing to configure or start; it only has to be present in the design.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::accessor::Accessor;
+use crate::addr::AccessSize;
+use crate::video::{VideoDmaParams, VideoWriteDma};
+
+/// Format regularizer: core ID
+pub const REG_FMTREG_CORE_ID: u64 = 0x00;
+/// Format regularizer: core version
+pub const REG_FMTREG_CORE_VERSION: u64 = 0x01;
+/// Format regularizer: control
+pub const REG_FMTREG_CTL_CONTROL: u64 = 0x04;
+/// Format regularizer: status
+pub const REG_FMTREG_CTL_STATUS: u64 = 0x05;
+/// Format regularizer: frame index
+pub const REG_FMTREG_CTL_INDEX: u64 = 0x07;
+/// Format regularizer: skip
+pub const REG_FMTREG_CTL_SKIP: u64 = 0x08;
+/// Format regularizer: frame timer enable
+pub const REG_FMTREG_CTL_FRM_TIMER_EN: u64 = 0x0a;
+/// Format regularizer: frame timeout
+pub const REG_FMTREG_CTL_FRM_TIMEOUT: u64 = 0x0b;
+/// Format regularizer: width
+pub const REG_FMTREG_PARAM_WIDTH: u64 = 0x10;
+/// Format regularizer: height
+pub const REG_FMTREG_PARAM_HEIGHT: u64 = 0x11;
+/// Format regularizer: fill value for missing pixels
+pub const REG_FMTREG_PARAM_FILL: u64 = 0x12;
+/// Format regularizer: line timeout
+pub const REG_FMTREG_PARAM_TIMEOUT: u64 = 0x13;
+
+/// Jelly video format regularizer
+///
+/// Normalizes the incoming sensor stream to a fixed frame size so that the
+/// write-DMA always sees complete frames.
+pub struct VideoFormatRegularizer {
+    regs: Accessor,
+    reg_size: AccessSize,
+}
+
+impl VideoFormatRegularizer {
+    /// Create driver
+    pub fn new(regs: Accessor) -> Self {
+        VideoFormatRegularizer {
+            regs,
+            reg_size: AccessSize::U64,
+        }
+    }
+
+    /// Set register access size (bus width, default `U64`)
+    pub fn set_reg_size(&mut self, size: AccessSize) {
+        self.reg_size = size;
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Read core ID
+    pub async fn core_id(&mut self) -> Result<u64, tonic::Status> {
+        self.regs
+            .read_reg_u(REG_FMTREG_CORE_ID, self.reg_size)
+            .await
+    }
+
+    /// Set output frame size
+    pub async fn set_size(&mut self, width: u64, height: u64) -> Result<(), tonic::Status> {
+        self.regs
+            .write_reg_u(REG_FMTREG_PARAM_WIDTH, width, self.reg_size)
+            .await?;
+        self.regs
+            .write_reg_u(REG_FMTREG_PARAM_HEIGHT, height, self.reg_size)
+            .await
+    }
+
+    /// Set fill value and line timeout (in cycles)
+    pub async fn set_fill(&mut self, fill: u64, timeout: u64) -> Result<(), tonic::Status> {
+        self.regs
+            .write_reg_u(REG_FMTREG_PARAM_FILL, fill, self.reg_size)
+            .await?;
+        self.regs
+            .write_reg_u(REG_FMTREG_PARAM_TIMEOUT, timeout, self.reg_size)
+            .await
+    }
+
+    /// Enable the frame timer, generating blank frames after `timeout` cycles without input
+    pub async fn set_frame_timer(
+        &mut self,
+        enable: bool,
+        timeout: u64,
+    ) -> Result<(), tonic::Status> {
+        self.regs
+            .write_reg_u(REG_FMTREG_CTL_FRM_TIMEOUT, timeout, self.reg_size)
+            .await?;
+        self.regs
+            .write_reg_u(REG_FMTREG_CTL_FRM_TIMER_EN, enable as u64, self.reg_size)
+            .await
+    }
+
+    /// Start (enable + update)
+    pub async fn start(&mut self) -> Result<(), tonic::Status> {
+        self.regs
+            .write_reg_u(REG_FMTREG_CTL_CONTROL, 0x03, self.reg_size)
+            .await
+    }
+
+    /// Stop
+    pub async fn stop(&mut self) -> Result<(), tonic::Status> {
+        self.regs
+            .write_reg_u(REG_FMTREG_CTL_CONTROL, 0x00, self.reg_size)
+            .await
+    }
+}
+
+/// Capture settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaptureConfig {
+    /// Frame width in pixels
+    pub width: u64,
+    /// Frame height in lines
+    pub height: u64,
+    /// Bytes per pixel as written by the DMA
+    pub pixel_bytes: u64,
+    /// Offset of the first frame inside the udmabuf
+    pub buf_offset: u64,
+    /// Timeout for each frame
+    pub frame_timeout: Duration,
+}
+
+impl CaptureConfig {
+    /// Config for `width * height` frames of `pixel_bytes` at the start of the buffer
+    pub fn new(width: u64, height: u64, pixel_bytes: u64) -> Self {
+        CaptureConfig {
+            width,
+            height,
+            pixel_bytes,
+            buf_offset: 0,
+            frame_timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Bytes per frame
+    pub fn frame_bytes(&self) -> u64 {
+        self.width * self.height * self.pixel_bytes
+    }
+}
+
+/// Capture pipeline: format regularizer + write-DMA into a udmabuf
+///
+/// The stream FIFO between the two has no registers and therefore no
+/// driver (see the module docs).
+pub struct Capture {
+    fmtreg: VideoFormatRegularizer,
+    wdma: VideoWriteDma,
+    buf: Accessor,
+    config: CaptureConfig,
+}
+
+impl Capture {
+    /// Create pipeline
+    pub fn new(
+        fmtreg: VideoFormatRegularizer,
+        wdma: VideoWriteDma,
+        buf: Accessor,
+        config: CaptureConfig,
+    ) -> Self {
+        Capture {
+            fmtreg,
+            wdma,
+            buf,
+            config,
+        }
+    }
+
+    /// Format regularizer
+    pub fn fmtreg(&mut self) -> &mut VideoFormatRegularizer {
+        &mut self.fmtreg
+    }
+
+    /// Write-DMA
+    pub fn wdma(&mut self) -> &mut VideoWriteDma {
+        &mut self.wdma
+    }
+
+    /// Configure the format regularizer and start it
+    pub async fn setup(&mut self) -> Result<(), tonic::Status> {
+        self.fmtreg
+            .set_size(self.config.width, self.config.height)
+            .await?;
+        self.fmtreg.start().await
+    }
+
+    /// Stop the pipeline
+    pub async fn shutdown(&mut self) -> Result<(), tonic::Status> {
+        self.wdma.stop().await?;
+        self.fmtreg.stop().await
+    }
+
+    async fn arm(&mut self, frames: u64, oneshot: bool) -> Result<u64, tonic::Status> {
+        let need = self.config.buf_offset + self.config.frame_bytes() * frames;
+        let size = self.buf.size().await?;
+        if need > size {
+            return Err(tonic::Status::out_of_range(format!(
+                "{} frames need {} bytes but udmabuf is {} bytes",
+                frames, need, size
+            )));
+        }
+        let addr = (self.buf.phys_addr().await? + self.config.buf_offset).0;
+        let params = VideoDmaParams::packed(
+            addr,
+            self.config.width,
+            self.config.height,
+            self.config.pixel_bytes,
+            frames,
+        );
+        let start_index = self.wdma.frame_index().await?;
+        self.wdma.set_params(&params).await?;
+        self.wdma.start(oneshot).await?;
+        Ok(start_index)
+    }
+
+    async fn wait_completed(&mut self, start_index: u64, count: u64) -> Result<(), tonic::Status> {
+        let deadline = tokio::time::Instant::now() + self.config.frame_timeout;
+        loop {
+            if self.wdma.frames_since(start_index).await? >= count {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded(format!(
+                    "timeout waiting for frame {}",
+                    count - 1
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    async fn read_frame(&mut self, n: u64) -> Result<Vec<u8>, tonic::Status> {
+        let frame_bytes = self.config.frame_bytes();
+        let offset = self.config.buf_offset + frame_bytes * n;
+        self.buf
+            .read_bytes_chunked(offset, frame_bytes, crate::framebuffer::DEFAULT_CHUNK_SIZE)
+            .await
+    }
+
+    /// Capture `frames` frames and return them
+    pub async fn capture(&mut self, frames: u64) -> Result<Vec<Vec<u8>>, tonic::Status> {
+        let start_index = self.arm(frames, true).await?;
+        let mut result = Vec::with_capacity(frames as usize);
+        for n in 0..frames {
+            self.wait_completed(start_index, n + 1).await?;
+            result.push(self.read_frame(n).await?);
+        }
+        Ok(result)
+    }
+
+    /// Capture `frames` frames, yielding each one as soon as it has been written
+    ///
+    /// The pipeline is moved into a background task; the stream ends after the
+    /// last frame or the first error.
+    pub fn into_stream(mut self, frames: u64) -> ReceiverStream<Result<Vec<u8>, tonic::Status>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(async move {
+            let start_index = match self.arm(frames, true).await {
+                Ok(index) => index,
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
+                }
+            };
+            for n in 0..frames {
+                let frame = match self.wait_completed(start_index, n + 1).await {
+                    Ok(()) => self.read_frame(n).await,
+                    Err(e) => Err(e),
+                };
+                let failed = frame.is_err();
+                if tx.send(frame).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+
+    /// Capture continuously into a ring of `buf_count` frames, yielding frames in order
+    ///
+    /// The write-DMA runs free over `buf_count` (at least 2) frame buffers
+    /// in the udmabuf. Each frame is read once the DMA reports it complete
+    /// and handed over when the consumer polls for it. A consumer slower
+    /// than the source loses frames: once the DMA has lapped the ring the
+    /// stream skips ahead to the newest complete frame, and a frame
+    /// overwritten while it was being read is dropped instead of yielded
+    /// torn. The DMA is stopped when the stream is dropped or after the
+    /// first error.
+    pub fn frame_stream(mut self, buf_count: u64) -> ReceiverStream<Result<Bytes, tonic::Status>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(async move {
+            let result = self.run_ring(buf_count, &tx).await;
+            let _ = self.wdma.stop().await;
+            if let Err(e) = result {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+
+    async fn run_ring(
+        &mut self,
+        buf_count: u64,
+        tx: &tokio::sync::mpsc::Sender<Result<Bytes, tonic::Status>>,
+    ) -> Result<(), tonic::Status> {
+        if buf_count < 2 {
+            return Err(tonic::Status::invalid_argument(
+                "frame_stream needs at least 2 buffers",
+            ));
+        }
+        let start_index = self.arm(buf_count, false).await?;
+        // frame n (counted from start) is written to slot n % buf_count and
+        // stays intact while fewer than buf_count later frames have completed
+        let mut next = 0;
+        loop {
+            self.wait_completed(start_index, next + 1).await?;
+            let completed = self.wdma.frames_since(start_index).await?;
+            if completed - next >= buf_count {
+                next = completed - 1;
+            }
+            let frame = self.read_frame(next % buf_count).await?;
+            let completed = self.wdma.frames_since(start_index).await?;
+            let overwritten = completed - next >= buf_count;
+            next += 1;
+            if overwritten {
+                continue;
+            }
+            if tx.send(Ok(Bytes::from(frame))).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Verified `mem_copy` transfers
+//!
+//! With [`set_transfer_verify`](JellyFpgaClient::set_transfer_verify),
+//! every `mem_copy_to` / `mem_copy_from` (and so every chunk of the bulk,
+//! framebuffer and weight helpers) is checked for silent corruption.
+//!
+//! A server advertising [`CHECKSUM_FEATURE`] answers a request carrying
+//! [`CHECKSUM_KEY`] with the CRC32 (IEEE, as hex) of the bytes it wrote or
+//! read in the same key of the response metadata; the client compares it
+//! with its own CRC32 of the chunk. Other servers are verified by reading
+//! written chunks back; reads cannot be checked without the checksum.
+
+use crate::JellyFpgaClient;
+
+/// Capability advertised by servers that return CRC32 checksums
+pub const CHECKSUM_FEATURE: &str = "crc32";
+/// Request/response metadata key asking for / carrying the CRC32 of a transfer
+pub const CHECKSUM_KEY: &str = "x-jelly-crc32";
+
+/// Verification of `mem_copy` transfers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferVerify {
+    /// No verification
+    #[default]
+    Off,
+    /// Read written chunks back and compare
+    ReadBack,
+    /// Server checksums, falling back to read-back for writes
+    Checksum,
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32 (IEEE 802.3, as used by zlib)
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// CRC32 in a response, if the server sent one
+pub(crate) fn response_crc(metadata: &tonic::metadata::MetadataMap) -> Option<u32> {
+    let value = metadata.get(CHECKSUM_KEY)?.to_str().ok()?;
+    u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+/// `data_loss` for a transfer whose check failed
+fn corrupted(op: &str, id: u32, offset: u64, len: usize, detail: String) -> tonic::Status {
+    tonic::Status::data_loss(format!(
+        "{} corrupted (id={} offset=0x{:x} len={}): {}",
+        op, id, offset, len, detail
+    ))
+}
+
+impl JellyFpgaClient {
+    /// Verify `mem_copy` transfers (clones inherit the setting)
+    pub fn set_transfer_verify(&mut self, verify: TransferVerify) {
+        self.verify = verify;
+    }
+
+    /// Current transfer verification
+    pub fn transfer_verify(&self) -> TransferVerify {
+        self.verify
+    }
+
+    /// Whether to ask the server for a checksum of the next transfer
+    pub(crate) async fn wants_checksum(&mut self) -> Result<bool, tonic::Status> {
+        Ok(self.verify == TransferVerify::Checksum
+            && self.supports(|c| c.has(CHECKSUM_FEATURE)).await?)
+    }
+
+    /// Check a completed `mem_copy_to` of `data`
+    pub(crate) async fn verify_copy_to(
+        &mut self,
+        id: u32,
+        offset: u64,
+        data: &[u8],
+        server_crc: Option<u32>,
+    ) -> Result<(), tonic::Status> {
+        if let Some(server_crc) = server_crc {
+            let crc = crc32(data);
+            if crc != server_crc {
+                return Err(corrupted(
+                    "mem_copy_to",
+                    id,
+                    offset,
+                    data.len(),
+                    format!("crc32 0x{:08x}, server 0x{:08x}", crc, server_crc),
+                ));
+            }
+            return Ok(());
+        }
+        let (result, back) = self.mem_copy_from(id, offset, data.len() as u64).await?;
+        crate::accessor::check(result, "mem_copy_to read-back")?;
+        match back.iter().zip(data).position(|(a, b)| a != b) {
+            None if back.len() == data.len() => Ok(()),
+            position => Err(corrupted(
+                "mem_copy_to",
+                id,
+                offset,
+                data.len(),
+                format!(
+                    "read-back differs at byte {}",
+                    position.unwrap_or(back.len().min(data.len()))
+                ),
+            )),
+        }
+    }
+
+    /// Check the `data` of a completed `mem_copy_from`
+    pub(crate) fn verify_copy_from(
+        &self,
+        id: u32,
+        offset: u64,
+        data: &[u8],
+        server_crc: Option<u32>,
+    ) -> Result<(), tonic::Status> {
+        match server_crc {
+            Some(server_crc) if crc32(data) != server_crc => Err(corrupted(
+                "mem_copy_from",
+                id,
+                offset,
+                data.len(),
+                format!("crc32 0x{:08x}, server 0x{:08x}", crc32(data), server_crc),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        metadata.insert(CHECKSUM_KEY, "cbf43926".parse().unwrap());
+        assert_eq!(response_crc(&metadata), Some(0xCBF4_3926));
+    }
+}
@@ -0,0 +1,187 @@
+//! Host / fabric clock correlation
+//!
+//! [`JellyFpgaClient::estimate_clock_offset`] reads a free-running hardware
+//! counter several times, pairs each value with the host time halfway
+//! through the read (compensating the round trip) and fits a line through
+//! the samples with the shortest round trips. The resulting [`ClockModel`]
+//! maps counter values of captured hardware events to host wall-clock time
+//! and back. Sampling over a longer interval estimates the skew better.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::JellyFpgaClient;
+use crate::accessor::check;
+
+/// Default number of counter reads
+pub const DEFAULT_SAMPLES: usize = 16;
+/// Default pause between counter reads
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Linear map between a hardware counter and host time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockModel {
+    /// Nominal counter frequency in Hz
+    pub freq: f64,
+    /// Counter value of the reference sample
+    pub base_counter: u64,
+    /// Host time of the reference sample
+    pub base_time: SystemTime,
+    /// Host seconds of counter `base_counter` relative to `base_time`
+    pub offset: f64,
+    /// Host seconds per nominal counter second (1.0 = no drift)
+    pub skew: f64,
+    /// Shortest round trip of a counter read
+    pub min_rtt: Duration,
+    /// Largest deviation of a used sample from the fit, in seconds
+    pub residual: f64,
+}
+
+impl ClockModel {
+    /// Host time at which the counter read `counter`
+    pub fn to_host(&self, counter: u64) -> SystemTime {
+        let ticks = counter as i128 - self.base_counter as i128;
+        let secs = self.offset + self.skew * ticks as f64 / self.freq;
+        if secs >= 0.0 {
+            self.base_time + Duration::from_secs_f64(secs)
+        } else {
+            self.base_time - Duration::from_secs_f64(-secs)
+        }
+    }
+
+    /// Counter value at host time `time`
+    pub fn to_counter(&self, time: SystemTime) -> u64 {
+        let secs = match time.duration_since(self.base_time) {
+            Ok(d) => d.as_secs_f64(),
+            Err(e) => -e.duration().as_secs_f64(),
+        };
+        let ticks = (secs - self.offset) / self.skew * self.freq;
+        (self.base_counter as i128 + ticks.round() as i128).max(0) as u64
+    }
+
+    /// Counter drift against the host clock in parts per million
+    pub fn skew_ppm(&self) -> f64 {
+        (self.skew - 1.0) * 1e6
+    }
+}
+
+/// Least-squares line `y = a + b x`; `b` is 1 with fewer than two distinct `x`
+fn fit(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mx = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let my = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mx).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mx) * (p.1 - my)).sum();
+    let b = if sxx > 0.0 { sxy / sxx } else { 1.0 };
+    (my - b * mx, b)
+}
+
+impl JellyFpgaClient {
+    /// Model of the counter in 64-bit register `counter_reg` of `id`, ticking at `freq` Hz
+    ///
+    /// Takes [`DEFAULT_SAMPLES`] reads [`DEFAULT_SAMPLE_INTERVAL`] apart.
+    pub async fn estimate_clock_offset(
+        &mut self,
+        id: u32,
+        counter_reg: u64,
+        freq: f64,
+    ) -> Result<ClockModel, tonic::Status> {
+        self.estimate_clock_offset_with(
+            id,
+            counter_reg,
+            freq,
+            DEFAULT_SAMPLES,
+            DEFAULT_SAMPLE_INTERVAL,
+        )
+        .await
+    }
+
+    /// [`estimate_clock_offset`](Self::estimate_clock_offset) with `samples` reads `interval` apart
+    ///
+    /// The half of the samples with the shortest round trips is fitted.
+    pub async fn estimate_clock_offset_with(
+        &mut self,
+        id: u32,
+        counter_reg: u64,
+        freq: f64,
+        samples: usize,
+        interval: Duration,
+    ) -> Result<ClockModel, tonic::Status> {
+        if freq <= 0.0 || samples < 2 {
+            return Err(tonic::Status::invalid_argument(
+                "clock estimation needs freq > 0 and at least 2 samples",
+            ));
+        }
+        let base_time = SystemTime::now();
+        let origin = Instant::now();
+        // (counter, host seconds since origin at the middle of the read, rtt)
+        let mut raw = Vec::with_capacity(samples);
+        for i in 0..samples {
+            if i > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            let sent = Instant::now();
+            let (result, counter) = self.read_reg_u(id, counter_reg, 8).await?;
+            let rtt = sent.elapsed();
+            check(result, "read_reg_u")?;
+            let mid = (sent - origin).as_secs_f64() + rtt.as_secs_f64() / 2.0;
+            raw.push((counter, mid, rtt));
+        }
+        let base_counter = raw[0].0;
+        raw.sort_by_key(|s| s.2);
+        raw.truncate(samples.div_ceil(2).max(2));
+        let points: Vec<(f64, f64)> = raw
+            .iter()
+            .map(|&(c, t, _)| ((c as i128 - base_counter as i128) as f64 / freq, t))
+            .collect();
+        let (offset, skew) = fit(&points);
+        let residual = points
+            .iter()
+            .map(|&(x, y)| (y - offset - skew * x).abs())
+            .fold(0.0, f64::max);
+        Ok(ClockModel {
+            freq,
+            base_counter,
+            base_time,
+            offset,
+            skew,
+            min_rtt: raw[0].2,
+            residual,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_model() {
+        // counter running 50 ppm fast, read 0.5 s after the reference time
+        let points: Vec<(f64, f64)> = (0..8)
+            .map(|i| {
+                let x = i as f64 * 0.01;
+                (x, 0.5 + x / 1.00005)
+            })
+            .collect();
+        let (offset, skew) = fit(&points);
+        assert!((offset - 0.5).abs() < 1e-9);
+        let model = ClockModel {
+            freq: 100e6,
+            base_counter: 200_000_000,
+            base_time: SystemTime::UNIX_EPOCH + Duration::from_secs(1000),
+            offset,
+            skew,
+            min_rtt: Duration::ZERO,
+            residual: 0.0,
+        };
+        assert!((model.skew_ppm() + 50.0).abs() < 0.01);
+        let t = model.to_host(200_000_000);
+        let secs = t
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        assert!((secs - 1000.5).abs() < 1e-6);
+        assert_eq!(model.to_counter(t), 200_000_000);
+        assert!(model.to_host(0) < model.base_time);
+    }
+}
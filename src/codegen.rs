@@ -0,0 +1,434 @@
+//! Rust driver generation from register maps
+//!
+//! [`generate_driver`] turns a [`RegisterMap`] into Rust source for a
+//! driver struct wrapping an [`Accessor`](crate::Accessor): one constant
+//! and `read_<reg>` / `write_<reg>` method per register (no read for
+//! write-only ones) and per bitfield, typed by register size and field
+//! width. Register names are then checked by the compiler instead of looked
+//! up at run time. [`generate_personality`] does the same for every region
+//! of a [`Personality`] and adds a struct taking the devices from a
+//! [`PersonalityHandle`](crate::personality::PersonalityHandle).
+//!
+//! From a build script:
+//!
+//! ```no_run
+//! // build.rs
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("gpio.rs");
+//! jelly_fpga_client::codegen::generate_driver_file("gpio.regs", "Gpio", &out).unwrap();
+//! println!("cargo::rerun-if-changed=gpio.regs");
+//! ```
+//!
+//! and `include!(concat!(env!("OUT_DIR"), "/gpio.rs"));` in the crate. The
+//! generated code uses `jelly_fpga_client` and `tonic` by path. The
+//! `jelly-fpga codegen` command writes the same source.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::field::Field;
+use crate::personality::Personality;
+use crate::regmap::{RegAccess, RegisterDef, RegisterMap};
+
+const HEADER: &str = "// Generated by jelly-fpga-client codegen. Do not edit.\n";
+
+/// Words of an identifier, split at non-alphanumerics and lower-to-upper changes
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        } else if c.is_ascii_uppercase() && prev_lower {
+            words.push(std::mem::replace(&mut word, c.to_string()));
+        } else {
+            word.push(c);
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Prefix identifiers starting with a digit (or empty)
+fn ident(s: String, prefix: &str) -> String {
+    if s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        s
+    } else {
+        format!("{}{}", prefix, s)
+    }
+}
+
+fn snake(name: &str) -> String {
+    ident(words(name).join("_").to_lowercase(), "r")
+}
+
+fn upper(name: &str) -> String {
+    ident(words(name).join("_").to_uppercase(), "R")
+}
+
+fn camel(name: &str) -> String {
+    let camel = words(name)
+        .iter()
+        .map(|w| {
+            let lower = w.to_lowercase();
+            let mut chars = lower.chars();
+            chars.next().map_or_else(String::new, |c| {
+                c.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect();
+    ident(camel, "R")
+}
+
+fn reg_type(size: u64) -> &'static str {
+    match size {
+        1 => "u8",
+        2 => "u16",
+        8 => "u64",
+        _ => "u32",
+    }
+}
+
+fn access_size(size: u64) -> &'static str {
+    match size {
+        1 => "U8",
+        2 => "U16",
+        8 => "U64",
+        _ => "U32",
+    }
+}
+
+fn field_type(width: u32) -> &'static str {
+    match width {
+        1 => "bool",
+        2..=8 => "u8",
+        9..=16 => "u16",
+        17..=32 => "u32",
+        _ => "u64",
+    }
+}
+
+/// Builder expression recreating `map`
+fn regmap_expr(map: &RegisterMap) -> String {
+    let mut s = String::from("::jelly_fpga_client::regmap::RegisterMap::new()");
+    for r in &map.registers {
+        let _ = write!(
+            s,
+            "\n            .register({:?}, 0x{:x}, {})",
+            r.name, r.reg, r.size
+        );
+        if r.access != RegAccess::Volatile {
+            let _ = write!(
+                s,
+                "\n            .access(::jelly_fpga_client::regmap::RegAccess::{:?})",
+                r.access
+            );
+        }
+        for (name, f) in &r.fields {
+            let _ = write!(
+                s,
+                "\n            .field({:?}, {}, {})",
+                name, f.shift, f.width
+            );
+        }
+    }
+    s
+}
+
+fn register_methods(s: &mut String, r: &RegisterDef) {
+    let (name, ty, size) = (snake(&r.name), reg_type(r.size), access_size(r.size));
+    let konst = upper(&r.name);
+    let (open, close, value) = match ty {
+        "u64" => ("", String::new(), "value"),
+        _ => ("Ok(", format!("? as {})", ty), "u64::from(value)"),
+    };
+    if r.access != RegAccess::WriteOnly {
+        let _ = write!(
+            s,
+            "
+    /// Read `{reg}`
+    pub async fn read_{name}(&mut self) -> Result<{ty}, tonic::Status> {{
+        {open}self
+            .accessor
+            .read_reg_u(Self::{konst}, ::jelly_fpga_client::AccessSize::{size})
+            .await{close}
+    }}
+",
+            reg = r.name
+        );
+    }
+    let _ = write!(
+        s,
+        "
+    /// Write `{reg}`
+    pub async fn write_{name}(&mut self, value: {ty}) -> Result<(), tonic::Status> {{
+        self.accessor
+            .write_reg_u(Self::{konst}, {value}, ::jelly_fpga_client::AccessSize::{size})
+            .await
+    }}
+",
+        reg = r.name
+    );
+    for (field, f) in &r.fields {
+        field_methods(s, r, field, f);
+    }
+}
+
+fn field_methods(s: &mut String, r: &RegisterDef, field: &str, f: &Field) {
+    let name = format!("{}_{}", snake(&r.name), snake(field));
+    let konst = format!("{}_{}", upper(&r.name), upper(field));
+    let ty = field_type(f.width);
+    let (open, close, value) = match ty {
+        "bool" => ("Ok(", "? != 0)".to_string(), "u64::from(value)"),
+        "u64" => ("", String::new(), "value"),
+        _ => ("Ok(", format!("? as {})", ty), "u64::from(value)"),
+    };
+    if r.access != RegAccess::WriteOnly {
+        let _ = write!(
+            s,
+            "
+    /// Read `{reg}.{field}`
+    pub async fn read_{name}(&mut self) -> Result<{ty}, tonic::Status> {{
+        {open}self.accessor.read_field(&Self::{konst}).await{close}
+    }}
+",
+            reg = r.name
+        );
+    }
+    let _ = write!(
+        s,
+        "
+    /// Write `{reg}.{field}` (read-modify-write)
+    pub async fn write_{name}(&mut self, value: {ty}) -> Result<(), tonic::Status> {{
+        self.accessor.write_field(&Self::{konst}, {value}).await
+    }}
+",
+        reg = r.name
+    );
+}
+
+/// Driver struct `name` for `map`, without the file header
+fn driver(name: &str, map: &RegisterMap) -> String {
+    let ty = camel(name);
+    let mut s = format!(
+        "
+/// Registers of `{name}`
+#[derive(Clone)]
+pub struct {ty} {{
+    /// Accessor of the IP (with the register map as shadow)
+    pub accessor: ::jelly_fpga_client::Accessor,
+}}
+
+impl {ty} {{
+"
+    );
+    for r in &map.registers {
+        let _ = writeln!(s, "    /// Register `{}`", r.name);
+        let _ = writeln!(s, "    pub const {}: u64 = 0x{:x};", upper(&r.name), r.reg);
+        for (field, f) in &r.fields {
+            let _ = writeln!(s, "    /// Field `{}.{}`", r.name, field);
+            let _ = writeln!(
+                s,
+                "    pub const {}_{}: ::jelly_fpga_client::field::Field =
+        ::jelly_fpga_client::field::Field::new(0x{:x}, {}, {});",
+                upper(&r.name),
+                upper(field),
+                f.reg,
+                f.shift,
+                f.width
+            );
+        }
+    }
+    let _ = write!(
+        s,
+        "
+    /// Wrap `accessor`, setting the register map as its shadow
+    pub fn new(mut accessor: ::jelly_fpga_client::Accessor) -> Self {{
+        accessor.set_shadow(&Self::regmap());
+        {ty} {{ accessor }}
+    }}
+
+    /// Register map the driver was generated from
+    pub fn regmap() -> ::jelly_fpga_client::regmap::RegisterMap {{
+        {}
+    }}
+",
+        regmap_expr(map)
+    );
+    for r in &map.registers {
+        register_methods(&mut s, r);
+    }
+    s.push_str("}\n");
+    s
+}
+
+/// Rust source of driver struct `name` for `map`
+pub fn generate_driver(name: &str, map: &RegisterMap) -> String {
+    format!("{}{}", HEADER, driver(name, map))
+}
+
+/// Rust source of the drivers of `personality`
+///
+/// `regmaps` holds the register map of each region by region name; regions
+/// without one are plain accessors in the personality struct.
+pub fn generate_personality(
+    personality: &Personality,
+    regmaps: &BTreeMap<String, RegisterMap>,
+) -> String {
+    let mut s = HEADER.to_string();
+    for (region, map) in regmaps {
+        s.push_str(&driver(region, map));
+    }
+    let ty = camel(&personality.name);
+    let _ = write!(
+        s,
+        "
+/// Devices of personality `{}`
+pub struct {ty} {{
+",
+        personality.name
+    );
+    for region in &personality.regions {
+        let field_ty = match regmaps.contains_key(&region.name) {
+            true => camel(&region.name),
+            false => "::jelly_fpga_client::Accessor".to_string(),
+        };
+        let _ = writeln!(s, "    /// Region `{}`", region.name);
+        let _ = writeln!(s, "    pub {}: {},", snake(&region.name), field_ty);
+    }
+    let _ = write!(
+        s,
+        "}}
+
+impl {ty} {{
+    /// Take the devices of an opened personality
+    pub fn new(
+        handle: &mut ::jelly_fpga_client::personality::PersonalityHandle,
+    ) -> Result<Self, tonic::Status> {{
+        Ok({ty} {{
+"
+    );
+    for region in &personality.regions {
+        let accessor = format!("handle.accessor({:?})?.clone()", region.name);
+        let value = match regmaps.contains_key(&region.name) {
+            true => format!("{}::new({})", camel(&region.name), accessor),
+            false => accessor,
+        };
+        let _ = writeln!(s, "            {}: {},", snake(&region.name), value);
+    }
+    s.push_str("        })\n    }\n}\n");
+    s
+}
+
+/// Read a register map file; IP-XACT `.xml` files (`ipxact` feature) use `unit`
+fn read_regmap(path: &Path, unit: u64) -> Result<RegisterMap, tonic::Status> {
+    #[cfg(not(feature = "ipxact"))]
+    let _ = unit;
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        tonic::Status::internal(format!("Failed to read file {}: {}", path.display(), e))
+    })?;
+    if path.extension().is_some_and(|e| e == "xml") {
+        #[cfg(feature = "ipxact")]
+        return RegisterMap::parse_ipxact(&text, unit);
+        #[cfg(not(feature = "ipxact"))]
+        return Err(tonic::Status::unimplemented(
+            "reading IP-XACT register maps requires the ipxact feature",
+        ));
+    }
+    RegisterMap::parse(&text).map_err(|e| {
+        tonic::Status::invalid_argument(format!("{}: {}", path.display(), e.message()))
+    })
+}
+
+fn write_source(out: &Path, source: &str) -> Result<(), tonic::Status> {
+    std::fs::write(out, source).map_err(|e| {
+        tonic::Status::internal(format!("Failed to write file {}: {}", out.display(), e))
+    })
+}
+
+/// Generate driver `name` from the register map file `map_path` into `out`
+///
+/// For build scripts; IP-XACT files are read with 4-byte registers.
+pub fn generate_driver_file(
+    map_path: impl AsRef<Path>,
+    name: &str,
+    out: impl AsRef<Path>,
+) -> Result<(), tonic::Status> {
+    let map = read_regmap(map_path.as_ref(), 4)?;
+    write_source(out.as_ref(), &generate_driver(name, &map))
+}
+
+/// Read the register maps of `personality` (paths as given, see [`Personality::with_base_dir`])
+pub fn personality_regmaps(
+    personality: &Personality,
+) -> Result<BTreeMap<String, RegisterMap>, tonic::Status> {
+    let mut regmaps = BTreeMap::new();
+    for file in &personality.regmaps {
+        let region = personality
+            .regions
+            .iter()
+            .find(|r| r.name == file.region)
+            .ok_or_else(|| {
+                tonic::Status::invalid_argument(format!(
+                    "register map for unknown region {:?}",
+                    file.region
+                ))
+            })?;
+        regmaps.insert(
+            file.region.clone(),
+            read_regmap(Path::new(&file.path), region.unit)?,
+        );
+    }
+    Ok(regmaps)
+}
+
+/// Generate the drivers of the personality TOML file `path` into `out`
+#[cfg(feature = "personality")]
+pub fn generate_personality_file(
+    path: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+) -> Result<(), tonic::Status> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        tonic::Status::internal(format!("Failed to read file {}: {}", path.display(), e))
+    })?;
+    let personality: Personality = toml::from_str(&text)
+        .map_err(|e| tonic::Status::invalid_argument(format!("{}: {}", path.display(), e)))?;
+    let personality = personality.with_base_dir(path.parent().unwrap_or(Path::new(".")));
+    let regmaps = personality_regmaps(&personality)?;
+    write_source(out.as_ref(), &generate_personality(&personality, &regmaps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_driver() {
+        assert_eq!(snake("IntStatus"), "int_status");
+        assert_eq!(upper("rx-fifo"), "RX_FIFO");
+        assert_eq!(camel("kv260_camera"), "Kv260Camera");
+        assert_eq!(snake("0ctrl"), "r0ctrl");
+        let map = RegisterMap::new()
+            .register("CONTROL", 0, 4)
+            .field("ENABLE", 0, 1)
+            .field("MODE", 1, 3)
+            .register("DIV", 2, 2)
+            .access(RegAccess::WriteOnly);
+        let source = generate_driver("axi_gpio", &map);
+        assert!(source.contains("pub struct AxiGpio {"));
+        assert!(source.contains("pub const CONTROL_MODE: ::jelly_fpga_client::field::Field"));
+        assert!(source.contains("pub async fn read_control(&mut self) -> Result<u32,"));
+        assert!(source.contains("pub async fn read_control_enable(&mut self) -> Result<bool,"));
+        assert!(source.contains("pub async fn write_control_mode(&mut self, value: u8)"));
+        assert!(source.contains("pub async fn write_div(&mut self, value: u16)"));
+        assert!(!source.contains("read_div"));
+        assert!(source.contains(".access(::jelly_fpga_client::regmap::RegAccess::WriteOnly)"));
+    }
+}
@@ -0,0 +1,207 @@
+//! Transactional register configuration
+//!
+//! [`apply_config`] writes a set of registers as one change: the previous
+//! values are read first, every written register is read back and
+//! compared, and if a write or verification fails the registers written so
+//! far are restored in reverse order. A successful apply returns a
+//! [`ConfigCommit`] that can be appended to a commit log file as one JSON
+//! line.
+//!
+//! The accessor's ordering queue is held for the whole transaction, so
+//! clones of the accessor cannot interleave with it (other clients can).
+//! With a shadow ([`Accessor::set_shadow`]) the reads go through it, so
+//! static registers are verified against the cached value.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Accessor;
+use crate::addr::AccessSize;
+use crate::regmap::{RegAccess, RegisterMap};
+
+/// One register of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigChange {
+    /// Register index
+    pub reg: u64,
+    /// Access size in bytes
+    pub size: u64,
+    /// Value before the transaction (`None` for a write-only register never written)
+    pub old: Option<u64>,
+    /// Value written
+    pub new: u64,
+    /// Whether the value was read back and compared
+    pub verified: bool,
+}
+
+/// Record of an applied configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigCommit {
+    /// Device id of the accessor
+    pub id: u32,
+    /// Commit time in seconds since the Unix epoch
+    pub committed_at: u64,
+    /// Registers in write order
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigCommit {
+    /// JSON object on one line
+    pub fn to_json(&self) -> String {
+        let changes: Vec<String> = self
+            .changes
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{\"reg\":{},\"size\":{},\"old\":{},\"new\":{},\"verified\":{}}}",
+                    c.reg,
+                    c.size,
+                    c.old.map_or_else(|| "null".to_string(), |v| v.to_string()),
+                    c.new,
+                    c.verified
+                )
+            })
+            .collect();
+        format!(
+            "{{\"id\":{},\"committed_at\":{},\"changes\":[{}]}}",
+            self.id,
+            self.committed_at,
+            changes.join(",")
+        )
+    }
+
+    /// Append [`to_json`](Self::to_json) as a line to the commit log `path`
+    pub async fn append_to(&self, path: &str) -> Result<(), tonic::Status> {
+        crate::fs::append(path, format!("{}\n", self.to_json()).as_bytes()).await
+    }
+}
+
+impl fmt::Display for ConfigCommit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "commit id={} at {}", self.id, self.committed_at)?;
+        for c in &self.changes {
+            let old = c
+                .old
+                .map_or_else(|| "?".to_string(), |v| format!("0x{:x}", v));
+            let verified = if c.verified { "" } else { " (not verified)" };
+            writeln!(
+                f,
+                "  reg 0x{:x}: {} -> 0x{:x}{}",
+                c.reg, old, c.new, verified
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `writes` (register, value) of 32-bit registers as one transaction
+///
+/// Every register is verified. On failure the written registers are
+/// restored and the error (`aborted`) says which register failed and
+/// whether the restore succeeded.
+pub async fn apply_config(
+    accessor: &mut Accessor,
+    writes: &[(u64, u64)],
+) -> Result<ConfigCommit, tonic::Status> {
+    let changes = writes
+        .iter()
+        .map(|&(reg, new)| ConfigChange {
+            reg,
+            size: AccessSize::U32.bytes(),
+            old: None,
+            new,
+            verified: true,
+        })
+        .collect();
+    apply(accessor, changes).await
+}
+
+/// [`apply_config`] with registers named in `map`
+///
+/// Sizes come from the map; write-only registers are not verified and are
+/// restored from the accessor's shadow value when one is known (see
+/// [`Accessor::set_shadow`]).
+pub async fn apply_config_map(
+    accessor: &mut Accessor,
+    map: &RegisterMap,
+    writes: &[(&str, u64)],
+) -> Result<ConfigCommit, tonic::Status> {
+    let mut changes = Vec::with_capacity(writes.len());
+    for &(name, new) in writes {
+        let def = map
+            .get(name)
+            .ok_or_else(|| tonic::Status::not_found(format!("unknown register {:?}", name)))?;
+        changes.push(ConfigChange {
+            reg: def.reg,
+            size: def.size,
+            old: None,
+            new,
+            verified: def.access != RegAccess::WriteOnly,
+        });
+    }
+    apply(accessor, changes).await
+}
+
+async fn apply(
+    accessor: &mut Accessor,
+    mut changes: Vec<ConfigChange>,
+) -> Result<ConfigCommit, tonic::Status> {
+    let mut locked = accessor.lock().await;
+    for c in &mut changes {
+        c.old = if c.verified {
+            Some(locked.read_reg_u_raw(c.reg, c.size).await?)
+        } else {
+            locked.shadow_value(c.reg)
+        };
+    }
+    for (i, c) in changes.iter().enumerate() {
+        let failure = match locked.write_reg_u_raw(c.reg, c.new, c.size).await {
+            Err(e) => Some(format!("write failed: {}", e.message())),
+            Ok(()) if c.verified => match locked.read_reg_u_raw(c.reg, c.size).await {
+                Ok(value) if value == c.new => None,
+                Ok(value) => Some(format!(
+                    "read back 0x{:x} after writing 0x{:x}",
+                    value, c.new
+                )),
+                Err(e) => Some(format!("verify failed: {}", e.message())),
+            },
+            Ok(()) => None,
+        };
+        if let Some(failure) = failure {
+            let restore = restore(&mut locked, &changes[..=i]).await;
+            return Err(tonic::Status::aborted(format!(
+                "apply_config: reg 0x{:x} {}; {}",
+                c.reg, failure, restore
+            )));
+        }
+    }
+    Ok(ConfigCommit {
+        id: locked.id(),
+        committed_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        changes,
+    })
+}
+
+/// Write back the old values of `changes` in reverse order; describes the outcome
+async fn restore(accessor: &mut Accessor, changes: &[ConfigChange]) -> String {
+    let mut failed = Vec::new();
+    for c in changes.iter().rev() {
+        let restored = match c.old {
+            Some(old) => accessor.write_reg_u_raw(c.reg, old, c.size).await.is_ok(),
+            None => false,
+        };
+        if !restored {
+            failed.push(format!("0x{:x}", c.reg));
+        }
+    }
+    if failed.is_empty() {
+        format!("restored {} registers", changes.len())
+    } else {
+        format!("could not restore registers {}", failed.join(", "))
+    }
+}
@@ -0,0 +1,135 @@
+//! Device RPCs as a trait, served by the server or by the board itself
+//!
+//! [`FpgaControl`] has the device RPCs of the generated client (opening
+//! and closing devices, register and memory access, `mem_copy_to` /
+//! `mem_copy_from`) with the same request and response messages. The
+//! generated client implements it by sending the RPC and, with the `local`
+//! feature, [`LocalDevices`](crate::local::LocalDevices) by accessing the
+//! devices directly. The device methods of [`JellyFpgaClient`] run on
+//! whichever backend the client uses, so accessors, drivers and helpers
+//! built on them work unchanged on either.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tonic::{Request, Response, Status};
+
+use crate::JellyFpgaClient;
+#[cfg(feature = "local")]
+use crate::local::LocalDevices;
+use crate::raw::{self, *};
+
+macro_rules! fpga_control {
+    ($($rpc:ident($req:ident) -> $resp:ident;)*) => {
+        /// Device RPCs of the Jelly FPGA control service
+        ///
+        /// Futures are not required to be `Send`, since the grpc-web
+        /// transport of the `wasm` feature is not.
+        pub trait FpgaControl {
+            $(
+                #[doc = concat!("`", stringify!($rpc), "` RPC")]
+                fn $rpc(
+                    &self,
+                    request: Request<$req>,
+                ) -> impl Future<Output = Result<Response<$resp>, Status>>;
+            )*
+        }
+
+        /// Sends the RPC on a clone of the client (clones share the connection)
+        impl FpgaControl for raw::RawClient {
+            $(
+                fn $rpc(
+                    &self,
+                    request: Request<$req>,
+                ) -> impl Future<Output = Result<Response<$resp>, Status>> {
+                    let mut client = self.clone();
+                    async move { raw::JellyFpgaControlClient::$rpc(&mut client, request).await }
+                }
+            )*
+        }
+
+        impl FpgaControl for Backend<'_> {
+            $(
+                fn $rpc(
+                    &self,
+                    request: Request<$req>,
+                ) -> impl Future<Output = Result<Response<$resp>, Status>> {
+                    async move {
+                        match self {
+                            Backend::Remote(client) => FpgaControl::$rpc(*client, request).await,
+                            #[cfg(feature = "local")]
+                            Backend::Local(local) => FpgaControl::$rpc(*local, request).await,
+                        }
+                    }
+                }
+            )*
+        }
+    };
+}
+
+fpga_control! {
+    open_mmap(OpenMmapRequest) -> OpenResponse;
+    open_uio(OpenUioRequest) -> OpenResponse;
+    open_udmabuf(OpenUdmabufRequest) -> OpenResponse;
+    close(CloseRequest) -> BoolResponse;
+    subclone(SubcloneRequest) -> OpenResponse;
+    get_addr(GetAddrRequest) -> GetAddrResponse;
+    get_size(GetSizeRequest) -> GetSizeResponse;
+    get_phys_addr(GetPhysAddrRequest) -> GetPhysAddrResponse;
+    write_mem_u(WriteMemURequest) -> BoolResponse;
+    write_mem_i(WriteMemIRequest) -> BoolResponse;
+    read_mem_u(ReadMemRequest) -> ReadUResponse;
+    read_mem_i(ReadMemRequest) -> ReadIResponse;
+    write_reg_u(WriteRegURequest) -> BoolResponse;
+    write_reg_i(WriteRegIRequest) -> BoolResponse;
+    read_reg_u(ReadRegRequest) -> ReadUResponse;
+    read_reg_i(ReadRegRequest) -> ReadIResponse;
+    write_mem_f32(WriteMemF32Request) -> BoolResponse;
+    write_mem_f64(WriteMemF64Request) -> BoolResponse;
+    read_mem_f32(ReadMemRequest) -> ReadF32Response;
+    read_mem_f64(ReadMemRequest) -> ReadF64Response;
+    write_reg_f32(WriteRegF32Request) -> BoolResponse;
+    write_reg_f64(WriteRegF64Request) -> BoolResponse;
+    read_reg_f32(ReadRegRequest) -> ReadF32Response;
+    read_reg_f64(ReadRegRequest) -> ReadF64Response;
+    mem_copy_to(MemCopyToRequest) -> BoolResponse;
+    mem_copy_from(MemCopyFromRequest) -> MemCopyFromResponse;
+}
+
+/// Backend a client's device RPCs run on
+#[derive(Clone, Copy)]
+pub(crate) enum Backend<'a> {
+    /// The server, through a generated client
+    Remote(&'a raw::RawClient),
+    /// The devices of the board the client runs on
+    #[cfg(feature = "local")]
+    Local(&'a LocalDevices),
+}
+
+impl JellyFpgaClient {
+    /// Backend of the device RPCs: the local devices if enabled, else the server
+    pub(crate) fn backend(&self) -> Backend<'_> {
+        #[cfg(feature = "local")]
+        if let Some(local) = &self.local {
+            return Backend::Local(local);
+        }
+        Backend::Remote(&self.client)
+    }
+
+    /// Wait up to `timeout` for an interrupt of UIO device `id`; `false` on timeout
+    ///
+    /// Enables the interrupt first (it is disabled again when it fires).
+    /// Needs [local access](crate::local): the server does not forward
+    /// interrupts, so through it this fails with `unimplemented` and callers
+    /// poll a status register instead.
+    #[cfg_attr(not(feature = "local"), allow(unused_variables))]
+    pub async fn wait_irq(&mut self, id: u32, timeout: Duration) -> Result<bool, Status> {
+        match self.backend() {
+            #[cfg(feature = "local")]
+            Backend::Local(local) => local.wait_irq(id, timeout).await,
+            _ => Err(Status::unimplemented(
+                "interrupts need local access; the server does not forward them",
+            )),
+        }
+    }
+}
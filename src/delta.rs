@@ -0,0 +1,107 @@
+//! Differential memory updates
+//!
+//! [`JellyFpgaClient::mem_update_delta`] compares a new image with the one
+//! written before (kept by the caller) in fixed-size blocks and writes only
+//! the blocks that changed, merging neighbouring ones into one transfer.
+//! Updating a few entries of a large coefficient table then costs a few
+//! small writes instead of the whole table.
+
+use std::ops::Range;
+
+use crate::JellyFpgaClient;
+use crate::bulk::DEFAULT_ITER_CHUNK_SIZE;
+
+/// Default comparison block size in bytes
+pub const DEFAULT_DELTA_BLOCK_SIZE: usize = 256;
+
+/// Byte ranges of `new` whose `block_size` blocks differ from `previous`
+///
+/// Bytes past the end of `previous` count as changed. Adjacent changed
+/// blocks are merged, up to `max_len` bytes per range.
+pub fn changed_ranges(
+    new: &[u8],
+    previous: &[u8],
+    block_size: usize,
+    max_len: usize,
+) -> Vec<Range<usize>> {
+    let block_size = block_size.max(1);
+    let max_len = max_len.max(block_size);
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for start in (0..new.len()).step_by(block_size) {
+        let end = (start + block_size).min(new.len());
+        if previous.get(start..end) == Some(&new[start..end]) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if last.end == start && end - last.start <= max_len => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
+}
+
+impl JellyFpgaClient {
+    /// Write the blocks of `new_data` that differ from `previous` at byte `offset` of `id`
+    ///
+    /// `previous` must be what the device holds (the last image written);
+    /// the caller keeps it and passes `new_data` next time. Returns the
+    /// number of bytes written. Fails on the first rejected write.
+    pub async fn mem_update_delta(
+        &mut self,
+        id: u32,
+        offset: u64,
+        new_data: &[u8],
+        previous: &[u8],
+    ) -> Result<u64, tonic::Status> {
+        self.mem_update_delta_blocked(id, offset, new_data, previous, DEFAULT_DELTA_BLOCK_SIZE)
+            .await
+    }
+
+    /// [`mem_update_delta`](Self::mem_update_delta) comparing blocks of `block_size` bytes
+    pub async fn mem_update_delta_blocked(
+        &mut self,
+        id: u32,
+        offset: u64,
+        new_data: &[u8],
+        previous: &[u8],
+        block_size: usize,
+    ) -> Result<u64, tonic::Status> {
+        let mut written = 0;
+        for range in changed_ranges(new_data, previous, block_size, DEFAULT_ITER_CHUNK_SIZE) {
+            let start = offset + range.start as u64;
+            written += self
+                .write_chunk(id, start, new_data[range].to_vec())
+                .await?;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_ranges() {
+        let previous = vec![0u8; 64];
+        let mut new = previous.clone();
+        assert!(changed_ranges(&new, &previous, 16, 1024).is_empty());
+        new[3] = 1;
+        new[20] = 1;
+        new[50] = 1;
+        assert_eq!(
+            changed_ranges(&new, &previous, 16, 1024),
+            vec![0..32, 48..64]
+        );
+        assert_eq!(
+            changed_ranges(&new, &previous, 16, 16),
+            vec![0..16, 16..32, 48..64]
+        );
+        new.extend_from_slice(&[0; 8]);
+        assert_eq!(
+            changed_ranges(&new, &previous, 16, 1024),
+            vec![0..32, 48..72]
+        );
+        assert_eq!(changed_ranges(&new, &[], 32, 1024), vec![0..72]);
+    }
+}
@@ -0,0 +1,767 @@
+//! Deployment helpers built from the firmware RPCs
+//!
+//! Follows the jelly naming convention: a design `name` is deployed as
+//! `name.bit` (uploaded bitstream), `name.bit.bin` (converted by the server)
+//! and `name.dtbo` (overlay referencing `name.bit.bin`).
+//!
+//! A [`DeployManifest`] describes a whole bring-up (bitstream, overlay and
+//! register initialization) and expands into a list of [`DeployStep`]s that
+//! can be printed as a plan, run one at a time, or run with independent
+//! steps overlapping. A dry run validates the manifest and returns the
+//! [`DeployPlan`] without changing the board.
+
+use std::fmt;
+use std::path::Path;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+
+#[cfg(not(feature = "wasm"))]
+use tokio::task::JoinSet;
+
+use crate::JellyFpgaClient;
+use crate::accessor::check;
+use crate::region::Region;
+#[cfg(not(feature = "wasm"))]
+use crate::report::{OperationReport, StepRecord};
+
+/// Firmware loaded by [`JellyFpgaClient::restore_default`] unless configured otherwise (KV260/KR260)
+pub const DEFAULT_FIRMWARE: &str = "k26-starter-kits";
+
+/// Architecture passed to `bitstream_to_bin` by [`JellyFpgaClient::deploy_pair`]
+pub const DEFAULT_ARCH: &str = "zynqmp";
+
+/// Steps [`JellyFpgaClient::deploy`] runs at once unless the manifest says otherwise
+///
+/// Two lets the bitstream and overlay uploads overlap.
+pub const DEFAULT_PARALLELISM: usize = 2;
+
+/// Firmware uploaded and loaded by [`JellyFpgaClient::deploy_pair`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Deployment {
+    /// Design base name
+    pub basename: String,
+    /// Uploaded bitstream (`basename.bit`)
+    pub bit: String,
+    /// Converted bitstream (`basename.bit.bin`)
+    pub bin: String,
+    /// Uploaded overlay (`basename.dtbo`)
+    pub dtbo: String,
+}
+
+impl Deployment {
+    /// Names derived from `basename`
+    pub fn new(basename: &str) -> Self {
+        Deployment {
+            basename: basename.to_string(),
+            bit: format!("{}.bit", basename),
+            bin: format!("{}.bit.bin", basename),
+            dtbo: format!("{}.dtbo", basename),
+        }
+    }
+
+    /// All firmware names, in upload order
+    pub fn artifacts(&self) -> [&str; 3] {
+        [&self.dtbo, &self.bit, &self.bin]
+    }
+}
+
+impl From<&str> for Deployment {
+    fn from(basename: &str) -> Self {
+        Deployment::new(basename)
+    }
+}
+
+impl From<&Deployment> for Deployment {
+    fn from(deployment: &Deployment) -> Self {
+        deployment.clone()
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_arch() -> String {
+    DEFAULT_ARCH.to_string()
+}
+
+#[cfg(feature = "serde")]
+fn default_reg_size() -> u64 {
+    8
+}
+
+#[cfg(feature = "serde")]
+fn default_parallelism() -> usize {
+    DEFAULT_PARALLELISM
+}
+
+/// Register write applied after the overlay is loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegInit {
+    /// UIO device name, or a device file such as `/dev/mem` mapped at `addr`
+    pub device: String,
+    /// Physical address mapped for a device file (ignored for UIO)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub addr: u64,
+    /// Register index
+    pub reg: u64,
+    /// Value to write
+    pub value: u64,
+    /// Register size in bytes (also the access unit; 8 if omitted)
+    #[cfg_attr(feature = "serde", serde(default = "default_reg_size"))]
+    pub size: u64,
+}
+
+/// Everything needed to bring up a design
+///
+/// With the `serde` feature it reads from a TOML/JSON manifest such as:
+///
+/// ```toml
+/// name = "kv260_blinking_led_ps"
+/// bit = "kv260_blinking_led_ps.bit"
+/// overlay = "kv260_blinking_led_ps.dts"   # or a prebuilt .dtbo
+///
+/// [[init]]
+/// device = "/dev/mem"
+/// addr = 0xa0000000
+/// reg = 0
+/// value = 1
+///
+/// [[region]]
+/// name = "led"
+/// addr = 0xa0000000
+/// size = 0x1000
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeployManifest {
+    /// Design base name (firmware names follow [`Deployment::new`])
+    pub name: String,
+    /// Local bitstream file
+    pub bit: String,
+    /// Local `.dtbo` file, or DTS file compiled on the server
+    pub overlay: String,
+    /// Architecture for `bitstream_to_bin`
+    #[cfg_attr(feature = "serde", serde(default = "default_arch"))]
+    pub arch: String,
+    /// Register writes after loading, in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub init: Vec<RegInit>,
+    /// Named regions, defined on the client by [`JellyFpgaClient::deploy`]
+    #[cfg_attr(feature = "serde", serde(default, rename = "region"))]
+    pub regions: Vec<Region>,
+    /// Independent steps run at once by [`JellyFpgaClient::deploy`] (1 runs them in order)
+    #[cfg_attr(feature = "serde", serde(default = "default_parallelism"))]
+    pub parallelism: usize,
+}
+
+impl DeployManifest {
+    /// Manifest without register initialization
+    pub fn new(name: &str, bit: &str, overlay: &str) -> Self {
+        DeployManifest {
+            name: name.to_string(),
+            bit: bit.to_string(),
+            overlay: overlay.to_string(),
+            arch: DEFAULT_ARCH.to_string(),
+            init: Vec::new(),
+            regions: Vec::new(),
+            parallelism: DEFAULT_PARALLELISM,
+        }
+    }
+
+    /// Set [`parallelism`](Self::parallelism)
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Resolve relative file paths against `dir` (e.g. the manifest's directory)
+    pub fn with_base_dir(mut self, dir: &Path) -> Self {
+        for path in [&mut self.bit, &mut self.overlay] {
+            if Path::new(path.as_str()).is_relative() {
+                *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        }
+        self
+    }
+
+    /// Firmware names of the deployment
+    pub fn deployment(&self) -> Deployment {
+        Deployment::new(&self.name)
+    }
+
+    /// Steps run by [`JellyFpgaClient::deploy`], in plan order
+    ///
+    /// A step starts once the earlier steps it [depends on](DeployStep::depends_on) are done.
+    pub fn steps(&self) -> Vec<DeployStep> {
+        let d = self.deployment();
+        let mut steps = Vec::with_capacity(5 + self.init.len());
+        if self.overlay.ends_with(".dtbo") {
+            steps.push(DeployStep::UploadDtbo {
+                name: d.dtbo.clone(),
+                path: self.overlay.clone(),
+            });
+        } else {
+            steps.push(DeployStep::CompileDts {
+                basename: d.basename.clone(),
+                path: self.overlay.clone(),
+            });
+        }
+        steps.push(DeployStep::UploadBitstream {
+            name: d.bit.clone(),
+            path: self.bit.clone(),
+        });
+        steps.push(DeployStep::ConvertBitstream {
+            bit: d.bit,
+            bin: d.bin,
+            arch: self.arch.clone(),
+        });
+        steps.push(DeployStep::UnloadAll);
+        steps.push(DeployStep::LoadDtbo { name: d.dtbo });
+        steps.extend(self.init.iter().cloned().map(DeployStep::WriteReg));
+        steps
+    }
+}
+
+/// One step of a deployment
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeployStep {
+    /// Upload a prebuilt overlay file
+    UploadDtbo {
+        /// Firmware name
+        name: String,
+        /// Local file
+        path: String,
+    },
+    /// Compile a DTS file on the server and upload it as `{basename}.dtbo`
+    CompileDts {
+        /// Design base name
+        basename: String,
+        /// Local file
+        path: String,
+    },
+    /// Upload a bitstream file
+    UploadBitstream {
+        /// Firmware name
+        name: String,
+        /// Local file
+        path: String,
+    },
+    /// Convert the bitstream on the server
+    ConvertBitstream {
+        /// Uploaded bitstream
+        bit: String,
+        /// Converted firmware name
+        bin: String,
+        /// Target architecture
+        arch: String,
+    },
+    /// Unload all firmware
+    UnloadAll,
+    /// Load the overlay
+    LoadDtbo {
+        /// Firmware name
+        name: String,
+    },
+    /// Write a register
+    WriteReg(RegInit),
+}
+
+impl DeployStep {
+    /// Step kind as used in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeployStep::UploadDtbo { .. } => "upload_dtbo",
+            DeployStep::CompileDts { .. } => "compile_dts",
+            DeployStep::UploadBitstream { .. } => "upload_bitstream",
+            DeployStep::ConvertBitstream { .. } => "convert_bitstream",
+            DeployStep::UnloadAll => "unload_all",
+            DeployStep::LoadDtbo { .. } => "load_dtbo",
+            DeployStep::WriteReg(_) => "write_reg",
+        }
+    }
+
+    /// The step must wait for `earlier`, a step before it in the plan
+    ///
+    /// Uploads are independent of each other and a conversion waits only
+    /// for its bitstream. Steps that change the board wait for everything
+    /// before them, so a failed upload leaves the running design loaded.
+    pub fn depends_on(&self, earlier: &DeployStep) -> bool {
+        match self {
+            DeployStep::UploadDtbo { .. }
+            | DeployStep::CompileDts { .. }
+            | DeployStep::UploadBitstream { .. } => false,
+            DeployStep::ConvertBitstream { bit, .. } => {
+                matches!(earlier, DeployStep::UploadBitstream { name, .. } if name == bit)
+            }
+            DeployStep::UnloadAll | DeployStep::LoadDtbo { .. } | DeployStep::WriteReg(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for DeployStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeployStep::UploadDtbo { name, path } => write!(f, "upload {} as {}", path, name),
+            DeployStep::CompileDts { basename, path } => {
+                write!(f, "compile {} and upload as {}.dtbo", path, basename)
+            }
+            DeployStep::UploadBitstream { name, path } => {
+                write!(f, "upload {} as {}", path, name)
+            }
+            DeployStep::ConvertBitstream { bit, bin, arch } => {
+                write!(f, "convert {} to {} ({})", bit, bin, arch)
+            }
+            DeployStep::UnloadAll => write!(f, "unload all firmware"),
+            DeployStep::LoadDtbo { name } => write!(f, "load {}", name),
+            DeployStep::WriteReg(init) if init.device.starts_with('/') => write!(
+                f,
+                "write 0x{:x} to {}@0x{:x} reg 0x{:x} ({} bytes)",
+                init.value, init.device, init.addr, init.reg, init.size
+            ),
+            DeployStep::WriteReg(init) => write!(
+                f,
+                "write 0x{:x} to {} reg 0x{:x} ({} bytes)",
+                init.value, init.device, init.reg, init.size
+            ),
+        }
+    }
+}
+
+/// Steps of a validated manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeployPlan {
+    /// Steps in order
+    pub steps: Vec<DeployStep>,
+    /// Bytes uploaded by the steps (a DTS counts once compiled on the server)
+    pub upload_bytes: u64,
+}
+
+impl fmt::Display for DeployPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{:2}. {}", i + 1, step)?;
+        }
+        writeln!(f, "upload {} bytes", self.upload_bytes)
+    }
+}
+
+impl DeployManifest {
+    /// Validate the manifest on the host and return its plan
+    ///
+    /// Checks that the name is usable as a firmware name, the bitstream and
+    /// overlay files exist and are not empty, the overlay references
+    /// `{name}.bit.bin`, the register writes have a device and a 1/2/4/8
+    /// byte size, and the regions have unique names and a size. All
+    /// problems are reported in one `invalid_argument` error.
+    pub async fn plan(&self) -> Result<DeployPlan, tonic::Status> {
+        let d = self.deployment();
+        let mut problems = Vec::new();
+        if self.name.is_empty() || self.name.contains('/') {
+            problems.push(format!("invalid name {:?}", self.name));
+        }
+        let mut upload_bytes = 0;
+        match crate::fs::size(&self.bit).await {
+            Ok(0) => problems.push(format!("{} is empty", self.bit)),
+            Ok(size) => upload_bytes += size,
+            Err(e) => problems.push(e.message().to_string()),
+        }
+        match crate::fs::read(&self.overlay).await {
+            Ok(data) if data.is_empty() => problems.push(format!("{} is empty", self.overlay)),
+            Ok(data) => {
+                if self.overlay.ends_with(".dtbo") {
+                    upload_bytes += data.len() as u64;
+                }
+                if !data.windows(d.bin.len()).any(|w| w == d.bin.as_bytes()) {
+                    problems.push(format!("{} does not reference {}", self.overlay, d.bin));
+                }
+            }
+            Err(e) => problems.push(e.message().to_string()),
+        }
+        for init in &self.init {
+            if init.device.is_empty() {
+                problems.push(format!(
+                    "register write to reg 0x{:x} has no device",
+                    init.reg
+                ));
+            }
+            if ![1, 2, 4, 8].contains(&init.size) {
+                problems.push(format!(
+                    "register size {} of {} is not 1, 2, 4 or 8",
+                    init.size, init.device
+                ));
+            }
+        }
+        for (i, region) in self.regions.iter().enumerate() {
+            if self.regions[..i].iter().any(|r| r.name == region.name) {
+                problems.push(format!("region {} is defined twice", region.name));
+            }
+            if region.size == 0 {
+                problems.push(format!("region {} is empty", region.name));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(tonic::Status::invalid_argument(problems.join("; ")));
+        }
+        Ok(DeployPlan {
+            steps: self.steps(),
+            upload_bytes,
+        })
+    }
+}
+
+/// Error for DTS overlays on a server without `dts_to_dtb`
+fn no_dts_to_dtb() -> tonic::Status {
+    crate::error::with_rpc(
+        tonic::Status::unimplemented("server cannot compile DTS; deploy a prebuilt .dtbo"),
+        "dts_to_dtb",
+    )
+}
+
+impl JellyFpgaClient {
+    /// Compile `dts` and upload it as firmware `{name}.dtbo`; returns the firmware name
+    ///
+    /// Fails if either step reports `result=false`, regardless of strict mode.
+    pub async fn upload_dtbo_from_dts(
+        &mut self,
+        name: &str,
+        dts: &str,
+    ) -> Result<String, tonic::Status> {
+        let dtbo_name = format!("{}.dtbo", name);
+        self.upload_compiled_dts(&dtbo_name, dts).await?;
+        Ok(dtbo_name)
+    }
+
+    /// Compile `dts` and upload it as `dtbo_name`; returns the overlay size
+    async fn upload_compiled_dts(
+        &mut self,
+        dtbo_name: &str,
+        dts: &str,
+    ) -> Result<u64, tonic::Status> {
+        if !self.supports(|c| c.dts_to_dtb).await? {
+            return Err(no_dts_to_dtb());
+        }
+        let (result, dtb) = self.dts_to_dtb(dts).await?;
+        check(result, "dts_to_dtb")?;
+        let size = dtb.len() as u64;
+        check(
+            self.upload_firmware(dtbo_name, dtb).await?,
+            "upload_firmware",
+        )?;
+        Ok(size)
+    }
+
+    /// Read a DTS file and upload it as firmware `{name}.dtbo`
+    pub async fn upload_dtbo_from_dts_file(
+        &mut self,
+        name: &str,
+        path: &str,
+    ) -> Result<String, tonic::Status> {
+        let dts = crate::fs::read(path).await?;
+        let dts = String::from_utf8(dts)
+            .map_err(|_| tonic::Status::invalid_argument(format!("{} is not UTF-8", path)))?;
+        self.upload_dtbo_from_dts(name, &dts).await
+    }
+
+    /// Upload and load a bitstream/overlay pair named after `basename`
+    ///
+    /// `dts_or_dtbo` is a local `.dtbo` file uploaded as is, or a DTS file
+    /// compiled on the server. The bitstream at `bit_path` is uploaded and
+    /// converted for [`DEFAULT_ARCH`]; then all firmware is unloaded and the
+    /// overlay loaded. Every step must succeed regardless of strict mode.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn deploy_pair(
+        &mut self,
+        basename: &str,
+        bit_path: &str,
+        dts_or_dtbo: &str,
+    ) -> Result<Deployment, tonic::Status> {
+        let manifest = DeployManifest::new(basename, bit_path, dts_or_dtbo);
+        self.deploy(&manifest).await?;
+        Ok(manifest.deployment())
+    }
+
+    /// Run every step of `manifest`; fails on the first step that fails
+    ///
+    /// Up to `manifest.parallelism` independent steps (e.g. the bitstream
+    /// and overlay uploads) run at once on clones of the client. Returns the
+    /// timing and transfer size of each step. On success the manifest's
+    /// regions are defined for [`open_region`](Self::open_region). Callers
+    /// that also need a record of failed runs can use
+    /// [`deploy_recorded`](Self::deploy_recorded).
+    #[cfg(not(feature = "wasm"))]
+    pub async fn deploy(
+        &mut self,
+        manifest: &DeployManifest,
+    ) -> Result<OperationReport, tonic::Status> {
+        let mut report = OperationReport::new("deploy");
+        self.deploy_recorded(manifest, &mut report, |_| {}).await?;
+        Ok(report)
+    }
+
+    /// [`deploy`](Self::deploy), adding a record to `report` for each finished step (also on failure)
+    ///
+    /// `on_step` is called with every record as it is added. After a
+    /// failure no new step starts; steps already running are awaited. With
+    /// [`with_failure_diagnostics`](Self::with_failure_diagnostics) the
+    /// error carries a diagnostics summary.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn deploy_recorded<F>(
+        &mut self,
+        manifest: &DeployManifest,
+        report: &mut OperationReport,
+        mut on_step: F,
+    ) -> Result<(), tonic::Status>
+    where
+        F: FnMut(&StepRecord),
+    {
+        let steps = manifest.steps();
+        let parallelism = manifest.parallelism.max(1);
+        let mut started = vec![false; steps.len()];
+        let mut done = vec![false; steps.len()];
+        let mut failure = None;
+        let mut tasks = JoinSet::new();
+        loop {
+            if failure.is_none() {
+                for index in 0..steps.len() {
+                    if tasks.len() >= parallelism {
+                        break;
+                    }
+                    let ready = (0..index).all(|j| done[j] || !steps[index].depends_on(&steps[j]));
+                    if started[index] || !ready {
+                        continue;
+                    }
+                    started[index] = true;
+                    let mut client = self.clone();
+                    let step = steps[index].clone();
+                    tasks.spawn(async move {
+                        let start = Instant::now();
+                        let bytes = client.run_deploy_step(&step).await;
+                        (index, start, bytes)
+                    });
+                }
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let (index, start, bytes) =
+                joined.map_err(|e| tonic::Status::internal(e.to_string()))?;
+            let step = &steps[index];
+            let output = bytes.map(|bytes| ((), true, bytes));
+            match report.finish(step.name(), &step.to_string(), start, output) {
+                Ok(()) => done[index] = true,
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            }
+            if let Some(record) = report.steps.last() {
+                on_step(record);
+            }
+        }
+        match failure {
+            Some(e) => Err(self.diagnose(e).await),
+            None => {
+                self.add_regions(manifest.regions.iter().cloned());
+                Ok(())
+            }
+        }
+    }
+
+    /// Validate `manifest` against the server without changing the board
+    ///
+    /// Runs [`DeployManifest::plan`], compiles a DTS overlay on the server
+    /// (the result is discarded) and checks the upload size against the
+    /// free firmware storage when the server reports it (see
+    /// [`storage_info`](Self::storage_info)).
+    pub async fn dry_run_deploy(
+        &mut self,
+        manifest: &DeployManifest,
+    ) -> Result<DeployPlan, tonic::Status> {
+        let mut plan = manifest.plan().await?;
+        if !manifest.overlay.ends_with(".dtbo") {
+            let dts = crate::fs::read_to_string(&manifest.overlay)
+                .await
+                .map_err(|_| {
+                    tonic::Status::invalid_argument(format!("{} is not UTF-8", manifest.overlay))
+                })?;
+            if !self.supports(|c| c.dts_to_dtb).await? {
+                return Err(no_dts_to_dtb());
+            }
+            let (result, dtb) = self.dts_to_dtb(&dts).await?;
+            if !result {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "{} does not compile",
+                    manifest.overlay
+                )));
+            }
+            plan.upload_bytes += dtb.len() as u64;
+        }
+        if let Some(free) = self.storage_info().await?.free_bytes
+            && plan.upload_bytes > free
+        {
+            return Err(tonic::Status::resource_exhausted(format!(
+                "deployment uploads {} bytes but only {} are free",
+                plan.upload_bytes, free
+            )));
+        }
+        Ok(plan)
+    }
+
+    /// Run one step and return the bytes it uploaded or wrote
+    ///
+    /// `result=false` is an error regardless of strict mode.
+    pub async fn run_deploy_step(&mut self, step: &DeployStep) -> Result<u64, tonic::Status> {
+        match step {
+            DeployStep::UploadDtbo { name, path } | DeployStep::UploadBitstream { name, path } => {
+                let data = crate::fs::read(path).await?;
+                let size = data.len() as u64;
+                check(self.upload_firmware(name, data).await?, "upload_firmware")?;
+                Ok(size)
+            }
+            DeployStep::CompileDts { basename, path } => {
+                let dts = crate::fs::read(path).await?;
+                let dts = String::from_utf8(dts).map_err(|_| {
+                    tonic::Status::invalid_argument(format!("{} is not UTF-8", path))
+                })?;
+                self.upload_compiled_dts(&format!("{}.dtbo", basename), &dts)
+                    .await
+            }
+            DeployStep::ConvertBitstream { bit, bin, arch } => {
+                check(
+                    self.bitstream_to_bin(bit, bin, arch).await?,
+                    "bitstream_to_bin",
+                )?;
+                Ok(0)
+            }
+            DeployStep::UnloadAll => check(self.unload_all().await?, "unload_all").map(|_| 0),
+            DeployStep::LoadDtbo { name } => {
+                check(self.load_dtbo(name).await?, "load_dtbo").map(|_| 0)
+            }
+            DeployStep::WriteReg(init) => {
+                let (result, id) = if init.device.starts_with('/') {
+                    // map whole pages up to the register
+                    let len = ((init.reg + 1) * init.size).next_multiple_of(0x1000);
+                    self.open_mmap(&init.device, init.addr, len, init.size)
+                        .await?
+                } else {
+                    self.open_uio(&init.device, init.size).await?
+                };
+                check(result, "open")?;
+                let written = self.write_reg_u(id, init.reg, init.value, init.size).await;
+                self.close(id).await?;
+                check(written?, "write_reg_u")?;
+                Ok(init.size)
+            }
+        }
+    }
+
+    /// [`run_deploy_step`](Self::run_deploy_step), adding a record to `report` (also on failure)
+    #[cfg(not(feature = "wasm"))]
+    pub async fn run_deploy_step_recorded(
+        &mut self,
+        step: &DeployStep,
+        report: &mut OperationReport,
+    ) -> Result<(), tonic::Status> {
+        report
+            .record(step.name(), &step.to_string(), async {
+                self.run_deploy_step(step)
+                    .await
+                    .map(|bytes| ((), true, bytes))
+            })
+            .await
+    }
+
+    /// Unload all firmware and remove the artifacts of a deployment
+    ///
+    /// Accepts a [`Deployment`] or a base name. Missing files are skipped,
+    /// so a partially failed `deploy_pair` can be cleaned up too. In the
+    /// returned report, `remove_firmware` steps with `result=true` are the
+    /// files that were removed.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn cleanup_deployment<D: Into<Deployment>>(
+        &mut self,
+        deployment: D,
+    ) -> Result<OperationReport, tonic::Status> {
+        let deployment = deployment.into();
+        let mut report = OperationReport::new("cleanup");
+        // a non-strict clone, so missing files come back as `false`
+        let mut client = self.clone();
+        client.set_strict(false);
+        report
+            .record("unload_all", "unload all firmware", async {
+                client.unload_all().await.map(|result| ((), result, 0))
+            })
+            .await?;
+        for name in [&deployment.dtbo, &deployment.bin, &deployment.bit] {
+            report
+                .record("remove_firmware", &format!("remove {}", name), async {
+                    client
+                        .remove_firmware(name)
+                        .await
+                        .map(|result| ((), result, 0))
+                })
+                .await?;
+        }
+        Ok(report)
+    }
+
+    /// Firmware loaded by [`restore_default`](Self::restore_default) (clones inherit it)
+    pub fn set_default_firmware(&mut self, name: &str) {
+        self.default_firmware = name.to_string();
+    }
+
+    /// Configured default firmware
+    pub fn default_firmware(&self) -> &str {
+        &self.default_firmware
+    }
+
+    /// Unload all firmware and load `name`, or the configured default; returns the slot
+    pub async fn restore_default(&mut self, name: Option<&str>) -> Result<i32, tonic::Status> {
+        let name = name.map_or_else(|| self.default_firmware.clone(), str::to_string);
+        check(self.unload_all().await?, "unload_all")?;
+        let (result, slot) = self.load(&name).await?;
+        check(result, "load")?;
+        Ok(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_steps() {
+        let mut manifest = DeployManifest::new("led", "led.bit", "led.dts");
+        manifest.init.push(RegInit {
+            device: "uio_pl_peri".to_string(),
+            addr: 0,
+            reg: 2,
+            value: 1,
+            size: 8,
+        });
+        let steps = manifest.with_base_dir(Path::new("/work")).steps();
+        assert_eq!(
+            steps[0],
+            DeployStep::CompileDts {
+                basename: "led".to_string(),
+                path: "/work/led.dts".to_string()
+            }
+        );
+        assert_eq!(
+            steps[2].to_string(),
+            "convert led.bit to led.bit.bin (zynqmp)"
+        );
+        assert_eq!(
+            steps[4],
+            DeployStep::LoadDtbo {
+                name: "led.dtbo".to_string()
+            }
+        );
+        assert_eq!(steps.len(), 6);
+        assert!(!steps[1].depends_on(&steps[0]));
+        assert!(steps[2].depends_on(&steps[1]));
+        assert!(!steps[2].depends_on(&steps[0]));
+        assert!(steps[3].depends_on(&steps[0]));
+    }
+}
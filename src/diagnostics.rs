@@ -0,0 +1,223 @@
+//! Failure diagnostics
+//!
+//! [`JellyFpgaClient::collect_diagnostics`] gathers what is known about the
+//! board into one [`DiagnosticsReport`] for a bug report: server version
+//! and capabilities, the FPGA manager state, firmware and overlays loaded
+//! through the client, open handles, the board lock and recent server log
+//! lines. Each part is best effort; parts that could not be read are listed
+//! in `errors` instead of failing the collection.
+//!
+//! With [`with_failure_diagnostics`](JellyFpgaClient::with_failure_diagnostics)
+//! a failed `deploy` collects a report automatically, appends its summary
+//! to the error message and keeps it for
+//! [`last_diagnostics`](JellyFpgaClient::last_diagnostics).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::JellyFpgaClient;
+use crate::capabilities::Capabilities;
+
+/// sysfs file with the state of the first FPGA manager
+pub const FPGA_STATE_PATH: &str = "/sys/class/fpga_manager/fpga0/state";
+/// Server log lines kept in a report
+pub const DIAGNOSTICS_LOG_LINES: usize = 50;
+
+/// Snapshot of the board for a bug report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiagnosticsReport {
+    /// Collection time in seconds since the Unix epoch
+    pub collected_at: u64,
+    /// Server version
+    pub version: Option<String>,
+    /// Server capabilities
+    pub capabilities: Option<Capabilities>,
+    /// FPGA manager state (e.g. `operating`)
+    pub fpga_state: Option<String>,
+    /// Firmware name per slot loaded through the client
+    pub firmware: BTreeMap<i32, String>,
+    /// Last bitstream loaded through the client
+    pub bitstream: Option<String>,
+    /// Overlays applied through the client
+    pub overlays: Vec<String>,
+    /// Ids opened through the client and not closed
+    pub open_handles: Vec<u32>,
+    /// Whether the client holds the board lock
+    pub lock_held: bool,
+    /// Current holder of the board lock
+    pub lock_owner: Option<String>,
+    /// Most recent server log lines (oldest first)
+    pub server_log: Vec<String>,
+    /// Parts that could not be collected
+    pub errors: Vec<String>,
+}
+
+impl DiagnosticsReport {
+    /// One-line summary for error messages
+    pub fn summary(&self) -> String {
+        let mut s = format!(
+            "fpga_state={} firmware={:?} overlays={:?} open_handles={:?}",
+            self.fpga_state.as_deref().unwrap_or("?"),
+            self.firmware.values().collect::<Vec<_>>(),
+            self.overlays,
+            self.open_handles
+        );
+        if let Some(line) = self.server_log.last() {
+            let _ = write!(s, " last_log={:?}", line);
+        }
+        s
+    }
+
+    /// JSON object with the same fields
+    pub fn to_json(&self) -> String {
+        let opt = |v: &Option<String>| match v {
+            Some(v) => format!("{:?}", v),
+            None => "null".to_string(),
+        };
+        let list = |v: &[String]| {
+            let items: Vec<String> = v.iter().map(|s| format!("{:?}", s)).collect();
+            format!("[{}]", items.join(","))
+        };
+        let firmware: Vec<String> = self
+            .firmware
+            .iter()
+            .map(|(slot, name)| format!("\"{}\":{:?}", slot, name))
+            .collect();
+        let features: Vec<String> = self
+            .capabilities
+            .iter()
+            .flat_map(|c| &c.features)
+            .cloned()
+            .collect();
+        let handles: Vec<String> = self.open_handles.iter().map(u32::to_string).collect();
+        format!(
+            "{{\"collected_at\":{},\"version\":{},\"features\":{},\"fpga_state\":{},\"firmware\":{{{}}},\"bitstream\":{},\"overlays\":{},\"open_handles\":[{}],\"lock_held\":{},\"lock_owner\":{},\"server_log\":{},\"errors\":{}}}",
+            self.collected_at,
+            opt(&self.version),
+            list(&features),
+            opt(&self.fpga_state),
+            firmware.join(","),
+            opt(&self.bitstream),
+            list(&self.overlays),
+            handles.join(","),
+            self.lock_held,
+            opt(&self.lock_owner),
+            list(&self.server_log),
+            list(&self.errors)
+        )
+    }
+}
+
+impl fmt::Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version: {}", self.version.as_deref().unwrap_or("?"))?;
+        writeln!(
+            f,
+            "fpga state: {}",
+            self.fpga_state.as_deref().unwrap_or("?")
+        )?;
+        for (slot, name) in &self.firmware {
+            writeln!(f, "slot {}: {}", slot, name)?;
+        }
+        if let Some(bitstream) = &self.bitstream {
+            writeln!(f, "bitstream: {}", bitstream)?;
+        }
+        for overlay in &self.overlays {
+            writeln!(f, "overlay: {}", overlay)?;
+        }
+        writeln!(f, "open handles: {:?}", self.open_handles)?;
+        match &self.lock_owner {
+            Some(owner) => writeln!(f, "lock: {} (held here: {})", owner, self.lock_held)?,
+            None => writeln!(f, "lock: free")?,
+        }
+        for line in &self.server_log {
+            writeln!(f, "log: {}", line)?;
+        }
+        for error in &self.errors {
+            writeln!(f, "not collected: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Last report of a client with failure diagnostics enabled
+#[derive(Default)]
+pub(crate) struct DiagnosticsSlot {
+    last: Mutex<Option<DiagnosticsReport>>,
+}
+
+impl JellyFpgaClient {
+    /// Collect a [`DiagnosticsReport`]; never fails
+    pub async fn collect_diagnostics(&mut self) -> DiagnosticsReport {
+        let mut report = DiagnosticsReport {
+            collected_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            open_handles: self.list_open_handles(),
+            lock_held: self.holds_lock(),
+            ..Default::default()
+        };
+        let loads = self.load_state();
+        report.firmware = loads.firmware;
+        report.bitstream = loads.bitstream;
+        report.overlays = loads.overlays;
+        match self.get_version().await {
+            Ok(version) => report.version = Some(version),
+            Err(e) => report.errors.push(format!("version: {}", e.message())),
+        }
+        match self.capabilities().await {
+            Ok(capabilities) => report.capabilities = Some(capabilities),
+            Err(e) => report.errors.push(format!("capabilities: {}", e.message())),
+        }
+        // sysfs attributes usually cannot be mapped; then the state stays unknown
+        match self.read_board_file(FPGA_STATE_PATH, 64).await {
+            Ok(Some(data)) => {
+                let state = String::from_utf8_lossy(&data);
+                report.fpga_state = Some(state.trim_end_matches('\0').trim().to_string());
+            }
+            Ok(None) => report
+                .errors
+                .push(format!("fpga state: cannot read {}", FPGA_STATE_PATH)),
+            Err(e) => report.errors.push(format!("fpga state: {}", e.message())),
+        }
+        match self.lock_owner().await {
+            Ok(owner) => report.lock_owner = owner,
+            Err(e) => report.errors.push(format!("lock: {}", e.message())),
+        }
+        match self.server_logs("").await {
+            Ok(mut lines) => {
+                let skip = lines.len().saturating_sub(DIAGNOSTICS_LOG_LINES);
+                report.server_log = lines.split_off(skip);
+            }
+            Err(e) => report.errors.push(format!("server log: {}", e.message())),
+        }
+        report
+    }
+
+    /// Collect diagnostics when a `deploy` fails (shared with clones made afterwards)
+    pub fn with_failure_diagnostics(mut self) -> Self {
+        self.diagnostics = Some(Arc::new(DiagnosticsSlot::default()));
+        self
+    }
+
+    /// Report collected for the last failure, if failure diagnostics are enabled
+    pub fn last_diagnostics(&self) -> Option<DiagnosticsReport> {
+        self.diagnostics.as_ref()?.last.lock().unwrap().clone()
+    }
+
+    /// Collect diagnostics for `error` if enabled and append their summary
+    pub(crate) async fn diagnose(&mut self, error: tonic::Status) -> tonic::Status {
+        let Some(slot) = self.diagnostics.clone() else {
+            return error;
+        };
+        let report = self.collect_diagnostics().await;
+        let message = format!("{} [diagnostics: {}]", error.message(), report.summary());
+        *slot.last.lock().unwrap() = Some(report);
+        tonic::Status::with_metadata(error.code(), message, error.metadata().clone())
+    }
+}
@@ -0,0 +1,108 @@
+//! Human-readable formatting of values, sizes and addresses
+//!
+//! Shared by the CLI and log messages so that every tool prints the same
+//! thing the same way:
+//!
+//! - [`format_register`]: `CTRL = 0x0000_0003 [EN=1, IRQ_EN=1]`
+//! - [`format_size`]: `64 MiB`, `1.5 KiB`, `100 B`
+//! - [`format_addr`]: `0xa000_0000`, or 16 digits above 4 GiB
+//! - [`format_hex`]: zero-padded to the register size, `_` every 4 digits
+
+use crate::regmap::RegisterDef;
+
+/// `value` as `0x` hex with `2 * bytes` digits in groups of 4 (`0x0000_0003`)
+///
+/// Values wider than `bytes` are not truncated.
+pub fn format_hex(value: u64, bytes: u64) -> String {
+    let digits = format!("{:0width$x}", value, width = (bytes * 2) as usize);
+    let mut out = String::with_capacity(digits.len() + digits.len() / 4 + 2);
+    out.push_str("0x");
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 4 == 0 {
+            out.push('_');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Physical address in canonical form: 8 hex digits, or 16 if it does not fit in 32 bits
+pub fn format_addr(addr: u64) -> String {
+    format_hex(addr, if addr > u32::MAX as u64 { 8 } else { 4 })
+}
+
+/// Byte count with a binary unit (`512 B`, `4 KiB`, `1.5 MiB`)
+///
+/// Exact multiples print without decimals; others are rounded to one.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut unit = 0;
+    while unit + 1 < UNITS.len() && bytes >> (10 * (unit + 1)) != 0 {
+        unit += 1;
+    }
+    let scale = 1u64 << (10 * unit);
+    if bytes.is_multiple_of(scale) {
+        format!("{} {}", bytes / scale, UNITS[unit])
+    } else {
+        format!("{:.1} {}", bytes as f64 / scale as f64, UNITS[unit])
+    }
+}
+
+/// Field value: decimal below 10 (where both read the same), else hex
+fn format_field(value: u64) -> String {
+    if value < 10 {
+        value.to_string()
+    } else {
+        format!("0x{:x}", value)
+    }
+}
+
+/// Register value with its fields decomposed (`CTRL = 0x0000_0003 [EN=1, IRQ_EN=1]`)
+///
+/// The value is padded to the register size; registers without fields
+/// print without the brackets.
+pub fn format_register(def: &RegisterDef, value: u64) -> String {
+    let mut out = format!("{} = {}", def.name, format_hex(value, def.size));
+    if !def.fields.is_empty() {
+        let fields: Vec<String> = def
+            .fields
+            .iter()
+            .map(|(name, field)| format!("{}={}", name, format_field(field.extract(value))))
+            .collect();
+        out.push_str(&format!(" [{}]", fields.join(", ")));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regmap::RegisterMap;
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format_hex(3, 4), "0x0000_0003");
+        assert_eq!(format_hex(0xab, 1), "0xab");
+        assert_eq!(format_hex(0x12345, 2), "0x1_2345");
+        assert_eq!(format_addr(0xa000_0000), "0xa000_0000");
+        assert_eq!(format_addr(0x4_0000_0000), "0x0000_0004_0000_0000");
+        assert_eq!(format_size(100), "100 B");
+        assert_eq!(format_size(64 << 20), "64 MiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+
+        let map = RegisterMap::new()
+            .register("CTRL", 0, 4)
+            .field("EN", 0, 1)
+            .field("IRQ_EN", 1, 1)
+            .field("DIV", 8, 8)
+            .register("STATUS", 1, 4);
+        assert_eq!(
+            format_register(map.get("CTRL").unwrap(), 0x1403),
+            "CTRL = 0x0000_1403 [EN=1, IRQ_EN=1, DIV=0x14]"
+        );
+        assert_eq!(
+            format_register(map.get("STATUS").unwrap(), 7),
+            "STATUS = 0x0000_0007"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Xilinx AXI DMA (simple mode) driver
+
+use std::time::Duration;
+
+use crate::accessor::Accessor;
+use crate::addr::{PhysAddr, RegOffset};
+
+/// MM2S control register
+pub const REG_MM2S_DMACR: u64 = 0x00;
+/// MM2S status register
+pub const REG_MM2S_DMASR: u64 = 0x04;
+/// MM2S source address
+pub const REG_MM2S_SA: u64 = 0x18;
+/// MM2S source address (upper 32 bits)
+pub const REG_MM2S_SA_MSB: u64 = 0x1c;
+/// MM2S transfer length
+pub const REG_MM2S_LENGTH: u64 = 0x28;
+/// S2MM control register
+pub const REG_S2MM_DMACR: u64 = 0x30;
+/// S2MM status register
+pub const REG_S2MM_DMASR: u64 = 0x34;
+/// S2MM destination address
+pub const REG_S2MM_DA: u64 = 0x48;
+/// S2MM destination address (upper 32 bits)
+pub const REG_S2MM_DA_MSB: u64 = 0x4c;
+/// S2MM transfer length
+pub const REG_S2MM_LENGTH: u64 = 0x58;
+
+/// DMACR: run/stop
+pub const DMACR_RS: u32 = 1 << 0;
+/// DMACR: soft reset
+pub const DMACR_RESET: u32 = 1 << 2;
+/// DMACR: interrupt on complete enable
+pub const DMACR_IOC_IRQ_EN: u32 = 1 << 12;
+/// DMACR: error interrupt enable
+pub const DMACR_ERR_IRQ_EN: u32 = 1 << 14;
+
+/// DMASR: channel halted
+pub const DMASR_HALTED: u32 = 1 << 0;
+/// DMASR: channel idle
+pub const DMASR_IDLE: u32 = 1 << 1;
+/// DMASR: internal error
+pub const DMASR_INT_ERR: u32 = 1 << 4;
+/// DMASR: slave error
+pub const DMASR_SLV_ERR: u32 = 1 << 5;
+/// DMASR: decode error
+pub const DMASR_DEC_ERR: u32 = 1 << 6;
+/// DMASR: interrupt on complete
+pub const DMASR_IOC_IRQ: u32 = 1 << 12;
+/// DMASR: error interrupt
+pub const DMASR_ERR_IRQ: u32 = 1 << 14;
+
+const DMASR_ERR_MASK: u32 = DMASR_INT_ERR | DMASR_SLV_ERR | DMASR_DEC_ERR;
+
+/// DMA channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DmaChannel {
+    /// Memory to stream (device reads host buffer)
+    Mm2s,
+    /// Stream to memory (device writes host buffer)
+    S2mm,
+}
+
+impl DmaChannel {
+    fn dmacr(self) -> u64 {
+        match self {
+            DmaChannel::Mm2s => REG_MM2S_DMACR,
+            DmaChannel::S2mm => REG_S2MM_DMACR,
+        }
+    }
+
+    fn dmasr(self) -> u64 {
+        match self {
+            DmaChannel::Mm2s => REG_MM2S_DMASR,
+            DmaChannel::S2mm => REG_S2MM_DMASR,
+        }
+    }
+
+    fn addr(self) -> u64 {
+        match self {
+            DmaChannel::Mm2s => REG_MM2S_SA,
+            DmaChannel::S2mm => REG_S2MM_DA,
+        }
+    }
+
+    fn addr_msb(self) -> u64 {
+        match self {
+            DmaChannel::Mm2s => REG_MM2S_SA_MSB,
+            DmaChannel::S2mm => REG_S2MM_DA_MSB,
+        }
+    }
+
+    fn length(self) -> u64 {
+        match self {
+            DmaChannel::Mm2s => REG_MM2S_LENGTH,
+            DmaChannel::S2mm => REG_S2MM_LENGTH,
+        }
+    }
+}
+
+/// Decoded DMASR value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DmaStatus(pub u32);
+
+impl DmaStatus {
+    /// Channel is halted
+    pub fn is_halted(&self) -> bool {
+        self.0 & DMASR_HALTED != 0
+    }
+
+    /// Channel is idle (transfer finished)
+    pub fn is_idle(&self) -> bool {
+        self.0 & DMASR_IDLE != 0
+    }
+
+    /// Any of the internal/slave/decode error bits is set
+    pub fn is_error(&self) -> bool {
+        self.0 & DMASR_ERR_MASK != 0
+    }
+
+    /// Interrupt on complete is pending
+    pub fn is_ioc(&self) -> bool {
+        self.0 & DMASR_IOC_IRQ != 0
+    }
+}
+
+/// AXI DMA driver (direct register mode, no scatter-gather)
+///
+/// The accessor must map the AXI-Lite register space of the core with byte
+/// offsets (e.g. `open_mmap("/dev/mem", base, 0x10000, 4)` or a UIO device).
+/// Completion is detected by polling DMASR since the server does not forward
+/// interrupts; with local access to a UIO device whose interrupt is the
+/// core's, [`set_irq`](Self::set_irq) waits for the interrupt instead.
+pub struct AxiDma {
+    regs: Accessor,
+    poll_interval: Duration,
+    irq: bool,
+}
+
+impl AxiDma {
+    /// Create driver
+    pub fn new(regs: Accessor) -> Self {
+        AxiDma {
+            regs,
+            poll_interval: Duration::from_millis(1),
+            irq: false,
+        }
+    }
+
+    /// Set status polling interval
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Wait for the completion and error interrupts instead of polling (or stop doing so)
+    ///
+    /// Applies to transfers started afterwards, which enable the interrupts
+    /// in DMACR. Needs local access to a UIO device (see
+    /// [`Accessor::wait_irq`]); DMASR is still read after each interrupt, so
+    /// a line shared by both channels works.
+    pub fn set_irq(&mut self, enabled: bool) {
+        self.irq = enabled;
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Soft reset the core (resets both channels)
+    pub async fn reset(&mut self, timeout: Duration) -> Result<(), tonic::Status> {
+        self.regs.write_mem_u32(REG_MM2S_DMACR, DMACR_RESET).await?;
+        self.regs
+            .wait_mem_u32(REG_MM2S_DMACR, DMACR_RESET, 0, self.poll_interval, timeout)
+            .await?;
+        Ok(())
+    }
+
+    /// Read channel status
+    pub async fn status(&mut self, ch: DmaChannel) -> Result<DmaStatus, tonic::Status> {
+        Ok(DmaStatus(self.regs.read_mem_u32(ch.dmasr()).await?))
+    }
+
+    /// Start a transfer of `len` bytes at physical address `addr`
+    pub async fn start(
+        &mut self,
+        ch: DmaChannel,
+        addr: impl Into<PhysAddr>,
+        len: u32,
+    ) -> Result<(), tonic::Status> {
+        let addr = addr.into().0;
+        if len == 0 {
+            return Err(tonic::Status::invalid_argument(
+                "DMA length must not be zero",
+            ));
+        }
+        let irq_en = DMACR_IOC_IRQ_EN | DMACR_ERR_IRQ_EN;
+        let dmacr = self.regs.read_mem_u32(ch.dmacr()).await? & !irq_en;
+        let dmacr = if self.irq { dmacr | irq_en } else { dmacr };
+        self.regs
+            .write_mem_u32(ch.dmacr(), dmacr | DMACR_RS)
+            .await?;
+        // clear pending IOC/error interrupts (write one to clear)
+        self.regs
+            .write_mem_u32(ch.dmasr(), DMASR_IOC_IRQ | DMASR_ERR_IRQ)
+            .await?;
+        self.regs.write_mem_u32(ch.addr(), addr as u32).await?;
+        self.regs
+            .write_mem_u32(ch.addr_msb(), (addr >> 32) as u32)
+            .await?;
+        // writing LENGTH starts the transfer
+        self.regs.write_mem_u32(ch.length(), len).await
+    }
+
+    /// Wait for the channel to become idle and return the transferred length
+    ///
+    /// For S2MM the length register holds the number of bytes actually
+    /// received, which may be smaller than requested when TLAST came early.
+    pub async fn wait(&mut self, ch: DmaChannel, timeout: Duration) -> Result<u32, tonic::Status> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let status = self.status(ch).await?;
+            if status.is_error() {
+                return Err(tonic::Status::internal(format!(
+                    "AXI DMA {:?} error (DMASR=0x{:08x})",
+                    ch, status.0
+                )));
+            }
+            if status.is_idle() {
+                return self.regs.read_mem_u32(ch.length()).await;
+            }
+            if status.is_halted() {
+                return Err(tonic::Status::failed_precondition(format!(
+                    "AXI DMA {:?} halted (DMASR=0x{:08x})",
+                    ch, status.0
+                )));
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(tonic::Status::deadline_exceeded(format!(
+                    "AXI DMA {:?} timeout (DMASR=0x{:08x})",
+                    ch, status.0
+                )));
+            }
+            if self.irq {
+                self.regs.wait_irq(deadline - now).await?;
+                // acknowledge (write one to clear) so the line is released
+                self.regs
+                    .write_mem_u32(ch.dmasr(), DMASR_IOC_IRQ | DMASR_ERR_IRQ)
+                    .await?;
+            } else {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+    }
+
+    /// Stop the channel
+    pub async fn stop(&mut self, ch: DmaChannel) -> Result<(), tonic::Status> {
+        let dmacr = self.regs.read_mem_u32(ch.dmacr()).await?;
+        self.regs.write_mem_u32(ch.dmacr(), dmacr & !DMACR_RS).await
+    }
+
+    /// Start MM2S transfer from physical address
+    pub async fn mm2s_start(&mut self, addr: impl Into<PhysAddr>, len: u32) -> Result<(), tonic::Status> {
+        self.start(DmaChannel::Mm2s, addr, len).await
+    }
+
+    /// Start S2MM transfer to physical address
+    pub async fn s2mm_start(&mut self, addr: impl Into<PhysAddr>, len: u32) -> Result<(), tonic::Status> {
+        self.start(DmaChannel::S2mm, addr, len).await
+    }
+
+    /// Wait MM2S completion
+    pub async fn mm2s_wait(&mut self, timeout: Duration) -> Result<u32, tonic::Status> {
+        self.wait(DmaChannel::Mm2s, timeout).await
+    }
+
+    /// Wait S2MM completion
+    pub async fn s2mm_wait(&mut self, timeout: Duration) -> Result<u32, tonic::Status> {
+        self.wait(DmaChannel::S2mm, timeout).await
+    }
+
+    /// Send `len` bytes from a udmabuf (at `offset`) to the stream and wait
+    pub async fn mm2s_transfer(
+        &mut self,
+        buf: &mut Accessor,
+        offset: impl Into<RegOffset>,
+        len: u32,
+        timeout: Duration,
+    ) -> Result<u32, tonic::Status> {
+        let addr = buf.phys_addr().await? + offset.into();
+        self.mm2s_start(addr, len).await?;
+        self.mm2s_wait(timeout).await
+    }
+
+    /// Receive up to `len` bytes from the stream into a udmabuf (at `offset`) and wait
+    pub async fn s2mm_transfer(
+        &mut self,
+        buf: &mut Accessor,
+        offset: impl Into<RegOffset>,
+        len: u32,
+        timeout: Duration,
+    ) -> Result<u32, tonic::Status> {
+        let addr = buf.phys_addr().await? + offset.into();
+        self.s2mm_start(addr, len).await?;
+        self.s2mm_wait(timeout).await
+    }
+}
@@ -0,0 +1,51 @@
+//! Byte order of values in device memory
+
+/// Byte order of device registers/memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Endian {
+    /// Little-endian (target native, default)
+    #[default]
+    Little,
+    /// Big-endian (network order structures, big-endian peripherals)
+    Big,
+}
+
+impl Endian {
+    /// Convert between a host value and its `size`-byte representation in this byte order
+    ///
+    /// The conversion is its own inverse, so it is used for both reads and writes.
+    pub fn convert(self, value: u64, size: u64) -> u64 {
+        match self {
+            Endian::Little => value,
+            Endian::Big => swap_bytes(value, size),
+        }
+    }
+}
+
+/// Reverse the low `size` bytes of `value`
+pub fn swap_bytes(value: u64, size: u64) -> u64 {
+    match size {
+        1 => value & 0xff,
+        2 => (value as u16).swap_bytes() as u64,
+        4 => (value as u32).swap_bytes() as u64,
+        8 => value.swap_bytes(),
+        _ => {
+            let size = size.min(8) as u32;
+            value.swap_bytes() >> (64 - 8 * size)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_bytes() {
+        assert_eq!(swap_bytes(0x1234, 2), 0x3412);
+        assert_eq!(swap_bytes(0x0012_3456, 3), 0x0056_3412);
+        assert_eq!(Endian::Big.convert(0x1122_3344, 4), 0x4433_2211);
+        assert_eq!(Endian::Little.convert(0x1122_3344, 4), 0x1122_3344);
+    }
+}
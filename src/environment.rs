@@ -0,0 +1,130 @@
+//! Snapshot and restore of the board environment
+//!
+//! [`snapshot_environment`](JellyFpgaClient::snapshot_environment) records
+//! what this client (and its clones) loaded and opened: the
+//! [`load_state`](JellyFpgaClient::load_state) and the open parameters of
+//! every registered id. After a `reset()` or a server restart,
+//! [`restore_environment`](JellyFpgaClient::restore_environment) loads the
+//! same firmware again and reopens the devices. The server hands out new
+//! ids, so accessors made before the reset must be recreated from the
+//! returned id map.
+
+use std::collections::BTreeMap;
+
+use crate::JellyFpgaClient;
+use crate::accessor::check;
+use crate::loads::LoadState;
+
+/// How an id was opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpenSpec {
+    /// `open_mmap(path, offset, size, unit)`
+    Mmap {
+        path: String,
+        offset: u64,
+        size: u64,
+        unit: u64,
+    },
+    /// `open_uio(name, unit)`
+    Uio { name: String, unit: u64 },
+    /// `open_udmabuf(name, cache_enable, unit)`
+    Udmabuf {
+        name: String,
+        cache_enable: bool,
+        unit: u64,
+    },
+    /// `subclone(parent, offset, size, unit)`
+    Subclone {
+        parent: u32,
+        offset: u64,
+        size: u64,
+        unit: u64,
+    },
+}
+
+/// Loaded firmware and open devices of a client
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvironmentSnapshot {
+    /// Firmware, bitstream and overlays
+    pub loads: LoadState,
+    /// Open ids with their open parameters, ascending (parents before subclones)
+    pub handles: Vec<(u32, OpenSpec)>,
+}
+
+impl JellyFpgaClient {
+    /// Record the loaded firmware and open devices
+    pub fn snapshot_environment(&self) -> EnvironmentSnapshot {
+        EnvironmentSnapshot {
+            loads: self.load_state(),
+            handles: self.handles.specs(),
+        }
+    }
+
+    /// Load and open everything in `snapshot` again
+    ///
+    /// Meant for after a `reset()` or server restart: the ids of the
+    /// snapshot are forgotten without closing them (the server may already
+    /// have reused them). The bitstream is loaded first, then the overlays
+    /// in order, then the firmware of each slot. Stops at the first failure
+    /// (`result=false` is an error regardless of strict mode). Returns the
+    /// new id for each id of the snapshot.
+    pub async fn restore_environment(
+        &mut self,
+        snapshot: &EnvironmentSnapshot,
+    ) -> Result<BTreeMap<u32, u32>, tonic::Status> {
+        self.lock.check_op("restore_environment")?;
+        self.loads.clear();
+        if let Some(name) = &snapshot.loads.bitstream {
+            let result = self.load_bitstream(name).await?;
+            check(result, &format!("load_bitstream {}", name))?;
+        }
+        for name in &snapshot.loads.overlays {
+            let result = self.load_dtbo(name).await?;
+            check(result, &format!("load_dtbo {}", name))?;
+        }
+        for name in snapshot.loads.firmware.values() {
+            let (result, _) = self.load(name).await?;
+            check(result, &format!("load {}", name))?;
+        }
+
+        for (id, _) in &snapshot.handles {
+            self.handles.remove(*id);
+        }
+        let mut ids = BTreeMap::new();
+        for (old, spec) in &snapshot.handles {
+            let (result, new) = match spec {
+                OpenSpec::Mmap {
+                    path,
+                    offset,
+                    size,
+                    unit,
+                } => self.open_mmap(path, *offset, *size, *unit).await?,
+                OpenSpec::Uio { name, unit } => self.open_uio(name, *unit).await?,
+                OpenSpec::Udmabuf {
+                    name,
+                    cache_enable,
+                    unit,
+                } => self.open_udmabuf(name, *cache_enable, *unit).await?,
+                OpenSpec::Subclone {
+                    parent,
+                    offset,
+                    size,
+                    unit,
+                } => {
+                    let parent = *ids.get(parent).ok_or_else(|| {
+                        tonic::Status::failed_precondition(format!(
+                            "parent {} of id {} is not in the snapshot",
+                            parent, old
+                        ))
+                    })?;
+                    self.subclone(parent, *offset, *size, *unit).await?
+                }
+            };
+            check(result, &format!("reopen id {}", old))?;
+            ids.insert(*old, new);
+        }
+        Ok(ids)
+    }
+}
@@ -0,0 +1,203 @@
+//! Typed errors mapped from `tonic::Status`
+//!
+//! Client methods keep returning `tonic::Status`; failed RPCs carry the RPC
+//! name in the status metadata so converting with `Error::from` (or `?` in a
+//! function returning [`Result`]) yields a variant that can be matched on.
+
+use std::fmt;
+
+use crate::capabilities::PROTO_VERSION_KEY;
+use crate::raw::PROTO_VERSION;
+
+/// Status metadata key holding the name of the failed RPC
+pub const RPC_KEY: &str = "x-jelly-rpc";
+
+/// Attach the RPC name to a status (keeps an existing one)
+pub(crate) fn with_rpc(mut status: tonic::Status, rpc: &str) -> tonic::Status {
+    if !status.metadata().contains_key(RPC_KEY)
+        && let Ok(value) = rpc.parse()
+    {
+        status.metadata_mut().insert(RPC_KEY, value);
+    }
+    status
+}
+
+/// Status metadata key holding the client protocol version of a version mismatch
+const CLIENT_VERSION_KEY: &str = "x-jelly-client-proto-version";
+
+/// `failed_precondition` for a server speaking protocol `server_ver`
+///
+/// Converts to [`Error::IncompatibleServer`].
+pub(crate) fn incompatible_server(server_ver: &str) -> tonic::Status {
+    let mut status = tonic::Status::failed_precondition(format!(
+        "server protocol {} is incompatible with client protocol {}",
+        server_ver, PROTO_VERSION
+    ));
+    if let Ok(value) = PROTO_VERSION.parse() {
+        status.metadata_mut().insert(CLIENT_VERSION_KEY, value);
+    }
+    if let Ok(value) = server_ver.parse() {
+        status.metadata_mut().insert(PROTO_VERSION_KEY, value);
+    }
+    status
+}
+
+/// Semantic error
+#[derive(Debug)]
+pub enum Error {
+    /// Server unreachable or connection lost
+    NotConnected {
+        /// Failed RPC
+        rpc: Option<String>,
+        /// Detail
+        message: String,
+    },
+    /// Operation not permitted (server side, client-side guard or board lock)
+    PermissionDenied {
+        /// Failed RPC
+        rpc: Option<String>,
+        /// Detail
+        message: String,
+    },
+    /// Named resource (firmware, device, ...) does not exist
+    NotFound {
+        /// Failed RPC
+        rpc: Option<String>,
+        /// Detail (usually names the resource)
+        message: String,
+    },
+    /// RPC or feature not supported by the server
+    Unsupported {
+        /// Unsupported RPC
+        rpc: Option<String>,
+    },
+    /// Deadline exceeded (RPC deadline or polling timeout)
+    Timeout {
+        /// Failed RPC
+        rpc: Option<String>,
+        /// Detail
+        message: String,
+    },
+    /// Server speaks a protocol version the generated types do not match
+    IncompatibleServer {
+        /// [`PROTO_VERSION`] of this client
+        client_ver: String,
+        /// Version reported by the server
+        server_ver: String,
+    },
+    /// Any other status
+    Other(tonic::Status),
+}
+
+impl Error {
+    /// Name of the failed RPC, if known
+    pub fn rpc(&self) -> Option<&str> {
+        match self {
+            Error::NotConnected { rpc, .. }
+            | Error::PermissionDenied { rpc, .. }
+            | Error::NotFound { rpc, .. }
+            | Error::Unsupported { rpc }
+            | Error::Timeout { rpc, .. } => rpc.as_deref(),
+            Error::IncompatibleServer { .. } => None,
+            Error::Other(status) => status.metadata().get(RPC_KEY).and_then(|v| v.to_str().ok()),
+        }
+    }
+}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        let rpc = status
+            .metadata()
+            .get(RPC_KEY)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let message = status.message().to_string();
+        let header = |key| {
+            status
+                .metadata()
+                .get(key)
+                .map(|v| v.to_str().unwrap_or_default().to_string())
+        };
+        if status.code() == tonic::Code::FailedPrecondition
+            && let Some(client_ver) = header(CLIENT_VERSION_KEY)
+            && let Some(server_ver) = header(PROTO_VERSION_KEY)
+        {
+            return Error::IncompatibleServer {
+                client_ver,
+                server_ver,
+            };
+        }
+        match status.code() {
+            tonic::Code::Unavailable => Error::NotConnected { rpc, message },
+            tonic::Code::PermissionDenied | tonic::Code::Unauthenticated => {
+                Error::PermissionDenied { rpc, message }
+            }
+            tonic::Code::NotFound => Error::NotFound { rpc, message },
+            tonic::Code::Unimplemented => Error::Unsupported { rpc },
+            tonic::Code::DeadlineExceeded => Error::Timeout { rpc, message },
+            _ => Error::Other(status),
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+impl From<tonic::transport::Error> for Error {
+    fn from(err: tonic::transport::Error) -> Self {
+        Error::NotConnected {
+            rpc: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rpc = self.rpc().unwrap_or("request");
+        match self {
+            Error::NotConnected { message, .. } => write!(f, "{}: not connected: {}", rpc, message),
+            Error::PermissionDenied { message, .. } => {
+                write!(f, "{}: permission denied: {}", rpc, message)
+            }
+            Error::NotFound { message, .. } => write!(f, "{}: not found: {}", rpc, message),
+            Error::Unsupported { .. } => write!(f, "{}: not supported by server", rpc),
+            Error::Timeout { message, .. } => write!(f, "{}: timeout: {}", rpc, message),
+            Error::IncompatibleServer {
+                client_ver,
+                server_ver,
+            } => write!(
+                f,
+                "server protocol {} is incompatible with client protocol {}",
+                server_ver, client_ver
+            ),
+            Error::Other(status) => write!(f, "{}: {}", rpc, status.message()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Result with [`Error`]
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_mapping() {
+        let status = with_rpc(tonic::Status::unimplemented(""), "load_dtbo");
+        let err = Error::from(status);
+        assert!(matches!(err, Error::Unsupported { .. }));
+        assert_eq!(err.rpc(), Some("load_dtbo"));
+
+        let err = Error::from(tonic::Status::not_found("abc.bit"));
+        assert!(matches!(err, Error::NotFound { ref message, .. } if message == "abc.bit"));
+
+        let err = Error::from(incompatible_server("2.1"));
+        assert!(
+            matches!(err, Error::IncompatibleServer { ref server_ver, .. } if server_ver == "2.1")
+        );
+        assert!(crate::capabilities::proto_compatible("1.0", "1.3"));
+        assert!(!crate::capabilities::proto_compatible("1.0", "2.0"));
+    }
+}
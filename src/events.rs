@@ -0,0 +1,186 @@
+//! Connection state events
+//!
+//! [`JellyFpgaClient::events`] returns a stream of [`ClientEvent`]s. While
+//! any stream is alive a background task checks the server with
+//! `get_version` every health interval, reporting when the connection is
+//! lost, each retry, and when the server answers again (e.g. after a board
+//! reboot). A [`Lease`](crate::Lease) reports failed renewals and expiry on
+//! the same streams. Clones share the subscribers.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::JellyFpgaClient;
+
+/// Default interval between health checks
+pub const DEFAULT_HEALTH_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest wait between retries while disconnected
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// A health check not answered within this time counts as disconnected
+pub const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Events buffered per stream; a stream that falls further behind misses events
+const EVENT_BUFFER: usize = 64;
+
+/// Connection state change
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClientEvent {
+    /// First successful health check
+    Connected {
+        /// Server version
+        version: String,
+    },
+    /// A health check failed after the server was reachable (or on the first check)
+    Disconnected {
+        /// Error message
+        error: String,
+    },
+    /// The next health check is scheduled after `delay`
+    Retrying {
+        /// Failed checks since the connection was lost
+        attempt: u32,
+        /// Wait before the next check
+        delay: Duration,
+    },
+    /// The server answers again
+    Reconnected {
+        /// Server version
+        version: String,
+        /// Time since the connection was lost
+        downtime: Duration,
+    },
+    /// A lease keepalive failed or was not acknowledged
+    LeaseRenewalFailed {
+        /// Session id
+        session: String,
+        /// Error message
+        error: String,
+    },
+    /// No lease keepalive was acknowledged within the TTL; the server may
+    /// have released the session's handles
+    LeaseExpired {
+        /// Session id
+        session: String,
+    },
+}
+
+/// Subscribers shared by the clones of a client
+#[derive(Default)]
+pub(crate) struct EventHub {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    subscribers: Vec<mpsc::Sender<ClientEvent>>,
+    watching: bool,
+    interval: Option<Duration>,
+}
+
+impl EventHub {
+    /// Send `event` to every live stream
+    pub(crate) fn emit(&self, event: ClientEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.subscribers.retain(|sender| {
+            !matches!(
+                sender.try_send(event.clone()),
+                Err(mpsc::error::TrySendError::Closed(_))
+            )
+        });
+    }
+
+    /// End every stream and mark the watcher stopped (it is aborted by the caller)
+    pub(crate) fn close(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.subscribers.clear();
+        inner.watching = false;
+    }
+
+    /// Stop the watcher if every stream was dropped
+    fn keep_watching(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        inner.subscribers.retain(|sender| !sender.is_closed());
+        inner.watching = !inner.subscribers.is_empty();
+        inner.watching
+    }
+
+    fn interval(&self) -> Duration {
+        self.inner
+            .lock()
+            .unwrap()
+            .interval
+            .unwrap_or(DEFAULT_HEALTH_INTERVAL)
+    }
+}
+
+/// Health check loop run while any stream is alive
+async fn watch(mut client: JellyFpgaClient, hub: Arc<EventHub>) {
+    let mut reachable = None;
+    let mut lost_at = Instant::now();
+    let mut attempt = 0;
+    while hub.keep_watching() {
+        let interval = hub.interval();
+        let checked = match tokio::time::timeout(HEALTH_TIMEOUT, client.get_version()).await {
+            Ok(checked) => checked.map_err(|e| e.message().to_string()),
+            Err(_) => Err(format!("no answer within {:?}", HEALTH_TIMEOUT)),
+        };
+        let delay = match checked {
+            Ok(version) => {
+                match reachable {
+                    None => hub.emit(ClientEvent::Connected { version }),
+                    Some(false) => hub.emit(ClientEvent::Reconnected {
+                        version,
+                        downtime: lost_at.elapsed(),
+                    }),
+                    Some(true) => {}
+                }
+                reachable = Some(true);
+                attempt = 0;
+                interval
+            }
+            Err(error) => {
+                if reachable != Some(false) {
+                    hub.emit(ClientEvent::Disconnected { error });
+                    lost_at = Instant::now();
+                }
+                reachable = Some(false);
+                attempt += 1;
+                let delay = interval
+                    .saturating_mul(1 << (attempt - 1).min(16))
+                    .min(MAX_RETRY_DELAY);
+                hub.emit(ClientEvent::Retrying { attempt, delay });
+                delay
+            }
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+impl JellyFpgaClient {
+    /// Stream of connection events; starts the health check task if needed
+    ///
+    /// Must be called inside a tokio runtime. The task stops once every
+    /// stream is dropped.
+    pub fn events(&self) -> ReceiverStream<ClientEvent> {
+        let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+        let start = {
+            let mut inner = self.events.inner.lock().unwrap();
+            inner.subscribers.push(sender);
+            !std::mem::replace(&mut inner.watching, true)
+        };
+        if start {
+            let task = tokio::spawn(watch(self.clone(), self.events.clone()));
+            self.tasks.register(task.abort_handle());
+        }
+        ReceiverStream::new(receiver)
+    }
+
+    /// Interval between health checks of [`events`](Self::events) (shared by clones)
+    pub fn set_health_interval(&mut self, interval: Duration) {
+        self.events.inner.lock().unwrap().interval = Some(interval);
+    }
+}
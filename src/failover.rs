@@ -0,0 +1,194 @@
+//! Failover between redundant servers
+//!
+//! A [`Failover`] holds clients of a primary and a standby server that
+//! control the same hardware, as in production systems with a standby
+//! controller. [`Failover::run`] runs a block of calls on the active one;
+//! when the block fails with `unavailable`, the active server is probed
+//! every [`PROBE_INTERVAL`] and the block runs again once it answers. If
+//! it stays unreachable beyond the threshold, the other server takes over:
+//! the [environment snapshot](JellyFpgaClient::snapshot_environment) of the
+//! failed client is restored there (same firmware loaded, same devices
+//! opened) and the block runs again on it. The standby must have the
+//! firmware stored under the same names.
+//!
+//! Reopened devices get new ids, and ids of the two servers overlap, so a
+//! device opened through [`Failover::client`] is registered with
+//! [`Failover::register`] and kept as the returned [`DeviceHandle`]. A
+//! block looks up the current id with [`Failover::id`] or makes accessors
+//! with [`Failover::accessor`] instead of keeping ids across calls. A block
+//! may run more than once and should be safe to repeat.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::events::HEALTH_TIMEOUT;
+use crate::{Accessor, JellyFpgaClient};
+
+/// Default time the active server may stay unreachable before failing over
+pub const DEFAULT_THRESHOLD: Duration = Duration::from_secs(10);
+/// Interval between probes of an unreachable server
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Device registered with a [`Failover`], stable across failovers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceHandle(u32);
+
+/// Primary and standby client
+pub struct Failover {
+    clients: [JellyFpgaClient; 2],
+    active: usize,
+    threshold: Duration,
+    /// Handle -> id on the active server
+    ids: BTreeMap<DeviceHandle, u32>,
+    next_handle: u32,
+    failovers: usize,
+}
+
+impl Failover {
+    /// Start on `primary`, failing over to `standby`
+    ///
+    /// Create `standby` with [`connect_lazy`](JellyFpgaClient::connect_lazy)
+    /// so it may be down until needed. Settings such as strict mode apply
+    /// per client, so configure both alike.
+    pub fn new(primary: JellyFpgaClient, standby: JellyFpgaClient) -> Self {
+        Failover {
+            clients: [primary, standby],
+            active: 0,
+            threshold: DEFAULT_THRESHOLD,
+            ids: BTreeMap::new(),
+            next_handle: 0,
+            failovers: 0,
+        }
+    }
+
+    /// Fail over once the active server is unreachable for `threshold`
+    pub fn with_threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Client of the active server
+    pub fn client(&mut self) -> &mut JellyFpgaClient {
+        &mut self.clients[self.active]
+    }
+
+    /// The primary server is active
+    pub fn is_primary(&self) -> bool {
+        self.active == 0
+    }
+
+    /// Number of failovers so far (either way)
+    pub fn failovers(&self) -> usize {
+        self.failovers
+    }
+
+    /// Register the device opened as `id` on the active server
+    ///
+    /// Registering an id again returns the same handle.
+    pub fn register(&mut self, id: u32) -> DeviceHandle {
+        if let Some((&handle, _)) = self.ids.iter().find(|&(_, &current)| current == id) {
+            return handle;
+        }
+        let handle = DeviceHandle(self.next_handle);
+        self.next_handle += 1;
+        self.ids.insert(handle, id);
+        handle
+    }
+
+    /// Current id of the device registered as `handle`
+    ///
+    /// Fails with `not_found` if the device was not reopened on failover.
+    pub fn id(&self, handle: DeviceHandle) -> Result<u32, tonic::Status> {
+        self.ids
+            .get(&handle)
+            .copied()
+            .ok_or_else(|| tonic::Status::not_found(format!("no device for {:?}", handle)))
+    }
+
+    /// Accessor for the device registered as `handle`
+    pub fn accessor(&self, handle: DeviceHandle) -> Result<Accessor, tonic::Status> {
+        Ok(self.clients[self.active].accessor(self.id(handle)?))
+    }
+
+    /// Run `f`, waiting for the server or failing over while it fails with `unavailable`
+    ///
+    /// Other errors are returned as they are. Fails with `unavailable` if
+    /// the active server stays unreachable and restoring on the other
+    /// one fails too.
+    pub async fn run<T, F>(&mut self, mut f: F) -> Result<T, tonic::Status>
+    where
+        F: AsyncFnMut(&mut Failover) -> Result<T, tonic::Status>,
+    {
+        loop {
+            match f(&mut *self).await {
+                Err(e) if e.code() == tonic::Code::Unavailable => self.recover(&e).await?,
+                output => return output,
+            }
+        }
+    }
+
+    /// Wait for the active server to answer, or fail over after the threshold
+    async fn recover(&mut self, error: &tonic::Status) -> Result<(), tonic::Status> {
+        let deadline = Instant::now() + self.threshold;
+        while Instant::now() < deadline {
+            tokio::time::sleep(PROBE_INTERVAL.min(self.threshold)).await;
+            let probe = tokio::time::timeout(HEALTH_TIMEOUT, self.client().get_version()).await;
+            if matches!(probe, Ok(Ok(_))) {
+                return Ok(());
+            }
+        }
+        self.fail_over().await.map_err(|e| {
+            tonic::Status::unavailable(format!(
+                "{} (failover failed: {})",
+                error.message(),
+                e.message()
+            ))
+        })
+    }
+
+    /// Switch to the other server now, restoring the environment there
+    pub async fn fail_over(&mut self) -> Result<(), tonic::Status> {
+        let failed = self.active;
+        let other = 1 - failed;
+        let snapshot = self.clients[failed].snapshot_environment();
+        let remap = self.clients[other].restore_environment(&snapshot).await?;
+        // the failed server's ids are gone with it
+        for (id, _) in &snapshot.handles {
+            self.clients[failed].handles.remove(*id);
+        }
+        self.ids = compose(&self.ids, &remap);
+        self.active = other;
+        self.failovers += 1;
+        Ok(())
+    }
+}
+
+/// Map the ids of registered devices through `remap` (old server id -> new server id)
+///
+/// Devices that were not reopened are dropped.
+fn compose(
+    ids: &BTreeMap<DeviceHandle, u32>,
+    remap: &BTreeMap<u32, u32>,
+) -> BTreeMap<DeviceHandle, u32> {
+    ids.iter()
+        .filter_map(|(&handle, id)| remap.get(id).map(|&new| (handle, new)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose() {
+        let (a, b, c) = (DeviceHandle(0), DeviceHandle(1), DeviceHandle(2));
+        let ids = BTreeMap::from([(a, 1), (b, 2)]);
+        let mut first = compose(&ids, &BTreeMap::from([(1, 3), (2, 4)]));
+        assert_eq!(first, BTreeMap::from([(a, 3), (b, 4)]));
+        // opened on the standby under an id the primary used before
+        first.insert(c, 1);
+        // back again; 9 is not registered, b was not reopened
+        let second = compose(&first, &BTreeMap::from([(3, 1), (1, 2), (9, 5)]));
+        assert_eq!(second, BTreeMap::from([(a, 1), (c, 2)]));
+    }
+}
@@ -0,0 +1,177 @@
+//! Board pool for parallel CI
+//!
+//! A [`Farm`] is a list of server addresses. [`Farm::run`] finds a board
+//! whose [board lock](JellyFpgaClient::acquire_lock) is free, takes it,
+//! runs a closure with the connected client and releases the lock again,
+//! so concurrent CI jobs spread over the pool without a separate
+//! coordination service. Unreachable boards are skipped. As with the lock
+//! itself, this only works if every user of the boards goes through it.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::JellyFpgaClient;
+
+/// Environment variable with comma-separated server addresses
+pub const FARM_ENV: &str = "JELLY_FPGA_FARM";
+
+/// Pause between rounds over a fully busy pool
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Lock owner names handed out in this process, to keep them unique
+static NEXT_OWNER: AtomicUsize = AtomicUsize::new(0);
+
+/// Pool of boards
+#[derive(Debug)]
+pub struct Farm {
+    boards: Vec<String>,
+    owner: String,
+    timeout: Duration,
+    next: AtomicUsize,
+}
+
+impl Farm {
+    /// Pool of `boards` (server addresses), waiting up to 10 minutes for a free one
+    pub fn new<I, S>(boards: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Farm {
+            boards: boards.into_iter().map(Into::into).collect(),
+            owner: format!("farm-{}", std::process::id()),
+            timeout: Duration::from_secs(600),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pool from `$JELLY_FPGA_FARM`; empty if unset
+    pub fn from_env() -> Self {
+        let boards = std::env::var(FARM_ENV).unwrap_or_default();
+        Farm::new(
+            boards
+                .split(',')
+                .map(str::trim)
+                .filter(|b| !b.is_empty())
+                .map(str::to_string),
+        )
+    }
+
+    /// Prefix of the lock owner names (default `farm-<pid>`), e.g. the CI job id
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = owner.to_string();
+        self
+    }
+
+    /// Give up after `timeout` if every board stays busy
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Server addresses of the pool
+    pub fn boards(&self) -> &[String] {
+        &self.boards
+    }
+
+    /// Lock a free board and return its client
+    ///
+    /// Boards are tried round-robin, starting after the one handed out
+    /// last. Fails with `unavailable` after the timeout (carrying the last
+    /// connection error if no board was reachable).
+    pub async fn acquire(&self) -> Result<FarmBoard, tonic::Status> {
+        if self.boards.is_empty() {
+            return Err(tonic::Status::invalid_argument("board farm is empty"));
+        }
+        let owner = format!(
+            "{}#{}",
+            self.owner,
+            NEXT_OWNER.fetch_add(1, Ordering::Relaxed)
+        );
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut last_error = None;
+        loop {
+            for _ in 0..self.boards.len() {
+                let i = self.next.fetch_add(1, Ordering::Relaxed) % self.boards.len();
+                let target = &self.boards[i];
+                let mut client = match JellyFpgaClient::connect(target.clone()).await {
+                    Ok(client) => client,
+                    Err(e) => {
+                        last_error = Some(format!("{}: {}", target, e));
+                        continue;
+                    }
+                };
+                match client.acquire_lock(&owner, Duration::ZERO).await {
+                    Ok(()) => {
+                        return Ok(FarmBoard {
+                            target: target.clone(),
+                            client,
+                        });
+                    }
+                    Err(e)
+                        if matches!(
+                            e.code(),
+                            tonic::Code::PermissionDenied | tonic::Code::Unavailable
+                        ) => {}
+                    Err(e) => last_error = Some(format!("{}: {}", target, e.message())),
+                }
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::unavailable(match last_error {
+                    Some(e) => format!("no free board in the farm (last error: {})", e),
+                    None => "no free board in the farm".to_string(),
+                }));
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Run `f` with the client of a locked board, then release the board
+    ///
+    /// `f` runs as its own task, so the lock is also released if it
+    /// panics (the panic is resumed afterwards). Handles `f` leaves open
+    /// are closed by the release.
+    pub async fn run<F, Fut, T>(&self, f: F) -> Result<T, tonic::Status>
+    where
+        F: FnOnce(JellyFpgaClient) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let board = self.acquire().await?;
+        let outcome = tokio::spawn(f(board.client.clone())).await;
+        let released = board.release().await;
+        match outcome {
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => Err(tonic::Status::aborted(format!("farm task: {}", e))),
+            Ok(result) => {
+                let value = result?;
+                released?;
+                Ok(value)
+            }
+        }
+    }
+}
+
+/// Locked board of a [`Farm`]
+pub struct FarmBoard {
+    target: String,
+    client: JellyFpgaClient,
+}
+
+impl FarmBoard {
+    /// Server address
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Client holding the board lock
+    pub fn client(&mut self) -> &mut JellyFpgaClient {
+        &mut self.client
+    }
+
+    /// Close the client's handles and release the board lock
+    pub async fn release(self) -> Result<(), tonic::Status> {
+        self.client.shutdown().await.map(|_| ())
+    }
+}
@@ -0,0 +1,225 @@
+//! Fault injection for testing
+//!
+//! [`JellyFpgaClient::with_fault_injection`] makes RPCs randomly slow,
+//! lost or failed according to a [`FaultPolicy`], so retry and cleanup code
+//! can be exercised against a flaky link without unplugging the board. The
+//! random sequence is seeded, so a failing run can be repeated (as long as
+//! the calls are issued in the same order).
+//!
+//! - a *delay* sleeps up to `max_delay` before the request is sent
+//! - a *drop* fails with `unavailable` without sending the request
+//! - an *error* sends the request, then discards the response and fails with
+//!   the configured code, as when the link breaks before the reply arrives
+//!   (the operation did take effect on the board)
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::JellyFpgaClient;
+use crate::limiter::Plane;
+
+/// What to inject and how often
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultPolicy {
+    /// Seed of the random sequence
+    pub seed: u64,
+    /// Probability of delaying a call
+    pub delay_rate: f64,
+    /// Longest injected delay
+    pub max_delay: Duration,
+    /// Probability of dropping a request
+    pub drop_rate: f64,
+    /// Probability of losing a response
+    pub error_rate: f64,
+    /// Status code of lost responses
+    pub error_code: tonic::Code,
+    /// Only affect RPCs of this plane
+    pub plane: Option<Plane>,
+}
+
+impl FaultPolicy {
+    /// Policy injecting nothing yet
+    pub fn new(seed: u64) -> Self {
+        FaultPolicy {
+            seed,
+            delay_rate: 0.0,
+            max_delay: Duration::ZERO,
+            drop_rate: 0.0,
+            error_rate: 0.0,
+            error_code: tonic::Code::Unavailable,
+            plane: None,
+        }
+    }
+
+    /// Delay a fraction `rate` of calls by up to `max_delay`
+    pub fn with_delay(mut self, rate: f64, max_delay: Duration) -> Self {
+        self.delay_rate = rate;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Drop a fraction `rate` of requests
+    pub fn with_drop(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Lose the response of a fraction `rate` of calls, failing with `code`
+    pub fn with_error(mut self, rate: f64, code: tonic::Code) -> Self {
+        self.error_rate = rate;
+        self.error_code = code;
+        self
+    }
+
+    /// Leave RPCs of the other plane alone
+    pub fn only(mut self, plane: Plane) -> Self {
+        self.plane = Some(plane);
+        self
+    }
+}
+
+/// Counts of injected faults
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    /// Calls the policy applied to
+    pub calls: u64,
+    /// Delayed calls
+    pub delayed: u64,
+    /// Dropped requests
+    pub dropped: u64,
+    /// Lost responses
+    pub errored: u64,
+}
+
+/// Policy and random state shared by a client and its clones
+pub(crate) struct FaultInjector {
+    policy: FaultPolicy,
+    state: Mutex<(u64, FaultStats)>,
+}
+
+/// splitmix64 step
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Uniform value in `[0, 1)`
+fn unit(state: &mut u64) -> f64 {
+    (next(state) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Faults chosen for one call
+#[derive(Debug, Default, PartialEq)]
+struct Decision {
+    delay: Option<Duration>,
+    drop: bool,
+    error: bool,
+}
+
+impl FaultInjector {
+    pub(crate) fn new(policy: FaultPolicy) -> Self {
+        let seed = policy.seed;
+        FaultInjector {
+            policy,
+            state: Mutex::new((seed, FaultStats::default())),
+        }
+    }
+
+    fn decide(&self, plane: Plane) -> Decision {
+        if self.policy.plane.is_some_and(|p| p != plane) {
+            return Decision::default();
+        }
+        let mut state = self.state.lock().unwrap();
+        let (rng, stats) = &mut *state;
+        // draw every value so the sequence does not depend on the rates
+        let delay = unit(rng) < self.policy.delay_rate;
+        let delay_by = self.policy.max_delay.mul_f64(unit(rng));
+        let drop = unit(rng) < self.policy.drop_rate;
+        let error = unit(rng) < self.policy.error_rate && !drop;
+        stats.calls += 1;
+        stats.delayed += delay as u64;
+        stats.dropped += drop as u64;
+        stats.errored += error as u64;
+        Decision {
+            delay: delay.then_some(delay_by),
+            drop,
+            error,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> FaultStats {
+        self.state.lock().unwrap().1
+    }
+
+    /// Run `call` with the faults the policy picks for it
+    pub(crate) async fn inject<T, F>(&self, plane: Plane, call: F) -> Result<T, tonic::Status>
+    where
+        F: Future<Output = Result<T, tonic::Status>>,
+    {
+        let decision = self.decide(plane);
+        if let Some(delay) = decision.delay {
+            tokio::time::sleep(delay).await;
+        }
+        if decision.drop {
+            return Err(tonic::Status::unavailable(
+                "injected fault: request dropped",
+            ));
+        }
+        let response = call.await?;
+        if decision.error {
+            return Err(tonic::Status::new(
+                self.policy.error_code,
+                "injected fault: response lost",
+            ));
+        }
+        Ok(response)
+    }
+}
+
+impl JellyFpgaClient {
+    /// Inject faults into the RPCs of this client and clones made afterwards
+    ///
+    /// Only for tests. Calls through [`raw_mut`](Self::raw_mut) are not
+    /// affected.
+    pub fn with_fault_injection(mut self, policy: FaultPolicy) -> Self {
+        std::sync::Arc::make_mut(&mut self.limiter).set_faults(FaultInjector::new(policy));
+        self
+    }
+
+    /// Faults injected so far, if fault injection is enabled
+    pub fn fault_stats(&self) -> Option<FaultStats> {
+        self.limiter.faults().map(|f| f.stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_decisions() {
+        let policy = FaultPolicy::new(42)
+            .with_drop(0.5)
+            .with_error(0.5, tonic::Code::Internal)
+            .only(Plane::Data);
+        let a = FaultInjector::new(policy.clone());
+        let b = FaultInjector::new(policy);
+        let runs: Vec<Decision> = (0..64).map(|_| a.decide(Plane::Data)).collect();
+        assert!(
+            runs.iter()
+                .zip((0..64).map(|_| b.decide(Plane::Data)))
+                .all(|(x, y)| *x == y)
+        );
+        assert!(runs.iter().any(|d| d.drop) && runs.iter().any(|d| d.error));
+        assert!(
+            runs.iter()
+                .all(|d| !(d.drop && d.error) && d.delay.is_none())
+        );
+        assert_eq!(a.decide(Plane::Control), Decision::default());
+        assert_eq!(a.stats().calls, 64);
+    }
+}
@@ -0,0 +1,110 @@
+//! Register bitfields
+
+use crate::addr::AccessSize;
+
+/// Bitfield `[shift, shift + width)` of register `reg`
+///
+/// Accessed with 32-bit register reads/writes, or 64-bit ones if the field
+/// reaches beyond bit 31.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Field {
+    /// Register index
+    pub reg: u64,
+    /// Lowest bit
+    pub shift: u32,
+    /// Width in bits (1..=64)
+    pub width: u32,
+}
+
+impl Field {
+    /// Create field
+    pub const fn new(reg: u64, shift: u32, width: u32) -> Self {
+        Field { reg, shift, width }
+    }
+
+    /// Single bit field
+    pub const fn bit(reg: u64, bit: u32) -> Self {
+        Field::new(reg, bit, 1)
+    }
+
+    /// Register access size in bytes
+    pub const fn size(&self) -> u64 {
+        self.access_size().bytes()
+    }
+
+    /// Register access size
+    pub const fn access_size(&self) -> AccessSize {
+        if self.shift + self.width > 32 {
+            AccessSize::U64
+        } else {
+            AccessSize::U32
+        }
+    }
+
+    /// Field mask in register position
+    pub const fn mask(&self) -> u64 {
+        let bits = if self.width >= 64 {
+            !0
+        } else {
+            (1u64 << self.width) - 1
+        };
+        bits << self.shift
+    }
+
+    /// Extract the unsigned field value from a register value
+    pub const fn extract(&self, reg_value: u64) -> u64 {
+        (reg_value & self.mask()) >> self.shift
+    }
+
+    /// Extract the field as a two's complement signed value
+    pub const fn extract_signed(&self, reg_value: u64) -> i64 {
+        let sh = 64 - self.width;
+        ((self.extract(reg_value) << sh) as i64) >> sh
+    }
+
+    /// Replace the field in a register value; fails if `value` does not fit
+    pub fn insert(&self, reg_value: u64, value: u64) -> Result<u64, tonic::Status> {
+        if value & !(self.mask() >> self.shift) != 0 {
+            return Err(tonic::Status::out_of_range(format!(
+                "value 0x{:x} does not fit in {}-bit field",
+                value, self.width
+            )));
+        }
+        Ok((reg_value & !self.mask()) | (value << self.shift))
+    }
+
+    /// Replace the field with a signed value; fails if `value` does not fit
+    pub fn insert_signed(&self, reg_value: u64, value: i64) -> Result<u64, tonic::Status> {
+        let sh = 64 - self.width;
+        if (value << sh) >> sh != value {
+            return Err(tonic::Status::out_of_range(format!(
+                "value {} does not fit in signed {}-bit field",
+                value, self.width
+            )));
+        }
+        let bits = if self.width >= 64 {
+            value as u64
+        } else {
+            value as u64 & ((1u64 << self.width) - 1)
+        };
+        self.insert(reg_value, bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field() {
+        let f = Field::new(0, 4, 4);
+        assert_eq!(f.mask(), 0xf0);
+        assert_eq!(f.extract(0x1234), 0x3);
+        assert_eq!(f.insert(0x1234, 0xa).unwrap(), 0x12a4);
+        assert!(f.insert(0, 0x10).is_err());
+        assert_eq!(f.extract_signed(0xf0), -1);
+        assert_eq!(f.insert_signed(0, -2).unwrap(), 0xe0);
+        assert!(f.insert_signed(0, 8).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Firmware file metadata
+//!
+//! The server has no stat or statvfs RPC, so metadata is kept in a
+//! manifest: a reserved firmware file listing size, upload time and SHA-256
+//! of files uploaded by [`JellyFpgaClient::ensure_firmware`], or by every
+//! `upload_firmware` once enabled with
+//! [`JellyFpgaClient::set_record_manifest`] (entries are dropped again by
+//! `remove_firmware`). Recording is off by default because it costs a hash
+//! of the data and a read and rewrite of the manifest per upload. Presence on the board is probed by mapping the first
+//! byte of the file. Updates of the manifest are serialized between clones
+//! of a client, but like the board lock it is advisory across clients:
+//! files copied by other means have no metadata, and concurrent uploads
+//! from two separate clients may drop an entry.
+//!
+//! [`JellyFpgaClient::storage_info`] sums the manifest for the same reason.
+//! Free space of the firmware directory is not known to the client: it is
+//! reported as `None` until the server offers a statvfs RPC.
+
+use sha2::{Digest, Sha256};
+
+use crate::JellyFpgaClient;
+use crate::jelly_fpga_control::{
+    CloseRequest, MemCopyFromRequest, OpenMmapRequest, UploadFirmwareRequest,
+};
+
+/// Server firmware directory
+pub const FIRMWARE_DIR: &str = "/lib/firmware";
+/// Firmware name of the metadata manifest
+pub const MANIFEST_FIRMWARE_NAME: &str = "jelly-fpga-client.manifest";
+/// Manifest file size (entries padded with zeros so it can be memory mapped)
+pub const MANIFEST_FILE_SIZE: u64 = 64 * 1024;
+
+/// Metadata of a firmware file on the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FirmwareStat {
+    /// Firmware name
+    pub name: String,
+    /// Size in bytes (`None` if not uploaded through this client library)
+    pub size: Option<u64>,
+    /// Upload time recorded by the uploading client, in seconds since the
+    /// Unix epoch by its clock (not the board's file time)
+    pub mtime: Option<u64>,
+    /// SHA-256 of the contents as lowercase hex
+    pub sha256: Option<String>,
+}
+
+/// Firmware storage usage
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StorageInfo {
+    /// Files recorded in the manifest
+    pub files: usize,
+    /// Total size of the recorded files
+    pub used_bytes: u64,
+    /// Free bytes in the firmware directory (`None`: not reported by the server)
+    pub free_bytes: Option<u64>,
+}
+
+/// Manifest entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub(crate) name: String,
+    pub(crate) size: u64,
+    pub(crate) mtime: u64,
+    pub(crate) sha256: String,
+}
+
+/// Parse manifest lines `sha256 size mtime name` (trailing zero padding ignored)
+pub(crate) fn parse_manifest(data: &[u8]) -> Vec<Entry> {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end])
+        .lines()
+        .filter_map(|line| {
+            let mut it = line.splitn(4, ' ');
+            let sha256 = it.next()?.to_string();
+            let size = it.next()?.parse().ok()?;
+            let mtime = it.next()?.parse().ok()?;
+            let name = it.next()?.to_string();
+            Some(Entry {
+                name,
+                size,
+                mtime,
+                sha256,
+            })
+        })
+        .collect()
+}
+
+/// Format entries, padded to [`MANIFEST_FILE_SIZE`]
+pub(crate) fn format_manifest(entries: &[Entry]) -> Result<Vec<u8>, tonic::Status> {
+    let mut data: Vec<u8> = entries
+        .iter()
+        .map(|e| format!("{} {} {} {}\n", e.sha256, e.size, e.mtime, e.name))
+        .collect::<String>()
+        .into_bytes();
+    if data.len() as u64 > MANIFEST_FILE_SIZE {
+        return Err(tonic::Status::resource_exhausted(
+            "firmware manifest is full",
+        ));
+    }
+    data.resize(MANIFEST_FILE_SIZE as usize, 0);
+    Ok(data)
+}
+
+/// SHA-256 as lowercase hex
+pub(crate) fn sha256_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 of `data` as lowercase hex
+pub fn sha256(data: &[u8]) -> String {
+    sha256_hex(&Sha256::digest(data))
+}
+
+/// [`sha256`] on the blocking pool, handing `data` back
+///
+/// Hashing a bitstream of a hundred megabytes takes long enough to stall
+/// the executor. The wasm build has no blocking pool and hashes in place.
+pub(crate) async fn sha256_blocking(data: Vec<u8>) -> Result<(Vec<u8>, String), tonic::Status> {
+    #[cfg(not(feature = "wasm"))]
+    return tokio::task::spawn_blocking(move || {
+        let digest = sha256(&data);
+        (data, digest)
+    })
+    .await
+    .map_err(|e| tonic::Status::internal(e.to_string()));
+    #[cfg(feature = "wasm")]
+    {
+        let digest = sha256(&data);
+        Ok((data, digest))
+    }
+}
+
+fn now_secs() -> u64 {
+    #[cfg(not(feature = "wasm"))]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    #[cfg(feature = "wasm")]
+    return 0;
+}
+
+impl JellyFpgaClient {
+    /// Record every uploaded firmware file in the manifest (clones inherit the setting)
+    ///
+    /// Off by default; [`ensure_firmware`](Self::ensure_firmware) records
+    /// its uploads either way. With recording on, an upload whose manifest
+    /// update fails returns the error although the file was uploaded.
+    pub fn set_record_manifest(&mut self, record: bool) {
+        self.record_manifest = record;
+    }
+
+    /// Whether uploads are recorded in the manifest
+    pub fn records_manifest(&self) -> bool {
+        self.record_manifest
+    }
+
+    /// Metadata of firmware `name`, or `None` if it is not on the board
+    ///
+    /// Files that exist but were not recorded are returned with all
+    /// metadata fields `None`. A file replaced by an unrecorded upload keeps
+    /// its old entry, so the metadata may be stale unless all uploads are
+    /// recorded. Empty files cannot be
+    /// mapped and are reported as missing.
+    pub async fn firmware_stat(
+        &mut self,
+        name: &str,
+    ) -> Result<Option<FirmwareStat>, tonic::Status> {
+        let path = format!("{}/{}", FIRMWARE_DIR, name);
+        if self.read_board_file(&path, 1).await?.is_none() {
+            return Ok(None);
+        }
+        let entry = self.manifest().await?.into_iter().find(|e| e.name == name);
+        Ok(Some(FirmwareStat {
+            name: name.to_string(),
+            size: entry.as_ref().map(|e| e.size),
+            mtime: entry.as_ref().map(|e| e.mtime),
+            sha256: entry.map(|e| e.sha256),
+        }))
+    }
+
+    /// Upload `local_path` as firmware `name` unless the board already has an identical copy
+    ///
+    /// The manifest only preselects: if its size and SHA-256 match, the
+    /// board's copy is read back and compared byte for byte, and the upload
+    /// is skipped only if it is identical. Files without a matching entry
+    /// (placed by other means, or changed since) and copies that cannot be
+    /// read back are uploaded. The manifest size also bounds the read, as
+    /// mapping past the end of a file faults in the server. The upload is
+    /// recorded in the manifest whether or not
+    /// [`set_record_manifest`](Self::set_record_manifest) is enabled.
+    /// Returns whether an upload happened.
+    pub async fn ensure_firmware(
+        &mut self,
+        name: &str,
+        local_path: &str,
+    ) -> Result<bool, tonic::Status> {
+        let data = crate::fs::read(local_path).await?;
+        let (data, digest) = sha256_blocking(data).await?;
+
+        if let Some(stat) = self.firmware_stat(name).await?
+            && stat.size == Some(data.len() as u64)
+            && stat.sha256.as_deref() == Some(digest.as_str())
+        {
+            let path = format!("{}/{}", FIRMWARE_DIR, name);
+            let board = self.read_board_file(&path, data.len() as u64).await;
+            if matches!(board, Ok(Some(ref board)) if *board == data) {
+                return Ok(false);
+            }
+        }
+        let result = self
+            .upload_firmware_recorded(name, data, Some(digest))
+            .await?;
+        crate::accessor::check(result, "upload_firmware")?;
+        Ok(true)
+    }
+
+    /// Usage of the firmware directory by files recorded in the manifest
+    ///
+    /// Files placed by other means are not counted. `free_bytes` is always
+    /// `None` with current servers, which have no way to report free space.
+    pub async fn storage_info(&mut self) -> Result<StorageInfo, tonic::Status> {
+        let entries = self.manifest().await?;
+        Ok(StorageInfo {
+            files: entries.len(),
+            used_bytes: entries.iter().map(|e| e.size).sum(),
+            free_bytes: None,
+        })
+    }
+
+    /// Read manifest entries (empty if there is no manifest)
+    pub(crate) async fn manifest(&mut self) -> Result<Vec<Entry>, tonic::Status> {
+        let path = format!("{}/{}", FIRMWARE_DIR, MANIFEST_FIRMWARE_NAME);
+        Ok(self
+            .read_board_file(&path, MANIFEST_FILE_SIZE)
+            .await?
+            .map(|data| parse_manifest(&data))
+            .unwrap_or_default())
+    }
+
+    /// Record an uploaded file
+    pub(crate) async fn record_firmware(
+        &mut self,
+        name: &str,
+        size: u64,
+        sha256: String,
+    ) -> Result<(), tonic::Status> {
+        let entry = Entry {
+            name: name.to_string(),
+            size,
+            mtime: now_secs(),
+            sha256,
+        };
+        self.update_manifest(name, Some(entry)).await
+    }
+
+    /// Drop a removed file from the manifest
+    ///
+    /// Best effort, as the file is gone already: a stale entry is ignored by
+    /// `firmware_stat` (presence is probed first) and `ensure_firmware`
+    /// (contents are compared).
+    pub(crate) async fn forget_firmware(&mut self, name: &str) {
+        let _ = self.update_manifest(name, None).await;
+    }
+
+    async fn update_manifest(
+        &mut self,
+        name: &str,
+        entry: Option<Entry>,
+    ) -> Result<(), tonic::Status> {
+        if name == MANIFEST_FIRMWARE_NAME
+            || name == crate::LOCK_FIRMWARE_NAME
+            || name == crate::accel::ACCELS_FIRMWARE_NAME
+        {
+            return Ok(());
+        }
+        // clones upload in parallel (e.g. `deploy`); without the lock one
+        // read-modify-write would drop the other's entry
+        let board_files = self.board_files.clone();
+        let _guard = board_files.lock().await;
+        let mut entries = self.manifest().await?;
+        let len = entries.len();
+        entries.retain(|e| e.name != name);
+        if entry.is_none() && entries.len() == len {
+            return Ok(());
+        }
+        entries.extend(entry);
+        let data = format_manifest(&entries)?;
+        self.write_board_firmware(MANIFEST_FIRMWARE_NAME, data)
+            .await
+    }
+
+    /// Upload a reserved firmware file without touching the manifest
+    pub(crate) async fn write_board_firmware(
+        &mut self,
+        name: &str,
+        data: Vec<u8>,
+    ) -> Result<(), tonic::Status> {
+        let stream = tokio_stream::iter(vec![UploadFirmwareRequest {
+            name: name.to_string(),
+            data,
+        }]);
+        let request = self.request(stream);
+        let response = self
+            .limiter
+            .run(
+                "upload_firmware",
+                self.bulk_client().upload_firmware(request),
+            )
+            .await
+            .map_err(|e| crate::error::with_rpc(e, "upload_firmware"))?;
+        crate::accessor::check(response.into_inner().result, &format!("upload {}", name))
+    }
+
+    /// Read `size` bytes of a board file through a temporary mapping
+    ///
+    /// Returns `None` if the file cannot be mapped (missing or empty). Goes
+    /// through the raw client so strict mode and the handle registry are
+    /// not involved.
+    pub(crate) async fn read_board_file(
+        &mut self,
+        path: &str,
+        size: u64,
+    ) -> Result<Option<Vec<u8>>, tonic::Status> {
+        let request = self.request(OpenMmapRequest {
+            path: path.to_string(),
+            offset: 0,
+            size,
+            unit: 1,
+        });
+        let open = self
+            .limiter
+            .run("open_mmap", self.client.open_mmap(request))
+            .await
+            .map_err(|e| crate::error::with_rpc(e, "open_mmap"))?
+            .into_inner();
+        if !open.result {
+            return Ok(None);
+        }
+        let read = self.read_board_chunks(open.id, size).await;
+        let request = self.request(CloseRequest { id: open.id });
+        self.limiter
+            .run("close", self.client.close(request))
+            .await
+            .map_err(|e| crate::error::with_rpc(e, "close"))?;
+        read
+    }
+
+    /// Read a mapped file in messages of at most the upload chunk size
+    async fn read_board_chunks(
+        &mut self,
+        id: u32,
+        size: u64,
+    ) -> Result<Option<Vec<u8>>, tonic::Status> {
+        let mut data = Vec::with_capacity(size as usize);
+        while (data.len() as u64) < size {
+            let offset = data.len() as u64;
+            let request = self.request(MemCopyFromRequest {
+                id,
+                offset,
+                size: (size - offset).min(self.upload_chunk_size as u64),
+            });
+            let read = self
+                .limiter
+                .run("mem_copy_from", self.bulk_client().mem_copy_from(request))
+                .await
+                .map_err(|e| crate::error::with_rpc(e, "mem_copy_from"))?
+                .into_inner();
+            if !read.result || read.data.is_empty() {
+                return Ok(None);
+            }
+            data.extend_from_slice(&read.data);
+        }
+        Ok(Some(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let entries = vec![
+            Entry {
+                name: "kv260_blinking_led_ps.bit.bin".to_string(),
+                size: 7_797_356,
+                mtime: 1_700_000_000,
+                sha256: sha256(b"bitstream"),
+            },
+            Entry {
+                name: "with space.dtbo".to_string(),
+                size: 1234,
+                mtime: 0,
+                sha256: sha256(b""),
+            },
+        ];
+        let data = format_manifest(&entries).unwrap();
+        assert_eq!(data.len() as u64, MANIFEST_FILE_SIZE);
+        assert_eq!(parse_manifest(&data), entries);
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
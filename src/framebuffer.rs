@@ -0,0 +1,281 @@
+//! Frame buffer in device memory
+//!
+//! With the `image` feature enabled, frames can be converted from/to
+//! `image::DynamicImage` and image files.
+
+use crate::accessor::Accessor;
+
+/// Default transfer chunk size (1 MiB)
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Pixel layout in device memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PixelFormat {
+    /// 8-bit gray
+    Gray8,
+    /// 16-bit gray (little endian)
+    Gray16,
+    /// R, G, B
+    Rgb888,
+    /// B, G, R
+    Bgr888,
+    /// R, G, B, A
+    Rgba8888,
+    /// B, G, R, A
+    Bgra8888,
+}
+
+impl PixelFormat {
+    /// Bytes per pixel
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Gray8 => 1,
+            PixelFormat::Gray16 => 2,
+            PixelFormat::Rgb888 | PixelFormat::Bgr888 => 3,
+            PixelFormat::Rgba8888 | PixelFormat::Bgra8888 => 4,
+        }
+    }
+}
+
+/// Frame buffer wrapping a memory region
+pub struct FrameBuffer {
+    mem: Accessor,
+    offset: u64,
+    width: usize,
+    height: usize,
+    stride: usize,
+    format: PixelFormat,
+    chunk_size: usize,
+}
+
+impl FrameBuffer {
+    /// Create frame buffer at `offset` of `mem` with packed lines
+    pub fn new(
+        mem: Accessor,
+        offset: u64,
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+    ) -> Self {
+        FrameBuffer {
+            mem,
+            offset,
+            width,
+            height,
+            stride: width * format.bytes_per_pixel(),
+            format,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Set line stride in bytes
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    /// Set transfer chunk size
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size;
+    }
+
+    /// Width in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in lines
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Line stride in bytes
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Pixel format
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Memory accessor
+    pub fn mem(&mut self) -> &mut Accessor {
+        &mut self.mem
+    }
+
+    /// Bytes of one packed line
+    pub fn line_bytes(&self) -> usize {
+        self.width * self.format.bytes_per_pixel()
+    }
+
+    /// Bytes of one packed frame
+    pub fn frame_bytes(&self) -> usize {
+        self.line_bytes() * self.height
+    }
+
+    /// Write a packed frame (`frame_bytes()` long)
+    pub async fn write_frame(&mut self, data: &[u8]) -> Result<(), tonic::Status> {
+        if data.len() != self.frame_bytes() {
+            return Err(tonic::Status::invalid_argument(format!(
+                "frame size mismatch: expected {} bytes, got {}",
+                self.frame_bytes(),
+                data.len()
+            )));
+        }
+        let line_bytes = self.line_bytes();
+        if self.stride == line_bytes {
+            return self
+                .mem
+                .write_bytes_chunked(self.offset, data, self.chunk_size)
+                .await;
+        }
+        for (y, line) in data.chunks(line_bytes).enumerate() {
+            let offset = self.offset + (y * self.stride) as u64;
+            self.mem.mem_copy_to(offset, line.to_vec()).await?;
+        }
+        Ok(())
+    }
+
+    /// Read a packed frame
+    pub async fn read_frame(&mut self) -> Result<Vec<u8>, tonic::Status> {
+        let line_bytes = self.line_bytes();
+        if self.stride == line_bytes {
+            return self
+                .mem
+                .read_bytes_chunked(self.offset, self.frame_bytes() as u64, self.chunk_size)
+                .await;
+        }
+        let mut data = Vec::with_capacity(self.frame_bytes());
+        for y in 0..self.height {
+            let offset = self.offset + (y * self.stride) as u64;
+            data.extend(self.mem.mem_copy_from(offset, line_bytes as u64).await?);
+        }
+        Ok(data)
+    }
+
+    /// Fill a rectangle with one pixel value (`bytes_per_pixel()` long)
+    pub async fn fill_rect(
+        &mut self,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+        pixel: &[u8],
+    ) -> Result<(), tonic::Status> {
+        let bpp = self.format.bytes_per_pixel();
+        if pixel.len() != bpp {
+            return Err(tonic::Status::invalid_argument(format!(
+                "pixel must be {} bytes for {:?}",
+                bpp, self.format
+            )));
+        }
+        if x + w > self.width || y + h > self.height {
+            return Err(tonic::Status::out_of_range(format!(
+                "rectangle ({}, {}, {}, {}) exceeds {}x{} frame",
+                x, y, w, h, self.width, self.height
+            )));
+        }
+        let line = pixel.repeat(w);
+        for row in y..y + h {
+            let offset = self.offset + (row * self.stride + x * bpp) as u64;
+            self.mem.mem_copy_to(offset, line.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Fill the whole frame with one pixel value
+    pub async fn fill(&mut self, pixel: &[u8]) -> Result<(), tonic::Status> {
+        self.fill_rect(0, 0, self.width, self.height, pixel).await
+    }
+}
+
+#[cfg(feature = "image")]
+impl FrameBuffer {
+    /// Write an image (must match the frame size; converted to the pixel format)
+    pub async fn write_image(&mut self, img: &image::DynamicImage) -> Result<(), tonic::Status> {
+        if img.width() as usize != self.width || img.height() as usize != self.height {
+            return Err(tonic::Status::invalid_argument(format!(
+                "image size {}x{} does not match frame {}x{}",
+                img.width(),
+                img.height(),
+                self.width,
+                self.height
+            )));
+        }
+        let data = match self.format {
+            PixelFormat::Gray8 => img.to_luma8().into_raw(),
+            PixelFormat::Gray16 => img
+                .to_luma16()
+                .into_raw()
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+            PixelFormat::Rgb888 => img.to_rgb8().into_raw(),
+            PixelFormat::Bgr888 => swap_rb(img.to_rgb8().into_raw(), 3),
+            PixelFormat::Rgba8888 => img.to_rgba8().into_raw(),
+            PixelFormat::Bgra8888 => swap_rb(img.to_rgba8().into_raw(), 4),
+        };
+        self.write_frame(&data).await
+    }
+
+    /// Read the frame back as an image
+    pub async fn read_image(&mut self) -> Result<image::DynamicImage, tonic::Status> {
+        let data = self.read_frame().await?;
+        let (w, h) = (self.width as u32, self.height as u32);
+        let img = match self.format {
+            PixelFormat::Gray8 => image::GrayImage::from_raw(w, h, data).map(Into::into),
+            PixelFormat::Gray16 => {
+                let pixels = data
+                    .chunks_exact(2)
+                    .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                    .collect();
+                image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(w, h, pixels)
+                    .map(Into::into)
+            }
+            PixelFormat::Rgb888 => image::RgbImage::from_raw(w, h, data).map(Into::into),
+            PixelFormat::Bgr888 => {
+                image::RgbImage::from_raw(w, h, swap_rb(data, 3)).map(Into::into)
+            }
+            PixelFormat::Rgba8888 => image::RgbaImage::from_raw(w, h, data).map(Into::into),
+            PixelFormat::Bgra8888 => {
+                image::RgbaImage::from_raw(w, h, swap_rb(data, 4)).map(Into::into)
+            }
+        };
+        img.ok_or_else(|| tonic::Status::internal("frame data size mismatch"))
+    }
+
+    /// Load an image file and write it to the frame buffer
+    ///
+    /// The file is read asynchronously and decoded in memory (format from
+    /// the contents).
+    pub async fn write_image_file(&mut self, path: &str) -> Result<(), tonic::Status> {
+        let data = crate::fs::read(path).await?;
+        let img = image::load_from_memory(&data).map_err(|e| {
+            tonic::Status::internal(format!("Failed to open image {}: {}", path, e))
+        })?;
+        self.write_image(&img).await
+    }
+
+    /// Capture the frame buffer into an image file (format from extension)
+    pub async fn save_image_file(&mut self, path: &str) -> Result<(), tonic::Status> {
+        let failed = |e: image::ImageError| {
+            tonic::Status::internal(format!("Failed to save image {}: {}", path, e))
+        };
+        let format = image::ImageFormat::from_path(path).map_err(failed)?;
+        let img = self.read_image().await?;
+        let mut data = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut data), format)
+            .map_err(failed)?;
+        crate::fs::write(path, data).await
+    }
+}
+
+#[cfg(feature = "image")]
+fn swap_rb(mut data: Vec<u8>, bpp: usize) -> Vec<u8> {
+    for px in data.chunks_exact_mut(bpp) {
+        px.swap(0, 2);
+    }
+    data
+}
@@ -0,0 +1,172 @@
+//! Host file access for the upload/boot helpers
+//!
+//! Natively the reads go through `tokio::fs`, which runs them on the
+//! blocking pool so a large bitstream does not stall the executor. The wasm
+//! build has no blocking pool and falls back to `std::fs`.
+
+use std::path::Path;
+
+/// Read a whole file, mapping I/O errors to `internal`
+pub(crate) async fn read(path: impl AsRef<Path>) -> Result<Vec<u8>, tonic::Status> {
+    let path = path.as_ref();
+    #[cfg(not(feature = "wasm"))]
+    let data = tokio::fs::read(path).await;
+    #[cfg(feature = "wasm")]
+    let data = std::fs::read(path);
+    data.map_err(|e| {
+        tonic::Status::internal(format!("Failed to read file {}: {}", path.display(), e))
+    })
+}
+
+/// Read a whole file as UTF-8
+pub(crate) async fn read_to_string(path: impl AsRef<Path>) -> std::io::Result<String> {
+    #[cfg(not(feature = "wasm"))]
+    return tokio::fs::read_to_string(path).await;
+    #[cfg(feature = "wasm")]
+    return std::fs::read_to_string(path);
+}
+
+/// Size of a file, mapping I/O errors to `internal`
+pub(crate) async fn size(path: impl AsRef<Path>) -> Result<u64, tonic::Status> {
+    let path = path.as_ref();
+    #[cfg(not(feature = "wasm"))]
+    let metadata = tokio::fs::metadata(path).await;
+    #[cfg(feature = "wasm")]
+    let metadata = std::fs::metadata(path);
+    metadata.map(|m| m.len()).map_err(|e| {
+        tonic::Status::internal(format!("Failed to read file {}: {}", path.display(), e))
+    })
+}
+
+/// Write a whole file, mapping I/O errors to `internal`
+pub(crate) async fn write(path: impl AsRef<Path>, data: Vec<u8>) -> Result<(), tonic::Status> {
+    let path = path.as_ref();
+    #[cfg(not(feature = "wasm"))]
+    let result = tokio::fs::write(path, data).await;
+    #[cfg(feature = "wasm")]
+    let result = std::fs::write(path, data);
+    result.map_err(|e| {
+        tonic::Status::internal(format!("Failed to write file {}: {}", path.display(), e))
+    })
+}
+
+/// Append to a file, creating it if missing
+pub(crate) async fn append(path: impl AsRef<Path>, data: &[u8]) -> Result<(), tonic::Status> {
+    let path = path.as_ref();
+    #[cfg(not(feature = "wasm"))]
+    let result = async {
+        use tokio::io::AsyncWriteExt;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(data).await
+    }
+    .await;
+    #[cfg(feature = "wasm")]
+    let result = {
+        use std::io::Write;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(data))
+    };
+    result.map_err(|e| {
+        tonic::Status::internal(format!("Failed to write file {}: {}", path.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use crate::JellyFpgaClient;
+
+    const LARGE: usize = 100 * 1024 * 1024;
+
+    /// Write a 100 MB file unique to `test`
+    fn large_file(test: &str) -> std::path::PathBuf {
+        let name = format!("jelly-fs-{}-{}.bin", test, std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, vec![0x5a; LARGE]).unwrap();
+        path
+    }
+
+    /// Run `f` next to a 1 ms ticker and return the longest time the ticker could not run
+    async fn longest_stall<F: Future>(f: F) -> (F::Output, Duration) {
+        let last = Arc::new(Mutex::new((Instant::now(), Duration::ZERO)));
+        let ticker = {
+            let last = last.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    let mut last = last.lock().unwrap();
+                    let now = Instant::now();
+                    last.1 = last.1.max(now - last.0);
+                    last.0 = now;
+                }
+            })
+        };
+        tokio::task::yield_now().await;
+        let output = f.await;
+        ticker.abort();
+        let (at, longest) = *last.lock().unwrap();
+        (output, longest.max(at.elapsed()))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_read_does_not_stall_executor() {
+        let path = large_file("read");
+        let (data, stall) = longest_stall(read(&path)).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(data.unwrap().len(), LARGE);
+        // the single executor thread kept running the ticker during the read
+        assert!(
+            stall < Duration::from_millis(500),
+            "stalled for {:?}",
+            stall
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_upload_does_not_stall_executor() {
+        // reading and hashing happen before the first RPC, which fails
+        // as nothing listens on the port
+        let path = large_file("upload");
+        let path_str = path.to_str().unwrap();
+        let mut client = JellyFpgaClient::connect_lazy("http://127.0.0.1:1").unwrap();
+        client.set_record_manifest(true);
+
+        let upload = client.upload_firmware_file("large.bit.bin", path_str);
+        let (result, upload_stall) = longest_stall(upload).await;
+        assert!(result.is_err());
+        let ensure = client.ensure_firmware("large.bit.bin", path_str);
+        let (result, ensure_stall) = longest_stall(ensure).await;
+        assert!(result.is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(
+            upload_stall < Duration::from_millis(500),
+            "stalled for {:?}",
+            upload_stall
+        );
+        assert!(
+            ensure_stall < Duration::from_millis(500),
+            "stalled for {:?}",
+            ensure_stall
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_missing_file() {
+        let err = read("/nonexistent/jelly.bit").await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Internal);
+        assert!(read_to_string("/nonexistent/jelly.txt").await.is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Golden-model co-verification
+//!
+//! A [`GoldenModel`] sees every successful memory and register write of a
+//! client (and its clones and accessors) and predicts what reads should
+//! return. Reads whose hardware value differs from the prediction are
+//! recorded as [`Divergence`]s, or fail with [`OnDivergence::Fail`]. The
+//! same model runs against a simulator or the board, so a test can check
+//! both without its own bookkeeping.
+//!
+//! Values are passed as little-endian bytes as sent to the server: integer
+//! accesses of `size` bytes, `f32`/`f64` accesses as 4/8 bytes and
+//! `mem_copy_to` / `mem_copy_from` as their data.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::JellyFpgaClient;
+
+/// Address space of an access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Space {
+    /// Byte offset (`*_mem_*`, `mem_copy_*`)
+    Mem,
+    /// Register index (`*_reg_*`)
+    Reg,
+}
+
+/// One memory or register access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    /// Opened id
+    pub id: u32,
+    /// Memory or register access
+    pub space: Space,
+    /// Byte offset for [`Space::Mem`], register index for [`Space::Reg`]
+    pub offset: u64,
+    /// Length in bytes
+    pub len: u64,
+}
+
+impl Access {
+    /// Memory access of `len` bytes at byte `offset`
+    pub fn mem(id: u32, offset: u64, len: u64) -> Self {
+        Access {
+            id,
+            space: Space::Mem,
+            offset,
+            len,
+        }
+    }
+
+    /// Register access of `len` bytes at register `reg`
+    pub fn reg(id: u32, reg: u64, len: u64) -> Self {
+        Access {
+            id,
+            space: Space::Reg,
+            offset: reg,
+            len,
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let space = match self.space {
+            Space::Mem => "offset",
+            Space::Reg => "reg",
+        };
+        write!(
+            f,
+            "id={} {}=0x{:x} len={}",
+            self.id, space, self.offset, self.len
+        )
+    }
+}
+
+/// Software model of the hardware
+pub trait GoldenModel: Send {
+    /// `data` was written to `access`
+    fn write(&mut self, access: &Access, data: &[u8]);
+
+    /// Bytes `access` should read, or `None` if the model does not know
+    fn read(&mut self, access: &Access) -> Option<Vec<u8>>;
+}
+
+/// Model of plain memory: reads return what was last written
+///
+/// Predicts a read only if every byte of it was written before. Registers
+/// are byte-addressed by index here, so mixing register sizes on one
+/// index is not modelled.
+#[derive(Debug, Default)]
+pub struct SparseMemory {
+    bytes: HashMap<(u32, Space, u64), u8>,
+}
+
+impl SparseMemory {
+    /// Memory with nothing written yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl GoldenModel for SparseMemory {
+    fn write(&mut self, access: &Access, data: &[u8]) {
+        for (i, &b) in data.iter().enumerate() {
+            self.bytes
+                .insert((access.id, access.space, access.offset + i as u64), b);
+        }
+    }
+
+    fn read(&mut self, access: &Access) -> Option<Vec<u8>> {
+        (0..access.len)
+            .map(|i| {
+                self.bytes
+                    .get(&(access.id, access.space, access.offset + i))
+                    .copied()
+            })
+            .collect()
+    }
+}
+
+/// A read that did not match the model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The read
+    pub access: Access,
+    /// Model prediction
+    pub expected: Vec<u8>,
+    /// Hardware value
+    pub actual: Vec<u8>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {:02x?}, read {:02x?}",
+            self.access, self.expected, self.actual
+        )
+    }
+}
+
+/// What a divergent read does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnDivergence {
+    /// Return the hardware value and record the divergence
+    #[default]
+    Record,
+    /// Record the divergence and fail the read with `internal`
+    Fail,
+}
+
+/// Model and divergences shared by a client and its clones
+pub(crate) struct Golden {
+    model: Mutex<Box<dyn GoldenModel>>,
+    divergences: Mutex<Vec<Divergence>>,
+    on_divergence: OnDivergence,
+}
+
+/// Low `size` bytes of an integer access
+pub(crate) fn int_bytes(value: u64, size: u64) -> Vec<u8> {
+    value.to_le_bytes()[..size.min(8) as usize].to_vec()
+}
+
+impl JellyFpgaClient {
+    /// Check reads against `model` from now on (shared with clones made afterwards)
+    pub fn with_golden_model<M: GoldenModel + 'static>(
+        mut self,
+        model: M,
+        on_divergence: OnDivergence,
+    ) -> Self {
+        self.golden = Some(Arc::new(Golden {
+            model: Mutex::new(Box::new(model)),
+            divergences: Mutex::new(Vec::new()),
+            on_divergence,
+        }));
+        self
+    }
+
+    /// Divergences recorded so far
+    pub fn divergences(&self) -> Vec<Divergence> {
+        self.golden
+            .as_ref()
+            .map(|g| g.divergences.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Return and clear the recorded divergences
+    pub fn take_divergences(&self) -> Vec<Divergence> {
+        self.golden
+            .as_ref()
+            .map(|g| std::mem::take(&mut *g.divergences.lock().unwrap()))
+            .unwrap_or_default()
+    }
+
+    /// Tell the model about a successful write
+    pub(crate) fn golden_write(&self, access: Access, data: &[u8]) {
+        if let Some(golden) = &self.golden {
+            golden.model.lock().unwrap().write(&access, data);
+        }
+    }
+
+    /// Compare a successful read with the model
+    pub(crate) fn golden_read(&self, access: Access, actual: &[u8]) -> Result<(), tonic::Status> {
+        let Some(golden) = &self.golden else {
+            return Ok(());
+        };
+        let Some(expected) = golden.model.lock().unwrap().read(&access) else {
+            return Ok(());
+        };
+        if expected == actual {
+            return Ok(());
+        }
+        let divergence = Divergence {
+            access,
+            expected,
+            actual: actual.to_vec(),
+        };
+        let message = format!("golden model divergence: {}", divergence);
+        golden.divergences.lock().unwrap().push(divergence);
+        match golden.on_divergence {
+            OnDivergence::Record => Ok(()),
+            OnDivergence::Fail => Err(tonic::Status::internal(message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_memory() {
+        let mut model = SparseMemory::new();
+        let at = |offset, len| Access::mem(1, offset, len);
+        model.write(&at(0x10, 4), &int_bytes(0x1234_5678, 4));
+        assert_eq!(model.read(&at(0x12, 2)), Some(vec![0x34, 0x12]));
+        assert_eq!(model.read(&at(0x12, 4)), None);
+        assert_eq!(model.read(&Access::reg(1, 0x10, 1)), None);
+    }
+}
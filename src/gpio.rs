@@ -0,0 +1,217 @@
+//! GPIO over memory-mapped GPIO IP (Xilinx AXI GPIO or jelly GPIO)
+//!
+//! Pin updates (direction, set, toggle) hold the accessor lock across the
+//! read and the write, so clones driving other pins of the same bank never
+//! overwrite each other.
+
+use crate::accessor::Accessor;
+use crate::addr::AccessSize;
+
+/// How register offsets in a [`GpioLayout`] are interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Addressing {
+    /// Byte offsets (memory access)
+    Byte,
+    /// Register indices scaled by the accessor unit (register access)
+    Reg,
+}
+
+/// Register layout of a GPIO bank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GpioLayout {
+    /// Offset interpretation
+    pub addressing: Addressing,
+    /// Access size in bytes
+    pub size: u64,
+    /// Output data register
+    pub output: u64,
+    /// Input data register
+    pub input: u64,
+    /// Direction register
+    pub direction: u64,
+    /// A set direction bit means output (jelly) instead of input (AXI GPIO tri-state)
+    pub direction_bit_is_output: bool,
+}
+
+impl GpioLayout {
+    /// Xilinx AXI GPIO, channel 1 or 2
+    pub fn axi_gpio(channel: u32) -> Self {
+        let base = if channel == 2 { 0x08 } else { 0x00 };
+        GpioLayout {
+            addressing: Addressing::Byte,
+            size: 4,
+            output: base,
+            input: base,
+            direction: base + 0x04,
+            direction_bit_is_output: false,
+        }
+    }
+
+    /// jelly GPIO (DIRECTION=0, INPUT=1, OUTPUT=2 in register units)
+    pub fn jelly() -> Self {
+        GpioLayout {
+            addressing: Addressing::Reg,
+            size: 4,
+            output: 0x02,
+            input: 0x01,
+            direction: 0x00,
+            direction_bit_is_output: true,
+        }
+    }
+
+    /// Mask of the bank's pins (the low `size` bytes)
+    pub fn mask(&self) -> u64 {
+        AccessSize::from_bytes(self.size).map_or(!0, AccessSize::mask)
+    }
+}
+
+/// Pin direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// Input
+    Input,
+    /// Output
+    Output,
+}
+
+/// GPIO bank
+#[derive(Clone)]
+pub struct Gpio {
+    regs: Accessor,
+    layout: GpioLayout,
+}
+
+impl Gpio {
+    /// Create GPIO bank
+    pub fn new(regs: Accessor, layout: GpioLayout) -> Self {
+        Gpio { regs, layout }
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Layout
+    pub fn layout(&self) -> &GpioLayout {
+        &self.layout
+    }
+
+    async fn read(&mut self, offset: u64) -> Result<u64, tonic::Status> {
+        read(&mut self.regs, &self.layout, offset).await
+    }
+
+    async fn write(&mut self, offset: u64, data: u64) -> Result<(), tonic::Status> {
+        write(&mut self.regs, &self.layout, offset, data).await
+    }
+
+    /// Read-modify-write of `offset` under the accessor lock; returns the written value
+    ///
+    /// Clones of the accessor updating other pins wait, so no update is lost.
+    async fn modify<F>(&mut self, offset: u64, f: F) -> Result<u64, tonic::Status>
+    where
+        F: FnOnce(u64) -> u64,
+    {
+        let layout = self.layout;
+        let mut regs = self.regs.lock_unless_held().await;
+        let value = f(read(&mut regs, &layout, offset).await?);
+        write(&mut regs, &layout, offset, value).await?;
+        Ok(value)
+    }
+
+    fn check_pin(&self, pin: u32) -> Result<u64, tonic::Status> {
+        if (pin as u64) < self.layout.size * 8 {
+            Ok(1 << pin)
+        } else {
+            Err(tonic::Status::out_of_range(format!(
+                "GPIO pin {} out of range",
+                pin
+            )))
+        }
+    }
+
+    /// Set direction of all pins; bits set in `outputs` become outputs
+    pub async fn set_bank_direction(&mut self, outputs: u64) -> Result<(), tonic::Status> {
+        let value = if self.layout.direction_bit_is_output {
+            outputs
+        } else {
+            !outputs
+        };
+        self.write(self.layout.direction, value & self.layout.mask())
+            .await
+    }
+
+    /// Set direction of one pin
+    pub async fn set_direction(&mut self, pin: u32, dir: Direction) -> Result<(), tonic::Status> {
+        let mask = self.check_pin(pin)?;
+        let set = (dir == Direction::Output) == self.layout.direction_bit_is_output;
+        self.modify(self.layout.direction, |value| {
+            if set { value | mask } else { value & !mask }
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Read all input pins
+    pub async fn read_bank(&mut self) -> Result<u64, tonic::Status> {
+        self.read(self.layout.input).await
+    }
+
+    /// Write all output pins
+    pub async fn write_bank(&mut self, value: u64) -> Result<(), tonic::Status> {
+        self.write(self.layout.output, value).await
+    }
+
+    /// Read one pin
+    pub async fn get_pin(&mut self, pin: u32) -> Result<bool, tonic::Status> {
+        let mask = self.check_pin(pin)?;
+        Ok(self.read_bank().await? & mask != 0)
+    }
+
+    /// Read back the driven level of one output pin
+    pub async fn get_output(&mut self, pin: u32) -> Result<bool, tonic::Status> {
+        let mask = self.check_pin(pin)?;
+        Ok(self.read(self.layout.output).await? & mask != 0)
+    }
+
+    /// Drive one output pin (read-modify-write of the output register)
+    pub async fn set_pin(&mut self, pin: u32, level: bool) -> Result<(), tonic::Status> {
+        let mask = self.check_pin(pin)?;
+        self.modify(self.layout.output, |value| {
+            if level { value | mask } else { value & !mask }
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Invert one output pin and return the new level
+    pub async fn toggle(&mut self, pin: u32) -> Result<bool, tonic::Status> {
+        let mask = self.check_pin(pin)?;
+        let value = self
+            .modify(self.layout.output, |value| value ^ mask)
+            .await?;
+        Ok(value & mask != 0)
+    }
+}
+
+async fn read(regs: &mut Accessor, layout: &GpioLayout, offset: u64) -> Result<u64, tonic::Status> {
+    match layout.addressing {
+        Addressing::Byte => regs.read_mem_u_raw(offset, layout.size).await,
+        Addressing::Reg => regs.read_reg_u_raw(offset, layout.size).await,
+    }
+}
+
+async fn write(
+    regs: &mut Accessor,
+    layout: &GpioLayout,
+    offset: u64,
+    data: u64,
+) -> Result<(), tonic::Status> {
+    match layout.addressing {
+        Addressing::Byte => regs.write_mem_u_raw(offset, data, layout.size).await,
+        Addressing::Reg => regs.write_reg_u_raw(offset, data, layout.size).await,
+    }
+}
@@ -0,0 +1,91 @@
+//! Client-side address guard for accessor writes
+//!
+//! A guard lists permitted and forbidden ranges, either as offsets within
+//! the accessor or as physical addresses. Writes outside the allowlist or
+//! touching a denied range are rejected with `permission_denied` before any
+//! RPC is sent, so a scripted test cannot clobber e.g. the PS DDR controller
+//! through a `/dev/mem` mapping.
+
+use std::ops::Range;
+
+/// Address space the guard ranges are expressed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AddressSpace {
+    /// Byte offsets within the accessor
+    Offset,
+    /// Physical addresses (accessor physical address + offset)
+    Physical,
+}
+
+/// Allowlist/denylist of address ranges
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddressGuard {
+    space: AddressSpace,
+    allow: Vec<Range<u64>>,
+    deny: Vec<Range<u64>>,
+}
+
+impl AddressGuard {
+    /// Guard with no ranges (everything permitted)
+    pub fn new(space: AddressSpace) -> Self {
+        AddressGuard {
+            space,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+
+    /// Permit a range; once any range is allowed, writes must fall entirely inside one
+    pub fn allow(mut self, range: Range<u64>) -> Self {
+        self.allow.push(range);
+        self
+    }
+
+    /// Forbid a range
+    pub fn deny(mut self, range: Range<u64>) -> Self {
+        self.deny.push(range);
+        self
+    }
+
+    /// Address space of the ranges
+    pub fn space(&self) -> AddressSpace {
+        self.space
+    }
+
+    /// Check a write of `len` bytes at `addr` (in the guard's address space)
+    pub fn check(&self, addr: u64, len: u64) -> Result<(), tonic::Status> {
+        let end = addr.saturating_add(len.max(1));
+        if let Some(r) = self.deny.iter().find(|r| addr < r.end && r.start < end) {
+            return Err(tonic::Status::permission_denied(format!(
+                "write to 0x{:x}..0x{:x} overlaps denied range 0x{:x}..0x{:x}",
+                addr, end, r.start, r.end
+            )));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|r| r.start <= addr && end <= r.end) {
+            return Err(tonic::Status::permission_denied(format!(
+                "write to 0x{:x}..0x{:x} outside allowed ranges",
+                addr, end
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_check() {
+        let guard = AddressGuard::new(AddressSpace::Offset)
+            .allow(0x0000..0x1000)
+            .deny(0x0100..0x0200);
+        assert!(guard.check(0x0000, 4).is_ok());
+        assert!(guard.check(0x00fe, 4).is_err());
+        assert!(guard.check(0x0200, 4).is_ok());
+        assert!(guard.check(0x0ffe, 4).is_err());
+        assert!(guard.check(0x2000, 4).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! embedded-hal 1.0 implementations backed by remote register access
+//!
+//! The traits are blocking, so every type carries a tokio runtime
+//! [`Handle`] and drives the async drivers with `block_on`. Use them from a
+//! plain thread (or `spawn_blocking`), not from inside an async task.
+
+use embedded_hal::{digital, i2c, spi};
+use tokio::runtime::Handle;
+
+use crate::gpio::Gpio;
+use crate::iic::{AxiIic, I2cOp};
+use crate::spi::AxiQuadSpi;
+
+/// Error returned by the embedded-hal implementations
+#[derive(Debug)]
+pub struct HalError(pub tonic::Status);
+
+impl std::fmt::Display for HalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for HalError {}
+
+impl From<tonic::Status> for HalError {
+    fn from(status: tonic::Status) -> Self {
+        HalError(status)
+    }
+}
+
+impl digital::Error for HalError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+impl i2c::Error for HalError {
+    fn kind(&self) -> i2c::ErrorKind {
+        match self.0.code() {
+            tonic::Code::Unavailable => {
+                i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Unknown)
+            }
+            tonic::Code::Aborted => i2c::ErrorKind::ArbitrationLoss,
+            _ => i2c::ErrorKind::Other,
+        }
+    }
+}
+
+impl spi::Error for HalError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+/// One GPIO pin as an embedded-hal digital pin
+pub struct RemotePin {
+    gpio: Gpio,
+    pin: u32,
+    rt: Handle,
+}
+
+impl RemotePin {
+    /// Create pin
+    pub fn new(gpio: Gpio, pin: u32, rt: Handle) -> Self {
+        RemotePin { gpio, pin, rt }
+    }
+}
+
+impl digital::ErrorType for RemotePin {
+    type Error = HalError;
+}
+
+impl digital::OutputPin for RemotePin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(self.rt.block_on(self.gpio.set_pin(self.pin, false))?)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(self.rt.block_on(self.gpio.set_pin(self.pin, true))?)
+    }
+}
+
+impl digital::StatefulOutputPin for RemotePin {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.rt.block_on(self.gpio.get_output(self.pin))?)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.rt.block_on(self.gpio.get_output(self.pin))?)
+    }
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.rt.block_on(self.gpio.toggle(self.pin))?;
+        Ok(())
+    }
+}
+
+impl digital::InputPin for RemotePin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.rt.block_on(self.gpio.get_pin(self.pin))?)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.rt.block_on(self.gpio.get_pin(self.pin))?)
+    }
+}
+
+/// AXI IIC controller as an embedded-hal I2C bus
+///
+/// Transactions run through [`AxiIic::transaction`], which merges adjacent
+/// operations of the same kind as the `I2c` contract requires.
+pub struct RemoteI2c {
+    iic: AxiIic,
+    rt: Handle,
+}
+
+impl RemoteI2c {
+    /// Create bus (call [`AxiIic::init`] first)
+    pub fn new(iic: AxiIic, rt: Handle) -> Self {
+        RemoteI2c { iic, rt }
+    }
+}
+
+impl i2c::ErrorType for RemoteI2c {
+    type Error = HalError;
+}
+
+impl i2c::I2c for RemoteI2c {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut ops: Vec<I2cOp<'_>> = operations
+            .iter_mut()
+            .map(|op| match op {
+                i2c::Operation::Read(buf) => I2cOp::Read(&mut **buf),
+                i2c::Operation::Write(data) => I2cOp::Write(*data),
+            })
+            .collect();
+        Ok(self.rt.block_on(self.iic.transaction(address, &mut ops))?)
+    }
+}
+
+/// AXI Quad SPI chip select as an embedded-hal SPI device
+pub struct RemoteSpiDevice {
+    spi: AxiQuadSpi,
+    rt: Handle,
+}
+
+impl RemoteSpiDevice {
+    /// Create device (call [`AxiQuadSpi::init`] first)
+    pub fn new(spi: AxiQuadSpi, rt: Handle) -> Self {
+        RemoteSpiDevice { spi, rt }
+    }
+
+    async fn run(
+        &mut self,
+        operations: &mut [spi::Operation<'_, u8>],
+    ) -> Result<(), tonic::Status> {
+        for op in operations.iter_mut() {
+            match op {
+                spi::Operation::Read(buf) => {
+                    buf.fill(0);
+                    self.spi.transfer_in_place(buf).await?;
+                }
+                spi::Operation::Write(data) => {
+                    for &b in data.iter() {
+                        self.spi.transfer_byte(b).await?;
+                    }
+                }
+                spi::Operation::Transfer(read, write) => {
+                    let n = read.len().max(write.len());
+                    for i in 0..n {
+                        let b = self
+                            .spi
+                            .transfer_byte(write.get(i).copied().unwrap_or(0))
+                            .await?;
+                        if let Some(r) = read.get_mut(i) {
+                            *r = b;
+                        }
+                    }
+                }
+                spi::Operation::TransferInPlace(buf) => {
+                    self.spi.transfer_in_place(buf).await?;
+                }
+                spi::Operation::DelayNs(ns) => {
+                    tokio::time::sleep(std::time::Duration::from_nanos(*ns as u64)).await;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl spi::ErrorType for RemoteSpiDevice {
+    type Error = HalError;
+}
+
+impl spi::SpiDevice for RemoteSpiDevice {
+    fn transaction(
+        &mut self,
+        operations: &mut [spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        let rt = self.rt.clone();
+        rt.block_on(async {
+            self.spi.select().await?;
+            let result = self.run(operations).await;
+            self.spi.deselect().await?;
+            result
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Registry of ids opened through a client
+//!
+//! Shared between clones of a client so that `close_all` also covers ids
+//! opened through accessors and drivers. Optionally mirrored to a file so a
+//! later session can close handles leaked by a crashed process. The open
+//! parameters are kept with each id for
+//! [`snapshot_environment`](crate::JellyFpgaClient::snapshot_environment).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::environment::OpenSpec;
+
+#[derive(Default)]
+struct Inner {
+    ids: BTreeMap<u32, OpenSpec>,
+    file: Option<PathBuf>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct HandleRegistry {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl HandleRegistry {
+    pub(crate) fn insert(&self, id: u32, spec: OpenSpec) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.ids.insert(id, spec);
+        save(&inner);
+    }
+
+    pub(crate) fn remove(&self, id: u32) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.ids.remove(&id);
+        save(&inner);
+    }
+
+    pub(crate) fn list(&self) -> Vec<u32> {
+        self.inner.lock().unwrap().ids.keys().copied().collect()
+    }
+
+    pub(crate) fn specs(&self) -> Vec<(u32, OpenSpec)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .ids
+            .iter()
+            .map(|(id, spec)| (*id, spec.clone()))
+            .collect()
+    }
+
+    pub(crate) fn set_file(&self, path: Option<PathBuf>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.file = path;
+        save(&inner);
+    }
+}
+
+/// Write the current ids, one per line (best effort; the registry is advisory)
+///
+/// Kept synchronous: the file is a few bytes and is written under the
+/// registry lock so concurrent clones cannot reorder the snapshots.
+fn save(inner: &Inner) {
+    if let Some(path) = &inner.file {
+        let text: String = inner.ids.keys().map(|id| format!("{}\n", id)).collect();
+        let _ = std::fs::write(path, text);
+    }
+}
+
+/// Read ids written by a previous session
+pub(crate) async fn load(path: &Path) -> std::io::Result<Vec<u32>> {
+    let text = crate::fs::read_to_string(path).await?;
+    Ok(text
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect())
+}
@@ -0,0 +1,279 @@
+//! Xilinx AXI IIC driver (dynamic controller mode)
+
+use std::time::Duration;
+
+use crate::accessor::Accessor;
+
+/// Global interrupt enable
+pub const REG_GIE: u64 = 0x1c;
+/// Interrupt status
+pub const REG_ISR: u64 = 0x20;
+/// Interrupt enable
+pub const REG_IER: u64 = 0x28;
+/// Soft reset
+pub const REG_SOFTR: u64 = 0x40;
+/// Control
+pub const REG_CR: u64 = 0x100;
+/// Status
+pub const REG_SR: u64 = 0x104;
+/// Transmit FIFO
+pub const REG_TX_FIFO: u64 = 0x108;
+/// Receive FIFO
+pub const REG_RX_FIFO: u64 = 0x10c;
+/// Receive FIFO programmable depth interrupt
+pub const REG_RX_FIFO_PIRQ: u64 = 0x120;
+
+/// SOFTR reset key
+pub const SOFTR_KEY: u32 = 0x0a;
+/// CR: enable
+pub const CR_EN: u32 = 1 << 0;
+/// CR: transmit FIFO reset
+pub const CR_TX_FIFO_RESET: u32 = 1 << 1;
+/// SR: bus busy
+pub const SR_BB: u32 = 1 << 2;
+/// SR: transmit FIFO full
+pub const SR_TX_FIFO_FULL: u32 = 1 << 4;
+/// SR: receive FIFO empty
+pub const SR_RX_FIFO_EMPTY: u32 = 1 << 6;
+/// SR: transmit FIFO empty
+pub const SR_TX_FIFO_EMPTY: u32 = 1 << 7;
+/// ISR: arbitration lost
+pub const ISR_ARB_LOST: u32 = 1 << 0;
+/// ISR: transmit error (no acknowledge)
+pub const ISR_TX_ERROR: u32 = 1 << 1;
+/// TX_FIFO: generate start before this byte
+pub const TX_START: u32 = 1 << 8;
+/// TX_FIFO: generate stop after this byte
+pub const TX_STOP: u32 = 1 << 9;
+
+/// Longest read of one transfer (the count byte of a dynamic-mode read)
+pub const MAX_READ_LEN: usize = 255;
+
+/// One step of an I2C transaction
+pub enum I2cOp<'a> {
+    /// Write bytes
+    Write(&'a [u8]),
+    /// Read bytes
+    Read(&'a mut [u8]),
+}
+
+/// AXI IIC controller
+#[derive(Clone)]
+pub struct AxiIic {
+    regs: Accessor,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl AxiIic {
+    /// Create driver
+    pub fn new(regs: Accessor) -> Self {
+        AxiIic {
+            regs,
+            poll_interval: Duration::from_micros(100),
+            timeout: Duration::from_millis(100),
+        }
+    }
+
+    /// Set timeout for each byte
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Reset and enable the controller
+    pub async fn init(&mut self) -> Result<(), tonic::Status> {
+        self.regs.write_mem_u32(REG_SOFTR, SOFTR_KEY).await?;
+        self.regs.write_mem_u32(REG_RX_FIFO_PIRQ, 0x0f).await?;
+        self.regs.write_mem_u32(REG_CR, CR_TX_FIFO_RESET).await?;
+        self.regs.write_mem_u32(REG_CR, CR_EN).await
+    }
+
+    async fn check_isr(&mut self) -> Result<(), tonic::Status> {
+        let isr = self.regs.read_mem_u32(REG_ISR).await?;
+        if isr & (ISR_ARB_LOST | ISR_TX_ERROR) != 0 {
+            // clear (toggle on write) and flush
+            self.regs
+                .write_mem_u32(REG_ISR, isr & (ISR_ARB_LOST | ISR_TX_ERROR))
+                .await?;
+            self.regs
+                .write_mem_u32(REG_CR, CR_EN | CR_TX_FIFO_RESET)
+                .await?;
+            self.regs.write_mem_u32(REG_CR, CR_EN).await?;
+            return Err(if isr & ISR_ARB_LOST != 0 {
+                tonic::Status::aborted("I2C arbitration lost")
+            } else {
+                tonic::Status::unavailable("I2C no acknowledge")
+            });
+        }
+        Ok(())
+    }
+
+    async fn wait_status(&mut self, mask: u32, expected: u32) -> Result<(), tonic::Status> {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        loop {
+            self.check_isr().await?;
+            let sr = self.regs.read_mem_u32(REG_SR).await?;
+            if sr & mask == expected {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded(format!(
+                    "I2C timeout (SR=0x{:02x})",
+                    sr
+                )));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Push one word into the transmit FIFO, waiting while it is full
+    async fn push(&mut self, word: u32) -> Result<(), tonic::Status> {
+        self.wait_status(SR_TX_FIFO_FULL, 0).await?;
+        self.regs.write_mem_u32(REG_TX_FIFO, word).await
+    }
+
+    /// Run a transaction with repeated starts between operations and a stop at the end
+    ///
+    /// Adjacent operations of the same kind are merged into one transfer
+    /// without a repeated start in between, as embedded-hal requires. Merged
+    /// reads must be 1 to [`MAX_READ_LEN`] bytes long; otherwise fails with
+    /// `invalid_argument` before anything is sent.
+    pub async fn transaction(
+        &mut self,
+        addr: u8,
+        ops: &mut [I2cOp<'_>],
+    ) -> Result<(), tonic::Status> {
+        let words = tx_words(addr, ops)?;
+        for (run, words) in ops.chunk_by_mut(same_kind).zip(words) {
+            for word in words {
+                self.push(word).await?;
+            }
+            for op in run.iter_mut() {
+                match op {
+                    I2cOp::Write(_) => {}
+                    I2cOp::Read(buf) => {
+                        for b in buf.iter_mut() {
+                            self.wait_status(SR_RX_FIFO_EMPTY, 0).await?;
+                            *b = self.regs.read_mem_u32(REG_RX_FIFO).await? as u8;
+                        }
+                    }
+                }
+            }
+            if let [I2cOp::Write(_), ..] = run {
+                self.wait_status(SR_TX_FIFO_EMPTY, SR_TX_FIFO_EMPTY).await?;
+            }
+        }
+        self.wait_status(SR_BB, 0).await
+    }
+
+    /// Write bytes
+    pub async fn write(&mut self, addr: u8, data: &[u8]) -> Result<(), tonic::Status> {
+        self.transaction(addr, &mut [I2cOp::Write(data)]).await
+    }
+
+    /// Read bytes
+    pub async fn read(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), tonic::Status> {
+        self.transaction(addr, &mut [I2cOp::Read(buf)]).await
+    }
+
+    /// Write then read with a repeated start (typical register read)
+    pub async fn write_read(
+        &mut self,
+        addr: u8,
+        data: &[u8],
+        buf: &mut [u8],
+    ) -> Result<(), tonic::Status> {
+        self.transaction(addr, &mut [I2cOp::Write(data), I2cOp::Read(buf)])
+            .await
+    }
+}
+
+fn same_kind(a: &I2cOp<'_>, b: &I2cOp<'_>) -> bool {
+    matches!(
+        (a, b),
+        (I2cOp::Write(_), I2cOp::Write(_)) | (I2cOp::Read(_), I2cOp::Read(_))
+    )
+}
+
+/// Transmit FIFO words of each run of adjacent same-kind operations
+fn tx_words(addr: u8, ops: &[I2cOp<'_>]) -> Result<Vec<Vec<u32>>, tonic::Status> {
+    let addr = (addr as u32) << 1;
+    let count = ops.chunk_by(same_kind).count();
+    let mut runs = Vec::with_capacity(count);
+    for (i, run) in ops.chunk_by(same_kind).enumerate() {
+        let stop = if i + 1 == count { TX_STOP } else { 0 };
+        let mut words = Vec::new();
+        if let [I2cOp::Read(_), ..] = run {
+            let len: usize = run
+                .iter()
+                .map(|op| match op {
+                    I2cOp::Read(buf) => buf.len(),
+                    I2cOp::Write(_) => 0,
+                })
+                .sum();
+            if !(1..=MAX_READ_LEN).contains(&len) {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "I2C read of {} bytes (1 to {} supported)",
+                    len, MAX_READ_LEN
+                )));
+            }
+            words.push(TX_START | addr | 1);
+            words.push(stop | len as u32);
+        } else {
+            let data: Vec<u8> = run
+                .iter()
+                .filter_map(|op| match op {
+                    I2cOp::Write(data) => Some(*data),
+                    I2cOp::Read(_) => None,
+                })
+                .flatten()
+                .copied()
+                .collect();
+            match data.split_last() {
+                None => words.push(TX_START | addr | stop),
+                Some((&last, rest)) => {
+                    words.push(TX_START | addr);
+                    words.extend(rest.iter().map(|&b| b as u32));
+                    words.push(stop | last as u32);
+                }
+            }
+        }
+        runs.push(words);
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_words() {
+        let (mut a, mut b) = ([0u8; 2], [0u8; 3]);
+        let ops = [
+            I2cOp::Write(&[0x10]),
+            I2cOp::Write(&[0xaa, 0xbb]),
+            I2cOp::Read(&mut a),
+            I2cOp::Read(&mut b),
+        ];
+        assert_eq!(
+            tx_words(0x50, &ops).unwrap(),
+            vec![
+                vec![TX_START | 0xa0, 0x10, 0xaa, 0xbb],
+                vec![TX_START | 0xa1, TX_STOP | 5],
+            ]
+        );
+        // address-only probe
+        assert_eq!(
+            tx_words(0x50, &[I2cOp::Write(&[])]).unwrap(),
+            vec![vec![TX_START | 0xa0 | TX_STOP]]
+        );
+        assert!(tx_words(0x50, &[I2cOp::Read(&mut [])]).is_err());
+        assert!(tx_words(0x50, &[I2cOp::Read(&mut [0; 256])]).is_err());
+    }
+}
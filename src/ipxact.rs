@@ -0,0 +1,242 @@
+//! IP-XACT register description import
+//!
+//! Reads the `memoryMaps` of an IP-XACT component (1685-2009 `spirit:` or
+//! 1685-2014/2022 `ipxact:` namespace) into a [`RegisterMap`], for vendor
+//! IP that ships IP-XACT but no other register description. Register byte
+//! offsets are the sum of the `addressBlock` base, enclosing
+//! `registerFile` offsets and the register's `addressOffset`; they become
+//! register indices by dividing by the accessor's `unit`. Register arrays
+//! (`dim`) are read as their first element. `write-only` registers become
+//! [`RegAccess::WriteOnly`] and `read-write` ones explicitly marked
+//! non-`volatile` become [`RegAccess::Static`].
+
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use crate::field::Field;
+use crate::regmap::{RegAccess, RegisterDef, RegisterMap, parse_num};
+
+fn invalid(msg: String) -> tonic::Status {
+    tonic::Status::invalid_argument(msg)
+}
+
+/// Parse IP-XACT numbers: `16`, `0x10`, `'h10`, `32'h0000_0010`, `'b1010`
+fn parse_value(s: &str) -> Option<u64> {
+    let s = s.trim().replace('_', "");
+    match s.split_once('\'') {
+        Some((_, rest)) => {
+            let (radix, digits) = rest.split_at(1);
+            let radix = match radix {
+                "h" | "H" => 16,
+                "d" | "D" => 10,
+                "o" | "O" => 8,
+                "b" | "B" => 2,
+                _ => return None,
+            };
+            u64::from_str_radix(digits, radix).ok()
+        }
+        None => parse_num(&s),
+    }
+}
+
+/// Element with an offset, name and (for registers/fields) a size
+#[derive(Default)]
+struct Frame {
+    tag: &'static str,
+    name: String,
+    offset: u64,
+    size: Option<u64>,
+    fields: Vec<(String, u32, u32)>,
+    access: Option<String>,
+    volatile: Option<bool>,
+}
+
+const FRAMES: [&str; 4] = ["addressBlock", "registerFile", "register", "field"];
+
+impl RegisterMap {
+    /// Registers of every address block of an IP-XACT component
+    ///
+    /// `unit` is the register size the device is opened with (byte offset
+    /// / `unit` = register index). Register sizes other than 8, 16, 32 or
+    /// 64 bits and offsets not divisible by `unit` are errors.
+    pub fn parse_ipxact(xml: &str, unit: u64) -> Result<Self, tonic::Status> {
+        if unit == 0 {
+            return Err(invalid("unit must not be 0".to_string()));
+        }
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+        let mut path: Vec<String> = Vec::new();
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut map = RegisterMap::new();
+        loop {
+            let event = reader
+                .read_event()
+                .map_err(|e| invalid(format!("IP-XACT: {}", e)))?;
+            match event {
+                Event::Start(e) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                    if let Some(&tag) = FRAMES.iter().find(|t| **t == name) {
+                        frames.push(Frame {
+                            tag,
+                            ..Default::default()
+                        });
+                    }
+                    path.push(name);
+                }
+                Event::Text(t) => {
+                    let (Some(element), Some(frame)) = (path.last(), frames.last_mut()) else {
+                        continue;
+                    };
+                    // only direct children of the frame element describe it
+                    if path.len() < 2 || path[path.len() - 2] != frame.tag {
+                        continue;
+                    }
+                    let text = t
+                        .decode()
+                        .map_err(|e| invalid(format!("IP-XACT: {}", e)))?
+                        .into_owned();
+                    let number = || {
+                        parse_value(&text)
+                            .ok_or_else(|| invalid(format!("IP-XACT: bad {} {:?}", element, text)))
+                    };
+                    match (frame.tag, element.as_str()) {
+                        (_, "name") => frame.name = text.clone(),
+                        ("addressBlock", "baseAddress")
+                        | ("registerFile" | "register", "addressOffset")
+                        | ("field", "bitOffset") => frame.offset = number()?,
+                        ("register", "size") | ("field", "bitWidth") => {
+                            frame.size = Some(number()?)
+                        }
+                        ("register", "access") => frame.access = Some(text.clone()),
+                        ("register", "volatile") => frame.volatile = Some(text == "true"),
+                        _ => {}
+                    }
+                }
+                Event::End(_) => {
+                    let Some(name) = path.pop() else {
+                        continue;
+                    };
+                    if !FRAMES.contains(&name.as_str()) {
+                        continue;
+                    }
+                    let Some(frame) = frames.pop() else {
+                        continue;
+                    };
+                    match frame.tag {
+                        "field" => {
+                            let width = frame.size.unwrap_or(1);
+                            if frame.offset + width > 64 || width == 0 {
+                                return Err(invalid(format!(
+                                    "IP-XACT: field {} out of range",
+                                    frame.name
+                                )));
+                            }
+                            if let Some(register) = frames.last_mut() {
+                                register.fields.push((
+                                    frame.name,
+                                    frame.offset as u32,
+                                    width as u32,
+                                ));
+                            }
+                        }
+                        "register" => {
+                            let offset =
+                                frames.iter().map(|f| f.offset).sum::<u64>() + frame.offset;
+                            let bits = frame.size.unwrap_or(32);
+                            if ![8, 16, 32, 64].contains(&bits) {
+                                return Err(invalid(format!(
+                                    "IP-XACT: register {} has {} bits",
+                                    frame.name, bits
+                                )));
+                            }
+                            if !offset.is_multiple_of(unit) {
+                                return Err(invalid(format!(
+                                    "IP-XACT: register {} at 0x{:x} is not a multiple of unit {}",
+                                    frame.name, offset, unit
+                                )));
+                            }
+                            let reg = offset / unit;
+                            let mut name = frame.name;
+                            if map.get(&name).is_some() {
+                                let scope: Vec<&str> =
+                                    frames.iter().map(|f| f.name.as_str()).collect();
+                                name = format!("{}.{}", scope.join("."), name);
+                            }
+                            let access = match (frame.access.as_deref(), frame.volatile) {
+                                (Some("write-only" | "writeOnce"), _) => RegAccess::WriteOnly,
+                                (None | Some("read-write"), Some(false)) => RegAccess::Static,
+                                _ => RegAccess::Volatile,
+                            };
+                            map.registers.push(RegisterDef {
+                                name,
+                                reg,
+                                size: bits / 8,
+                                access,
+                                fields: frame
+                                    .fields
+                                    .into_iter()
+                                    .map(|(name, shift, width)| {
+                                        (name, Field::new(reg, shift, width))
+                                    })
+                                    .collect(),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ipxact() {
+        let xml = r#"<?xml version="1.0"?>
+<spirit:component xmlns:spirit="http://www.spiritconsortium.org/XMLSchema/SPIRIT/1685-2009">
+  <spirit:name>axi_gpio</spirit:name>
+  <spirit:memoryMaps><spirit:memoryMap>
+    <spirit:name>S_AXI</spirit:name>
+    <spirit:addressBlock>
+      <spirit:name>Reg</spirit:name>
+      <spirit:baseAddress>0</spirit:baseAddress>
+      <spirit:range>512</spirit:range>
+      <spirit:width>32</spirit:width>
+      <spirit:register>
+        <spirit:name>GPIO_DATA</spirit:name>
+        <spirit:addressOffset>0x0</spirit:addressOffset>
+        <spirit:size>32</spirit:size>
+        <spirit:access>write-only</spirit:access>
+      </spirit:register>
+      <spirit:register>
+        <spirit:name>GIER</spirit:name>
+        <spirit:addressOffset>'h11C</spirit:addressOffset>
+        <spirit:size>32</spirit:size>
+        <spirit:field>
+          <spirit:name>Global_Interrupt_Enable</spirit:name>
+          <spirit:bitOffset>31</spirit:bitOffset>
+          <spirit:bitWidth>1</spirit:bitWidth>
+        </spirit:field>
+      </spirit:register>
+    </spirit:addressBlock>
+  </spirit:memoryMap></spirit:memoryMaps>
+</spirit:component>"#;
+        let map = RegisterMap::parse_ipxact(xml, 4).unwrap();
+        assert_eq!(map.registers.len(), 2);
+        let gier = map.get("GIER").unwrap();
+        assert_eq!((gier.reg, gier.size), (0x47, 4));
+        assert_eq!(gier.access, RegAccess::Volatile);
+        assert_eq!(map.get("GPIO_DATA").unwrap().access, RegAccess::WriteOnly);
+        assert_eq!(
+            map.lookup_field("GIER", "Global_Interrupt_Enable"),
+            Some(&Field::new(0x47, 31, 1))
+        );
+        assert!(RegisterMap::parse_ipxact(xml, 8).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Typed structures in device memory
+//!
+//! A [`MemLayout`] type converts to and from its bytes in device memory,
+//! so descriptors and mailboxes are read and written as Rust values with
+//! [`write_mem_struct`](JellyFpgaClient::write_mem_struct) /
+//! [`read_mem_struct`](JellyFpgaClient::read_mem_struct) instead of byte
+//! offsets. With the `derive` feature, `#[derive(MemLayout)]` implements it
+//! for a `#[repr(C)]` struct: fields sit at their `repr(C)` offsets, and
+//! `#[mem_layout(big_endian)]` on the struct or a field selects the byte
+//! order (little-endian otherwise). Padding bytes are written as zero.
+//!
+//! Fields may be integers, `f32`/`f64`, arrays of fields, or other
+//! `MemLayout` structs (which keep their own byte order).
+
+use crate::JellyFpgaClient;
+use crate::endian::Endian;
+
+#[cfg(feature = "derive")]
+pub use jelly_fpga_client_derive::MemLayout;
+
+/// Value stored as `BYTES` bytes in a given byte order
+pub trait LayoutField: Sized {
+    /// Bytes in device memory
+    const BYTES: usize;
+
+    /// Store into `buf` (exactly `BYTES` bytes)
+    fn put(&self, endian: Endian, buf: &mut [u8]);
+
+    /// Load from `buf` (exactly `BYTES` bytes)
+    fn get(endian: Endian, buf: &[u8]) -> Self;
+}
+
+macro_rules! impl_layout_field {
+    ($($t:ty),*) => {$(
+        impl LayoutField for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+
+            fn put(&self, endian: Endian, buf: &mut [u8]) {
+                buf.copy_from_slice(&match endian {
+                    Endian::Little => self.to_le_bytes(),
+                    Endian::Big => self.to_be_bytes(),
+                });
+            }
+
+            fn get(endian: Endian, buf: &[u8]) -> Self {
+                let bytes = buf.try_into().unwrap();
+                match endian {
+                    Endian::Little => <$t>::from_le_bytes(bytes),
+                    Endian::Big => <$t>::from_be_bytes(bytes),
+                }
+            }
+        }
+    )*};
+}
+
+impl_layout_field!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl<T: LayoutField, const N: usize> LayoutField for [T; N] {
+    const BYTES: usize = T::BYTES * N;
+
+    fn put(&self, endian: Endian, buf: &mut [u8]) {
+        for (value, chunk) in self.iter().zip(buf.chunks_exact_mut(T::BYTES)) {
+            value.put(endian, chunk);
+        }
+    }
+
+    fn get(endian: Endian, buf: &[u8]) -> Self {
+        std::array::from_fn(|i| T::get(endian, &buf[i * T::BYTES..][..T::BYTES]))
+    }
+}
+
+/// Nested structures keep their own byte order
+impl<T: MemLayout> LayoutField for T {
+    const BYTES: usize = T::SIZE;
+
+    fn put(&self, _endian: Endian, buf: &mut [u8]) {
+        self.encode(buf);
+    }
+
+    fn get(_endian: Endian, buf: &[u8]) -> Self {
+        T::decode(buf)
+    }
+}
+
+/// Structure with a fixed image in device memory
+pub trait MemLayout: Sized {
+    /// Bytes in device memory
+    const SIZE: usize;
+
+    /// Store into `buf` (at least `SIZE` bytes, padding left untouched)
+    fn encode(&self, buf: &mut [u8]);
+
+    /// Load from `buf` (at least `SIZE` bytes)
+    fn decode(buf: &[u8]) -> Self;
+
+    /// Memory image of the value
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0; Self::SIZE];
+        self.encode(&mut buf);
+        buf
+    }
+}
+
+impl JellyFpgaClient {
+    /// Write `value` at byte `offset` of `id` in one `mem_copy_to`
+    pub async fn write_mem_struct<T: MemLayout>(
+        &mut self,
+        id: u32,
+        offset: u64,
+        value: &T,
+    ) -> Result<(), tonic::Status> {
+        self.write_chunk(id, offset, value.to_bytes())
+            .await
+            .map(|_| ())
+    }
+
+    /// Read a `T` from byte `offset` of `id` in one `mem_copy_from`
+    pub async fn read_mem_struct<T: MemLayout>(
+        &mut self,
+        id: u32,
+        offset: u64,
+    ) -> Result<T, tonic::Status> {
+        let data = self.read_chunk(id, offset, T::SIZE as u64).await?;
+        if data.len() < T::SIZE {
+            return Err(tonic::Status::data_loss(format!(
+                "read_mem_struct: got {} of {} bytes",
+                data.len(),
+                T::SIZE
+            )));
+        }
+        Ok(T::decode(&data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Descriptor {
+        next: u32,
+        len: u16,
+        flags: [u8; 2],
+    }
+
+    impl MemLayout for Descriptor {
+        const SIZE: usize = 8;
+
+        fn encode(&self, buf: &mut [u8]) {
+            self.next.put(Endian::Big, &mut buf[0..4]);
+            self.len.put(Endian::Little, &mut buf[4..6]);
+            self.flags.put(Endian::Little, &mut buf[6..8]);
+        }
+
+        fn decode(buf: &[u8]) -> Self {
+            Descriptor {
+                next: u32::get(Endian::Big, &buf[0..4]),
+                len: u16::get(Endian::Little, &buf[4..6]),
+                flags: <[u8; 2]>::get(Endian::Little, &buf[6..8]),
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let desc = Descriptor {
+            next: 0x1122_3344,
+            len: 0x0102,
+            flags: [7, 8],
+        };
+        let bytes = desc.to_bytes();
+        assert_eq!(bytes, [0x11, 0x22, 0x33, 0x44, 0x02, 0x01, 7, 8]);
+        assert_eq!(Descriptor::decode(&bytes), desc);
+
+        let mut buf = [0u8; 8];
+        [1.5f32, -2.0].put(Endian::Big, &mut buf);
+        assert_eq!(<[f32; 2]>::get(Endian::Big, &buf), [1.5, -2.0]);
+        assert_eq!(&buf[..4], &1.5f32.to_be_bytes());
+    }
+}
@@ -0,0 +1,111 @@
+//! Session lease so the server can release resources after client death
+//!
+//! A leased client tags every request with a session id, and a background
+//! task renews the lease with a keepalive (`get_version` carrying the TTL).
+//! A server that supports leases echoes the TTL header back and releases
+//! the session's handles and overlays once no keepalive arrives within the TTL.
+//! Failed renewals and expiry are reported as [`ClientEvent`](crate::events::ClientEvent)s.
+
+#[cfg(not(feature = "wasm"))]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(feature = "wasm"))]
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(not(feature = "wasm"))]
+use tokio::task::JoinHandle;
+
+#[cfg(not(feature = "wasm"))]
+use crate::JellyFpgaClient;
+#[cfg(not(feature = "wasm"))]
+use crate::events::ClientEvent;
+
+/// Request metadata key carrying the session id
+pub const SESSION_KEY: &str = "x-jelly-session";
+/// Request/response metadata key carrying the lease TTL in milliseconds
+pub const LEASE_TTL_KEY: &str = "x-jelly-lease-ttl-ms";
+
+/// Generate a session id unique to this process and call
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn new_session_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!(
+        "{:x}-{:x}-{:x}",
+        std::process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Active lease; the keepalive stops when this is dropped or released
+#[cfg(not(feature = "wasm"))]
+pub struct Lease {
+    session: String,
+    ttl: Duration,
+    task: JoinHandle<()>,
+}
+
+#[cfg(not(feature = "wasm"))]
+impl Lease {
+    pub(crate) fn spawn(client: JellyFpgaClient, session: String, ttl: Duration) -> Self {
+        let id = session.clone();
+        let tasks = client.tasks.clone();
+        let task = tokio::spawn(async move {
+            let mut client = client;
+            let interval = ttl / 3;
+            let mut renewed = Instant::now();
+            let mut expired = false;
+            loop {
+                tokio::time::sleep(interval).await;
+                // a missed keepalive is retried on the next tick; the server
+                // only expires the lease after the whole TTL
+                let error = match client.lease_keepalive(ttl).await {
+                    Ok(true) => None,
+                    Ok(false) => Some("keepalive not acknowledged".to_string()),
+                    Err(e) => Some(e.message().to_string()),
+                };
+                match error {
+                    None => {
+                        renewed = Instant::now();
+                        expired = false;
+                    }
+                    Some(error) => client.events.emit(ClientEvent::LeaseRenewalFailed {
+                        session: id.clone(),
+                        error,
+                    }),
+                }
+                if !expired && renewed.elapsed() >= ttl {
+                    client.events.emit(ClientEvent::LeaseExpired {
+                        session: id.clone(),
+                    });
+                    expired = true;
+                }
+            }
+        });
+        tasks.register(task.abort_handle());
+        Lease { session, ttl, task }
+    }
+
+    /// Session id sent with every request of the leased client
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Lease TTL
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Stop renewing; the server releases the session after the TTL
+    pub fn release(self) {}
+}
+
+#[cfg(not(feature = "wasm"))]
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -1,63 +1,500 @@
-use tonic::transport::Channel;
 use tonic::Request;
+#[cfg(not(feature = "wasm"))]
+use tonic::transport::Channel;
 
 pub mod jelly_fpga_control {
     tonic::include_proto!("jelly_fpga_control");
 }
 
+pub mod accel;
+pub mod accessor;
+pub mod addr;
+pub mod addrmap;
+pub mod bulk;
+mod assertions;
+#[cfg(not(feature = "wasm"))]
+pub mod batch;
+#[cfg(not(feature = "wasm"))]
+pub mod bench;
+#[cfg(not(feature = "wasm"))]
+pub mod cache;
+pub mod capabilities;
+pub mod checksum;
+#[cfg(not(feature = "wasm"))]
+pub mod capture;
+#[cfg(not(feature = "wasm"))]
+pub mod clocksync;
+#[cfg(not(feature = "wasm"))]
+pub mod codegen;
+pub mod config;
+pub mod control;
+pub mod delta;
+pub mod deploy;
+#[cfg(not(feature = "wasm"))]
+pub mod diagnostics;
+pub mod display;
+#[cfg(not(feature = "wasm"))]
+pub mod dma;
+pub mod endian;
+pub mod environment;
+pub mod error;
+#[cfg(not(feature = "wasm"))]
+pub mod events;
+#[cfg(not(feature = "wasm"))]
+pub mod failover;
+#[cfg(not(feature = "wasm"))]
+pub mod farm;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
+pub mod field;
+pub mod firmware;
+pub mod framebuffer;
+mod fs;
+pub mod golden;
+pub mod gpio;
+pub mod guard;
+mod handles;
+#[cfg(feature = "embedded-hal-remote")]
+pub mod hal;
+#[cfg(not(feature = "wasm"))]
+pub mod iic;
+#[cfg(feature = "ipxact")]
+mod ipxact;
+pub mod layout;
+pub mod lease;
+pub mod limiter;
+pub mod loaded;
+pub mod loads;
+#[cfg(feature = "local")]
+pub mod local;
+#[cfg(not(feature = "wasm"))]
+pub mod logs;
+#[cfg(not(feature = "wasm"))]
+pub mod mailbox;
+mod lock;
+pub mod memdump;
+#[cfg(not(feature = "wasm"))]
+pub mod multiaddr;
+pub mod multiplex;
+pub mod pattern;
+#[cfg(not(feature = "wasm"))]
+pub mod perf;
+#[cfg(not(feature = "wasm"))]
+pub mod personality;
+mod pod;
+mod priority;
+#[cfg(not(feature = "wasm"))]
+pub mod progress;
+pub mod raw;
+pub mod region;
+#[cfg(not(feature = "wasm"))]
+pub mod recorder;
+pub mod regmap;
+mod shadow;
+pub mod remote_file;
+pub mod rtos;
+#[cfg(not(feature = "wasm"))]
+pub mod report;
+#[cfg(not(feature = "wasm"))]
+mod shutdown;
+#[cfg(not(feature = "wasm"))]
+pub mod soak;
+pub mod softcore;
+#[cfg(not(feature = "wasm"))]
+mod stopwatch;
+#[cfg(not(feature = "wasm"))]
+pub mod spi;
+#[cfg(feature = "ssh-tunnel")]
+pub mod ssh;
+pub mod stats;
+#[cfg(feature = "ndarray")]
+mod tensor;
+#[cfg(not(feature = "wasm"))]
+pub mod testing;
+#[cfg(not(feature = "wasm"))]
+pub mod trace;
+pub mod tuning;
+#[cfg(not(feature = "wasm"))]
+pub mod uart;
+pub mod value;
+pub mod vcd;
+#[cfg(not(feature = "wasm"))]
+pub mod video;
+#[cfg(not(feature = "wasm"))]
+pub mod weights;
+
+pub use accel::AccelInfo;
+pub use accessor::Accessor;
+pub use addr::{AccessSize, ByteLen, PhysAddr, RegOffset};
+pub use capabilities::Capabilities;
+pub use deploy::Deployment;
+pub use endian::Endian;
+pub use error::Error;
+#[cfg(not(feature = "wasm"))]
+pub use events::ClientEvent;
+pub use field::Field;
+pub use firmware::{FirmwareStat, StorageInfo};
+#[cfg(not(feature = "wasm"))]
+pub use lease::Lease;
+pub use loaded::LoadedFirmware;
+pub use region::Region;
+pub use lock::{LOCK_FILE_SIZE, LOCK_FIRMWARE_NAME, LOCK_FIRMWARE_PATH};
+
 use jelly_fpga_control::jelly_fpga_control_client::JellyFpgaControlClient;
+use control::FpgaControl;
 use jelly_fpga_control::*;
 
+/// gRPC transport (HTTP/2 channel, or grpc-web with the `wasm` feature)
+#[cfg(not(feature = "wasm"))]
+pub type Transport = Channel;
+/// gRPC transport (HTTP/2 channel, or grpc-web with the `wasm` feature)
+#[cfg(feature = "wasm")]
+pub type Transport = tonic_web_wasm_client::Client;
+
 /// Jelly FPGA Control Client
+#[derive(Clone)]
 pub struct JellyFpgaClient {
-    client: JellyFpgaControlClient<Channel>,
+    client: JellyFpgaControlClient<Transport>,
+    handles: handles::HandleRegistry,
+    session: Option<String>,
+    lock: lock::LockState,
+    strict: bool,
+    verify: checksum::TransferVerify,
+    default_firmware: String,
+    regions: region::RegionRegistry,
+    loads: loads::LoadRegistry,
+    limiter: std::sync::Arc<limiter::Limiter>,
+    priority: Option<std::sync::Arc<raw::RawClient>>,
+    bulk: Option<std::sync::Arc<raw::RawClient>>,
+    max_message_size: Option<usize>,
+    upload_chunk_size: usize,
+    golden: Option<std::sync::Arc<golden::Golden>>,
+    capabilities: std::sync::Arc<tokio::sync::OnceCell<Capabilities>>,
+    board_files: std::sync::Arc<tokio::sync::Mutex<()>>,
+    record_manifest: bool,
+    #[cfg(not(feature = "wasm"))]
+    events: std::sync::Arc<events::EventHub>,
+    #[cfg(not(feature = "wasm"))]
+    tasks: std::sync::Arc<shutdown::Tasks>,
+    #[cfg(not(feature = "wasm"))]
+    diagnostics: Option<std::sync::Arc<diagnostics::DiagnosticsSlot>>,
+    #[cfg(not(feature = "wasm"))]
+    disk_cache: Option<std::sync::Arc<cache::CacheSlot>>,
+    #[cfg(feature = "local")]
+    local: Option<std::sync::Arc<local::LocalDevices>>,
 }
 
 impl JellyFpgaClient {
     /// Create a new client connection
+    ///
+    /// Also probes the server [`Capabilities`]; if that fails, they are
+    /// probed again on first use. With the `local` feature, a loopback
+    /// address on the board itself selects [direct device access](local).
+    #[cfg(not(feature = "wasm"))]
     pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
     where
         D: std::convert::TryInto<tonic::transport::Endpoint>,
         D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
     {
-        let client = JellyFpgaControlClient::connect(dst).await?;
-        Ok(JellyFpgaClient { client })
+        let endpoint = tonic::transport::Endpoint::new(dst)?;
+        let channel = endpoint.connect().await?;
+        let mut client = Self::from_raw(JellyFpgaControlClient::new(channel));
+        #[cfg(feature = "local")]
+        if local::is_local_target(endpoint.uri()) {
+            client = client.with_local_access(true);
+        }
+        let _ = client.capabilities().await;
+        Ok(client)
+    }
+
+    /// Create a new client connection and check the protocol version
+    ///
+    /// Like [`connect`](Self::connect), but probing the capabilities must
+    /// succeed and a server reporting another major protocol version fails
+    /// with [`Error::IncompatibleServer`](error::Error::IncompatibleServer)
+    /// (see [`check_compatibility`](Self::check_compatibility)).
+    #[cfg(not(feature = "wasm"))]
+    pub async fn connect_checked<D>(dst: D) -> Result<Self, error::Error>
+    where
+        D: std::convert::TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let mut client = Self::connect(dst).await?;
+        client.check_compatibility().await?;
+        Ok(client)
+    }
+
+    /// Create a client without connecting yet
+    ///
+    /// The connection is made by the first RPC (and remade by later ones
+    /// after it drops), so a long-lived daemon can create the client while
+    /// the board is powered off; calls fail with `unavailable` until the
+    /// server answers. Only an invalid address is reported here.
+    /// Capabilities are probed on first use. Selects [direct device
+    /// access](local) like [`connect`](Self::connect).
+    #[cfg(not(feature = "wasm"))]
+    pub fn connect_lazy<D>(dst: D) -> Result<Self, tonic::transport::Error>
+    where
+        D: std::convert::TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let endpoint = tonic::transport::Endpoint::new(dst)?;
+        let client = Self::from_raw(JellyFpgaControlClient::new(endpoint.connect_lazy()));
+        #[cfg(feature = "local")]
+        if local::is_local_target(endpoint.uri()) {
+            return Ok(client.with_local_access(true));
+        }
+        Ok(client)
+    }
+
+    /// Create a client talking grpc-web to `base_url` (e.g. an Envoy or tonic-web proxy)
+    ///
+    /// Client streaming RPCs (`upload_firmware`) are not available over grpc-web.
+    #[cfg(feature = "wasm")]
+    pub fn connect_web(base_url: impl Into<String>) -> Self {
+        let transport = tonic_web_wasm_client::Client::new(base_url.into());
+        Self::from_raw(JellyFpgaControlClient::new(transport))
+    }
+
+    /// Wrap a message, tagging it with the lease session if any
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        if let Some(session) = &self.session
+            && let Ok(value) = session.parse()
+        {
+            request.metadata_mut().insert(lease::SESSION_KEY, value);
+        }
+        request
+    }
+
+    /// Turn `result=false` responses into errors carrying the operation and its arguments
+    ///
+    /// The server does not report why an operation failed, so the message
+    /// is synthesized from the request (e.g. `read_mem_u failed (id=3
+    /// offset=0x10 size=4)`). Clones inherit the setting.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Whether strict mode is enabled
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Firmware was loaded or unloaded: drop the server's disk cache entry
+    async fn loads_changed(&self) {
+        #[cfg(not(feature = "wasm"))]
+        if let Some(slot) = &self.disk_cache {
+            slot.invalidate().await;
+        }
+    }
+
+    /// Fail with context in strict mode when the server returned `result=false`
+    fn soft_check<F>(&self, result: bool, op: &str, context: F) -> Result<(), tonic::Status>
+    where
+        F: FnOnce() -> String,
+    {
+        if result || !self.strict {
+            return Ok(());
+        }
+        let context = context();
+        let status = tonic::Status::internal(if context.is_empty() {
+            format!("{} failed", op)
+        } else {
+            format!("{} failed ({})", op, context)
+        });
+        Err(error::with_rpc(status, op))
+    }
+
+    /// Start a session lease with the given TTL
+    ///
+    /// Returns a client whose requests carry the session id (clones share
+    /// it) and the [`Lease`] renewing it in the background. Requires server
+    /// support; returns `unimplemented` if the server does not acknowledge
+    /// the lease. Handles opened through other clients are not covered.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn with_lease(
+        mut self,
+        ttl: std::time::Duration,
+    ) -> Result<(Self, Lease), tonic::Status> {
+        self.session = Some(lease::new_session_id());
+        if !self.lease_keepalive(ttl).await? {
+            return Err(tonic::Status::unimplemented("server does not support session leases"));
+        }
+        let session = self.session.clone().unwrap();
+        let lease = Lease::spawn(self.clone(), session, ttl);
+        Ok((self, lease))
+    }
+
+    /// Renew the lease; returns whether the server acknowledged it
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) async fn lease_keepalive(
+        &mut self,
+        ttl: std::time::Duration,
+    ) -> Result<bool, tonic::Status> {
+        let mut request = self.request(Empty {});
+        request
+            .metadata_mut()
+            .insert(lease::LEASE_TTL_KEY, ttl.as_millis().to_string().parse().unwrap());
+        let response = self
+            .limiter
+            .run("get_version", self.client.get_version(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "get_version"))?;
+        Ok(response.metadata().contains_key(lease::LEASE_TTL_KEY))
+    }
+
+    /// Wrap an existing generated client
+    pub fn from_raw(client: raw::RawClient) -> Self {
+        JellyFpgaClient {
+            client,
+            handles: handles::HandleRegistry::default(),
+            session: None,
+            lock: lock::LockState::default(),
+            strict: false,
+            verify: checksum::TransferVerify::Off,
+            default_firmware: deploy::DEFAULT_FIRMWARE.to_string(),
+            regions: region::RegionRegistry::default(),
+            loads: loads::LoadRegistry::default(),
+            limiter: Default::default(),
+            priority: None,
+            bulk: None,
+            max_message_size: None,
+            upload_chunk_size: tuning::DEFAULT_UPLOAD_CHUNK_SIZE,
+            golden: None,
+            capabilities: Default::default(),
+            board_files: Default::default(),
+            record_manifest: false,
+            #[cfg(not(feature = "wasm"))]
+            events: Default::default(),
+            #[cfg(not(feature = "wasm"))]
+            tasks: Default::default(),
+            #[cfg(not(feature = "wasm"))]
+            diagnostics: None,
+            #[cfg(not(feature = "wasm"))]
+            disk_cache: None,
+            #[cfg(feature = "local")]
+            local: None,
+        }
+    }
+
+    /// Generated client, for RPCs not covered by this wrapper
+    pub fn raw_mut(&mut self) -> &mut raw::RawClient {
+        &mut self.client
+    }
+
+    /// Unwrap into the generated client
+    pub fn into_raw(self) -> raw::RawClient {
+        self.client
+    }
+
+    /// Create accessor for an opened id
+    pub fn accessor(&self, id: u32) -> Accessor {
+        Accessor::new(self.clone(), id)
     }
 
     /// Get server version
     pub async fn get_version(&mut self) -> Result<String, tonic::Status> {
-        let request = Request::new(Empty {});
-        let response = self.client.get_version(request).await?;
+        let request = self.request(Empty {});
+        let response = self
+            .limiter
+            .run("get_version", self.client.get_version(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "get_version"))?;
         Ok(response.into_inner().version)
     }
 
     /// Reset the FPGA
     pub async fn reset(&mut self) -> Result<bool, tonic::Status> {
-        let request = Request::new(ResetRequest {});
-        let response = self.client.reset(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("reset")?;
+        let request = self.request(ResetRequest {});
+        let response = self
+            .limiter
+            .run("reset", self.client.reset(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "reset"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "reset", String::new)?;
+        if result {
+            self.loads.clear();
+            self.loads_changed().await;
+        }
+        Ok(result)
+    }
+
+    /// Reset and wait until the server answers requests again
+    ///
+    /// Polls `get_version` every 100 ms and fails with `deadline_exceeded`
+    /// after `timeout`. The reset RPC takes no options, so this is always a
+    /// full reset; the server reports no separate fabric state.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn reset_and_wait_ready(
+        &mut self,
+        timeout: std::time::Duration,
+    ) -> Result<(), tonic::Status> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        accessor::check(self.reset().await?, "reset")?;
+        loop {
+            let last = match self.get_version().await {
+                Ok(_) => return Ok(()),
+                Err(e) => e,
+            };
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded(format!(
+                    "server not ready after reset: {}",
+                    last.message()
+                )));
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
     }
 
     /// Load firmware with name
     pub async fn load(&mut self, name: &str) -> Result<(bool, i32), tonic::Status> {
-        let request = Request::new(LoadRequest { name: name.to_string() });
-        let response = self.client.load(request).await?;
+        self.lock.check("load")?;
+        let request = self.request(LoadRequest { name: name.to_string() });
+        let response = self
+            .limiter
+            .run("load", self.client.load(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "load"))?;
         let inner = response.into_inner();
+        self.soft_check(inner.result, "load", || format!("name={:?}", name))?;
+        if inner.result {
+            self.loads.loaded(inner.slot, name);
+            self.loads_changed().await;
+        }
         Ok((inner.result, inner.slot))
     }
 
     /// Unload firmware from slot
     pub async fn unload(&mut self, slot: i32) -> Result<bool, tonic::Status> {
-        let request = Request::new(UnloadRequest { slot });
-        let response = self.client.unload(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("unload")?;
+        let request = self.request(UnloadRequest { slot });
+        let response = self
+            .limiter
+            .run("unload", self.client.unload(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "unload"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "unload", || format!("slot={}", slot))?;
+        if result {
+            self.loads.unloaded(slot);
+            self.loads_changed().await;
+        }
+        Ok(result)
     }
 
     /// Unload all firmware (convenience method)
     pub async fn unload_all(&mut self) -> Result<bool, tonic::Status> {
         // In practice, slot -1 or 0 might unload all, but this depends on server implementation
         // For now, we'll use slot 0 as a default
-        self.unload(0).await
+        let result = self.unload(0).await?;
+        if result {
+            self.loads.clear();
+            self.loads_changed().await;
+        }
+        Ok(result)
     }
 
     /// Register accelerator package
@@ -69,28 +506,82 @@ impl JellyFpgaClient {
         json_file: Option<&str>,
         overwrite: bool,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(RegisterAccelRequest {
+        self.lock.check("register_accel")?;
+        let request = self.request(RegisterAccelRequest {
             accel_name: accel_name.to_string(),
             bin_file: bin_file.to_string(),
             dtbo_file: dtbo_file.to_string(),
             json_file: json_file.unwrap_or("").to_string(),
             overwrite,
         });
-        let response = self.client.register_accel(request).await?;
-        Ok(response.into_inner().result)
+        let response = self
+            .limiter
+            .run("register_accel", self.client.register_accel(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "register_accel"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "register_accel", || {
+            format!(
+                "accel_name={:?} bin_file={:?} dtbo_file={:?} json_file={:?} overwrite={}",
+                accel_name,
+                bin_file,
+                dtbo_file,
+                json_file,
+                overwrite,
+            )
+        })?;
+        if result {
+            let info = AccelInfo {
+                name: accel_name.to_string(),
+                bin_file: bin_file.to_string(),
+                dtbo_file: dtbo_file.to_string(),
+                json_file: json_file.filter(|s| !s.is_empty()).map(str::to_string),
+                metadata: None,
+            };
+            self.update_accels(accel_name, Some(info)).await?;
+        }
+        Ok(result)
     }
 
     /// Unregister accelerator package
     pub async fn unregister_accel(&mut self, accel_name: &str) -> Result<bool, tonic::Status> {
-        let request = Request::new(UnregisterAccelRequest {
+        self.lock.check("unregister_accel")?;
+        let request = self.request(UnregisterAccelRequest {
             accel_name: accel_name.to_string(),
         });
-        let response = self.client.unregister_accel(request).await?;
-        Ok(response.into_inner().result)
+        let response = self
+            .limiter
+            .run("unregister_accel", self.client.unregister_accel(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "unregister_accel"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "unregister_accel", || format!("accel_name={:?}", accel_name))?;
+        if result {
+            self.update_accels(accel_name, None).await?;
+        }
+        Ok(result)
     }
 
     /// Upload firmware from data
+    ///
+    /// Also records the file in the firmware manifest if enabled with
+    /// [`set_record_manifest`](Self::set_record_manifest).
     pub async fn upload_firmware(&mut self, name: &str, data: Vec<u8>) -> Result<bool, tonic::Status> {
+        if !self.record_manifest {
+            return self.upload_firmware_recorded(name, data, None).await;
+        }
+        let (data, sha256) = firmware::sha256_blocking(data).await?;
+        self.upload_firmware_recorded(name, data, Some(sha256)).await
+    }
+
+    /// Upload firmware and record it in the manifest if `sha256` is given
+    pub(crate) async fn upload_firmware_recorded(
+        &mut self,
+        name: &str,
+        data: Vec<u8>,
+        sha256: Option<String>,
+    ) -> Result<bool, tonic::Status> {
+        self.lock.check("upload_firmware")?;
         use futures_core::stream::Stream;
         use std::pin::Pin;
         use std::task::{Context, Poll};
@@ -123,52 +614,159 @@ impl JellyFpgaClient {
             }
         }
         
+        let size = data.len() as u64;
         let stream = DataStream {
             name: name.to_string(),
             data,
-            chunk_size: 2 * 1024 * 1024, // 2MB chunks like Python version
+            chunk_size: self.upload_chunk_size,
             offset: 0,
         };
         
-        let response = self.client.upload_firmware(Request::new(stream)).await?;
-        Ok(response.into_inner().result)
+        let response = self
+            .limiter
+            .run("upload_firmware", self.bulk_client().upload_firmware(self.request(stream)))
+            .await
+            .map_err(|e| error::with_rpc(e, "upload_firmware"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "upload_firmware", || format!("name={:?}", name))?;
+        if result && let Some(sha256) = sha256 {
+            self.record_firmware(name, size, sha256).await?;
+        }
+        Ok(result)
     }
 
     /// Upload firmware from file
     pub async fn upload_firmware_file(&mut self, name: &str, file_path: &str) -> Result<bool, tonic::Status> {
-        let data = std::fs::read(file_path).map_err(|e| {
-            tonic::Status::internal(format!("Failed to read file {}: {}", file_path, e))
-        })?;
-        
+        let data = fs::read(file_path).await?;
         self.upload_firmware(name, data).await
     }
 
+    /// Upload firmware streamed from an HTTP(S) download
+    ///
+    /// Each downloaded chunk is forwarded as an upload message, so nothing
+    /// is buffered or written on the host. If the download fails midway the
+    /// partial firmware is removed (best effort) and `unavailable` is returned.
+    #[cfg(feature = "reqwest")]
+    pub async fn upload_firmware_from_url(&mut self, name: &str, url: &str) -> Result<bool, tonic::Status> {
+        use sha2::{Digest, Sha256};
+        use std::sync::{Arc, Mutex};
+        use tokio_stream::StreamExt;
+
+        self.lock.check("upload_firmware")?;
+        let download = reqwest::get(url)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| tonic::Status::unavailable(format!("Failed to download {}: {}", url, e)))?;
+
+        let failed = Arc::new(Mutex::new(None));
+        let hasher = Arc::new(Mutex::new((Sha256::new(), 0u64)));
+        let stream = {
+            let name = name.to_string();
+            let failed = failed.clone();
+            let hasher = hasher.clone();
+            download.bytes_stream().map_while(move |chunk| match chunk {
+                Ok(data) => {
+                    let mut h = hasher.lock().unwrap();
+                    h.0.update(&data);
+                    h.1 += data.len() as u64;
+                    Some(UploadFirmwareRequest {
+                        name: name.clone(),
+                        data: data.to_vec(),
+                    })
+                }
+                Err(e) => {
+                    *failed.lock().unwrap() = Some(e);
+                    None
+                }
+            })
+        };
+
+        let response = self
+            .limiter
+            .run("upload_firmware", self.bulk_client().upload_firmware(self.request(stream)))
+            .await
+            .map_err(|e| error::with_rpc(e, "upload_firmware"))?;
+        let failed = failed.lock().unwrap().take();
+        if let Some(e) = failed {
+            let _ = self.remove_firmware(name).await;
+            return Err(tonic::Status::unavailable(format!(
+                "Failed to download {}: {}",
+                url, e
+            )));
+        }
+        let result = response.into_inner().result;
+        self.soft_check(result, "upload_firmware", || format!("name={:?} url={:?}", name, url))?;
+        if result && self.record_manifest {
+            let (digest, size) = std::mem::take(&mut *hasher.lock().unwrap());
+            self.record_firmware(name, size, firmware::sha256_hex(&digest.finalize()))
+                .await?;
+        }
+        Ok(result)
+    }
+
     /// Remove firmware
     pub async fn remove_firmware(&mut self, name: &str) -> Result<bool, tonic::Status> {
-        let request = Request::new(RemoveFirmwareRequest { name: name.to_string() });
-        let response = self.client.remove_firmware(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("remove_firmware")?;
+        let request = self.request(RemoveFirmwareRequest { name: name.to_string() });
+        let response = self
+            .limiter
+            .run("remove_firmware", self.client.remove_firmware(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "remove_firmware"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "remove_firmware", || format!("name={:?}", name))?;
+        if result {
+            self.forget_firmware(name).await;
+        }
+        Ok(result)
     }
 
     /// Load bitstream
     pub async fn load_bitstream(&mut self, name: &str) -> Result<bool, tonic::Status> {
-        let request = Request::new(LoadBitstreamRequest { name: name.to_string() });
-        let response = self.client.load_bitstream(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("load_bitstream")?;
+        let request = self.request(LoadBitstreamRequest { name: name.to_string() });
+        let response = self
+            .limiter
+            .run("load_bitstream", self.client.load_bitstream(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "load_bitstream"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "load_bitstream", || format!("name={:?}", name))?;
+        if result {
+            self.loads.bitstream(name);
+            self.loads_changed().await;
+        }
+        Ok(result)
     }
 
     /// Load device tree overlay
     pub async fn load_dtbo(&mut self, name: &str) -> Result<bool, tonic::Status> {
-        let request = Request::new(LoadDtboRequest { name: name.to_string() });
-        let response = self.client.load_dtbo(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("load_dtbo")?;
+        let request = self.request(LoadDtboRequest { name: name.to_string() });
+        let response = self
+            .limiter
+            .run("load_dtbo", self.client.load_dtbo(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "load_dtbo"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "load_dtbo", || format!("name={:?}", name))?;
+        if result {
+            self.loads.overlay(name);
+            self.loads_changed().await;
+        }
+        Ok(result)
     }
 
     /// Convert DTS to DTB
     pub async fn dts_to_dtb(&mut self, dts: &str) -> Result<(bool, Vec<u8>), tonic::Status> {
-        let request = Request::new(DtsToDtbRequest { dts: dts.to_string() });
-        let response = self.client.dts_to_dtb(request).await?;
+        let request = self.request(DtsToDtbRequest { dts: dts.to_string() });
+        let response = self
+            .limiter
+            .run("dts_to_dtb", self.client.dts_to_dtb(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "dts_to_dtb"))?;
         let inner = response.into_inner();
+        self.soft_check(inner.result, "dts_to_dtb", || format!("dts_len={}", dts.len()))?;
         Ok((inner.result, inner.dtb))
     }
 
@@ -179,41 +777,86 @@ impl JellyFpgaClient {
         bin_name: &str,
         arch: &str,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(BitstreamToBinRequest {
+        let request = self.request(BitstreamToBinRequest {
             bitstream_name: bitstream_name.to_string(),
             bin_name: bin_name.to_string(),
             arch: arch.to_string(),
         });
-        let response = self.client.bitstream_to_bin(request).await?;
-        Ok(response.into_inner().result)
+        let response = self
+            .limiter
+            .run("bitstream_to_bin", self.client.bitstream_to_bin(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "bitstream_to_bin"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "bitstream_to_bin", || {
+            format!(
+                "bitstream_name={:?} bin_name={:?} arch={:?}",
+                bitstream_name,
+                bin_name,
+                arch,
+            )
+        })?;
+        Ok(result)
     }
 
     /// Load remote processor firmware
+    ///
+    /// The server protocol has no rpmsg endpoint RPCs, so messages to RPU
+    /// firmware cannot go through `/dev/rpmsg*`; use a
+    /// [`Mailbox`](crate::mailbox::Mailbox) in memory shared with the
+    /// firmware (e.g. an `open_mmap` of its carveout) instead.
     pub async fn load_remoteproc(
         &mut self,
         remoteproc_id: u64,
         elf_name: &str,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(LoadRemoteprocRequest {
+        self.lock.check("load_remoteproc")?;
+        let request = self.request(LoadRemoteprocRequest {
             remoteproc_id,
             elf_name: elf_name.to_string(),
         });
-        let response = self.client.load_remoteproc(request).await?;
-        Ok(response.into_inner().result)
+        let response = self
+            .limiter
+            .run("load_remoteproc", self.client.load_remoteproc(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "load_remoteproc"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "load_remoteproc", || {
+            format!(
+                "remoteproc_id={} elf_name={:?}",
+                remoteproc_id,
+                elf_name,
+            )
+        })?;
+        Ok(result)
     }
 
     /// Start remote processor
     pub async fn start_remoteproc(&mut self, remoteproc_id: u64) -> Result<bool, tonic::Status> {
-        let request = Request::new(RemoteprocIdRequest { remoteproc_id });
-        let response = self.client.start_remoteproc(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("start_remoteproc")?;
+        let request = self.request(RemoteprocIdRequest { remoteproc_id });
+        let response = self
+            .limiter
+            .run("start_remoteproc", self.client.start_remoteproc(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "start_remoteproc"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "start_remoteproc", || format!("remoteproc_id={}", remoteproc_id))?;
+        Ok(result)
     }
 
     /// Stop remote processor
     pub async fn stop_remoteproc(&mut self, remoteproc_id: u64) -> Result<bool, tonic::Status> {
-        let request = Request::new(RemoteprocIdRequest { remoteproc_id });
-        let response = self.client.stop_remoteproc(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("stop_remoteproc")?;
+        let request = self.request(RemoteprocIdRequest { remoteproc_id });
+        let response = self
+            .limiter
+            .run("stop_remoteproc", self.client.stop_remoteproc(request))
+            .await
+            .map_err(|e| error::with_rpc(e, "stop_remoteproc"))?;
+        let result = response.into_inner().result;
+        self.soft_check(result, "stop_remoteproc", || format!("remoteproc_id={}", remoteproc_id))?;
+        Ok(result)
     }
 
 
@@ -225,25 +868,59 @@ impl JellyFpgaClient {
         size: u64,
         unit: u64,
     ) -> Result<(bool, u32), tonic::Status> {
-        let request = Request::new(OpenMmapRequest {
+        let request = self.request(OpenMmapRequest {
             path: path.to_string(),
             offset,
             size,
             unit,
         });
-        let response = self.client.open_mmap(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.id))
+        let (result, id) = self
+            .limiter
+            .run("open_mmap", self.backend().open_mmap(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().id))
+            .map_err(|e| error::with_rpc(e, "open_mmap"))?;
+        self.soft_check(result, "open_mmap", || {
+            format!(
+                "path={:?} offset=0x{:x} size={} unit={}",
+                path,
+                offset,
+                size,
+                unit,
+            )
+        })?;
+        if result {
+            let spec = environment::OpenSpec::Mmap {
+                path: path.to_string(),
+                offset,
+                size,
+                unit,
+            };
+            self.handles.insert(id, spec);
+        }
+        Ok((result, id))
     }
 
 
 
     /// Open UIO device
     pub async fn open_uio(&mut self, name: &str, unit: u64) -> Result<(bool, u32), tonic::Status> {
-        let request = Request::new(OpenUioRequest { name: name.to_string(), unit });
-        let response = self.client.open_uio(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.id))
+        let request = self.request(OpenUioRequest { name: name.to_string(), unit });
+        let (result, id) = self
+            .limiter
+            .run("open_uio", self.backend().open_uio(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().id))
+            .map_err(|e| error::with_rpc(e, "open_uio"))?;
+        self.soft_check(result, "open_uio", || format!("name={:?} unit={}", name, unit))?;
+        if result {
+            let spec = environment::OpenSpec::Uio {
+                name: name.to_string(),
+                unit,
+            };
+            self.handles.insert(id, spec);
+        }
+        Ok((result, id))
     }
 
     /// Open UDMABUF device
@@ -253,21 +930,105 @@ impl JellyFpgaClient {
         cache_enable: bool,
         unit: u64,
     ) -> Result<(bool, u32), tonic::Status> {
-        let request = Request::new(OpenUdmabufRequest {
+        let request = self.request(OpenUdmabufRequest {
             name: name.to_string(),
             cache_enable,
             unit,
         });
-        let response = self.client.open_udmabuf(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.id))
+        let (result, id) = self
+            .limiter
+            .run("open_udmabuf", self.backend().open_udmabuf(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().id))
+            .map_err(|e| error::with_rpc(e, "open_udmabuf"))?;
+        self.soft_check(result, "open_udmabuf", || {
+            format!(
+                "name={:?} cache_enable={} unit={}",
+                name,
+                cache_enable,
+                unit,
+            )
+        })?;
+        if result {
+            let spec = environment::OpenSpec::Udmabuf {
+                name: name.to_string(),
+                cache_enable,
+                unit,
+            };
+            self.handles.insert(id, spec);
+        }
+        Ok((result, id))
     }
 
     /// Close device
     pub async fn close(&mut self, id: u32) -> Result<bool, tonic::Status> {
-        let request = Request::new(CloseRequest { id });
-        let response = self.client.close(request).await?;
-        Ok(response.into_inner().result)
+        let request = self.request(CloseRequest { id });
+        let result = self
+            .limiter
+            .run("close", self.backend().close(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "close"))?;
+        self.soft_check(result, "close", || format!("id={}", id))?;
+        if result {
+            self.handles.remove(id);
+        }
+        Ok(result)
+    }
+
+    /// Ids opened through this client (and its clones) and not yet closed
+    pub fn list_open_handles(&self) -> Vec<u32> {
+        self.handles.list()
+    }
+
+    /// Close every id in [`list_open_handles`](Self::list_open_handles)
+    ///
+    /// Subclones are closed before their parents (ids in descending order).
+    /// Returns `false` if any close failed; those ids stay registered.
+    pub async fn close_all(&mut self) -> Result<bool, tonic::Status> {
+        let mut all = true;
+        for id in self.handles.list().into_iter().rev() {
+            all &= self.close(id).await?;
+        }
+        Ok(all)
+    }
+
+    /// Mirror the open ids to `path` on every open/close (`None` to stop)
+    ///
+    /// If the process dies, a later session can pass the same file to
+    /// [`close_stale_handles`](Self::close_stale_handles).
+    pub fn set_handle_file<P: Into<std::path::PathBuf>>(&mut self, path: Option<P>) {
+        self.handles.set_file(path.map(Into::into));
+    }
+
+    /// Close ids left in a handle file by a previous session
+    ///
+    /// The server has no RPC to list open handles, so the file is the
+    /// only record; ids the server no longer knows (probed with `get_size`)
+    /// are skipped. Call before opening new devices, since the server may
+    /// reuse ids. Returns the ids that were closed.
+    pub async fn close_stale_handles<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<Vec<u32>, tonic::Status> {
+        let ids = handles::load(path.as_ref()).await.map_err(|e| {
+            tonic::Status::internal(format!(
+                "Failed to read handle file {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        let mut closed = Vec::new();
+        for id in ids.into_iter().rev() {
+            if self.handles.list().contains(&id) {
+                continue;
+            }
+            let (alive, _) = self.get_size(id).await?;
+            if alive && self.close(id).await? {
+                closed.push(id);
+            }
+        }
+        Ok(closed)
     }
 
     /// Create subclone of device
@@ -278,39 +1039,76 @@ impl JellyFpgaClient {
         size: u64,
         unit: u64,
     ) -> Result<(bool, u32), tonic::Status> {
-        let request = Request::new(SubcloneRequest {
+        let request = self.request(SubcloneRequest {
             id,
             offset,
             size,
             unit,
         });
-        let response = self.client.subclone(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.id))
+        let (result, new_id) = self
+            .limiter
+            .run("subclone", self.backend().subclone(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().id))
+            .map_err(|e| error::with_rpc(e, "subclone"))?;
+        self.soft_check(result, "subclone", || {
+            format!(
+                "id={} offset=0x{:x} size={} unit={}",
+                id,
+                offset,
+                size,
+                unit,
+            )
+        })?;
+        if result {
+            let spec = environment::OpenSpec::Subclone {
+                parent: id,
+                offset,
+                size,
+                unit,
+            };
+            self.handles.insert(new_id, spec);
+        }
+        Ok((result, new_id))
     }
 
     /// Get device address
     pub async fn get_addr(&mut self, id: u32) -> Result<(bool, u64), tonic::Status> {
-        let request = Request::new(GetAddrRequest { id });
-        let response = self.client.get_addr(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.addr))
+        let request = self.request(GetAddrRequest { id });
+        let (result, addr) = self
+            .limiter
+            .run("get_addr", self.backend().get_addr(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().addr))
+            .map_err(|e| error::with_rpc(e, "get_addr"))?;
+        self.soft_check(result, "get_addr", || format!("id={}", id))?;
+        Ok((result, addr))
     }
 
     /// Get device size
     pub async fn get_size(&mut self, id: u32) -> Result<(bool, u64), tonic::Status> {
-        let request = Request::new(GetSizeRequest { id });
-        let response = self.client.get_size(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.size))
+        let request = self.request(GetSizeRequest { id });
+        let (result, size) = self
+            .limiter
+            .run("get_size", self.backend().get_size(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().size))
+            .map_err(|e| error::with_rpc(e, "get_size"))?;
+        self.soft_check(result, "get_size", || format!("id={}", id))?;
+        Ok((result, size))
     }
 
     /// Get device physical address
     pub async fn get_phys_addr(&mut self, id: u32) -> Result<(bool, u64), tonic::Status> {
-        let request = Request::new(GetPhysAddrRequest { id });
-        let response = self.client.get_phys_addr(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.phys_addr))
+        let request = self.request(GetPhysAddrRequest { id });
+        let (result, phys_addr) = self
+            .limiter
+            .run("get_phys_addr", self.backend().get_phys_addr(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().phys_addr))
+            .map_err(|e| error::with_rpc(e, "get_phys_addr"))?;
+        self.soft_check(result, "get_phys_addr", || format!("id={}", id))?;
+        Ok((result, phys_addr))
     }
 
     /// Write unsigned integer to memory
@@ -321,14 +1119,31 @@ impl JellyFpgaClient {
         data: u64,
         size: u64,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteMemURequest {
+        self.lock.check("write_mem_u")?;
+        let request = self.request(WriteMemURequest {
             id,
             offset,
             data,
             size,
         });
-        let response = self.client.write_mem_u(request).await?;
-        Ok(response.into_inner().result)
+        let result = self
+            .limiter
+            .run("write_mem_u", self.backend().write_mem_u(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_mem_u"))?;
+        self.soft_check(result, "write_mem_u", || {
+            format!(
+                "id={} offset=0x{:x} size={}",
+                id,
+                offset,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_write(golden::Access::mem(id, offset, size), &golden::int_bytes(data, size));
+        }
+        Ok(result)
     }
 
     /// Write 8-bit unsigned integer to memory (convenience method)
@@ -338,7 +1153,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: u8,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_u(id, offset, data as u64, 1).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Write 16-bit unsigned integer to memory (convenience method)
@@ -348,7 +1163,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: u16,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_u(id, offset, data as u64, 2).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Write 32-bit unsigned integer to memory (convenience method)
@@ -358,7 +1173,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: u32,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_u(id, offset, data as u64, 4).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Write 64-bit unsigned integer to memory (convenience method)
@@ -368,7 +1183,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: u64,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_u(id, offset, data, 8).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Write signed integer to memory
@@ -379,14 +1194,31 @@ impl JellyFpgaClient {
         data: i64,
         size: u64,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteMemIRequest {
+        self.lock.check("write_mem_i")?;
+        let request = self.request(WriteMemIRequest {
             id,
             offset,
             data,
             size,
         });
-        let response = self.client.write_mem_i(request).await?;
-        Ok(response.into_inner().result)
+        let result = self
+            .limiter
+            .run("write_mem_i", self.backend().write_mem_i(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_mem_i"))?;
+        self.soft_check(result, "write_mem_i", || {
+            format!(
+                "id={} offset=0x{:x} size={}",
+                id,
+                offset,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_write(golden::Access::mem(id, offset, size), &golden::int_bytes(data as u64, size));
+        }
+        Ok(result)
     }
 
     /// Write 8-bit signed integer to memory (convenience method)
@@ -396,7 +1228,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: i8,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_i(id, offset, data as i64, 1).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Write 16-bit signed integer to memory (convenience method)
@@ -406,7 +1238,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: i16,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_i(id, offset, data as i64, 2).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Write 32-bit signed integer to memory (convenience method)
@@ -416,7 +1248,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: i32,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_i(id, offset, data as i64, 4).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Write 64-bit signed integer to memory (convenience method)
@@ -426,7 +1258,7 @@ impl JellyFpgaClient {
         offset: u64,
         data: i64,
     ) -> Result<bool, tonic::Status> {
-        self.write_mem_i(id, offset, data, 8).await
+        self.write_mem(id, offset, data).await
     }
 
     /// Read unsigned integer from memory
@@ -436,10 +1268,25 @@ impl JellyFpgaClient {
         offset: u64,
         size: u64,
     ) -> Result<(bool, u64), tonic::Status> {
-        let request = Request::new(ReadMemRequest { id, offset, size });
-        let response = self.client.read_mem_u(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let request = self.request(ReadMemRequest { id, offset, size });
+        let (result, data) = self
+            .limiter
+            .run("read_mem_u", self.backend().read_mem_u(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_mem_u"))?;
+        self.soft_check(result, "read_mem_u", || {
+            format!(
+                "id={} offset=0x{:x} size={}",
+                id,
+                offset,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_read(golden::Access::mem(id, offset, size), &golden::int_bytes(data, size))?;
+        }
+        Ok((result, data))
     }
 
     /// Read 8-bit unsigned integer from memory (convenience method)
@@ -448,8 +1295,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, u8), tonic::Status> {
-        let (result, data) = self.read_mem_u(id, offset, 1).await?;
-        Ok((result, data as u8))
+        self.read_mem(id, offset).await
     }
 
     /// Read 16-bit unsigned integer from memory (convenience method)
@@ -458,8 +1304,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, u16), tonic::Status> {
-        let (result, data) = self.read_mem_u(id, offset, 2).await?;
-        Ok((result, data as u16))
+        self.read_mem(id, offset).await
     }
 
     /// Read 32-bit unsigned integer from memory (convenience method)
@@ -468,8 +1313,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, u32), tonic::Status> {
-        let (result, data) = self.read_mem_u(id, offset, 4).await?;
-        Ok((result, data as u32))
+        self.read_mem(id, offset).await
     }
 
     /// Read 64-bit unsigned integer from memory (convenience method)
@@ -478,7 +1322,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, u64), tonic::Status> {
-        self.read_mem_u(id, offset, 8).await
+        self.read_mem(id, offset).await
     }
 
     /// Read signed integer from memory
@@ -488,10 +1332,25 @@ impl JellyFpgaClient {
         offset: u64,
         size: u64,
     ) -> Result<(bool, i64), tonic::Status> {
-        let request = Request::new(ReadMemRequest { id, offset, size });
-        let response = self.client.read_mem_i(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let request = self.request(ReadMemRequest { id, offset, size });
+        let (result, data) = self
+            .limiter
+            .run("read_mem_i", self.backend().read_mem_i(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_mem_i"))?;
+        self.soft_check(result, "read_mem_i", || {
+            format!(
+                "id={} offset=0x{:x} size={}",
+                id,
+                offset,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_read(golden::Access::mem(id, offset, size), &golden::int_bytes(data as u64, size))?;
+        }
+        Ok((result, data))
     }
 
     /// Read 8-bit signed integer from memory (convenience method)
@@ -500,8 +1359,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, i8), tonic::Status> {
-        let (result, data) = self.read_mem_i(id, offset, 1).await?;
-        Ok((result, data as i8))
+        self.read_mem(id, offset).await
     }
 
     /// Read 16-bit signed integer from memory (convenience method)
@@ -510,8 +1368,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, i16), tonic::Status> {
-        let (result, data) = self.read_mem_i(id, offset, 2).await?;
-        Ok((result, data as i16))
+        self.read_mem(id, offset).await
     }
 
     /// Read 32-bit signed integer from memory (convenience method)
@@ -520,8 +1377,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, i32), tonic::Status> {
-        let (result, data) = self.read_mem_i(id, offset, 4).await?;
-        Ok((result, data as i32))
+        self.read_mem(id, offset).await
     }
 
     /// Read 64-bit signed integer from memory (convenience method)
@@ -530,7 +1386,7 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, i64), tonic::Status> {
-        self.read_mem_i(id, offset, 8).await
+        self.read_mem(id, offset).await
     }
 
     /// Write unsigned integer to register
@@ -541,14 +1397,31 @@ impl JellyFpgaClient {
         data: u64,
         size: u64,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteRegURequest {
+        self.lock.check("write_reg_u")?;
+        let request = self.request(WriteRegURequest {
             id,
             reg,
             data,
             size,
         });
-        let response = self.client.write_reg_u(request).await?;
-        Ok(response.into_inner().result)
+        let result = self
+            .limiter
+            .run("write_reg_u", self.backend().write_reg_u(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_reg_u"))?;
+        self.soft_check(result, "write_reg_u", || {
+            format!(
+                "id={} reg=0x{:x} size={}",
+                id,
+                reg,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_write(golden::Access::reg(id, reg, size), &golden::int_bytes(data, size));
+        }
+        Ok(result)
     }
 
     /// Write 8-bit unsigned integer to register (convenience method)
@@ -558,7 +1431,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: u8,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_u(id, reg, data as u64, 1).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Write 16-bit unsigned integer to register (convenience method)
@@ -568,7 +1441,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: u16,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_u(id, reg, data as u64, 2).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Write 32-bit unsigned integer to register (convenience method)
@@ -578,7 +1451,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: u32,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_u(id, reg, data as u64, 4).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Write 64-bit unsigned integer to register (convenience method)
@@ -588,7 +1461,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: u64,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_u(id, reg, data, 8).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Write signed integer to register
@@ -599,14 +1472,31 @@ impl JellyFpgaClient {
         data: i64,
         size: u64,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteRegIRequest {
+        self.lock.check("write_reg_i")?;
+        let request = self.request(WriteRegIRequest {
             id,
             reg,
             data,
             size,
         });
-        let response = self.client.write_reg_i(request).await?;
-        Ok(response.into_inner().result)
+        let result = self
+            .limiter
+            .run("write_reg_i", self.backend().write_reg_i(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_reg_i"))?;
+        self.soft_check(result, "write_reg_i", || {
+            format!(
+                "id={} reg=0x{:x} size={}",
+                id,
+                reg,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_write(golden::Access::reg(id, reg, size), &golden::int_bytes(data as u64, size));
+        }
+        Ok(result)
     }
 
     /// Write 8-bit signed integer to register (convenience method)
@@ -616,7 +1506,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: i8,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_i(id, reg, data as i64, 1).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Write 16-bit signed integer to register (convenience method)
@@ -626,7 +1516,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: i16,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_i(id, reg, data as i64, 2).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Write 32-bit signed integer to register (convenience method)
@@ -636,7 +1526,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: i32,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_i(id, reg, data as i64, 4).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Write 64-bit signed integer to register (convenience method)
@@ -646,7 +1536,7 @@ impl JellyFpgaClient {
         reg: u64,
         data: i64,
     ) -> Result<bool, tonic::Status> {
-        self.write_reg_i(id, reg, data, 8).await
+        self.write_reg(id, reg, data).await
     }
 
     /// Read unsigned integer from register
@@ -656,10 +1546,25 @@ impl JellyFpgaClient {
         reg: u64,
         size: u64,
     ) -> Result<(bool, u64), tonic::Status> {
-        let request = Request::new(ReadRegRequest { id, reg, size });
-        let response = self.client.read_reg_u(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let request = self.request(ReadRegRequest { id, reg, size });
+        let (result, data) = self
+            .limiter
+            .run("read_reg_u", self.backend().read_reg_u(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_reg_u"))?;
+        self.soft_check(result, "read_reg_u", || {
+            format!(
+                "id={} reg=0x{:x} size={}",
+                id,
+                reg,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_read(golden::Access::reg(id, reg, size), &golden::int_bytes(data, size))?;
+        }
+        Ok((result, data))
     }
 
     /// Read 8-bit unsigned integer from register (convenience method)
@@ -668,8 +1573,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, u8), tonic::Status> {
-        let (result, data) = self.read_reg_u(id, reg, 1).await?;
-        Ok((result, data as u8))
+        self.read_reg(id, reg).await
     }
 
     /// Read 16-bit unsigned integer from register (convenience method)
@@ -678,8 +1582,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, u16), tonic::Status> {
-        let (result, data) = self.read_reg_u(id, reg, 2).await?;
-        Ok((result, data as u16))
+        self.read_reg(id, reg).await
     }
 
     /// Read 32-bit unsigned integer from register (convenience method)
@@ -688,8 +1591,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, u32), tonic::Status> {
-        let (result, data) = self.read_reg_u(id, reg, 4).await?;
-        Ok((result, data as u32))
+        self.read_reg(id, reg).await
     }
 
     /// Read 64-bit unsigned integer from register (convenience method)
@@ -698,7 +1600,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, u64), tonic::Status> {
-        self.read_reg_u(id, reg, 8).await
+        self.read_reg(id, reg).await
     }
 
     /// Read signed integer from register
@@ -708,10 +1610,25 @@ impl JellyFpgaClient {
         reg: u64,
         size: u64,
     ) -> Result<(bool, i64), tonic::Status> {
-        let request = Request::new(ReadRegRequest { id, reg, size });
-        let response = self.client.read_reg_i(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let request = self.request(ReadRegRequest { id, reg, size });
+        let (result, data) = self
+            .limiter
+            .run("read_reg_i", self.backend().read_reg_i(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_reg_i"))?;
+        self.soft_check(result, "read_reg_i", || {
+            format!(
+                "id={} reg=0x{:x} size={}",
+                id,
+                reg,
+                size,
+            )
+        })?;
+        if result {
+            self.golden_read(golden::Access::reg(id, reg, size), &golden::int_bytes(data as u64, size))?;
+        }
+        Ok((result, data))
     }
 
     /// Read 8-bit signed integer from register (convenience method)
@@ -720,8 +1637,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, i8), tonic::Status> {
-        let (result, data) = self.read_reg_i(id, reg, 1).await?;
-        Ok((result, data as i8))
+        self.read_reg(id, reg).await
     }
 
     /// Read 16-bit signed integer from register (convenience method)
@@ -730,8 +1646,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, i16), tonic::Status> {
-        let (result, data) = self.read_reg_i(id, reg, 2).await?;
-        Ok((result, data as i16))
+        self.read_reg(id, reg).await
     }
 
     /// Read 32-bit signed integer from register (convenience method)
@@ -740,8 +1655,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, i32), tonic::Status> {
-        let (result, data) = self.read_reg_i(id, reg, 4).await?;
-        Ok((result, data as i32))
+        self.read_reg(id, reg).await
     }
 
     /// Read 64-bit signed integer from register (convenience method)
@@ -750,7 +1664,7 @@ impl JellyFpgaClient {
         id: u32,
         reg: u64,
     ) -> Result<(bool, i64), tonic::Status> {
-        self.read_reg_i(id, reg, 8).await
+        self.read_reg(id, reg).await
     }
 
     /// Write 32-bit float to memory
@@ -760,9 +1674,19 @@ impl JellyFpgaClient {
         offset: u64,
         data: f32,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteMemF32Request { id, offset, data });
-        let response = self.client.write_mem_f32(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("write_mem_f32")?;
+        let request = self.request(WriteMemF32Request { id, offset, data });
+        let result = self
+            .limiter
+            .run("write_mem_f32", self.backend().write_mem_f32(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_mem_f32"))?;
+        self.soft_check(result, "write_mem_f32", || format!("id={} offset=0x{:x}", id, offset))?;
+        if result {
+            self.golden_write(golden::Access::mem(id, offset, 4), &data.to_le_bytes());
+        }
+        Ok(result)
     }
 
     /// Write 64-bit float to memory
@@ -772,9 +1696,19 @@ impl JellyFpgaClient {
         offset: u64,
         data: f64,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteMemF64Request { id, offset, data });
-        let response = self.client.write_mem_f64(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("write_mem_f64")?;
+        let request = self.request(WriteMemF64Request { id, offset, data });
+        let result = self
+            .limiter
+            .run("write_mem_f64", self.backend().write_mem_f64(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_mem_f64"))?;
+        self.soft_check(result, "write_mem_f64", || format!("id={} offset=0x{:x}", id, offset))?;
+        if result {
+            self.golden_write(golden::Access::mem(id, offset, 8), &data.to_le_bytes());
+        }
+        Ok(result)
     }
 
     /// Read 32-bit float from memory
@@ -783,14 +1717,28 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, f32), tonic::Status> {
-        let request = Request::new(ReadMemRequest {
+        let request = self.request(ReadMemRequest {
             id,
             offset,
             size: 4,
         });
-        let response = self.client.read_mem_f32(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let (result, data) = self
+            .limiter
+            .run("read_mem_f32", self.backend().read_mem_f32(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_mem_f32"))?;
+        self.soft_check(result, "read_mem_f32", || {
+            format!(
+                "id={} offset=0x{:x}",
+                id,
+                offset,
+            )
+        })?;
+        if result {
+            self.golden_read(golden::Access::mem(id, offset, 4), &data.to_le_bytes())?;
+        }
+        Ok((result, data))
     }
 
     /// Read 64-bit float from memory
@@ -799,44 +1747,94 @@ impl JellyFpgaClient {
         id: u32,
         offset: u64,
     ) -> Result<(bool, f64), tonic::Status> {
-        let request = Request::new(ReadMemRequest {
+        let request = self.request(ReadMemRequest {
             id,
             offset,
             size: 8,
         });
-        let response = self.client.read_mem_f64(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let (result, data) = self
+            .limiter
+            .run("read_mem_f64", self.backend().read_mem_f64(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_mem_f64"))?;
+        self.soft_check(result, "read_mem_f64", || {
+            format!(
+                "id={} offset=0x{:x}",
+                id,
+                offset,
+            )
+        })?;
+        if result {
+            self.golden_read(golden::Access::mem(id, offset, 8), &data.to_le_bytes())?;
+        }
+        Ok((result, data))
     }
 
     /// Write 32-bit float to register
     pub async fn write_reg_f32(&mut self, id: u32, reg: u64, data: f32) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteRegF32Request { id, reg, data });
-        let response = self.client.write_reg_f32(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("write_reg_f32")?;
+        let request = self.request(WriteRegF32Request { id, reg, data });
+        let result = self
+            .limiter
+            .run("write_reg_f32", self.backend().write_reg_f32(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_reg_f32"))?;
+        self.soft_check(result, "write_reg_f32", || format!("id={} reg=0x{:x}", id, reg))?;
+        if result {
+            self.golden_write(golden::Access::reg(id, reg, 4), &data.to_le_bytes());
+        }
+        Ok(result)
     }
 
     /// Write 64-bit float to register
     pub async fn write_reg_f64(&mut self, id: u32, reg: u64, data: f64) -> Result<bool, tonic::Status> {
-        let request = Request::new(WriteRegF64Request { id, reg, data });
-        let response = self.client.write_reg_f64(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("write_reg_f64")?;
+        let request = self.request(WriteRegF64Request { id, reg, data });
+        let result = self
+            .limiter
+            .run("write_reg_f64", self.backend().write_reg_f64(request))
+            .await
+            .map(|response| response.get_ref().result)
+            .map_err(|e| error::with_rpc(e, "write_reg_f64"))?;
+        self.soft_check(result, "write_reg_f64", || format!("id={} reg=0x{:x}", id, reg))?;
+        if result {
+            self.golden_write(golden::Access::reg(id, reg, 8), &data.to_le_bytes());
+        }
+        Ok(result)
     }
 
     /// Read 32-bit float from register
     pub async fn read_reg_f32(&mut self, id: u32, reg: u64) -> Result<(bool, f32), tonic::Status> {
-        let request = Request::new(ReadRegRequest { id, reg, size: 4 });
-        let response = self.client.read_reg_f32(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let request = self.request(ReadRegRequest { id, reg, size: 4 });
+        let (result, data) = self
+            .limiter
+            .run("read_reg_f32", self.backend().read_reg_f32(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_reg_f32"))?;
+        self.soft_check(result, "read_reg_f32", || format!("id={} reg=0x{:x}", id, reg))?;
+        if result {
+            self.golden_read(golden::Access::reg(id, reg, 4), &data.to_le_bytes())?;
+        }
+        Ok((result, data))
     }
 
     /// Read 64-bit float from register
     pub async fn read_reg_f64(&mut self, id: u32, reg: u64) -> Result<(bool, f64), tonic::Status> {
-        let request = Request::new(ReadRegRequest { id, reg, size: 8 });
-        let response = self.client.read_reg_f64(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let request = self.request(ReadRegRequest { id, reg, size: 8 });
+        let (result, data) = self
+            .limiter
+            .run("read_reg_f64", self.backend().read_reg_f64(request))
+            .await
+            .map(|response| (response.get_ref().result, response.get_ref().data))
+            .map_err(|e| error::with_rpc(e, "read_reg_f64"))?;
+        self.soft_check(result, "read_reg_f64", || format!("id={} reg=0x{:x}", id, reg))?;
+        if result {
+            self.golden_read(golden::Access::reg(id, reg, 8), &data.to_le_bytes())?;
+        }
+        Ok((result, data))
     }
 
     /// Copy data to memory
@@ -846,9 +1844,41 @@ impl JellyFpgaClient {
         offset: u64,
         data: Vec<u8>,
     ) -> Result<bool, tonic::Status> {
-        let request = Request::new(MemCopyToRequest { id, offset, data });
-        let response = self.client.mem_copy_to(request).await?;
-        Ok(response.into_inner().result)
+        self.lock.check("mem_copy_to")?;
+        let len = data.len();
+        let verify = self.verify != checksum::TransferVerify::Off;
+        let written = (self.golden.is_some() || verify).then(|| data.clone());
+        let want_crc = self.wants_checksum().await?;
+        let mut request = self.request(MemCopyToRequest { id, offset, data });
+        if want_crc {
+            request
+                .metadata_mut()
+                .insert(checksum::CHECKSUM_KEY, checksum::CHECKSUM_FEATURE.parse().unwrap());
+        }
+        let (result, server_crc) = self
+            .limiter
+            .run("mem_copy_to", self.bulk_backend().mem_copy_to(request))
+            .await
+            .map(|response| {
+                let server_crc = checksum::response_crc(response.metadata());
+                (response.get_ref().result, server_crc)
+            })
+            .map_err(|e| error::with_rpc(e, "mem_copy_to"))?;
+        self.soft_check(result, "mem_copy_to", || {
+            format!(
+                "id={} offset=0x{:x} len={}",
+                id,
+                offset,
+                len,
+            )
+        })?;
+        if result && let Some(written) = written {
+            if verify {
+                self.verify_copy_to(id, offset, &written, server_crc).await?;
+            }
+            self.golden_write(golden::Access::mem(id, offset, len as u64), &written);
+        }
+        Ok(result)
     }
 
     /// Copy data from memory
@@ -858,10 +1888,36 @@ impl JellyFpgaClient {
         offset: u64,
         size: u64,
     ) -> Result<(bool, Vec<u8>), tonic::Status> {
-        let request = Request::new(MemCopyFromRequest { id, offset, size });
-        let response = self.client.mem_copy_from(request).await?;
-        let inner = response.into_inner();
-        Ok((inner.result, inner.data))
+        let want_crc = self.wants_checksum().await?;
+        let mut request = self.request(MemCopyFromRequest { id, offset, size });
+        if want_crc {
+            request
+                .metadata_mut()
+                .insert(checksum::CHECKSUM_KEY, checksum::CHECKSUM_FEATURE.parse().unwrap());
+        }
+        let (result, data, server_crc) = self
+            .limiter
+            .run("mem_copy_from", self.bulk_backend().mem_copy_from(request))
+            .await
+            .map(|response| {
+                let server_crc = checksum::response_crc(response.metadata());
+                let inner = response.into_inner();
+                (inner.result, inner.data, server_crc)
+            })
+            .map_err(|e| error::with_rpc(e, "mem_copy_from"))?;
+        self.soft_check(result, "mem_copy_from", || {
+            format!(
+                "id={} offset=0x{:x} size={}",
+                id,
+                offset,
+                size,
+            )
+        })?;
+        if result {
+            self.verify_copy_from(id, offset, &data, server_crc)?;
+            self.golden_read(golden::Access::mem(id, offset, size), &data)?;
+        }
+        Ok((result, data))
     }
 }
 
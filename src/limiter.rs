@@ -0,0 +1,151 @@
+//! Client-wide limit on concurrent RPCs
+//!
+//! Fan-out code (`read_regs`, `join_all` over many accessors, parallel
+//! deploy steps) can issue more requests at once than a small embedded
+//! server handles. [`JellyFpgaClient::with_max_inflight`] bounds the number
+//! of RPCs in flight across the client and its clones;
+//! [`with_max_inflight_for`](JellyFpgaClient::with_max_inflight_for) adds a
+//! separate bound per [`Plane`]. Calls made through
+//! [`raw_mut`](JellyFpgaClient::raw_mut) are not limited.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+use crate::JellyFpgaClient;
+
+/// RPC category with its own concurrency limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Plane {
+    /// Loading, opening, closing, firmware management and queries
+    Control,
+    /// Memory and register reads/writes and `mem_copy_to` / `mem_copy_from`
+    Data,
+}
+
+impl Plane {
+    /// Category of the RPC `rpc`
+    pub fn of(rpc: &str) -> Self {
+        if rpc.contains("mem_") || rpc.contains("reg_") {
+            Plane::Data
+        } else {
+            Plane::Control
+        }
+    }
+}
+
+/// Semaphores shared by a client and its clones
+#[derive(Clone, Default)]
+pub(crate) struct Limiter {
+    total: Option<Arc<Semaphore>>,
+    control: Option<Arc<Semaphore>>,
+    data: Option<Arc<Semaphore>>,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<Arc<crate::fault::FaultInjector>>,
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) calls: Option<Arc<crate::stopwatch::CallLog>>,
+}
+
+fn semaphore(n: usize) -> Option<Arc<Semaphore>> {
+    (n > 0).then(|| Arc::new(Semaphore::new(n)))
+}
+
+async fn acquire(semaphore: &Option<Arc<Semaphore>>) -> Option<tokio::sync::OwnedSemaphorePermit> {
+    match semaphore {
+        // the semaphores are never closed
+        Some(s) => s.clone().acquire_owned().await.ok(),
+        None => None,
+    }
+}
+
+impl Limiter {
+    /// Same fault injection and call timing, no limits
+    pub(crate) fn unlimited(&self) -> Limiter {
+        Limiter {
+            #[cfg(feature = "fault-injection")]
+            faults: self.faults.clone(),
+            #[cfg(not(feature = "wasm"))]
+            calls: self.calls.clone(),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "fault-injection")]
+    pub(crate) fn set_faults(&mut self, faults: crate::fault::FaultInjector) {
+        self.faults = Some(Arc::new(faults));
+    }
+
+    #[cfg(feature = "fault-injection")]
+    pub(crate) fn faults(&self) -> Option<&crate::fault::FaultInjector> {
+        self.faults.as_deref()
+    }
+
+    /// Run the RPC `call` named `rpc` once a slot of its plane and of the client total is free
+    pub(crate) async fn run<T, F>(&self, rpc: &str, call: F) -> Result<T, tonic::Status>
+    where
+        F: Future<Output = Result<T, tonic::Status>>,
+    {
+        let plane = Plane::of(rpc);
+        // plane first, then total, so a waiting call never holds a total slot
+        let _plane = acquire(match plane {
+            Plane::Control => &self.control,
+            Plane::Data => &self.data,
+        })
+        .await;
+        let _total = acquire(&self.total).await;
+        #[cfg(not(feature = "wasm"))]
+        let start = std::time::Instant::now();
+        #[cfg(feature = "fault-injection")]
+        let output = match &self.faults {
+            Some(faults) => faults.inject(plane, call).await,
+            None => call.await,
+        };
+        #[cfg(not(feature = "fault-injection"))]
+        let output = call.await;
+        #[cfg(not(feature = "wasm"))]
+        if let Some(calls) = &self.calls {
+            calls.record(rpc, start, &output);
+        }
+        output
+    }
+}
+
+impl JellyFpgaClient {
+    /// Allow at most `n` RPCs in flight at once (0 = unlimited, the default)
+    ///
+    /// The limit is shared with clones made afterwards, including the
+    /// accessors and drivers created from them, so set it right after
+    /// connecting. Further calls wait for a free slot.
+    pub fn with_max_inflight(mut self, n: usize) -> Self {
+        Arc::make_mut(&mut self.limiter).total = semaphore(n);
+        self
+    }
+
+    /// Additionally allow at most `n` RPCs of `plane` in flight (0 = unlimited)
+    ///
+    /// E.g. keep bulk data transfers from starving control requests with
+    /// `with_max_inflight(8).with_max_inflight_for(Plane::Data, 6)`.
+    pub fn with_max_inflight_for(mut self, plane: Plane, n: usize) -> Self {
+        let limiter = Arc::make_mut(&mut self.limiter);
+        match plane {
+            Plane::Control => limiter.control = semaphore(n),
+            Plane::Data => limiter.data = semaphore(n),
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plane_of() {
+        assert_eq!(Plane::of("read_mem_u"), Plane::Data);
+        assert_eq!(Plane::of("write_reg_f32"), Plane::Data);
+        assert_eq!(Plane::of("mem_copy_from"), Plane::Data);
+        assert_eq!(Plane::of("get_phys_addr"), Plane::Control);
+        assert_eq!(Plane::of("open_mmap"), Plane::Control);
+    }
+}
@@ -0,0 +1,66 @@
+//! Scoped firmware slot
+//!
+//! [`LoadedFirmware`] owns the slot returned by `load` and unloads it when
+//! dropped, so a test that fails halfway still restores the board. The drop
+//! path spawns the unload on the current tokio runtime and is best effort
+//! (it cannot run once the runtime is shutting down, and is skipped on
+//! wasm); call [`unload`](LoadedFirmware::unload) to observe the result.
+
+use crate::JellyFpgaClient;
+
+/// Firmware slot unloaded on drop
+pub struct LoadedFirmware {
+    client: JellyFpgaClient,
+    slot: i32,
+    armed: bool,
+}
+
+impl LoadedFirmware {
+    /// Slot number
+    pub fn slot(&self) -> i32 {
+        self.slot
+    }
+
+    /// Unload now
+    pub async fn unload(mut self) -> Result<bool, tonic::Status> {
+        self.armed = false;
+        self.client.unload(self.slot).await
+    }
+
+    /// Keep the firmware loaded and return the slot
+    pub fn leak(mut self) -> i32 {
+        self.armed = false;
+        self.slot
+    }
+}
+
+impl Drop for LoadedFirmware {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        #[cfg(not(feature = "wasm"))]
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let mut client = self.client.clone();
+            let slot = self.slot;
+            handle.spawn(async move {
+                let _ = client.unload(slot).await;
+            });
+        }
+    }
+}
+
+impl JellyFpgaClient {
+    /// Load firmware and return a guard that unloads it on drop
+    ///
+    /// Fails if the server reports `result=false`, regardless of strict mode.
+    pub async fn load_scoped(&mut self, name: &str) -> Result<LoadedFirmware, tonic::Status> {
+        let (result, slot) = self.load(name).await?;
+        crate::accessor::check(result, "load")?;
+        Ok(LoadedFirmware {
+            client: self.clone(),
+            slot,
+            armed: true,
+        })
+    }
+}
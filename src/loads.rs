@@ -0,0 +1,72 @@
+//! What this client loaded on the board
+//!
+//! The server cannot list loaded firmware or applied overlays, so the
+//! client records its own successful `load`, `unload`, `load_bitstream`,
+//! `load_dtbo` and `reset` calls (shared by clones). Loads by other clients
+//! or a reboot of the board are not seen.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::JellyFpgaClient;
+
+/// Firmware loaded through a client
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoadState {
+    /// Firmware name per slot returned by `load`
+    pub firmware: BTreeMap<i32, String>,
+    /// Last bitstream loaded with `load_bitstream`
+    pub bitstream: Option<String>,
+    /// Overlays applied with `load_dtbo`, in order
+    pub overlays: Vec<String>,
+}
+
+impl LoadState {
+    /// Whether nothing was loaded
+    pub fn is_empty(&self) -> bool {
+        self.firmware.is_empty() && self.bitstream.is_none() && self.overlays.is_empty()
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct LoadRegistry {
+    inner: Arc<Mutex<LoadState>>,
+}
+
+impl LoadRegistry {
+    pub(crate) fn loaded(&self, slot: i32, name: &str) {
+        self.inner
+            .lock()
+            .unwrap()
+            .firmware
+            .insert(slot, name.to_string());
+    }
+
+    pub(crate) fn unloaded(&self, slot: i32) {
+        self.inner.lock().unwrap().firmware.remove(&slot);
+    }
+
+    pub(crate) fn bitstream(&self, name: &str) {
+        self.inner.lock().unwrap().bitstream = Some(name.to_string());
+    }
+
+    pub(crate) fn overlay(&self, name: &str) {
+        self.inner.lock().unwrap().overlays.push(name.to_string());
+    }
+
+    pub(crate) fn clear(&self) {
+        *self.inner.lock().unwrap() = LoadState::default();
+    }
+
+    pub(crate) fn get(&self) -> LoadState {
+        self.inner.lock().unwrap().clone()
+    }
+}
+
+impl JellyFpgaClient {
+    /// Firmware, bitstream and overlays loaded through this client and its clones
+    pub fn load_state(&self) -> LoadState {
+        self.loads.get()
+    }
+}
@@ -0,0 +1,767 @@
+//! Direct device access when running on the board itself (`local` feature)
+//!
+//! Every register access through the server is an HTTP/2 round trip, even
+//! when client and server share the board. [`LocalDevices`] implements
+//! [`FpgaControl`] on the devices themselves: `open_mmap`, `open_uio` and
+//! `open_udmabuf` map `/dev/mem`, UIO and u-dma-buf devices with
+//! `jelly-mem-access`, as the server does, and register, memory and
+//! `mem_copy` accesses become volatile accesses of the mapping. Firmware
+//! management still goes to the server, so a program loads its design and
+//! drives it through one API whether it runs on the board or not.
+//!
+//! [`connect`](JellyFpgaClient::connect) and
+//! [`connect_lazy`](JellyFpgaClient::connect_lazy) select the local backend
+//! automatically when the address is a loopback one (`localhost`,
+//! `127.0.0.1`, `::1`) and the machine has an FPGA manager
+//! (`/sys/class/fpga_manager/fpga0`). The second check keeps a development
+//! PC talking to the board through an SSH tunnel, or to a mock server, on
+//! the RPCs. [`with_local_access`](JellyFpgaClient::with_local_access)
+//! overrides the choice either way. The process needs the permissions the
+//! server has on the device files, usually root; an open it may not make
+//! fails with `result=false`, like the server's. Ids of locally opened
+//! devices are only known to the client; the raw gRPC client does not see
+//! them.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use jelly_mem_access::*;
+use tonic::codegen::http::Uri;
+use tonic::{Request, Response, Status};
+
+use crate::JellyFpgaClient;
+use crate::addr::AccessSize;
+use crate::control::FpgaControl;
+use crate::raw::*;
+
+/// FPGA manager whose presence marks the machine as a board
+const FPGA_MANAGER: &str = "/sys/class/fpga_manager/fpga0";
+
+/// Device mapping made by `jelly-mem-access`
+enum Mapping {
+    Mmap(MmapAccessor<usize>),
+    Uio(UioAccessor<usize>),
+    Udmabuf(UdmabufAccessor<usize>),
+    /// Stands in for a device in tests
+    #[cfg(test)]
+    Anon(memmap2::MmapRaw),
+}
+
+// SAFETY: the accessors own their mappings, which stay valid until drop;
+// the memory is only accessed through volatile reads and writes or copies
+// of windows checked against the mapped size
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn base(&self) -> *mut u8 {
+        match self {
+            Mapping::Mmap(acc) => acc.addr() as *mut u8,
+            Mapping::Uio(acc) => acc.addr() as *mut u8,
+            Mapping::Udmabuf(acc) => acc.addr() as *mut u8,
+            #[cfg(test)]
+            Mapping::Anon(map) => map.as_mut_ptr(),
+        }
+    }
+}
+
+/// Newly mapped device
+struct Opened {
+    map: Mapping,
+    size: u64,
+    phys_addr: u64,
+}
+
+/// Open device: a window of a mapping (shared with its subclones)
+struct Device {
+    map: Arc<Mapping>,
+    /// Offset of the window in the mapping
+    start: usize,
+    size: u64,
+    unit: u64,
+    phys_addr: u64,
+}
+
+impl Device {
+    /// Pointer to `len` bytes at `offset`, if inside the window
+    fn ptr(&self, offset: u64, len: u64) -> Option<*mut u8> {
+        let end = offset.checked_add(len)?;
+        // SAFETY: start + offset + len lies within the mapping
+        (end <= self.size).then(|| unsafe { self.map.base().add(self.start + offset as usize) })
+    }
+
+    /// Pointer for a `size` byte access, which must be naturally aligned
+    fn access(&self, offset: u64, size: u64) -> Option<*mut u8> {
+        AccessSize::from_bytes(size)?;
+        self.ptr(offset, size)
+            .filter(|ptr| (*ptr as usize).is_multiple_of(size as usize))
+    }
+
+    fn read(&self, offset: u64, size: u64) -> Option<u64> {
+        let ptr = self.access(offset, size)?;
+        // SAFETY: aligned and inside a live mapping
+        unsafe {
+            Some(match size {
+                1 => ptr.read_volatile() as u64,
+                2 => (ptr as *const u16).read_volatile() as u64,
+                4 => (ptr as *const u32).read_volatile() as u64,
+                _ => (ptr as *const u64).read_volatile(),
+            })
+        }
+    }
+
+    fn write(&self, offset: u64, size: u64, data: u64) -> bool {
+        let Some(ptr) = self.access(offset, size) else {
+            return false;
+        };
+        // SAFETY: aligned and inside a live mapping
+        unsafe {
+            match size {
+                1 => ptr.write_volatile(data as u8),
+                2 => (ptr as *mut u16).write_volatile(data as u16),
+                4 => (ptr as *mut u32).write_volatile(data as u32),
+                _ => (ptr as *mut u64).write_volatile(data),
+            }
+        }
+        true
+    }
+}
+
+/// Map a device on the blocking pool (opening reads sysfs and device files)
+///
+/// `None` if it cannot be opened, which the server reports as `result=false`.
+async fn open<F>(open: F) -> Result<Option<Opened>, Status>
+where
+    F: FnOnce() -> Option<Opened> + Send + 'static,
+{
+    tokio::task::spawn_blocking(open)
+        .await
+        .map_err(|e| Status::internal(format!("open task failed: {}", e)))
+}
+
+fn reply<T>(message: T) -> Result<Response<T>, Status> {
+    Ok(Response::new(message))
+}
+
+/// Whether clients connecting to `uri` should access the devices directly
+///
+/// True for a loopback host on a machine with an FPGA manager.
+pub(crate) fn is_local_target(uri: &Uri) -> bool {
+    let Some(host) = uri.host() else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let loopback = host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    loopback && Path::new(FPGA_MANAGER).exists()
+}
+
+/// Devices opened by a client and its clones, accessed directly
+#[derive(Default)]
+pub struct LocalDevices {
+    devices: Mutex<BTreeMap<u32, Arc<Device>>>,
+}
+
+impl LocalDevices {
+    fn insert(&self, device: Device) -> u32 {
+        let mut devices = self.devices.lock().unwrap();
+        let id = devices.keys().next_back().map_or(1, |last| last + 1);
+        devices.insert(id, Arc::new(device));
+        id
+    }
+
+    fn get(&self, id: u32) -> Option<Arc<Device>> {
+        self.devices.lock().unwrap().get(&id).cloned()
+    }
+
+    fn opened(&self, opened: Option<Opened>, unit: u64) -> OpenResponse {
+        let Some(opened) = opened else {
+            return OpenResponse {
+                result: false,
+                id: 0,
+            };
+        };
+        let id = self.insert(Device {
+            map: Arc::new(opened.map),
+            start: 0,
+            size: opened.size,
+            unit,
+            phys_addr: opened.phys_addr,
+        });
+        OpenResponse { result: true, id }
+    }
+
+    /// Read `size` bytes at `offset`
+    fn read(&self, id: u32, offset: u64, size: u64) -> Option<u64> {
+        self.get(id)?.read(offset, size)
+    }
+
+    /// Read sign-extended from `size` bytes
+    fn read_i(&self, id: u32, offset: u64, size: u64) -> Option<i64> {
+        let data = self.read(id, offset, size)?;
+        Some(AccessSize::from_bytes(size)?.sign_extend(data))
+    }
+
+    /// Write the low `size` bytes of `data`
+    fn write(&self, id: u32, offset: u64, size: u64, data: u64) -> bool {
+        self.get(id).is_some_and(|d| d.write(offset, size, data))
+    }
+
+    /// Byte offset of register `reg` (`reg * unit`)
+    fn reg_offset(&self, id: u32, reg: u64) -> u64 {
+        self.get(id).map_or(0, |d| reg.saturating_mul(d.unit))
+    }
+
+    /// Enable the interrupt of the UIO device behind `id` and wait for it; `false` on timeout
+    ///
+    /// The wait blocks a thread of the blocking pool, which stays blocked
+    /// until the next interrupt if `timeout` passes first.
+    pub(crate) async fn wait_irq(&self, id: u32, timeout: Duration) -> Result<bool, Status> {
+        let device = self
+            .get(id)
+            .ok_or_else(|| Status::not_found(format!("no local device {}", id)))?;
+        if !matches!(*device.map, Mapping::Uio(_)) {
+            return Err(Status::failed_precondition(format!(
+                "device {} is not a UIO device",
+                id
+            )));
+        }
+        let map = device.map.clone();
+        let wait = tokio::task::spawn_blocking(move || {
+            let Mapping::Uio(uio) = &*map else {
+                unreachable!()
+            };
+            uio.set_irq_enable(true)
+                .and_then(|_| uio.wait_irq())
+                .map_err(|e| e.to_string())
+        });
+        match tokio::time::timeout(timeout, wait).await {
+            Err(_) => Ok(false),
+            Ok(result) => result
+                .map_err(|e| e.to_string())
+                .and_then(|waited| waited)
+                .map(|()| true)
+                .map_err(|e| Status::internal(format!("UIO interrupt wait failed: {}", e))),
+        }
+    }
+}
+
+/// Unsigned read response
+fn read_u(data: Option<u64>) -> Result<Response<ReadUResponse>, Status> {
+    reply(ReadUResponse {
+        result: data.is_some(),
+        data: data.unwrap_or(0),
+    })
+}
+
+/// Signed read response
+fn read_i(data: Option<i64>) -> Result<Response<ReadIResponse>, Status> {
+    reply(ReadIResponse {
+        result: data.is_some(),
+        data: data.unwrap_or(0),
+    })
+}
+
+fn done(result: bool) -> Result<Response<BoolResponse>, Status> {
+    reply(BoolResponse { result })
+}
+
+impl FpgaControl for LocalDevices {
+    fn open_mmap(
+        &self,
+        request: Request<OpenMmapRequest>,
+    ) -> impl Future<Output = Result<Response<OpenResponse>, Status>> {
+        let OpenMmapRequest {
+            path,
+            offset,
+            size,
+            unit,
+        } = request.into_inner();
+        async move {
+            let opened = open(move || {
+                let map = MmapAccessor::new(&path, offset as usize, size as usize).ok()?;
+                Some(Opened {
+                    map: Mapping::Mmap(map),
+                    size,
+                    phys_addr: offset,
+                })
+            })
+            .await?;
+            reply(self.opened(opened, unit))
+        }
+    }
+
+    fn open_uio(
+        &self,
+        request: Request<OpenUioRequest>,
+    ) -> impl Future<Output = Result<Response<OpenResponse>, Status>> {
+        let OpenUioRequest { name, unit } = request.into_inner();
+        async move {
+            let opened = open(move || {
+                let map = UioAccessor::new_with_name(&name).ok()?;
+                Some(Opened {
+                    size: map.size() as u64,
+                    phys_addr: map.phys_addr() as u64,
+                    map: Mapping::Uio(map),
+                })
+            })
+            .await?;
+            reply(self.opened(opened, unit))
+        }
+    }
+
+    fn open_udmabuf(
+        &self,
+        request: Request<OpenUdmabufRequest>,
+    ) -> impl Future<Output = Result<Response<OpenResponse>, Status>> {
+        let OpenUdmabufRequest {
+            name,
+            cache_enable,
+            unit,
+        } = request.into_inner();
+        async move {
+            let opened = open(move || {
+                let map = UdmabufAccessor::new(&name, cache_enable).ok()?;
+                Some(Opened {
+                    size: map.size() as u64,
+                    phys_addr: map.phys_addr() as u64,
+                    map: Mapping::Udmabuf(map),
+                })
+            })
+            .await?;
+            reply(self.opened(opened, unit))
+        }
+    }
+
+    fn close(
+        &self,
+        request: Request<CloseRequest>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let result = self
+            .devices
+            .lock()
+            .unwrap()
+            .remove(&request.get_ref().id)
+            .is_some();
+        async move { done(result) }
+    }
+
+    /// `size` 0 takes the rest of the parent window
+    fn subclone(
+        &self,
+        request: Request<SubcloneRequest>,
+    ) -> impl Future<Output = Result<Response<OpenResponse>, Status>> {
+        let SubcloneRequest {
+            id,
+            offset,
+            size,
+            unit,
+        } = request.into_inner();
+        let parent = self.get(id).and_then(|parent| {
+            let size = if size == 0 {
+                parent.size.saturating_sub(offset)
+            } else {
+                size
+            };
+            parent.ptr(offset, size)?;
+            Some(Device {
+                map: parent.map.clone(),
+                start: parent.start + offset as usize,
+                size,
+                unit,
+                phys_addr: parent.phys_addr + offset,
+            })
+        });
+        let response = match parent {
+            Some(device) => OpenResponse {
+                result: true,
+                id: self.insert(device),
+            },
+            None => OpenResponse {
+                result: false,
+                id: 0,
+            },
+        };
+        async move { reply(response) }
+    }
+
+    fn get_addr(
+        &self,
+        request: Request<GetAddrRequest>,
+    ) -> impl Future<Output = Result<Response<GetAddrResponse>, Status>> {
+        let addr = self.get(request.get_ref().id).and_then(|d| d.ptr(0, 0));
+        async move {
+            reply(GetAddrResponse {
+                result: addr.is_some(),
+                addr: addr.map_or(0, |ptr| ptr as u64),
+            })
+        }
+    }
+
+    fn get_size(
+        &self,
+        request: Request<GetSizeRequest>,
+    ) -> impl Future<Output = Result<Response<GetSizeResponse>, Status>> {
+        let size = self.get(request.get_ref().id).map(|d| d.size);
+        async move {
+            reply(GetSizeResponse {
+                result: size.is_some(),
+                size: size.unwrap_or(0),
+            })
+        }
+    }
+
+    fn get_phys_addr(
+        &self,
+        request: Request<GetPhysAddrRequest>,
+    ) -> impl Future<Output = Result<Response<GetPhysAddrResponse>, Status>> {
+        let phys_addr = self.get(request.get_ref().id).map(|d| d.phys_addr);
+        async move {
+            reply(GetPhysAddrResponse {
+                result: phys_addr.is_some(),
+                phys_addr: phys_addr.unwrap_or(0),
+            })
+        }
+    }
+
+    fn write_mem_u(
+        &self,
+        request: Request<WriteMemURequest>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(r.id, r.offset, r.size, r.data);
+        async move { done(result) }
+    }
+
+    fn write_mem_i(
+        &self,
+        request: Request<WriteMemIRequest>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(r.id, r.offset, r.size, r.data as u64);
+        async move { done(result) }
+    }
+
+    fn read_mem_u(
+        &self,
+        request: Request<ReadMemRequest>,
+    ) -> impl Future<Output = Result<Response<ReadUResponse>, Status>> {
+        let r = request.get_ref();
+        let data = self.read(r.id, r.offset, r.size);
+        async move { read_u(data) }
+    }
+
+    fn read_mem_i(
+        &self,
+        request: Request<ReadMemRequest>,
+    ) -> impl Future<Output = Result<Response<ReadIResponse>, Status>> {
+        let r = request.get_ref();
+        let data = self.read_i(r.id, r.offset, r.size);
+        async move { read_i(data) }
+    }
+
+    fn write_reg_u(
+        &self,
+        request: Request<WriteRegURequest>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(r.id, self.reg_offset(r.id, r.reg), r.size, r.data);
+        async move { done(result) }
+    }
+
+    fn write_reg_i(
+        &self,
+        request: Request<WriteRegIRequest>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(r.id, self.reg_offset(r.id, r.reg), r.size, r.data as u64);
+        async move { done(result) }
+    }
+
+    fn read_reg_u(
+        &self,
+        request: Request<ReadRegRequest>,
+    ) -> impl Future<Output = Result<Response<ReadUResponse>, Status>> {
+        let r = request.get_ref();
+        let data = self.read(r.id, self.reg_offset(r.id, r.reg), r.size);
+        async move { read_u(data) }
+    }
+
+    fn read_reg_i(
+        &self,
+        request: Request<ReadRegRequest>,
+    ) -> impl Future<Output = Result<Response<ReadIResponse>, Status>> {
+        let r = request.get_ref();
+        let data = self.read_i(r.id, self.reg_offset(r.id, r.reg), r.size);
+        async move { read_i(data) }
+    }
+
+    fn write_mem_f32(
+        &self,
+        request: Request<WriteMemF32Request>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(r.id, r.offset, 4, r.data.to_bits() as u64);
+        async move { done(result) }
+    }
+
+    fn write_mem_f64(
+        &self,
+        request: Request<WriteMemF64Request>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(r.id, r.offset, 8, r.data.to_bits());
+        async move { done(result) }
+    }
+
+    fn read_mem_f32(
+        &self,
+        request: Request<ReadMemRequest>,
+    ) -> impl Future<Output = Result<Response<ReadF32Response>, Status>> {
+        let r = request.get_ref();
+        let data = self.read(r.id, r.offset, 4);
+        async move {
+            reply(ReadF32Response {
+                result: data.is_some(),
+                data: f32::from_bits(data.unwrap_or(0) as u32),
+            })
+        }
+    }
+
+    fn read_mem_f64(
+        &self,
+        request: Request<ReadMemRequest>,
+    ) -> impl Future<Output = Result<Response<ReadF64Response>, Status>> {
+        let r = request.get_ref();
+        let data = self.read(r.id, r.offset, 8);
+        async move {
+            reply(ReadF64Response {
+                result: data.is_some(),
+                data: f64::from_bits(data.unwrap_or(0)),
+            })
+        }
+    }
+
+    fn write_reg_f32(
+        &self,
+        request: Request<WriteRegF32Request>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(
+            r.id,
+            self.reg_offset(r.id, r.reg),
+            4,
+            r.data.to_bits() as u64,
+        );
+        async move { done(result) }
+    }
+
+    fn write_reg_f64(
+        &self,
+        request: Request<WriteRegF64Request>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let result = self.write(r.id, self.reg_offset(r.id, r.reg), 8, r.data.to_bits());
+        async move { done(result) }
+    }
+
+    fn read_reg_f32(
+        &self,
+        request: Request<ReadRegRequest>,
+    ) -> impl Future<Output = Result<Response<ReadF32Response>, Status>> {
+        let r = request.get_ref();
+        let data = self.read(r.id, self.reg_offset(r.id, r.reg), 4);
+        async move {
+            reply(ReadF32Response {
+                result: data.is_some(),
+                data: f32::from_bits(data.unwrap_or(0) as u32),
+            })
+        }
+    }
+
+    fn read_reg_f64(
+        &self,
+        request: Request<ReadRegRequest>,
+    ) -> impl Future<Output = Result<Response<ReadF64Response>, Status>> {
+        let r = request.get_ref();
+        let data = self.read(r.id, self.reg_offset(r.id, r.reg), 8);
+        async move {
+            reply(ReadF64Response {
+                result: data.is_some(),
+                data: f64::from_bits(data.unwrap_or(0)),
+            })
+        }
+    }
+
+    fn mem_copy_to(
+        &self,
+        request: Request<MemCopyToRequest>,
+    ) -> impl Future<Output = Result<Response<BoolResponse>, Status>> {
+        let r = request.get_ref();
+        let ptr = self
+            .get(r.id)
+            .and_then(|d| d.ptr(r.offset, r.data.len() as u64));
+        if let Some(ptr) = ptr {
+            // SAFETY: the destination lies inside a live mapping
+            unsafe { std::ptr::copy_nonoverlapping(r.data.as_ptr(), ptr, r.data.len()) };
+        }
+        async move { done(ptr.is_some()) }
+    }
+
+    fn mem_copy_from(
+        &self,
+        request: Request<MemCopyFromRequest>,
+    ) -> impl Future<Output = Result<Response<MemCopyFromResponse>, Status>> {
+        let r = request.get_ref();
+        let device = self.get(r.id);
+        let data = device
+            .as_ref()
+            .and_then(|d| d.ptr(r.offset, r.size))
+            .map(|ptr| {
+                let mut data = vec![0; r.size as usize];
+                // SAFETY: the source lies inside a live mapping
+                unsafe { std::ptr::copy_nonoverlapping(ptr, data.as_mut_ptr(), data.len()) };
+                data
+            });
+        async move {
+            reply(MemCopyFromResponse {
+                result: data.is_some(),
+                data: data.unwrap_or_default(),
+            })
+        }
+    }
+}
+
+impl JellyFpgaClient {
+    /// Access devices directly instead of through the server (or stop doing so)
+    ///
+    /// Overrides the choice [`connect`](Self::connect) made from the
+    /// address. Only enable this when the client runs on the board the
+    /// server controls.
+    ///
+    /// Devices opened before the switch keep their ids on the other side,
+    /// so switch before opening any. Clones made afterwards share the
+    /// local devices.
+    pub fn with_local_access(mut self, enabled: bool) -> Self {
+        self.local = enabled.then(Default::default);
+        self
+    }
+
+    /// Devices are accessed directly (see [`with_local_access`](Self::with_local_access))
+    pub fn is_local(&self) -> bool {
+        self.local.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_target() {
+        let remote: Uri = "http://192.168.0.10:8051".parse().unwrap();
+        assert!(!is_local_target(&remote));
+        let local: Uri = "http://[::1]:8051".parse().unwrap();
+        assert_eq!(is_local_target(&local), Path::new(FPGA_MANAGER).exists());
+    }
+
+    #[tokio::test]
+    async fn test_local_devices() {
+        // an anonymous mapping stands in for a device
+        let local = LocalDevices::default();
+        let map = memmap2::MmapOptions::new()
+            .len(0x100)
+            .map_anon()
+            .unwrap()
+            .into();
+        let opened = Opened {
+            map: Mapping::Anon(map),
+            size: 0x100,
+            phys_addr: 0xa000_0000,
+        };
+        let id = local.opened(Some(opened), 4).id;
+        assert!(local.write(id, 0x10, 4, 0xffff_fffe));
+        assert_eq!(local.read(id, 0x10, 4), Some(0xffff_fffe));
+        assert_eq!(local.read_i(id, 0x10, 2), Some(-2));
+        assert!(!local.write(id, 0x11, 4, 0));
+        assert!(!local.write(id, 0x100, 1, 0));
+
+        let request = SubcloneRequest {
+            id,
+            offset: 0x10,
+            size: 0,
+            unit: 4,
+        };
+        let sub = local
+            .subclone(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(sub.result);
+        let sub = sub.id;
+        let size = local
+            .get_size(Request::new(GetSizeRequest { id: sub }))
+            .await
+            .unwrap();
+        assert_eq!(size.get_ref().size, 0xf0);
+        let phys = local.get_phys_addr(Request::new(GetPhysAddrRequest { id: sub }));
+        assert_eq!(phys.await.unwrap().get_ref().phys_addr, 0xa000_0010);
+        assert_eq!(local.reg_offset(sub, 2), 8);
+        let request = MemCopyToRequest {
+            id: sub,
+            offset: 8,
+            data: vec![1, 2, 3],
+        };
+        assert!(
+            local
+                .mem_copy_to(Request::new(request))
+                .await
+                .unwrap()
+                .get_ref()
+                .result
+        );
+        let request = MemCopyFromRequest {
+            id,
+            offset: 0x18,
+            size: 3,
+        };
+        let copied = local.mem_copy_from(Request::new(request)).await.unwrap();
+        assert_eq!(copied.get_ref().data, vec![1, 2, 3]);
+        let request = ReadRegRequest {
+            id: sub,
+            reg: 0,
+            size: 4,
+        };
+        assert_eq!(
+            local
+                .read_reg_u(Request::new(request))
+                .await
+                .unwrap()
+                .get_ref()
+                .data,
+            0xffff_fffe
+        );
+        assert!(
+            local
+                .close(Request::new(CloseRequest { id }))
+                .await
+                .unwrap()
+                .get_ref()
+                .result
+        );
+        assert!(
+            !local
+                .close(Request::new(CloseRequest { id }))
+                .await
+                .unwrap()
+                .get_ref()
+                .result
+        );
+        assert_eq!(local.read(sub, 0, 4), Some(0xffff_fffe));
+    }
+}
@@ -0,0 +1,183 @@
+//! Cooperative board lock
+//!
+//! The server has no lock RPC, so the lock is a reserved firmware file
+//! holding the owner name. It is advisory: every client sharing the board
+//! has to use `acquire_lock`. Checking and taking the lock are separate
+//! RPCs, so the owner is read back after writing to catch most races.
+//!
+//! Lock failures (not holding a required lock, another owner keeping it
+//! past the timeout) are `permission_denied`, i.e.
+//! [`Error::PermissionDenied`](crate::error::Error::PermissionDenied).
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::JellyFpgaClient;
+use crate::error::with_rpc;
+use crate::jelly_fpga_control::{RemoveFirmwareRequest, UploadFirmwareRequest};
+
+/// Firmware name of the lock file
+pub const LOCK_FIRMWARE_NAME: &str = "jelly-fpga-client.lock";
+/// Path of the lock file on the board (server firmware directory)
+pub const LOCK_FIRMWARE_PATH: &str = "/lib/firmware/jelly-fpga-client.lock";
+/// Lock file size (owner name padded with zeros so it can be memory mapped)
+pub const LOCK_FILE_SIZE: u64 = 256;
+
+#[derive(Default)]
+struct Inner {
+    held: Option<String>,
+    required: bool,
+}
+
+/// Lock state shared between clones of a client
+#[derive(Clone, Default)]
+pub(crate) struct LockState {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl LockState {
+    /// Fail RPC `rpc` with `permission_denied` while a required lock is not held
+    pub(crate) fn check(&self, rpc: &str) -> Result<(), tonic::Status> {
+        self.check_op(rpc).map_err(|e| with_rpc(e, rpc))
+    }
+
+    /// Same for an operation that is not an RPC itself (no RPC name attached)
+    pub(crate) fn check_op(&self, op: &str) -> Result<(), tonic::Status> {
+        let inner = self.inner.lock().unwrap();
+        if inner.required && inner.held.is_none() {
+            Err(tonic::Status::permission_denied(format!(
+                "{} requires the board lock",
+                op
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl JellyFpgaClient {
+    /// Owner of the board lock, if any
+    pub async fn lock_owner(&mut self) -> Result<Option<String>, tonic::Status> {
+        let Some(data) = self.read_board_file(LOCK_FIRMWARE_PATH, LOCK_FILE_SIZE).await? else {
+            return Ok(None);
+        };
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        let owner = String::from_utf8_lossy(&data[..end]).into_owned();
+        Ok(if owner.is_empty() { None } else { Some(owner) })
+    }
+
+    /// Acquire the board lock as `owner`, waiting up to `timeout` for another owner to release it
+    ///
+    /// Fails with `permission_denied` if the other owner keeps it.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn acquire_lock(
+        &mut self,
+        owner: &str,
+        timeout: Duration,
+    ) -> Result<(), tonic::Status> {
+        if owner.is_empty() || owner.len() as u64 >= LOCK_FILE_SIZE {
+            return Err(tonic::Status::invalid_argument("invalid lock owner name"));
+        }
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let current = self.lock_owner().await?;
+            match current.as_deref() {
+                Some(o) if o != owner => {
+                    if tokio::time::Instant::now() >= deadline {
+                        return Err(tonic::Status::permission_denied(format!(
+                            "board is locked by {}",
+                            o
+                        )));
+                    }
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+                _ => {
+                    self.write_lock_file(owner).await?;
+                    if self.lock_owner().await?.as_deref() == Some(owner) {
+                        self.lock.inner.lock().unwrap().held = Some(owner.to_string());
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Release the board lock held by this client
+    pub async fn release_lock(&mut self) -> Result<(), tonic::Status> {
+        let Some(owner) = self.lock.inner.lock().unwrap().held.clone() else {
+            return Ok(());
+        };
+        if self.lock_owner().await?.as_deref() == Some(owner.as_str()) {
+            crate::accessor::check(self.remove_lock_file().await?, "remove lock file")?;
+        }
+        self.lock.inner.lock().unwrap().held = None;
+        Ok(())
+    }
+
+    /// Remove the lock regardless of owner (e.g. left behind by a crashed session)
+    pub async fn force_release_lock(&mut self) -> Result<(), tonic::Status> {
+        // a missing lock file is not an error here
+        self.remove_lock_file().await?;
+        self.lock.inner.lock().unwrap().held = None;
+        Ok(())
+    }
+
+    /// Whether this client holds the board lock
+    pub fn holds_lock(&self) -> bool {
+        self.lock.inner.lock().unwrap().held.is_some()
+    }
+
+    /// Fail mutating calls (load, reset, writes, ...) with `permission_denied` while the lock is not held
+    pub fn set_require_lock(&mut self, required: bool) {
+        self.lock.inner.lock().unwrap().required = required;
+    }
+
+    async fn write_lock_file(&mut self, owner: &str) -> Result<(), tonic::Status> {
+        let mut data = owner.as_bytes().to_vec();
+        data.resize(LOCK_FILE_SIZE as usize, 0);
+        let stream = tokio_stream::iter(vec![UploadFirmwareRequest {
+            name: LOCK_FIRMWARE_NAME.to_string(),
+            data,
+        }]);
+        let request = self.request(stream);
+        let response = self
+            .limiter
+            .run("upload_firmware", self.bulk_client().upload_firmware(request))
+            .await
+            .map_err(|e| with_rpc(e, "upload_firmware"))?;
+        crate::accessor::check(response.into_inner().result, "upload lock file")
+    }
+
+    async fn remove_lock_file(&mut self) -> Result<bool, tonic::Status> {
+        let request = self.request(RemoveFirmwareRequest {
+            name: LOCK_FIRMWARE_NAME.to_string(),
+        });
+        let response = self
+            .limiter
+            .run("remove_firmware", self.client.remove_firmware(request))
+            .await
+            .map_err(|e| with_rpc(e, "remove_firmware"))?;
+        Ok(response.into_inner().result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_lock_errors() {
+        let state = LockState::default();
+        state.check("load_bitstream").unwrap();
+        state.inner.lock().unwrap().required = true;
+
+        let err = Error::from(state.check("load_bitstream").unwrap_err());
+        assert!(matches!(err, Error::PermissionDenied { .. }));
+        assert_eq!(err.rpc(), Some("load_bitstream"));
+
+        let err = Error::from(state.check_op("write_remote_file").unwrap_err());
+        assert!(matches!(err, Error::PermissionDenied { .. }));
+        assert_eq!(err.rpc(), None);
+    }
+}
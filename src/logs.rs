@@ -0,0 +1,131 @@
+//! Server log and dmesg lines
+//!
+//! Like the lease, this rides on `get_version`: a server advertising the
+//! [`LOGS_FEATURE`] capability answers a request carrying
+//! [`LOG_CURSOR_KEY`] (and optionally [`LOG_FILTER_KEY`]) with the buffered
+//! log lines from that cursor on in [`LOG_LINES_KEY`] and the cursor of the
+//! next line in [`LOG_CURSOR_KEY`]. Cursor 0 starts at the oldest buffered
+//! line. Other servers get `unimplemented` without a request being sent.
+
+use std::time::Duration;
+
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::JellyFpgaClient;
+use crate::jelly_fpga_control::Empty;
+
+/// Capability advertised by servers that return their logs
+pub const LOGS_FEATURE: &str = "logs";
+/// Request/response metadata key carrying the log cursor
+pub const LOG_CURSOR_KEY: &str = "x-jelly-log-cursor";
+/// Request metadata key carrying a substring the lines must contain
+pub const LOG_FILTER_KEY: &str = "x-jelly-log-filter";
+/// Binary response metadata key carrying newline separated log lines
+pub const LOG_LINES_KEY: &str = "x-jelly-log-lines-bin";
+
+/// Interval between polls of a log stream
+pub const LOG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Lines buffered per stream before polling pauses
+const LOG_BUFFER: usize = 256;
+
+impl JellyFpgaClient {
+    /// Fail with `unimplemented` unless the server returns its logs
+    async fn check_logs_supported(&mut self) -> Result<(), tonic::Status> {
+        if self.supports(|c| c.has(LOGS_FEATURE)).await? {
+            Ok(())
+        } else {
+            Err(tonic::Status::unimplemented(
+                "server does not provide its logs",
+            ))
+        }
+    }
+
+    /// Log lines from `cursor` on containing `filter`, and the next cursor
+    async fn fetch_server_logs(
+        &mut self,
+        cursor: u64,
+        filter: &str,
+    ) -> Result<(Vec<String>, u64), tonic::Status> {
+        let mut request = self.request(Empty {});
+        request
+            .metadata_mut()
+            .insert(LOG_CURSOR_KEY, cursor.to_string().parse().unwrap());
+        if !filter.is_empty() {
+            let value = filter
+                .parse()
+                .map_err(|_| tonic::Status::invalid_argument("log filter must be ASCII"))?;
+            request.metadata_mut().insert(LOG_FILTER_KEY, value);
+        }
+        let response = self
+            .limiter
+            .run("get_version", self.client.get_version(request))
+            .await
+            .map_err(|e| crate::error::with_rpc(e, "get_version"))?;
+        let metadata = response.metadata();
+        let next = metadata
+            .get(LOG_CURSOR_KEY)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(cursor);
+        let lines = metadata
+            .get_bin(LOG_LINES_KEY)
+            .and_then(|v| v.to_bytes().ok())
+            .map(|data| {
+                String::from_utf8_lossy(&data)
+                    .lines()
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok((lines, next))
+    }
+
+    /// Buffered server log lines containing `filter` (all lines if empty)
+    pub async fn server_logs(&mut self, filter: &str) -> Result<Vec<String>, tonic::Status> {
+        self.check_logs_supported().await?;
+        self.fetch_server_logs(0, filter)
+            .await
+            .map(|(lines, _)| lines)
+    }
+
+    /// Stream server log lines containing `filter`, starting with the buffered ones
+    ///
+    /// The server is polled every [`LOG_POLL_INTERVAL`] by a background
+    /// task that ends when the stream is dropped, on the first error (which
+    /// is the last item) or on [`shutdown`](Self::shutdown).
+    pub async fn stream_server_logs(
+        &mut self,
+        filter: &str,
+    ) -> Result<ReceiverStream<Result<String, tonic::Status>>, tonic::Status> {
+        self.check_logs_supported().await?;
+        let (tx, rx) = tokio::sync::mpsc::channel(LOG_BUFFER);
+        let mut client = self.clone();
+        let filter = filter.to_string();
+        let task = tokio::spawn(async move {
+            let mut cursor = 0;
+            loop {
+                match client.fetch_server_logs(cursor, &filter).await {
+                    Ok((lines, next)) => {
+                        cursor = next;
+                        for line in lines {
+                            if tx.send(Ok(line)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(LOG_POLL_INTERVAL) => {}
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+        self.tasks.register(task.abort_handle());
+        Ok(ReceiverStream::new(rx))
+    }
+}
@@ -0,0 +1,214 @@
+//! Shared-memory mailbox
+//!
+//! Two rings of fixed-size slots in shared memory, one per direction, each
+//! with a producer (`head`) and consumer (`tail`) index as 32-bit words.
+//! A ring is empty when `head == tail` and full when `head + 1 == tail`
+//! (modulo the slot count), so one slot always stays unused. The host
+//! writes the slot before advancing `head`, optionally rings a doorbell
+//! register afterwards, and frees received slots by advancing `tail`.
+//!
+//! The server does not forward interrupts; an interrupt status register
+//! can be configured instead, which is polled while waiting for a message
+//! and cleared (write one to clear) when it fires.
+
+use std::time::Duration;
+
+use crate::accessor::Accessor;
+
+/// Placement of one ring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RingLayout {
+    /// Byte offset of the first slot in the data accessor
+    pub base: u64,
+    /// Number of slots (one of them is always free)
+    pub slots: u32,
+    /// Bytes per slot
+    pub slot_size: u32,
+    /// Byte offset of the producer index in the control accessor
+    pub head: u64,
+    /// Byte offset of the consumer index in the control accessor
+    pub tail: u64,
+}
+
+impl RingLayout {
+    /// Byte offset of slot `index`
+    fn slot(&self, index: u32) -> u64 {
+        self.base + index as u64 * self.slot_size as u64
+    }
+
+    /// Index after `index`
+    fn next(&self, index: u32) -> u32 {
+        (index + 1) % self.slots
+    }
+}
+
+/// Request/response channel over two rings
+#[derive(Clone)]
+pub struct Mailbox {
+    data: Accessor,
+    ctrl: Accessor,
+    tx: RingLayout,
+    rx: RingLayout,
+    doorbell: Option<(u64, u32)>,
+    irq: Option<(u64, u32)>,
+    poll_interval: Duration,
+}
+
+impl Mailbox {
+    /// Mailbox with slots in `data` and indices in `ctrl`
+    ///
+    /// `tx` carries host-to-device messages, `rx` device-to-host ones.
+    /// `data` and `ctrl` may be clones of the same accessor.
+    pub fn new(data: Accessor, ctrl: Accessor, tx: RingLayout, rx: RingLayout) -> Self {
+        Mailbox {
+            data,
+            ctrl,
+            tx,
+            rx,
+            doorbell: None,
+            irq: None,
+            poll_interval: Duration::from_millis(1),
+        }
+    }
+
+    /// Write `value` to byte offset `reg` of the control accessor after each send
+    pub fn with_doorbell(mut self, reg: u64, value: u32) -> Self {
+        self.doorbell = Some((reg, value));
+        self
+    }
+
+    /// Wait for `mask` in the status register at byte offset `reg` instead of polling `rx`
+    pub fn with_irq_status(mut self, reg: u64, mask: u32) -> Self {
+        self.irq = Some((reg, mask));
+        self
+    }
+
+    /// Set polling interval
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Control accessor (indices, doorbell, interrupt status)
+    pub fn ctrl(&mut self) -> &mut Accessor {
+        &mut self.ctrl
+    }
+
+    /// Zero the indices of both rings
+    ///
+    /// Only safe while the device side is idle or held in reset.
+    pub async fn reset(&mut self) -> Result<(), tonic::Status> {
+        for ring in [self.tx, self.rx] {
+            self.ctrl.write_mem_u32(ring.head, 0).await?;
+            self.ctrl.write_mem_u32(ring.tail, 0).await?;
+        }
+        Ok(())
+    }
+
+    /// Read the `(head, tail)` indices of `ring`
+    async fn indices(&mut self, ring: RingLayout) -> Result<(u32, u32), tonic::Status> {
+        let head = self.ctrl.read_mem_u32(ring.head).await?;
+        let tail = self.ctrl.read_mem_u32(ring.tail).await?;
+        if head >= ring.slots || tail >= ring.slots {
+            return Err(tonic::Status::data_loss(format!(
+                "mailbox index out of range (head={} tail={} slots={})",
+                head, tail, ring.slots
+            )));
+        }
+        Ok((head, tail))
+    }
+
+    /// Messages waiting in the device-to-host ring
+    pub async fn pending(&mut self) -> Result<u32, tonic::Status> {
+        let (head, tail) = self.indices(self.rx).await?;
+        Ok((head + self.rx.slots - tail) % self.rx.slots)
+    }
+
+    /// Send `message` if a slot is free; `false` if the ring is full
+    ///
+    /// Messages shorter than a slot are zero padded.
+    pub async fn try_send(&mut self, message: &[u8]) -> Result<bool, tonic::Status> {
+        let ring = self.tx;
+        if message.len() > ring.slot_size as usize {
+            return Err(tonic::Status::invalid_argument(format!(
+                "mailbox message of {} bytes exceeds the slot size {}",
+                message.len(),
+                ring.slot_size
+            )));
+        }
+        let (head, tail) = self.indices(ring).await?;
+        if ring.next(head) == tail {
+            return Ok(false);
+        }
+        let mut slot = message.to_vec();
+        slot.resize(ring.slot_size as usize, 0);
+        self.data.mem_copy_to(ring.slot(head), slot).await?;
+        self.ctrl.write_mem_u32(ring.head, ring.next(head)).await?;
+        if let Some((reg, value)) = self.doorbell {
+            self.ctrl.write_mem_u32(reg, value).await?;
+        }
+        Ok(true)
+    }
+
+    /// Send `message`, waiting up to `timeout` for a free slot
+    pub async fn send(&mut self, message: &[u8], timeout: Duration) -> Result<(), tonic::Status> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !self.try_send(message).await? {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded("mailbox send: ring full"));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+        Ok(())
+    }
+
+    /// Take the next message (a whole slot) if there is one
+    pub async fn try_recv(&mut self) -> Result<Option<Vec<u8>>, tonic::Status> {
+        let ring = self.rx;
+        let (head, tail) = self.indices(ring).await?;
+        if head == tail {
+            return Ok(None);
+        }
+        let message = self
+            .data
+            .mem_copy_from(ring.slot(tail), ring.slot_size as u64)
+            .await?;
+        self.ctrl.write_mem_u32(ring.tail, ring.next(tail)).await?;
+        Ok(Some(message))
+    }
+
+    /// Wait up to `timeout` for the next message
+    pub async fn recv(&mut self, timeout: Duration) -> Result<Vec<u8>, tonic::Status> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(message) = self.try_recv().await? {
+                return Ok(message);
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(tonic::Status::deadline_exceeded("mailbox recv: no message"));
+            }
+            match self.irq {
+                Some((reg, mask)) => {
+                    self.ctrl
+                        .wait_mem_u32(reg, mask, mask, self.poll_interval, deadline - now)
+                        .await?;
+                    self.ctrl.write_mem_u32(reg, mask).await?;
+                }
+                None => tokio::time::sleep(self.poll_interval).await,
+            }
+        }
+    }
+
+    /// Send `request` and wait for the next message as its response
+    pub async fn call(
+        &mut self,
+        request: &[u8],
+        timeout: Duration,
+    ) -> Result<Vec<u8>, tonic::Status> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        self.send(request, timeout).await?;
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        self.recv(remaining).await
+    }
+}
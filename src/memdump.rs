@@ -0,0 +1,154 @@
+//! Memory snapshots
+//!
+//! A [`MemDump`] is a copy of a memory range; [`MemDump::diff`] lists the
+//! runs of bytes that changed between two snapshots.
+
+use std::fmt;
+
+use crate::Accessor;
+use crate::addr::{ByteLen, RegOffset};
+
+/// Snapshot of a memory range
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemDump {
+    /// Offset of the first byte
+    pub offset: u64,
+    /// Memory contents
+    pub data: Vec<u8>,
+}
+
+/// Run of consecutive changed bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemChange {
+    /// Offset of the first changed byte
+    pub offset: u64,
+    /// Old bytes
+    pub old: Vec<u8>,
+    /// New bytes
+    pub new: Vec<u8>,
+}
+
+/// Result of [`MemDump::diff`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemDiff {
+    /// Changed runs in offset order
+    pub changes: Vec<MemChange>,
+}
+
+impl MemDump {
+    /// Snapshot of `data` read at `offset`
+    pub fn new(offset: u64, data: Vec<u8>) -> Self {
+        MemDump { offset, data }
+    }
+
+    /// Bytes that differ from `self` (old) to `other` (new)
+    ///
+    /// Only the range covered by both dumps is compared.
+    pub fn diff(&self, other: &MemDump) -> MemDiff {
+        let start = self.offset.max(other.offset);
+        let end =
+            (self.offset + self.data.len() as u64).min(other.offset + other.data.len() as u64);
+        let mut changes: Vec<MemChange> = Vec::new();
+        for addr in start..end {
+            let old = self.data[(addr - self.offset) as usize];
+            let new = other.data[(addr - other.offset) as usize];
+            if old == new {
+                continue;
+            }
+            match changes.last_mut() {
+                Some(c) if c.offset + c.old.len() as u64 == addr => {
+                    c.old.push(old);
+                    c.new.push(new);
+                }
+                _ => changes.push(MemChange {
+                    offset: addr,
+                    old: vec![old],
+                    new: vec![new],
+                }),
+            }
+        }
+        MemDiff { changes }
+    }
+}
+
+impl MemDiff {
+    /// No byte changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Total number of changed bytes
+    pub fn changed_bytes(&self) -> usize {
+        self.changes.iter().map(|c| c.old.len()).sum()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl fmt::Display for MemDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in &self.changes {
+            // 16 bytes per line
+            for (i, (old, new)) in c.old.chunks(16).zip(c.new.chunks(16)).enumerate() {
+                let offset = c.offset + (i * 16) as u64;
+                writeln!(f, "0x{:08x}: {} -> {}", offset, hex(old), hex(new))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Accessor {
+    /// Snapshot `size` bytes at `offset`
+    pub async fn dump_mem(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        size: impl Into<ByteLen>,
+    ) -> Result<MemDump, tonic::Status> {
+        let offset = offset.into().0;
+        let data = self.mem_copy_from(offset, size).await?;
+        Ok(MemDump::new(offset, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = MemDump::new(0x100, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        let new = MemDump::new(0x102, vec![2, 9, 9, 5, 6, 0, 0xff]);
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.changes,
+            vec![
+                MemChange {
+                    offset: 0x103,
+                    old: vec![3, 4],
+                    new: vec![9, 9]
+                },
+                MemChange {
+                    offset: 0x107,
+                    old: vec![7],
+                    new: vec![0]
+                },
+            ]
+        );
+        assert_eq!(diff.changed_bytes(), 3);
+        assert_eq!(
+            diff.to_string(),
+            "0x00000103: 03 04 -> 09 09\n0x00000107: 07 -> 00\n"
+        );
+        assert!(old.diff(&old).is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+//! Connecting to the first answering of several addresses
+//!
+//! A board is often reachable under an mDNS hostname, an IPv4 address and
+//! an IPv6 link-local address, not all of which work from every lab
+//! network. [`JellyFpgaClient::connect_any`] races them in the manner of
+//! happy eyeballs (RFC 8305): the attempts start [`ATTEMPT_DELAY`] apart in
+//! the given order, or immediately when the previous one fails, and the
+//! first connection made wins while the others are abandoned.
+
+use std::time::Duration;
+
+use tokio::task::JoinSet;
+
+use crate::{JellyFpgaClient, raw};
+
+/// Time an attempt gets before the next candidate is tried in parallel
+pub const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// `uri: error: cause: ...`, as tonic's transport error alone only says "transport error"
+fn describe(uri: &str, error: &dyn std::error::Error) -> String {
+    let mut message = format!("{}: {}", uri, error);
+    let mut source = error.source();
+    let mut last = error.to_string();
+    while let Some(cause) = source {
+        let text = cause.to_string();
+        if text != last {
+            message.push_str(&format!(": {}", text));
+        }
+        last = text;
+        source = cause.source();
+    }
+    message
+}
+
+impl JellyFpgaClient {
+    /// Connect to whichever of `candidates` answers first
+    ///
+    /// List the preferred address first. Fails with `invalid_argument` if
+    /// the list is empty or an address is malformed, and with `unavailable`
+    /// listing every error if no candidate can be reached. Capabilities
+    /// are probed as in [`connect`](Self::connect).
+    pub async fn connect_any<I, D>(candidates: I) -> Result<Self, tonic::Status>
+    where
+        I: IntoIterator<Item = D>,
+        D: std::convert::TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let endpoints = candidates
+            .into_iter()
+            .map(tonic::transport::Endpoint::new)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| tonic::Status::invalid_argument(format!("invalid address: {}", e)))?;
+        if endpoints.is_empty() {
+            return Err(tonic::Status::invalid_argument("no address to connect to"));
+        }
+
+        let mut pending = endpoints.into_iter().peekable();
+        let mut attempts = JoinSet::new();
+        let mut errors = Vec::new();
+        loop {
+            if let Some(endpoint) = pending.next() {
+                attempts.spawn(async move {
+                    let uri = endpoint.uri().to_string();
+                    endpoint.connect().await.map_err(|e| describe(&uri, &e))
+                });
+            }
+            let joined = if pending.peek().is_some() {
+                match tokio::time::timeout(ATTEMPT_DELAY, attempts.join_next()).await {
+                    Ok(joined) => joined,
+                    // still waiting; start the next candidate alongside
+                    Err(_) => continue,
+                }
+            } else {
+                attempts.join_next().await
+            };
+            match joined {
+                Some(Ok(Ok(channel))) => {
+                    attempts.abort_all();
+                    let mut client = Self::from_raw(raw::JellyFpgaControlClient::new(channel));
+                    let _ = client.capabilities().await;
+                    return Ok(client);
+                }
+                Some(Ok(Err(e))) => errors.push(e),
+                Some(Err(e)) => errors.push(e.to_string()),
+                None => break,
+            }
+        }
+        Err(tonic::Status::unavailable(format!(
+            "no address answered ({})",
+            errors.join("; ")
+        )))
+    }
+}
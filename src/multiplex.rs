@@ -0,0 +1,77 @@
+//! Separate connections for bulk transfers and control requests
+//!
+//! All RPCs of a client share one HTTP/2 connection by default, so a
+//! firmware upload or a large `mem_copy_to` saturating it delays every
+//! register read issued meanwhile by the time its frames spend queued behind
+//! the transfer. After
+//! [`with_bulk_connection`](JellyFpgaClient::with_bulk_connection) (or
+//! [`connect_multiplexed`](JellyFpgaClient::connect_multiplexed)) the
+//! [`BULK_RPCS`] travel on a second connection and everything else keeps
+//! the first one to itself.
+
+use std::sync::Arc;
+
+use crate::control::Backend;
+use crate::{JellyFpgaClient, raw};
+
+/// RPCs sent on the bulk connection once one is opened
+pub const BULK_RPCS: &[&str] = &["upload_firmware", "mem_copy_to", "mem_copy_from"];
+
+impl JellyFpgaClient {
+    /// Connect to `dst` with one connection for [`BULK_RPCS`] and one for all other requests
+    #[cfg(not(feature = "wasm"))]
+    pub async fn connect_multiplexed<D>(dst: D) -> Result<Self, tonic::transport::Error>
+    where
+        D: std::convert::TryInto<tonic::transport::Endpoint> + Clone,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Self::connect(dst.clone())
+            .await?
+            .with_bulk_connection(dst)
+            .await
+    }
+
+    /// Open a second connection to `dst` for [`BULK_RPCS`]
+    ///
+    /// Usually the same address as the main connection. Shared with clones
+    /// made afterwards, including [`high_priority`](Self::high_priority)
+    /// ones.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn with_bulk_connection<D>(self, dst: D) -> Result<Self, tonic::transport::Error>
+    where
+        D: std::convert::TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let client = raw::JellyFpgaControlClient::connect(dst).await?;
+        Ok(self.with_bulk_raw(client))
+    }
+
+    /// Use an existing generated client for [`BULK_RPCS`]
+    pub fn with_bulk_raw(mut self, client: raw::RawClient) -> Self {
+        self.bulk = Some(Arc::new(self.limit_messages(client)));
+        self
+    }
+
+    /// Whether bulk transfers use a connection of their own
+    pub fn has_bulk_connection(&self) -> bool {
+        self.bulk.is_some()
+    }
+
+    /// Generated client for a bulk RPC: the bulk connection if opened, else the shared one
+    pub(crate) fn bulk_client(&self) -> raw::RawClient {
+        match &self.bulk {
+            Some(bulk) => (**bulk).clone(),
+            None => self.client.clone(),
+        }
+    }
+
+    /// Backend for a bulk device RPC, like [`bulk_client`](Self::bulk_client) unless local access is enabled
+    pub(crate) fn bulk_backend(&self) -> Backend<'_> {
+        match &self.bulk {
+            #[cfg(feature = "local")]
+            Some(_) if self.local.is_some() => self.backend(),
+            Some(bulk) => Backend::Remote(bulk),
+            None => self.backend(),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Memory test patterns
+//!
+//! [`JellyFpgaClient::mem_write_pattern`] fills a range with a [`Pattern`]
+//! and [`mem_check_pattern`](JellyFpgaClient::mem_check_pattern) reads it
+//! back chunk by chunk and compares it with the regenerated pattern, so
+//! memory and DMA soak tests neither keep the data on the host nor stop at
+//! the first bad word. Patterns are sequences of little-endian `u32` words
+//! starting at the first word of the range.
+
+use std::fmt;
+
+use crate::JellyFpgaClient;
+use crate::bulk::DEFAULT_ITER_CHUNK_SIZE;
+
+/// Word sequence to write and verify
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Word index (0, 1, 2, ...)
+    Ramp,
+    /// Pseudo-random words from a 32-bit xorshift generator seeded with the value
+    Prbs(u32),
+    /// The same word everywhere
+    Const(u32),
+}
+
+impl Pattern {
+    /// Words of the pattern
+    pub fn words(self) -> impl Iterator<Item = u32> {
+        let mut state = match self {
+            Pattern::Ramp => 0,
+            // xorshift never leaves 0
+            Pattern::Prbs(seed) => seed.max(1),
+            Pattern::Const(value) => value,
+        };
+        std::iter::repeat_with(move || {
+            let word = state;
+            state = match self {
+                Pattern::Ramp => state.wrapping_add(1),
+                Pattern::Prbs(_) => {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^ (state << 5)
+                }
+                Pattern::Const(_) => state,
+            };
+            word
+        })
+    }
+}
+
+/// First word that did not match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternMismatch {
+    /// Byte offset of the word
+    pub offset: u64,
+    /// Pattern word
+    pub expected: u32,
+    /// Word read back
+    pub actual: u32,
+}
+
+/// Result of [`mem_check_pattern`](JellyFpgaClient::mem_check_pattern)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PatternCheck {
+    /// Words compared
+    pub words: u64,
+    /// Words that did not match
+    pub errors: u64,
+    /// First mismatch, if any
+    pub first_error: Option<PatternMismatch>,
+}
+
+impl PatternCheck {
+    /// Whether every word matched
+    pub fn is_ok(&self) -> bool {
+        self.errors == 0
+    }
+}
+
+impl fmt::Display for PatternCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} words, {} errors", self.words, self.errors)?;
+        if let Some(e) = &self.first_error {
+            write!(
+                f,
+                " (first at offset 0x{:x}: expected 0x{:08x}, read 0x{:08x})",
+                e.offset, e.expected, e.actual
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare `data` with `words`, adding to `check`; `offset` is the byte offset of `data`
+fn compare(
+    check: &mut PatternCheck,
+    offset: u64,
+    data: &[u8],
+    words: &mut impl Iterator<Item = u32>,
+) {
+    for (i, chunk) in data.chunks_exact(4).enumerate() {
+        let actual = u32::from_le_bytes(chunk.try_into().unwrap());
+        let expected = words.next().unwrap_or_default();
+        check.words += 1;
+        if actual != expected {
+            check.errors += 1;
+            check.first_error.get_or_insert(PatternMismatch {
+                offset: offset + 4 * i as u64,
+                expected,
+                actual,
+            });
+        }
+    }
+}
+
+/// `len` must be whole words
+fn check_len(len: u64) -> Result<(), tonic::Status> {
+    if !len.is_multiple_of(4) {
+        return Err(tonic::Status::invalid_argument(format!(
+            "pattern length {} is not a multiple of 4 bytes",
+            len
+        )));
+    }
+    Ok(())
+}
+
+impl JellyFpgaClient {
+    /// Write `len` bytes of `pattern` from byte `offset` of `id`
+    ///
+    /// `len` must be a multiple of 4.
+    pub async fn mem_write_pattern(
+        &mut self,
+        id: u32,
+        offset: u64,
+        len: u64,
+        pattern: Pattern,
+    ) -> Result<(), tonic::Status> {
+        check_len(len)?;
+        let words = pattern.words().take((len / 4) as usize);
+        self.write_mem_iter(id, offset, words).await.map(|_| ())
+    }
+
+    /// Read `len` bytes from byte `offset` of `id` and compare them with `pattern`
+    ///
+    /// Mismatches are counted, not returned as errors; only failed reads are.
+    pub async fn mem_check_pattern(
+        &mut self,
+        id: u32,
+        offset: u64,
+        len: u64,
+        pattern: Pattern,
+    ) -> Result<PatternCheck, tonic::Status> {
+        check_len(len)?;
+        let mut words = pattern.words();
+        let mut check = PatternCheck::default();
+        let mut pos = 0;
+        while pos < len {
+            let size = (len - pos).min(DEFAULT_ITER_CHUNK_SIZE as u64);
+            let data = self.read_chunk(id, offset + pos, size).await?;
+            compare(&mut check, offset + pos, &data, &mut words);
+            pos += size;
+        }
+        Ok(check)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern() {
+        let ramp: Vec<u32> = Pattern::Ramp.words().take(3).collect();
+        assert_eq!(ramp, vec![0, 1, 2]);
+        let prbs: Vec<u32> = Pattern::Prbs(1).words().take(3).collect();
+        assert_eq!(prbs, vec![1, 0x0004_2021, 0x0408_0601]);
+        assert_eq!(Pattern::Prbs(0).words().next(), Some(1));
+
+        let mut check = PatternCheck::default();
+        let data: Vec<u8> = [0u32, 1, 7, 3]
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        compare(&mut check, 0x100, &data, &mut Pattern::Ramp.words());
+        assert_eq!(check.words, 4);
+        assert_eq!(check.errors, 1);
+        assert_eq!(
+            check.first_error,
+            Some(PatternMismatch {
+                offset: 0x108,
+                expected: 2,
+                actual: 7
+            })
+        );
+    }
+}
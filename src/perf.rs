@@ -0,0 +1,368 @@
+//! Performance counter readout (AXI Performance Monitor or jelly profiling counters)
+//!
+//! Counters are sampled periodically; the deltas between samples give
+//! per-interval rates (e.g. bytes/s) and ratios (e.g. average latency).
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use crate::accessor::Accessor;
+use crate::gpio::Addressing;
+
+/// AXI Performance Monitor: control register
+pub const APM_REG_CONTROL: u64 = 0x300;
+/// AXI Performance Monitor: first metric counter
+pub const APM_REG_METRIC_COUNTER0: u64 = 0x100;
+/// AXI Performance Monitor: metric counter stride
+pub const APM_METRIC_COUNTER_STRIDE: u64 = 0x10;
+/// APM control: enable metric counters
+pub const APM_CONTROL_ENABLE: u64 = 1 << 0;
+/// APM control: reset metric counters
+pub const APM_CONTROL_RESET: u64 = 1 << 1;
+
+/// One counter in a [`PerfLayout`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerfCounter {
+    /// Name used in reports
+    pub name: String,
+    /// Register offset (interpreted by the layout addressing)
+    pub offset: u64,
+}
+
+/// Register map of a counter block
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerfLayout {
+    /// Offset interpretation
+    pub addressing: Addressing,
+    /// Counter width in bytes
+    pub size: u64,
+    /// Counters to sample
+    pub counters: Vec<PerfCounter>,
+    /// Control register and its (start, stop) values, if the block has one
+    pub control: Option<(u64, u64, u64)>,
+    /// Value written to the control register before start to clear counters
+    pub reset_value: Option<u64>,
+}
+
+impl PerfLayout {
+    /// Empty layout
+    pub fn new(addressing: Addressing, size: u64) -> Self {
+        PerfLayout {
+            addressing,
+            size,
+            counters: Vec::new(),
+            control: None,
+            reset_value: None,
+        }
+    }
+
+    /// Add a counter
+    pub fn counter(mut self, name: &str, offset: u64) -> Self {
+        self.counters.push(PerfCounter {
+            name: name.to_string(),
+            offset,
+        });
+        self
+    }
+
+    /// Xilinx AXI Performance Monitor with the given metric counter names
+    ///
+    /// Metrics are assigned to counters by the hardware configuration (or
+    /// the metric selector registers); `names[n]` labels metric counter n.
+    pub fn axi_perf_monitor(names: &[&str]) -> Self {
+        let mut layout = PerfLayout::new(Addressing::Byte, 4);
+        for (i, name) in names.iter().enumerate() {
+            layout = layout.counter(
+                name,
+                APM_REG_METRIC_COUNTER0 + i as u64 * APM_METRIC_COUNTER_STRIDE,
+            );
+        }
+        layout.control = Some((APM_REG_CONTROL, APM_CONTROL_ENABLE, 0));
+        layout.reset_value = Some(APM_CONTROL_RESET);
+        layout
+    }
+}
+
+/// Counter values at one point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerfSample {
+    /// Time since sampling started
+    pub elapsed: Duration,
+    /// Raw counter values in layout order
+    pub values: Vec<u64>,
+}
+
+/// Min/max/mean of a per-second rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RateStats {
+    /// Minimum over intervals
+    pub min: f64,
+    /// Maximum over intervals
+    pub max: f64,
+    /// Total delta divided by total time
+    pub mean: f64,
+}
+
+/// Collected samples
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerfReport {
+    /// Counter names
+    pub names: Vec<String>,
+    /// Counter width in bytes (for wrap-around)
+    pub size: u64,
+    /// Samples in time order
+    pub samples: Vec<PerfSample>,
+}
+
+impl PerfReport {
+    fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    fn delta(&self, a: u64, b: u64) -> u64 {
+        if self.size >= 8 {
+            b.wrapping_sub(a)
+        } else {
+            b.wrapping_sub(a) & ((1u64 << (self.size * 8)) - 1)
+        }
+    }
+
+    /// Per-interval deltas of counter `name`
+    pub fn deltas(&self, name: &str) -> Option<Vec<u64>> {
+        let i = self.index(name)?;
+        Some(
+            self.samples
+                .windows(2)
+                .map(|w| self.delta(w[0].values[i], w[1].values[i]))
+                .collect(),
+        )
+    }
+
+    /// Per-second rate statistics of counter `name` (e.g. bytes/s)
+    pub fn rate(&self, name: &str) -> Option<RateStats> {
+        let deltas = self.deltas(name)?;
+        if deltas.is_empty() {
+            return None;
+        }
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for (d, w) in deltas.iter().zip(self.samples.windows(2)) {
+            let dt = (w[1].elapsed - w[0].elapsed).as_secs_f64();
+            if dt > 0.0 {
+                let r = *d as f64 / dt;
+                min = min.min(r);
+                max = max.max(r);
+            }
+        }
+        if min > max {
+            min = 0.0;
+            max = 0.0;
+        }
+        let total: u64 = deltas.iter().sum();
+        let span = (self.samples.last()?.elapsed - self.samples.first()?.elapsed).as_secs_f64();
+        let mean = if span > 0.0 { total as f64 / span } else { 0.0 };
+        Some(RateStats { min, max, mean })
+    }
+
+    /// Ratio of total deltas `numerator / denominator`
+    /// (e.g. total latency cycles / transactions = average latency)
+    pub fn ratio(&self, numerator: &str, denominator: &str) -> Option<f64> {
+        let num: u64 = self.deltas(numerator)?.iter().sum();
+        let den: u64 = self.deltas(denominator)?.iter().sum();
+        if den == 0 {
+            None
+        } else {
+            Some(num as f64 / den as f64)
+        }
+    }
+
+    /// CSV with one row per sample (`elapsed_s` followed by raw counter values)
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("elapsed_s");
+        for name in &self.names {
+            s.push(',');
+            s.push_str(name);
+        }
+        s.push('\n');
+        for sample in &self.samples {
+            let _ = write!(s, "{:.6}", sample.elapsed.as_secs_f64());
+            for v in &sample.values {
+                let _ = write!(s, ",{}", v);
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// JSON with the raw samples and per-counter rate statistics
+    pub fn to_json(&self) -> String {
+        let mut s = String::from("{\"counters\":[");
+        for (i, name) in self.names.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            let _ = write!(s, "{{\"name\":{:?}", name);
+            if let Some(r) = self.rate(name) {
+                let _ = write!(
+                    s,
+                    ",\"rate_min\":{},\"rate_max\":{},\"rate_mean\":{}",
+                    r.min, r.max, r.mean
+                );
+            }
+            s.push('}');
+        }
+        s.push_str("],\"samples\":[");
+        for (i, sample) in self.samples.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            let values: Vec<String> = sample.values.iter().map(|v| v.to_string()).collect();
+            let _ = write!(
+                s,
+                "{{\"elapsed_s\":{},\"values\":[{}]}}",
+                sample.elapsed.as_secs_f64(),
+                values.join(",")
+            );
+        }
+        s.push_str("]}");
+        s
+    }
+}
+
+/// Counter block sampler
+pub struct PerfMonitor {
+    regs: Accessor,
+    layout: PerfLayout,
+}
+
+impl PerfMonitor {
+    /// Create sampler
+    pub fn new(regs: Accessor, layout: PerfLayout) -> Self {
+        PerfMonitor { regs, layout }
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Layout
+    pub fn layout(&self) -> &PerfLayout {
+        &self.layout
+    }
+
+    async fn write(&mut self, offset: u64, data: u64) -> Result<(), tonic::Status> {
+        match self.layout.addressing {
+            Addressing::Byte => {
+                self.regs
+                    .write_mem_u_raw(offset, data, self.layout.size)
+                    .await
+            }
+            Addressing::Reg => {
+                self.regs
+                    .write_reg_u_raw(offset, data, self.layout.size)
+                    .await
+            }
+        }
+    }
+
+    async fn read(&mut self, offset: u64) -> Result<u64, tonic::Status> {
+        match self.layout.addressing {
+            Addressing::Byte => self.regs.read_mem_u_raw(offset, self.layout.size).await,
+            Addressing::Reg => self.regs.read_reg_u_raw(offset, self.layout.size).await,
+        }
+    }
+
+    /// Clear (if supported) and enable the counters
+    pub async fn start(&mut self) -> Result<(), tonic::Status> {
+        if let Some((reg, start, _)) = self.layout.control {
+            if let Some(reset) = self.layout.reset_value {
+                self.write(reg, reset).await?;
+            }
+            self.write(reg, start).await?;
+        }
+        Ok(())
+    }
+
+    /// Disable the counters
+    pub async fn stop(&mut self) -> Result<(), tonic::Status> {
+        if let Some((reg, _, stop)) = self.layout.control {
+            self.write(reg, stop).await?;
+        }
+        Ok(())
+    }
+
+    /// Read all counters once
+    pub async fn read_counters(&mut self) -> Result<Vec<u64>, tonic::Status> {
+        let offsets: Vec<u64> = self.layout.counters.iter().map(|c| c.offset).collect();
+        let mut values = Vec::with_capacity(offsets.len());
+        for offset in offsets {
+            values.push(self.read(offset).await?);
+        }
+        Ok(values)
+    }
+
+    /// Start the counters, take `count` samples `interval` apart, and stop
+    pub async fn sample(
+        &mut self,
+        count: usize,
+        interval: Duration,
+    ) -> Result<PerfReport, tonic::Status> {
+        self.start().await?;
+        let begin = Instant::now();
+        let mut samples = Vec::with_capacity(count);
+        for i in 0..count {
+            if i > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            let values = self.read_counters().await?;
+            samples.push(PerfSample {
+                elapsed: begin.elapsed(),
+                values,
+            });
+        }
+        self.stop().await?;
+        Ok(PerfReport {
+            names: self
+                .layout
+                .counters
+                .iter()
+                .map(|c| c.name.clone())
+                .collect(),
+            size: self.layout.size,
+            samples,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_wraparound() {
+        let report = PerfReport {
+            names: vec!["bytes".to_string(), "lat".to_string()],
+            size: 4,
+            samples: vec![
+                PerfSample {
+                    elapsed: Duration::from_secs(0),
+                    values: vec![0xffff_fff0, 0],
+                },
+                PerfSample {
+                    elapsed: Duration::from_secs(1),
+                    values: vec![0x10, 64],
+                },
+            ],
+        };
+        assert_eq!(report.deltas("bytes"), Some(vec![0x20]));
+        assert_eq!(report.rate("bytes").unwrap().mean, 32.0);
+        assert_eq!(report.ratio("lat", "bytes"), Some(2.0));
+        assert_eq!(report.to_csv().lines().count(), 3);
+    }
+}
@@ -0,0 +1,433 @@
+//! Device personality: everything application code needs from a design
+//!
+//! A [`Personality`] combines the address regions of a design with the
+//! register maps of its IPs, interrupt status registers and init/deinit
+//! register writes. [`JellyFpgaClient::open_personality`] defines and opens
+//! all regions (all or none, see [`open_all`](JellyFpgaClient::open_all)),
+//! loads the register maps as shadows of the accessors, runs the init
+//! writes and returns a [`PersonalityHandle`] with the opened [`Device`]s.
+//!
+//! With the `personality` feature it reads from a TOML file such as:
+//!
+//! ```toml
+//! name = "kv260_camera"
+//!
+//! [[region]]
+//! name = "gpio"
+//! addr = 0xa0000000
+//! size = 0x1000
+//!
+//! [[regmap]]
+//! region = "gpio"
+//! path = "gpio.regs"            # text format, or IP-XACT .xml (`ipxact` feature)
+//!
+//! [[irq]]
+//! name = "frame_done"
+//! region = "gpio"
+//! reg = "ISR"                   # register name or index
+//! mask = 0x1
+//!
+//! [[init]]
+//! region = "gpio"
+//! reg = "CONTROL"
+//! value = 1
+//!
+//! [[deinit]]
+//! region = "gpio"
+//! reg = 0
+//! value = 0
+//! size = 4                      # for register indices (4 if omitted)
+//! ```
+//!
+//! The server does not forward interrupts; [`PersonalityHandle::wait_irq`]
+//! polls the status register and clears it by writing the mask back.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::addr::AccessSize;
+use crate::region::Region;
+use crate::regmap::RegisterMap;
+use crate::{Accessor, JellyFpgaClient};
+
+/// Interval between polls of an interrupt status register
+pub const IRQ_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Register given by name (looked up in the region's map) or index
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum RegRef {
+    /// Register index
+    Index(u64),
+    /// Register name
+    Name(String),
+}
+
+/// Register map file of a region
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegMapFile {
+    /// Region the map describes
+    pub region: String,
+    /// Text register map, or IP-XACT `.xml`
+    pub path: String,
+}
+
+/// Interrupt status register
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IrqDef {
+    /// Interrupt name
+    pub name: String,
+    /// Region of the status register
+    pub region: String,
+    /// Status register (write one to clear)
+    pub reg: RegRef,
+    /// Status bits of this interrupt
+    pub mask: u64,
+}
+
+/// Register write of an init or deinit sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegWrite {
+    /// Region of the register
+    pub region: String,
+    /// Register
+    pub reg: RegRef,
+    /// Value to write
+    pub value: u64,
+    /// Access size in bytes for a register index (4 if omitted; names use the map)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub size: Option<u64>,
+}
+
+/// Regions, register maps, interrupts and init/deinit sequences of a design
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Personality {
+    /// Design name
+    pub name: String,
+    /// Regions opened as devices
+    #[cfg_attr(feature = "serde", serde(default, rename = "region"))]
+    pub regions: Vec<Region>,
+    /// Register maps per region
+    #[cfg_attr(feature = "serde", serde(default, rename = "regmap"))]
+    pub regmaps: Vec<RegMapFile>,
+    /// Interrupt status registers
+    #[cfg_attr(feature = "serde", serde(default, rename = "irq"))]
+    pub irqs: Vec<IrqDef>,
+    /// Register writes after opening, in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub init: Vec<RegWrite>,
+    /// Register writes before closing, in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub deinit: Vec<RegWrite>,
+}
+
+impl Personality {
+    /// Empty personality
+    pub fn new(name: &str) -> Self {
+        Personality {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a region
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.regions.push(region);
+        self
+    }
+
+    /// Add the register map file of `region`
+    pub fn with_regmap(mut self, region: &str, path: &str) -> Self {
+        self.regmaps.push(RegMapFile {
+            region: region.to_string(),
+            path: path.to_string(),
+        });
+        self
+    }
+
+    /// Add an interrupt status register
+    pub fn with_irq(mut self, irq: IrqDef) -> Self {
+        self.irqs.push(irq);
+        self
+    }
+
+    /// Add an init write
+    pub fn with_init(mut self, write: RegWrite) -> Self {
+        self.init.push(write);
+        self
+    }
+
+    /// Add a deinit write
+    pub fn with_deinit(mut self, write: RegWrite) -> Self {
+        self.deinit.push(write);
+        self
+    }
+
+    /// Resolve relative register map paths against `dir` (e.g. the file's directory)
+    pub fn with_base_dir(mut self, dir: &Path) -> Self {
+        for regmap in &mut self.regmaps {
+            if Path::new(&regmap.path).is_relative() {
+                regmap.path = dir.join(&regmap.path).to_string_lossy().into_owned();
+            }
+        }
+        self
+    }
+}
+
+/// Opened region of a personality
+#[derive(Clone)]
+pub struct Device {
+    /// Region
+    pub region: Region,
+    /// Accessor (with the register map as shadow)
+    pub accessor: Accessor,
+    /// Register map, if the personality has one
+    pub regmap: Option<RegisterMap>,
+}
+
+impl Device {
+    /// Index and access size of `reg`
+    fn resolve(&self, reg: &RegRef, size: Option<u64>) -> Result<(u64, AccessSize), tonic::Status> {
+        let (index, bytes) = match reg {
+            RegRef::Index(index) => (*index, size.unwrap_or(4)),
+            RegRef::Name(name) => {
+                let def = self
+                    .regmap
+                    .as_ref()
+                    .and_then(|map| map.get(name))
+                    .ok_or_else(|| {
+                        tonic::Status::not_found(format!(
+                            "no register {:?} in region {:?}",
+                            name, self.region.name
+                        ))
+                    })?;
+                (def.reg, def.size)
+            }
+        };
+        let size = AccessSize::from_bytes(bytes).ok_or_else(|| {
+            tonic::Status::invalid_argument(format!("invalid register size {}", bytes))
+        })?;
+        Ok((index, size))
+    }
+
+    /// Read register `name`
+    pub async fn read(&mut self, name: &str) -> Result<u64, tonic::Status> {
+        let (reg, size) = self.resolve(&RegRef::Name(name.to_string()), None)?;
+        self.accessor.read_reg_u(reg, size).await
+    }
+
+    /// Write register `name`
+    pub async fn write(&mut self, name: &str, value: u64) -> Result<(), tonic::Status> {
+        let (reg, size) = self.resolve(&RegRef::Name(name.to_string()), None)?;
+        self.accessor.write_reg_u(reg, value, size).await
+    }
+
+    /// Read field `field` of register `register`
+    pub async fn read_field(&mut self, register: &str, field: &str) -> Result<u64, tonic::Status> {
+        let field = self.field(register, field)?;
+        self.accessor.read_field(&field).await
+    }
+
+    /// Write field `field` of register `register` (read-modify-write)
+    pub async fn write_field(
+        &mut self,
+        register: &str,
+        field: &str,
+        value: u64,
+    ) -> Result<(), tonic::Status> {
+        let field = self.field(register, field)?;
+        self.accessor.write_field(&field, value).await
+    }
+
+    fn field(&self, register: &str, field: &str) -> Result<crate::field::Field, tonic::Status> {
+        self.regmap
+            .as_ref()
+            .and_then(|map| map.lookup_field(register, field))
+            .cloned()
+            .ok_or_else(|| {
+                tonic::Status::not_found(format!(
+                    "no field {}.{} in region {:?}",
+                    register, field, self.region.name
+                ))
+            })
+    }
+
+    async fn apply(&mut self, write: &RegWrite) -> Result<(), tonic::Status> {
+        let (reg, size) = self.resolve(&write.reg, write.size)?;
+        self.accessor.write_reg_u(reg, write.value, size).await
+    }
+}
+
+/// Devices opened by [`JellyFpgaClient::open_personality`]
+pub struct PersonalityHandle {
+    name: String,
+    devices: BTreeMap<String, Device>,
+    irqs: BTreeMap<String, IrqDef>,
+    deinit: Vec<RegWrite>,
+}
+
+impl PersonalityHandle {
+    /// Design name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Device of region `name`
+    pub fn device(&mut self, name: &str) -> Result<&mut Device, tonic::Status> {
+        self.devices
+            .get_mut(name)
+            .ok_or_else(|| tonic::Status::not_found(format!("unknown device {:?}", name)))
+    }
+
+    /// Accessor of region `name`
+    pub fn accessor(&mut self, name: &str) -> Result<&mut Accessor, tonic::Status> {
+        Ok(&mut self.device(name)?.accessor)
+    }
+
+    /// Names of the devices
+    pub fn devices(&self) -> Vec<String> {
+        self.devices.keys().cloned().collect()
+    }
+
+    /// Wait up to `timeout` for interrupt `name`, then clear it
+    pub async fn wait_irq(&mut self, name: &str, timeout: Duration) -> Result<(), tonic::Status> {
+        let irq = self
+            .irqs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| tonic::Status::not_found(format!("unknown irq {:?}", name)))?;
+        let device = self.device(&irq.region)?;
+        let (reg, size) = device.resolve(&irq.reg, None)?;
+        device
+            .accessor
+            .wait_reg_u(reg, size, irq.mask, irq.mask, IRQ_POLL_INTERVAL, timeout)
+            .await?;
+        device.accessor.write_reg_u(reg, irq.mask, size).await
+    }
+
+    /// Run the deinit writes and close the devices
+    ///
+    /// Every step runs even after a failure; the first error is returned.
+    pub async fn close(mut self) -> Result<(), tonic::Status> {
+        let mut first = None;
+        for write in std::mem::take(&mut self.deinit) {
+            let result = match self.device(&write.region) {
+                Ok(device) => device.apply(&write).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                first.get_or_insert(e);
+            }
+        }
+        for (_, device) in std::mem::take(&mut self.devices) {
+            if let Err(e) = device.accessor.close().await {
+                first.get_or_insert(e);
+            }
+        }
+        first.map_or(Ok(()), Err)
+    }
+}
+
+/// Load a register map file; IP-XACT files use the region's unit
+async fn load_regmap(path: &str, region: &Region) -> Result<RegisterMap, tonic::Status> {
+    #[cfg(not(feature = "ipxact"))]
+    let _ = region;
+    if path.ends_with(".xml") {
+        #[cfg(feature = "ipxact")]
+        return RegisterMap::parse_ipxact(&crate::fs::read_to_string(path).await?, region.unit);
+        #[cfg(not(feature = "ipxact"))]
+        return Err(tonic::Status::unimplemented(
+            "reading IP-XACT register maps requires the ipxact feature",
+        ));
+    }
+    RegisterMap::parse(&crate::fs::read_to_string(path).await?)
+        .map_err(|e| tonic::Status::invalid_argument(format!("{}: {}", path, e.message())))
+}
+
+impl JellyFpgaClient {
+    /// Open every region of `personality` and run its init writes
+    ///
+    /// The regions are also defined on the client. If an init write fails,
+    /// the devices are closed again.
+    pub async fn open_personality(
+        &mut self,
+        personality: &Personality,
+    ) -> Result<PersonalityHandle, tonic::Status> {
+        let mut regmaps = BTreeMap::new();
+        for file in &personality.regmaps {
+            let region = personality
+                .regions
+                .iter()
+                .find(|r| r.name == file.region)
+                .ok_or_else(|| {
+                    tonic::Status::invalid_argument(format!(
+                        "register map for unknown region {:?}",
+                        file.region
+                    ))
+                })?;
+            regmaps.insert(file.region.clone(), load_regmap(&file.path, region).await?);
+        }
+        self.add_regions(personality.regions.iter().cloned());
+        let accessors = self.open_all(&personality.regions).await?;
+        let devices = personality
+            .regions
+            .iter()
+            .zip(accessors)
+            .map(|(region, mut accessor)| {
+                let regmap = regmaps.remove(&region.name);
+                if let Some(map) = &regmap {
+                    accessor.set_shadow(map);
+                }
+                let device = Device {
+                    region: region.clone(),
+                    accessor,
+                    regmap,
+                };
+                (region.name.clone(), device)
+            })
+            .collect();
+        let mut handle = PersonalityHandle {
+            name: personality.name.clone(),
+            devices,
+            irqs: personality
+                .irqs
+                .iter()
+                .map(|irq| (irq.name.clone(), irq.clone()))
+                .collect(),
+            deinit: Vec::new(),
+        };
+        for write in &personality.init {
+            let result = match handle.device(&write.region) {
+                Ok(device) => device.apply(write).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                let _ = handle.close().await;
+                return Err(e);
+            }
+        }
+        handle.deinit = personality.deinit.clone();
+        Ok(handle)
+    }
+
+    /// Read a personality TOML file and [`open_personality`](Self::open_personality) it
+    ///
+    /// Register map paths are relative to the file's directory.
+    #[cfg(feature = "personality")]
+    pub async fn load_personality(
+        &mut self,
+        path: &str,
+    ) -> Result<PersonalityHandle, tonic::Status> {
+        let text = crate::fs::read_to_string(path).await?;
+        let personality: Personality = toml::from_str(&text)
+            .map_err(|e| tonic::Status::invalid_argument(format!("{}: {}", path, e)))?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+        self.open_personality(&personality.with_base_dir(dir)).await
+    }
+}
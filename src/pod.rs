@@ -0,0 +1,168 @@
+//! Generic memory/register access over `bytemuck::Pod` types
+//!
+//! The access size follows `size_of::<T>()`: `f32`/`f64` use the float RPCs,
+//! other 1/2/4/8 byte types the integer RPCs with their raw bits, and any
+//! other size (structs, arrays) a `mem_copy_to`/`mem_copy_from` of the bytes.
+
+use std::any::TypeId;
+
+use bytemuck::Pod;
+
+use crate::JellyFpgaClient;
+
+/// Raw bits of a 1/2/4/8 byte value as an integer
+fn to_bits<T: Pod>(value: &T) -> u64 {
+    let bytes = bytemuck::bytes_of(value);
+    let n = bytes.len();
+    let mut buf = [0u8; 8];
+    if cfg!(target_endian = "big") {
+        buf[8 - n..].copy_from_slice(bytes);
+    } else {
+        buf[..n].copy_from_slice(bytes);
+    }
+    u64::from_ne_bytes(buf)
+}
+
+/// Value of a 1/2/4/8 byte type from the low bits of an integer
+fn from_bits<T: Pod>(bits: u64) -> T {
+    let n = std::mem::size_of::<T>();
+    let buf = bits.to_ne_bytes();
+    if cfg!(target_endian = "big") {
+        bytemuck::pod_read_unaligned(&buf[8 - n..])
+    } else {
+        bytemuck::pod_read_unaligned(&buf[..n])
+    }
+}
+
+fn is<T: 'static, U: 'static>() -> bool {
+    TypeId::of::<T>() == TypeId::of::<U>()
+}
+
+impl JellyFpgaClient {
+    /// Write a value to memory, choosing size and RPC from `T`
+    pub async fn write_mem<T: Pod>(
+        &mut self,
+        id: u32,
+        offset: u64,
+        value: T,
+    ) -> Result<bool, tonic::Status> {
+        if is::<T, f32>() {
+            return self
+                .write_mem_f32(id, offset, from_bits(to_bits(&value)))
+                .await;
+        }
+        if is::<T, f64>() {
+            return self
+                .write_mem_f64(id, offset, from_bits(to_bits(&value)))
+                .await;
+        }
+        match std::mem::size_of::<T>() {
+            size @ (1 | 2 | 4 | 8) => {
+                self.write_mem_u(id, offset, to_bits(&value), size as u64)
+                    .await
+            }
+            _ => {
+                self.mem_copy_to(id, offset, bytemuck::bytes_of(&value).to_vec())
+                    .await
+            }
+        }
+    }
+
+    /// Read a value from memory, choosing size and RPC from `T`
+    pub async fn read_mem<T: Pod>(
+        &mut self,
+        id: u32,
+        offset: u64,
+    ) -> Result<(bool, T), tonic::Status> {
+        if is::<T, f32>() {
+            let (result, data) = self.read_mem_f32(id, offset).await?;
+            return Ok((result, from_bits(to_bits(&data))));
+        }
+        if is::<T, f64>() {
+            let (result, data) = self.read_mem_f64(id, offset).await?;
+            return Ok((result, from_bits(to_bits(&data))));
+        }
+        match std::mem::size_of::<T>() {
+            size @ (1 | 2 | 4 | 8) => {
+                let (result, data) = self.read_mem_u(id, offset, size as u64).await?;
+                Ok((result, from_bits(data)))
+            }
+            size => {
+                let (result, data) = self.mem_copy_from(id, offset, size as u64).await?;
+                if !result || data.len() != size {
+                    return Ok((false, T::zeroed()));
+                }
+                Ok((true, bytemuck::pod_read_unaligned(&data)))
+            }
+        }
+    }
+
+    /// Write a value to a register, choosing size and RPC from `T` (1/2/4/8 byte types only)
+    pub async fn write_reg<T: Pod>(
+        &mut self,
+        id: u32,
+        reg: u64,
+        value: T,
+    ) -> Result<bool, tonic::Status> {
+        if is::<T, f32>() {
+            return self
+                .write_reg_f32(id, reg, from_bits(to_bits(&value)))
+                .await;
+        }
+        if is::<T, f64>() {
+            return self
+                .write_reg_f64(id, reg, from_bits(to_bits(&value)))
+                .await;
+        }
+        match std::mem::size_of::<T>() {
+            size @ (1 | 2 | 4 | 8) => {
+                self.write_reg_u(id, reg, to_bits(&value), size as u64)
+                    .await
+            }
+            size => Err(unsupported_size(size)),
+        }
+    }
+
+    /// Read a value from a register, choosing size and RPC from `T` (1/2/4/8 byte types only)
+    pub async fn read_reg<T: Pod>(
+        &mut self,
+        id: u32,
+        reg: u64,
+    ) -> Result<(bool, T), tonic::Status> {
+        if is::<T, f32>() {
+            let (result, data) = self.read_reg_f32(id, reg).await?;
+            return Ok((result, from_bits(to_bits(&data))));
+        }
+        if is::<T, f64>() {
+            let (result, data) = self.read_reg_f64(id, reg).await?;
+            return Ok((result, from_bits(to_bits(&data))));
+        }
+        match std::mem::size_of::<T>() {
+            size @ (1 | 2 | 4 | 8) => {
+                let (result, data) = self.read_reg_u(id, reg, size as u64).await?;
+                Ok((result, from_bits(data)))
+            }
+            size => Err(unsupported_size(size)),
+        }
+    }
+}
+
+fn unsupported_size(size: usize) -> tonic::Status {
+    tonic::Status::invalid_argument(format!(
+        "register access of {} bytes is not supported",
+        size
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits_roundtrip() {
+        assert_eq!(to_bits(&-1i8), 0xff);
+        assert_eq!(from_bits::<i16>(0xfffe), -2);
+        assert_eq!(from_bits::<f32>(to_bits(&1.5f32)), 1.5);
+        assert_eq!(to_bits(&0x1234_5678u32), 0x1234_5678);
+    }
+}
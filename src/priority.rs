@@ -0,0 +1,51 @@
+//! Priority requests
+//!
+//! A long `mem_copy_to` occupies the HTTP/2 connection and any
+//! [`with_max_inflight`](JellyFpgaClient::with_max_inflight) slots, so a
+//! stop or abort register write issued meanwhile queues behind it.
+//! [`JellyFpgaClient::high_priority`] returns a clone whose requests skip
+//! the concurrency limits and, once
+//! [`with_priority_connection`](JellyFpgaClient::with_priority_connection)
+//! opened one, travel on a separate connection that bulk traffic does not
+//! use.
+
+use std::sync::Arc;
+
+use crate::{JellyFpgaClient, raw};
+
+impl JellyFpgaClient {
+    /// Open a second connection to `dst` for [`high_priority`](Self::high_priority) clones
+    ///
+    /// Usually the same address as the main connection. Shared with clones
+    /// made afterwards.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn with_priority_connection<D>(self, dst: D) -> Result<Self, tonic::transport::Error>
+    where
+        D: std::convert::TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let client = raw::JellyFpgaControlClient::connect(dst).await?;
+        Ok(self.with_priority_raw(client))
+    }
+
+    /// Use an existing generated client for [`high_priority`](Self::high_priority) clones
+    pub fn with_priority_raw(mut self, client: raw::RawClient) -> Self {
+        self.priority = Some(Arc::new(self.limit_messages(client)));
+        self
+    }
+
+    /// Clone for latency-sensitive control requests
+    ///
+    /// Its requests bypass [`with_max_inflight`](Self::with_max_inflight)
+    /// limits and use the priority connection if one was opened (otherwise
+    /// the shared one). Handles, lock, lease session and strict mode are
+    /// shared as with any clone.
+    pub fn high_priority(&self) -> Self {
+        let mut client = self.clone();
+        if let Some(priority) = &self.priority {
+            client.client = (**priority).clone();
+        }
+        client.limiter = Arc::new(self.limiter.unlimited());
+        client
+    }
+}
@@ -0,0 +1,145 @@
+//! Progress events for long server operations
+//!
+//! The server has no streaming status for `load` or `bitstream_to_bin`, so
+//! progress is emulated: the operation runs in a background task and the
+//! stream yields a heartbeat with the elapsed time every interval until it
+//! finishes.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::JellyFpgaClient;
+
+/// Progress of a long operation
+#[derive(Debug, Clone)]
+pub enum OpProgress<T> {
+    /// Request sent
+    Started {
+        /// Operation name
+        op: String,
+    },
+    /// Still waiting for the server
+    Running {
+        /// Operation name
+        op: String,
+        /// Time since start
+        elapsed: Duration,
+    },
+    /// Server answered
+    Finished {
+        /// Operation name
+        op: String,
+        /// Time since start
+        elapsed: Duration,
+        /// Return value of the operation
+        value: T,
+    },
+    /// RPC failed
+    Failed {
+        /// Operation name
+        op: String,
+        /// Time since start
+        elapsed: Duration,
+        /// Error
+        status: tonic::Status,
+    },
+}
+
+/// Run `fut` in a background task, yielding progress every `interval`
+///
+/// The stream ends after `Finished` or `Failed`. Dropping the stream does
+/// not cancel the operation.
+pub fn track<T, F>(op: &str, interval: Duration, fut: F) -> ReceiverStream<OpProgress<T>>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T, tonic::Status>> + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    let op = op.to_string();
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now();
+        let _ = tx.send(OpProgress::Started { op: op.clone() }).await;
+        let mut ticker = tokio::time::interval_at(start + interval, interval);
+        tokio::pin!(fut);
+        let event = loop {
+            tokio::select! {
+                result = &mut fut => {
+                    let elapsed = start.elapsed();
+                    break match result {
+                        Ok(value) => OpProgress::Finished { op, elapsed, value },
+                        Err(status) => OpProgress::Failed { op, elapsed, status },
+                    };
+                }
+                _ = ticker.tick() => {
+                    let elapsed = start.elapsed();
+                    let _ = tx.try_send(OpProgress::Running { op: op.clone(), elapsed });
+                }
+            }
+        };
+        let _ = tx.send(event).await;
+    });
+    ReceiverStream::new(rx)
+}
+
+impl JellyFpgaClient {
+    /// `load` with progress events; `Finished` carries `(result, slot)`
+    pub fn load_with_progress(
+        &self,
+        name: &str,
+        interval: Duration,
+    ) -> ReceiverStream<OpProgress<(bool, i32)>> {
+        let mut client = self.clone();
+        let name = name.to_string();
+        track("load", interval, async move { client.load(&name).await })
+    }
+
+    /// `bitstream_to_bin` with progress events; `Finished` carries the result
+    pub fn bitstream_to_bin_with_progress(
+        &self,
+        bitstream_name: &str,
+        bin_name: &str,
+        arch: &str,
+        interval: Duration,
+    ) -> ReceiverStream<OpProgress<bool>> {
+        let mut client = self.clone();
+        let (bitstream_name, bin_name, arch) = (
+            bitstream_name.to_string(),
+            bin_name.to_string(),
+            arch.to_string(),
+        );
+        track("bitstream_to_bin", interval, async move {
+            client
+                .bitstream_to_bin(&bitstream_name, &bin_name, &arch)
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_track() {
+        let events: Vec<_> = track("op", Duration::from_millis(20), async {
+            tokio::time::sleep(Duration::from_millis(90)).await;
+            Ok(7)
+        })
+        .collect()
+        .await;
+        assert!(matches!(&events[0], OpProgress::Started { op } if op == "op"));
+        assert!(
+            events[1..events.len() - 1]
+                .iter()
+                .all(|e| matches!(e, OpProgress::Running { .. }))
+        );
+        assert!(events.len() >= 3);
+        assert!(matches!(
+            events.last(),
+            Some(OpProgress::Finished { value: 7, .. })
+        ));
+    }
+}
@@ -0,0 +1,23 @@
+//! Generated gRPC client and message types
+//!
+//! For RPCs not yet wrapped by [`JellyFpgaClient`](crate::JellyFpgaClient).
+//! Obtain the client with `raw_mut()` or `into_raw()`, or wrap an existing
+//! one with `from_raw()`. Requests sent this way bypass the wrapper's lock,
+//! strict mode, handle registry and lease session.
+
+pub use crate::jelly_fpga_control::jelly_fpga_control_client::JellyFpgaControlClient;
+pub use crate::jelly_fpga_control::*;
+
+/// Protobuf package of the generated types
+pub const PROTO_PACKAGE: &str = env!("JELLY_FPGA_PROTO_PACKAGE");
+
+/// Version (`major.minor`) of the `.proto` the types are generated from
+///
+/// Set by the build script from a `// version: major.minor` line of the
+/// `.proto`, else from a `vN` package suffix (`N.0`); protos without either
+/// count as `1.0`. Servers report theirs in
+/// [`PROTO_VERSION_KEY`](crate::capabilities::PROTO_VERSION_KEY).
+pub const PROTO_VERSION: &str = env!("JELLY_FPGA_PROTO_VERSION");
+
+/// Generated client over the crate's transport
+pub type RawClient = JellyFpgaControlClient<crate::Transport>;
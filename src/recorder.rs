@@ -0,0 +1,332 @@
+//! Periodic telemetry recorder
+//!
+//! Samples a set of registers or memory words at a fixed rate and keeps
+//! timestamped rows for offline analysis (CSV, VCD for waveform viewers,
+//! or Parquet with the `parquet` feature).
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::accessor::Accessor;
+use crate::gpio::Addressing;
+use crate::stats::{self, Histogram, Summary};
+use crate::vcd::VcdWriter;
+
+/// One recorded value
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channel {
+    /// Column name
+    pub name: String,
+    /// Offset interpretation
+    pub addressing: Addressing,
+    /// Register index or byte offset
+    pub offset: u64,
+    /// Access size in bytes
+    pub size: u64,
+}
+
+/// One row of a [`Recording`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record {
+    /// Wall-clock time since the Unix epoch
+    pub timestamp: Duration,
+    /// Time since recording started
+    pub elapsed: Duration,
+    /// Values in channel order
+    pub values: Vec<u64>,
+}
+
+/// Rows collected by [`Recorder::record`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recording {
+    /// Channel names
+    pub names: Vec<String>,
+    /// Rows in time order
+    pub records: Vec<Record>,
+}
+
+pub(crate) fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+impl Recording {
+    /// Values of channel `name` in time order
+    pub fn column(&self, name: &str) -> Option<Vec<u64>> {
+        let i = self.names.iter().position(|n| n == name)?;
+        Some(self.records.iter().map(|r| r.values[i]).collect())
+    }
+
+    /// [`Summary`] of channel `name`
+    pub fn summary(&self, name: &str) -> Option<Summary> {
+        Summary::of_u64(&self.column(name)?)
+    }
+
+    /// [`Histogram`] of channel `name` over its value range
+    pub fn histogram(&self, name: &str, bins: usize) -> Option<Histogram> {
+        let values: Vec<f64> = self.column(name)?.iter().map(|&v| v as f64).collect();
+        Some(Histogram::new(&values, bins))
+    }
+
+    /// Per-second rate of change of channel `name` between rows
+    ///
+    /// Use [`counter_rates`](Self::counter_rates) for free-running counters.
+    pub fn rates(&self, name: &str) -> Option<Vec<f64>> {
+        let values: Vec<f64> = self.column(name)?.iter().map(|&v| v as f64).collect();
+        Some(stats::rates(&self.elapsed(), &values))
+    }
+
+    /// Per-second increments of the `bits` wide counter in channel `name`
+    pub fn counter_rates(&self, name: &str, bits: u32) -> Option<Vec<f64>> {
+        Some(stats::counter_rates(
+            &self.elapsed(),
+            &self.column(name)?,
+            bits,
+        ))
+    }
+
+    fn elapsed(&self) -> Vec<Duration> {
+        self.records.iter().map(|r| r.elapsed).collect()
+    }
+
+    /// CSV with one row per sample (`timestamp_us`, `elapsed_us`, then the channels)
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("timestamp_us,elapsed_us");
+        for name in &self.names {
+            s.push(',');
+            s.push_str(&csv_field(name));
+        }
+        s.push('\n');
+        for record in &self.records {
+            let _ = write!(
+                s,
+                "{},{}",
+                record.timestamp.as_micros(),
+                record.elapsed.as_micros()
+            );
+            for v in &record.values {
+                let _ = write!(s, ",{}", v);
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Write [`to_csv`](Self::to_csv) to a file
+    pub async fn save_csv(&self, path: &str) -> Result<(), tonic::Status> {
+        crate::fs::write(path, self.to_csv().into_bytes()).await
+    }
+
+    /// VCD with one variable per channel at the elapsed time (microseconds)
+    ///
+    /// Each variable is as wide as the largest value recorded in it, so
+    /// flags show as single bits.
+    pub fn to_vcd(&self) -> String {
+        let widths: Vec<u32> = (0..self.names.len())
+            .map(|i| {
+                let max = self.records.iter().map(|r| r.values[i]).max().unwrap_or(0);
+                (64 - max.leading_zeros()).max(1)
+            })
+            .collect();
+        let signals: Vec<(&str, u32)> = self.names.iter().map(String::as_str).zip(widths).collect();
+        let mut vcd = VcdWriter::new("1us", "recording", &signals);
+        for record in &self.records {
+            vcd.sample(record.elapsed.as_micros() as u64, &record.values);
+        }
+        let end = self
+            .records
+            .last()
+            .map_or(0, |r| r.elapsed.as_micros() as u64);
+        vcd.finish(end)
+    }
+
+    /// Write [`to_vcd`](Self::to_vcd) to a file
+    pub async fn save_vcd(&self, path: &str) -> Result<(), tonic::Status> {
+        crate::fs::write(path, self.to_vcd().into_bytes()).await
+    }
+
+    /// Parquet file contents with the same columns as the CSV (all `UINT64`)
+    #[cfg(feature = "parquet")]
+    pub fn to_parquet(&self) -> Result<Vec<u8>, tonic::Status> {
+        use parquet::data_type::Int64Type;
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use std::sync::Arc;
+
+        let to_status = |e: parquet::errors::ParquetError| tonic::Status::internal(e.to_string());
+        let mut message = String::from("message recording {\n");
+        for name in ["timestamp_us", "elapsed_us"]
+            .into_iter()
+            .chain(self.names.iter().map(String::as_str))
+        {
+            let _ = writeln!(message, "  REQUIRED INT64 {} (INTEGER(64,false));", name);
+        }
+        message.push('}');
+        let schema = parquet::schema::parser::parse_message_type(&message).map_err(to_status)?;
+
+        let mut columns: Vec<Vec<i64>> = vec![
+            self.records
+                .iter()
+                .map(|r| r.timestamp.as_micros() as i64)
+                .collect(),
+            self.records
+                .iter()
+                .map(|r| r.elapsed.as_micros() as i64)
+                .collect(),
+        ];
+        for i in 0..self.names.len() {
+            // stored bit for bit; the logical type marks them unsigned
+            columns.push(self.records.iter().map(|r| r.values[i] as i64).collect());
+        }
+
+        let mut buf = Vec::new();
+        let props = Arc::new(WriterProperties::builder().build());
+        let mut writer =
+            SerializedFileWriter::new(&mut buf, Arc::new(schema), props).map_err(to_status)?;
+        let mut row_group = writer.next_row_group().map_err(to_status)?;
+        let mut values = columns.iter();
+        while let Some(mut column) = row_group.next_column().map_err(to_status)? {
+            let data = values.next().map_or(&[][..], Vec::as_slice);
+            column
+                .typed::<Int64Type>()
+                .write_batch(data, None, None)
+                .map_err(to_status)?;
+            column.close().map_err(to_status)?;
+        }
+        row_group.close().map_err(to_status)?;
+        writer.close().map_err(to_status)?;
+        Ok(buf)
+    }
+
+    /// Write [`to_parquet`](Self::to_parquet) to a file
+    #[cfg(feature = "parquet")]
+    pub async fn save_parquet(&self, path: &str) -> Result<(), tonic::Status> {
+        crate::fs::write(path, self.to_parquet()?).await
+    }
+}
+
+/// Fixed-rate sampler of registers and memory words
+pub struct Recorder {
+    regs: Accessor,
+    channels: Vec<Channel>,
+}
+
+impl Recorder {
+    /// Create recorder without channels
+    pub fn new(regs: Accessor) -> Self {
+        Recorder {
+            regs,
+            channels: Vec::new(),
+        }
+    }
+
+    /// Add a channel
+    pub fn channel(mut self, name: &str, addressing: Addressing, offset: u64, size: u64) -> Self {
+        self.channels.push(Channel {
+            name: name.to_string(),
+            addressing,
+            offset,
+            size,
+        });
+        self
+    }
+
+    /// Add a register channel (`read_reg_u`)
+    pub fn reg(self, name: &str, reg: u64, size: u64) -> Self {
+        self.channel(name, Addressing::Reg, reg, size)
+    }
+
+    /// Add a memory word channel (`read_mem_u`)
+    pub fn mem(self, name: &str, offset: u64, size: u64) -> Self {
+        self.channel(name, Addressing::Byte, offset, size)
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Channels in column order
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Read all channels once
+    pub async fn read_channels(&mut self) -> Result<Vec<u64>, tonic::Status> {
+        let mut values = Vec::with_capacity(self.channels.len());
+        for c in &self.channels {
+            values.push(match c.addressing {
+                Addressing::Byte => self.regs.read_mem_u_raw(c.offset, c.size).await?,
+                Addressing::Reg => self.regs.read_reg_u_raw(c.offset, c.size).await?,
+            });
+        }
+        Ok(values)
+    }
+
+    /// Sample every `interval` for `duration`
+    ///
+    /// Samples stay on the `interval` grid; a tick missed because the reads
+    /// took longer than `interval` is skipped rather than bunched up.
+    pub async fn record(
+        &mut self,
+        interval: Duration,
+        duration: Duration,
+    ) -> Result<Recording, tonic::Status> {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let begin = Instant::now();
+        let mut records = Vec::new();
+        loop {
+            ticker.tick().await;
+            let elapsed = begin.elapsed();
+            if elapsed > duration {
+                break;
+            }
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let values = self.read_channels().await?;
+            records.push(Record {
+                timestamp,
+                elapsed,
+                values,
+            });
+        }
+        Ok(Recording {
+            names: self.channels.iter().map(|c| c.name.clone()).collect(),
+            records,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_csv() {
+        let recording = Recording {
+            names: vec!["temp".to_string(), "a,b".to_string()],
+            records: vec![Record {
+                timestamp: Duration::from_secs(1),
+                elapsed: Duration::from_millis(2),
+                values: vec![40, 7],
+            }],
+        };
+        assert_eq!(
+            recording.to_csv(),
+            "timestamp_us,elapsed_us,temp,\"a,b\"\n1000000,2000,40,7\n"
+        );
+        assert_eq!(recording.column("temp"), Some(vec![40]));
+        assert_eq!(recording.summary("temp").unwrap().max, 40.0);
+        assert_eq!(recording.rates("temp"), Some(vec![]));
+        assert!(recording.to_vcd().contains("$var wire 6 ! temp $end\n"));
+        assert!(recording.to_vcd().ends_with("#2000\nb101000 !\nb111 \"\n"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Named address regions
+//!
+//! Application code opens peripherals by name with
+//! [`JellyFpgaClient::open_region`]; the physical addresses live in one
+//! place, either [`define_region`](JellyFpgaClient::define_region) calls or
+//! the `[[region]]` tables of a [`DeployManifest`](crate::deploy::DeployManifest).
+//! Clones share the registry.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::accessor::check;
+use crate::{Accessor, JellyFpgaClient};
+
+/// Device file mapped by regions unless configured otherwise
+pub const DEFAULT_REGION_DEVICE: &str = "/dev/mem";
+
+#[cfg(feature = "serde")]
+fn default_device() -> String {
+    DEFAULT_REGION_DEVICE.to_string()
+}
+
+#[cfg(feature = "serde")]
+fn default_unit() -> u64 {
+    8
+}
+
+/// Physical address range of a peripheral or memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    /// Name used by [`JellyFpgaClient::open_region`]
+    pub name: String,
+    /// Physical base address
+    pub addr: u64,
+    /// Size in bytes
+    pub size: u64,
+    /// Device file mapped at `addr`
+    #[cfg_attr(feature = "serde", serde(default = "default_device"))]
+    pub device: String,
+    /// Register size in bytes (the `unit` of `open_mmap`)
+    #[cfg_attr(feature = "serde", serde(default = "default_unit"))]
+    pub unit: u64,
+}
+
+impl Region {
+    /// Region of `/dev/mem` with 8-byte registers
+    pub fn new(name: &str, addr: u64, size: u64) -> Self {
+        Region {
+            name: name.to_string(),
+            addr,
+            size,
+            device: DEFAULT_REGION_DEVICE.to_string(),
+            unit: 8,
+        }
+    }
+
+    /// Set the register size
+    pub fn with_unit(mut self, unit: u64) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Map a different device file
+    pub fn with_device(mut self, device: &str) -> Self {
+        self.device = device.to_string();
+        self
+    }
+}
+
+/// Regions shared between clones of a client
+#[derive(Clone, Default)]
+pub(crate) struct RegionRegistry {
+    regions: Arc<Mutex<BTreeMap<String, Region>>>,
+}
+
+impl JellyFpgaClient {
+    /// Define (or redefine) a `/dev/mem` region with 8-byte registers
+    pub fn define_region(&mut self, name: &str, addr: u64, size: u64) {
+        self.add_region(Region::new(name, addr, size));
+    }
+
+    /// Define (or redefine) a region
+    pub fn add_region(&mut self, region: Region) {
+        self.regions
+            .regions
+            .lock()
+            .unwrap()
+            .insert(region.name.clone(), region);
+    }
+
+    /// Define all `regions`
+    pub fn add_regions<I: IntoIterator<Item = Region>>(&mut self, regions: I) {
+        let mut map = self.regions.regions.lock().unwrap();
+        for region in regions {
+            map.insert(region.name.clone(), region);
+        }
+    }
+
+    /// Look up a region
+    pub fn region(&self, name: &str) -> Option<Region> {
+        self.regions.regions.lock().unwrap().get(name).cloned()
+    }
+
+    /// All regions, ordered by name
+    pub fn regions(&self) -> Vec<Region> {
+        self.regions
+            .regions
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Map the region `name` and return an accessor for it
+    ///
+    /// Fails with `not_found` for an undefined name; `result=false` is an
+    /// error regardless of strict mode.
+    pub async fn open_region(&mut self, name: &str) -> Result<Accessor, tonic::Status> {
+        let region = self
+            .region(name)
+            .ok_or_else(|| tonic::Status::not_found(format!("unknown region {:?}", name)))?;
+        self.open_region_def(&region).await
+    }
+
+    /// Map `region` (defined or not) and return an accessor for it
+    async fn open_region_def(&mut self, region: &Region) -> Result<Accessor, tonic::Status> {
+        let (result, id) = self
+            .open_mmap(&region.device, region.addr, region.size, region.unit)
+            .await?;
+        check(result, "open_mmap")?;
+        Ok(self.accessor(id))
+    }
+
+    /// Map all `regions`, or none of them
+    ///
+    /// The regions need not be defined. If one fails, those already opened
+    /// are closed again (close errors are ignored) and the error names the
+    /// failed region, keeping the code of the underlying error.
+    pub async fn open_all(&mut self, regions: &[Region]) -> Result<Vec<Accessor>, tonic::Status> {
+        let mut accessors = Vec::with_capacity(regions.len());
+        for region in regions {
+            match self.open_region_def(region).await {
+                Ok(accessor) => accessors.push(accessor),
+                Err(e) => {
+                    for accessor in accessors.into_iter().rev() {
+                        let _ = self.close(accessor.id()).await;
+                    }
+                    let message = format!(
+                        "open_all: region {:?} (0x{:x}, {} bytes) failed: {}",
+                        region.name,
+                        region.addr,
+                        region.size,
+                        e.message()
+                    );
+                    return Err(tonic::Status::with_metadata(
+                        e.code(),
+                        message,
+                        e.metadata().clone(),
+                    ));
+                }
+            }
+        }
+        Ok(accessors)
+    }
+}
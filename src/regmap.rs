@@ -0,0 +1,493 @@
+//! Register maps and register dumps
+//!
+//! A [`RegisterMap`] names the registers (and optionally their bitfields)
+//! of an IP core. It can be built in code or parsed from a plain text file:
+//!
+//! ```text
+//! # name  reg  [size] [access]
+//! CONTROL 0x00 4
+//!   ENABLE 0 1      # field: shift width
+//!   MODE   1 3
+//! STATUS  0x04
+//! DIVIDER 0x08 4 wo # write-only; also `static` and `volatile` (default)
+//! ```
+//!
+//! The access kind tells a shadow register layer
+//! ([`Accessor::set_shadow`](crate::Accessor::set_shadow)) which reads it
+//! may serve from the last written value.
+//!
+//! With the `ipxact` feature, [`RegisterMap::parse_ipxact`] reads IP-XACT
+//! component XML instead.
+//!
+//! [`dump_regmap`] reads every register and returns a [`RegDump`], whose
+//! text form (`Display`) can be parsed back to compare with a later dump
+//! using [`RegDump::diff`].
+
+use std::fmt;
+
+use crate::addr::AccessSize;
+use crate::field::Field;
+
+/// How a register's value may change, for shadow registers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegAccess {
+    /// Hardware may change the value; reads always hit the hardware
+    #[default]
+    Volatile,
+    /// Changes only when written; the first read is cached
+    Static,
+    /// Reads return nothing useful; served from the last written value
+    WriteOnly,
+}
+
+impl RegAccess {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "volatile" | "rw" => Some(RegAccess::Volatile),
+            "static" => Some(RegAccess::Static),
+            "wo" | "write-only" => Some(RegAccess::WriteOnly),
+            _ => None,
+        }
+    }
+}
+
+/// Register definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterDef {
+    /// Register name
+    pub name: String,
+    /// Register index
+    pub reg: u64,
+    /// Access size in bytes
+    pub size: u64,
+    /// Named bitfields
+    pub fields: Vec<(String, Field)>,
+    /// Access kind
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub access: RegAccess,
+}
+
+/// Named registers of an IP core
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterMap {
+    /// Registers in definition order
+    pub registers: Vec<RegisterDef>,
+}
+
+/// Parse a decimal or `0x` hexadecimal number
+pub(crate) fn parse_num(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn invalid(line: usize, msg: &str) -> tonic::Status {
+    tonic::Status::invalid_argument(format!("line {}: {}", line + 1, msg))
+}
+
+impl RegisterMap {
+    /// Empty map
+    pub fn new() -> Self {
+        RegisterMap::default()
+    }
+
+    /// Add a register
+    pub fn register(mut self, name: &str, reg: u64, size: u64) -> Self {
+        self.registers.push(RegisterDef {
+            name: name.to_string(),
+            reg,
+            size,
+            fields: Vec::new(),
+            access: RegAccess::Volatile,
+        });
+        self
+    }
+
+    /// Set the access kind of the last register
+    pub fn access(mut self, access: RegAccess) -> Self {
+        if let Some(r) = self.registers.last_mut() {
+            r.access = access;
+        }
+        self
+    }
+
+    /// Add a bitfield `[shift, shift + width)` to the last register
+    pub fn field(mut self, name: &str, shift: u32, width: u32) -> Self {
+        if let Some(r) = self.registers.last_mut() {
+            r.fields
+                .push((name.to_string(), Field::new(r.reg, shift, width)));
+        }
+        self
+    }
+
+    /// Register by name
+    pub fn get(&self, name: &str) -> Option<&RegisterDef> {
+        self.registers.iter().find(|r| r.name == name)
+    }
+
+    /// Bitfield `field` of register `register`, for `Accessor::read_field` / `write_field`
+    pub fn lookup_field(&self, register: &str, field: &str) -> Option<&Field> {
+        self.get(register)?
+            .fields
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, f)| f)
+    }
+
+    /// Parse the text format (register size defaults to 4, access to `volatile`)
+    pub fn parse(text: &str) -> Result<Self, tonic::Status> {
+        let mut map = RegisterMap::new();
+        for (n, line) in text.lines().enumerate() {
+            let content = line.split('#').next().unwrap_or("");
+            let words: Vec<&str> = content.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            if content.starts_with(char::is_whitespace) {
+                let [name, shift, width] = words[..] else {
+                    return Err(invalid(n, "expected `field shift width`"));
+                };
+                if map.registers.is_empty() {
+                    return Err(invalid(n, "field before any register"));
+                }
+                let shift = shift.parse().map_err(|_| invalid(n, "invalid shift"))?;
+                let width: u32 = width.parse().map_err(|_| invalid(n, "invalid width"))?;
+                if width == 0 || shift + width > 64 {
+                    return Err(invalid(n, "field out of range"));
+                }
+                map = map.field(name, shift, width);
+            } else {
+                let mut words = &words[..];
+                let mut access = RegAccess::Volatile;
+                if words.len() > 2
+                    && let Some(a) = RegAccess::parse(words[words.len() - 1])
+                {
+                    access = a;
+                    words = &words[..words.len() - 1];
+                }
+                let (name, reg, size) = match *words {
+                    [name, reg] => (name, reg, "4"),
+                    [name, reg, size] => (name, reg, size),
+                    _ => return Err(invalid(n, "expected `name reg [size] [access]`")),
+                };
+                let reg = parse_num(reg).ok_or_else(|| invalid(n, "invalid register"))?;
+                let size = parse_num(size).ok_or_else(|| invalid(n, "invalid size"))?;
+                if ![1, 2, 4, 8].contains(&size) {
+                    return Err(invalid(n, "size must be 1, 2, 4 or 8"));
+                }
+                map = map.register(name, reg, size).access(access);
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Value of one register in a dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegValue {
+    /// Register name
+    pub name: String,
+    /// Register index
+    pub reg: u64,
+    /// Raw value
+    pub value: u64,
+    /// Field values
+    pub fields: Vec<(String, u64)>,
+}
+
+/// Snapshot of a register map
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegDump {
+    /// Registers in map order
+    pub registers: Vec<RegValue>,
+}
+
+impl RegDump {
+    /// Build a dump from raw values read in map order
+    pub fn from_values(map: &RegisterMap, values: &[u64]) -> Self {
+        let registers = map
+            .registers
+            .iter()
+            .zip(values)
+            .map(|(def, &value)| RegValue {
+                name: def.name.clone(),
+                reg: def.reg,
+                value,
+                fields: def
+                    .fields
+                    .iter()
+                    .map(|(name, f)| (name.clone(), f.extract(value)))
+                    .collect(),
+            })
+            .collect();
+        RegDump { registers }
+    }
+
+    /// Register by name
+    pub fn get(&self, name: &str) -> Option<&RegValue> {
+        self.registers.iter().find(|r| r.name == name)
+    }
+
+    /// Parse the `Display` output of a dump
+    pub fn parse(text: &str) -> Result<Self, tonic::Status> {
+        let mut dump = RegDump::default();
+        for (n, line) in text.lines().enumerate() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                let ([name, value], Some(r)) = (&words[..], dump.registers.last_mut()) else {
+                    return Err(invalid(n, "expected `field value`"));
+                };
+                let value = parse_num(value).ok_or_else(|| invalid(n, "invalid value"))?;
+                r.fields.push((name.to_string(), value));
+            } else {
+                let [name, reg, value] = words[..] else {
+                    return Err(invalid(n, "expected `name reg value`"));
+                };
+                dump.registers.push(RegValue {
+                    name: name.to_string(),
+                    reg: parse_num(reg).ok_or_else(|| invalid(n, "invalid register"))?,
+                    value: parse_num(value).ok_or_else(|| invalid(n, "invalid value"))?,
+                    fields: Vec::new(),
+                });
+            }
+        }
+        Ok(dump)
+    }
+
+    /// Registers and fields that differ from `self` (old) to `other` (new)
+    ///
+    /// Registers are matched by name; those present on one side only are
+    /// reported with `None` on the other.
+    pub fn diff(&self, other: &RegDump) -> RegDiff {
+        let mut changes = Vec::new();
+        for old in &self.registers {
+            match other.get(&old.name) {
+                Some(new) if new.value == old.value => {}
+                Some(new) => changes.push(RegChange {
+                    name: old.name.clone(),
+                    reg: new.reg,
+                    old: Some(old.value),
+                    new: Some(new.value),
+                    fields: diff_fields(&old.fields, &new.fields),
+                }),
+                None => changes.push(RegChange {
+                    name: old.name.clone(),
+                    reg: old.reg,
+                    old: Some(old.value),
+                    new: None,
+                    fields: Vec::new(),
+                }),
+            }
+        }
+        for new in other
+            .registers
+            .iter()
+            .filter(|r| self.get(&r.name).is_none())
+        {
+            changes.push(RegChange {
+                name: new.name.clone(),
+                reg: new.reg,
+                old: None,
+                new: Some(new.value),
+                fields: Vec::new(),
+            });
+        }
+        RegDiff { changes }
+    }
+}
+
+fn diff_fields(old: &[(String, u64)], new: &[(String, u64)]) -> Vec<FieldChange> {
+    old.iter()
+        .filter_map(|(name, old)| {
+            let (_, new) = new.iter().find(|(n, _)| n == name)?;
+            (old != new).then(|| FieldChange {
+                name: name.clone(),
+                old: *old,
+                new: *new,
+            })
+        })
+        .collect()
+}
+
+/// Changed field of a register
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldChange {
+    /// Field name
+    pub name: String,
+    /// Old value
+    pub old: u64,
+    /// New value
+    pub new: u64,
+}
+
+/// Changed register
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegChange {
+    /// Register name
+    pub name: String,
+    /// Register index
+    pub reg: u64,
+    /// Old value (`None` if the register was not in the old dump)
+    pub old: Option<u64>,
+    /// New value (`None` if the register is not in the new dump)
+    pub new: Option<u64>,
+    /// Fields whose value changed
+    pub fields: Vec<FieldChange>,
+}
+
+/// Result of [`RegDump::diff`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegDiff {
+    /// Changed registers, old dump order first
+    pub changes: Vec<RegChange>,
+}
+
+impl RegDiff {
+    /// No register changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+fn fmt_opt(value: Option<u64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("0x{:08x}", v))
+}
+
+impl fmt::Display for RegDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.changes.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for c in &self.changes {
+            writeln!(
+                f,
+                "{:<width$} 0x{:04x} {} -> {}",
+                c.name,
+                c.reg,
+                fmt_opt(c.old),
+                fmt_opt(c.new)
+            )?;
+            for field in &c.fields {
+                writeln!(
+                    f,
+                    "  {:<w$} 0x{:x} -> 0x{:x}",
+                    field.name,
+                    field.old,
+                    field.new,
+                    w = width
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RegDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .registers
+            .iter()
+            .map(|r| r.name.len())
+            .max()
+            .unwrap_or(0);
+        for r in &self.registers {
+            writeln!(f, "{:<width$} 0x{:04x} 0x{:08x}", r.name, r.reg, r.value)?;
+            for (name, value) in &r.fields {
+                writeln!(f, "  {:<w$} 0x{:x}", name, value, w = width)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read every register of `map` (concurrently per access size)
+#[cfg(not(feature = "wasm"))]
+pub async fn dump_regmap(
+    accessor: &mut crate::Accessor,
+    map: &RegisterMap,
+) -> Result<RegDump, tonic::Status> {
+    let mut values = vec![0; map.registers.len()];
+    for size in [
+        AccessSize::U8,
+        AccessSize::U16,
+        AccessSize::U32,
+        AccessSize::U64,
+    ] {
+        let (index, regs): (Vec<usize>, Vec<u64>) = map
+            .registers
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.size == size.bytes())
+            .map(|(i, r)| (i, r.reg))
+            .unzip();
+        if regs.is_empty() {
+            continue;
+        }
+        for (i, v) in index
+            .into_iter()
+            .zip(accessor.read_regs(&regs, size).await?)
+        {
+            values[i] = v;
+        }
+    }
+    Ok(RegDump::from_values(map, &values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regmap_and_dump() {
+        let map = RegisterMap::parse(
+            "# test\nCONTROL 0x00 4\n  ENABLE 0 1\n  MODE 1 3 # mode\nSTATUS 0x04\nWIDE 0x08 8\n",
+        )
+        .unwrap();
+        assert_eq!(map.registers.len(), 3);
+        assert_eq!(map.get("STATUS").unwrap().access, RegAccess::Volatile);
+        let wo = RegisterMap::parse(
+            "DIV 0x08 wo
+GAIN 0x0c 2 static
+",
+        )
+        .unwrap();
+        assert_eq!(wo.get("DIV").unwrap().access, RegAccess::WriteOnly);
+        assert_eq!(wo.get("GAIN").unwrap().size, 2);
+        assert_eq!(wo.get("GAIN").unwrap().access, RegAccess::Static);
+        assert_eq!(map.get("CONTROL").unwrap().fields[1].1, Field::new(0, 1, 3));
+        assert_eq!(map.get("WIDE").unwrap().size, 8);
+        assert!(RegisterMap::parse("  ENABLE 0 1\n").is_err());
+        assert!(RegisterMap::parse("CTRL 0x00 3\n").is_err());
+
+        let dump = RegDump::from_values(&map, &[0x0b, 0x1, 0x1234_5678_9abc]);
+        assert_eq!(
+            dump.get("CONTROL").unwrap().fields,
+            vec![("ENABLE".to_string(), 1), ("MODE".to_string(), 5)]
+        );
+        assert_eq!(RegDump::parse(&dump.to_string()).unwrap(), dump);
+
+        let after = RegDump::from_values(&map, &[0x03, 0x1, 0x1234_5678_9abc]);
+        let diff = dump.diff(&after);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].old, Some(0x0b));
+        assert_eq!(
+            diff.changes[0].fields,
+            vec![FieldChange {
+                name: "MODE".to_string(),
+                old: 5,
+                new: 1
+            }]
+        );
+        assert!(dump.diff(&dump).is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+//! Files on the board's root filesystem
+//!
+//! The server has no file RPCs, so files are read and written through a
+//! temporary `open_mmap` of the file, as the firmware manifest is. This
+//! cannot create, grow or truncate a file, nor find out its size: reads and
+//! in-place writes must stay within the existing file. Mapping past the end
+//! of a file faults in servers that do not check, so both calls need a
+//! server advertising the [`FILES_FEATURE`] capability, which refuses such
+//! mappings (`result=false`); other servers get `unimplemented` without a
+//! request being sent. Files directly in the firmware directory are the
+//! exception: [`write_remote_file`](JellyFpgaClient::write_remote_file)
+//! replaces them with `upload_firmware` on any server.
+
+use crate::JellyFpgaClient;
+use crate::firmware::FIRMWARE_DIR;
+use crate::jelly_fpga_control::{CloseRequest, MemCopyToRequest, OpenMmapRequest};
+
+/// Capability of servers whose `open_mmap` refuses to map a file past its end
+pub const FILES_FEATURE: &str = "files";
+
+/// Name of `path` if it is a file directly in the firmware directory
+fn firmware_name(path: &str) -> Option<&str> {
+    let name = path.strip_prefix(FIRMWARE_DIR)?.strip_prefix('/')?;
+    (!name.is_empty() && !name.contains('/')).then_some(name)
+}
+
+impl JellyFpgaClient {
+    /// Fail with `unimplemented` unless mapping board files is safe
+    async fn check_files_supported(&mut self) -> Result<(), tonic::Status> {
+        if self.supports(|c| c.has(FILES_FEATURE)).await? {
+            Ok(())
+        } else {
+            Err(tonic::Status::unimplemented(
+                "server does not support remote file access",
+            ))
+        }
+    }
+
+    /// Read the first `size` bytes of the board file `path`
+    ///
+    /// Fails with `not_found` if the file is missing or shorter than
+    /// `size`, and with `unimplemented` on servers without [`FILES_FEATURE`].
+    pub async fn read_remote_file(
+        &mut self,
+        path: &str,
+        size: u64,
+    ) -> Result<Vec<u8>, tonic::Status> {
+        self.check_files_supported().await?;
+        self.read_board_file(path, size)
+            .await?
+            .ok_or_else(|| tonic::Status::not_found(format!("cannot read {}", path)))
+    }
+
+    /// Write `data` to the board file `path`
+    ///
+    /// Files directly in `/lib/firmware` are replaced (created if missing,
+    /// recorded in the firmware manifest). Other files must already exist
+    /// with at least `data.len()` bytes and are overwritten in place from
+    /// the start; the rest of the file is left as it was. They need a server
+    /// with [`FILES_FEATURE`] (`unimplemented` otherwise).
+    pub async fn write_remote_file(
+        &mut self,
+        path: &str,
+        data: Vec<u8>,
+    ) -> Result<(), tonic::Status> {
+        self.lock.check_op("write_remote_file")?;
+        if let Some(name) = firmware_name(path) {
+            let result = self.upload_firmware(name, data).await?;
+            return crate::accessor::check(result, &format!("write {}", path));
+        }
+        self.check_files_supported().await?;
+        if data.is_empty() {
+            return Ok(());
+        }
+        let request = self.request(OpenMmapRequest {
+            path: path.to_string(),
+            offset: 0,
+            size: data.len() as u64,
+            unit: 1,
+        });
+        let open = self
+            .limiter
+            .run("open_mmap", self.client.open_mmap(request))
+            .await
+            .map_err(|e| crate::error::with_rpc(e, "open_mmap"))?
+            .into_inner();
+        if !open.result {
+            return Err(tonic::Status::not_found(format!("cannot open {}", path)));
+        }
+        let request = self.request(MemCopyToRequest {
+            id: open.id,
+            offset: 0,
+            data,
+        });
+        let write = self
+            .limiter
+            .run("mem_copy_to", self.bulk_client().mem_copy_to(request))
+            .await
+            .map_err(|e| crate::error::with_rpc(e, "mem_copy_to"));
+        let request = self.request(CloseRequest { id: open.id });
+        self.limiter
+            .run("close", self.client.close(request))
+            .await
+            .map_err(|e| crate::error::with_rpc(e, "close"))?;
+        crate::accessor::check(write?.into_inner().result, &format!("write {}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_firmware_name() {
+        assert_eq!(firmware_name("/lib/firmware/cal.bin"), Some("cal.bin"));
+        assert_eq!(firmware_name("/lib/firmware/sub/cal.bin"), None);
+        assert_eq!(firmware_name("/lib/firmware/"), None);
+        assert_eq!(firmware_name("/lib/firmwarex/cal.bin"), None);
+        assert_eq!(firmware_name("/etc/cal.bin"), None);
+    }
+}
@@ -0,0 +1,284 @@
+//! Structured records of multi-step operations
+//!
+//! Deployment and cleanup fill an [`OperationReport`] with one
+//! [`StepRecord`] per step, so CI can store exactly what was done and
+//! compare timings across runs.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// One executed step
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StepRecord {
+    /// Step name (e.g. `upload_bitstream`)
+    pub name: String,
+    /// Human readable description
+    pub detail: String,
+    /// Start of the step, relative to the first step of the operation
+    pub start: Duration,
+    /// Time spent in the step
+    pub duration: Duration,
+    /// Server result (`false` also for steps that failed with an error)
+    pub result: bool,
+    /// Bytes sent to or read from the board
+    pub bytes: u64,
+    /// Error message if the step failed
+    pub error: Option<String>,
+}
+
+/// Steps of an operation in completion order
+///
+/// Steps may overlap (see [`JellyFpgaClient::deploy`](crate::JellyFpgaClient::deploy));
+/// their `start` and `duration` place them on one timeline.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OperationReport {
+    /// Operation name (e.g. `deploy`)
+    pub operation: String,
+    /// Executed steps
+    pub steps: Vec<StepRecord>,
+    /// Start of the first step
+    #[cfg_attr(feature = "serde", serde(skip))]
+    origin: Option<Instant>,
+}
+
+impl OperationReport {
+    /// Empty report
+    pub fn new(operation: &str) -> Self {
+        OperationReport {
+            operation: operation.to_string(),
+            steps: Vec::new(),
+            origin: None,
+        }
+    }
+
+    /// Time `fut` and record it; `fut` yields the server result and byte count
+    ///
+    /// The error, if any, is recorded and passed through.
+    pub async fn record<T, F>(
+        &mut self,
+        name: &str,
+        detail: &str,
+        fut: F,
+    ) -> Result<T, tonic::Status>
+    where
+        F: Future<Output = Result<(T, bool, u64), tonic::Status>>,
+    {
+        let start = Instant::now();
+        let output = fut.await;
+        self.finish(name, detail, start, output)
+    }
+
+    /// Record a step that started at `start` and just ended with `output`
+    ///
+    /// Used for steps run concurrently, which cannot borrow the report.
+    pub(crate) fn finish<T>(
+        &mut self,
+        name: &str,
+        detail: &str,
+        start: Instant,
+        output: Result<(T, bool, u64), tonic::Status>,
+    ) -> Result<T, tonic::Status> {
+        let origin = *self.origin.get_or_insert(start);
+        let (result, bytes, error) = match &output {
+            Ok((_, result, bytes)) => (*result, *bytes, None),
+            Err(e) => (false, 0, Some(e.message().to_string())),
+        };
+        self.steps.push(StepRecord {
+            name: name.to_string(),
+            detail: detail.to_string(),
+            start: start.saturating_duration_since(origin),
+            duration: start.elapsed(),
+            result,
+            bytes,
+            error,
+        });
+        output.map(|(value, _, _)| value)
+    }
+
+    /// No step failed with an error
+    pub fn ok(&self) -> bool {
+        self.steps.iter().all(|s| s.error.is_none())
+    }
+
+    /// Time from the first step start to the last step end
+    ///
+    /// Less than the sum of the step durations when steps overlapped.
+    pub fn duration(&self) -> Duration {
+        self.steps
+            .iter()
+            .map(|s| s.start + s.duration)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Number of steps and their summed duration per step name
+    pub fn by_name(&self) -> BTreeMap<String, (usize, Duration)> {
+        let mut totals: BTreeMap<String, (usize, Duration)> = BTreeMap::new();
+        for step in &self.steps {
+            let total = totals.entry(step.name.clone()).or_default();
+            total.0 += 1;
+            total.1 += step.duration;
+        }
+        totals
+    }
+
+    /// Total bytes of all steps
+    pub fn bytes(&self) -> u64 {
+        self.steps.iter().map(|s| s.bytes).sum()
+    }
+
+    /// JSON with the totals and one object per step
+    pub fn to_json(&self) -> String {
+        let mut s = String::new();
+        let _ = write!(
+            s,
+            "{{\"operation\":{:?},\"ok\":{},\"duration_s\":{},\"bytes\":{},\"steps\":[",
+            self.operation,
+            self.ok(),
+            self.duration().as_secs_f64(),
+            self.bytes()
+        );
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            let _ = write!(
+                s,
+                "{{\"name\":{:?},\"detail\":{:?},\"start_s\":{},\"duration_s\":{},\"result\":{},\"bytes\":{}",
+                step.name,
+                step.detail,
+                step.start.as_secs_f64(),
+                step.duration.as_secs_f64(),
+                step.result,
+                step.bytes
+            );
+            match &step.error {
+                Some(e) => {
+                    let _ = write!(s, ",\"error\":{:?}}}", e);
+                }
+                None => s.push_str(",\"error\":null}"),
+            }
+        }
+        s.push_str("]}");
+        s
+    }
+
+    /// Write [`to_json`](Self::to_json) to a file
+    pub async fn save_json(&self, path: &str) -> Result<(), tonic::Status> {
+        crate::fs::write(path, self.to_json().into_bytes()).await
+    }
+
+    /// Trace Event Format JSON for Perfetto (ui.perfetto.dev) or `chrome://tracing`
+    ///
+    /// Each step is a complete event with its detail, bytes, result and
+    /// error as arguments. Overlapping steps go to separate tracks (one
+    /// per step running at once), so serialized phases show up as a single
+    /// busy track and concurrent ones side by side.
+    pub fn to_chrome_trace(&self) -> String {
+        let mut order: Vec<usize> = (0..self.steps.len()).collect();
+        order.sort_by_key(|&i| self.steps[i].start);
+        // end of the last step placed on each track
+        let mut tracks: Vec<Duration> = Vec::new();
+        let mut s = String::from("{\"displayTimeUnit\":\"ms\",\"traceEvents\":[");
+        for (n, i) in order.into_iter().enumerate() {
+            let step = &self.steps[i];
+            let end = step.start + step.duration;
+            let track = match tracks.iter().position(|&busy| busy <= step.start) {
+                Some(track) => {
+                    tracks[track] = end;
+                    track
+                }
+                None => {
+                    tracks.push(end);
+                    tracks.len() - 1
+                }
+            };
+            if n > 0 {
+                s.push(',');
+            }
+            let _ = write!(
+                s,
+                "{{\"name\":{:?},\"cat\":{:?},\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{},\"dur\":{},\"args\":{{\"detail\":{:?},\"bytes\":{},\"result\":{}",
+                step.name,
+                self.operation,
+                track + 1,
+                step.start.as_micros(),
+                step.duration.as_micros(),
+                step.detail,
+                step.bytes,
+                step.result
+            );
+            if let Some(e) = &step.error {
+                let _ = write!(s, ",\"error\":{:?}", e);
+            }
+            s.push_str("}}");
+        }
+        s.push_str("]}");
+        s
+    }
+
+    /// Write [`to_chrome_trace`](Self::to_chrome_trace) to a file
+    pub async fn save_chrome_trace(&self, path: &str) -> Result<(), tonic::Status> {
+        crate::fs::write(path, self.to_chrome_trace().into_bytes()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record() {
+        let mut report = OperationReport::new("deploy");
+        let value = report
+            .record("upload", "upload a", async { Ok((7, true, 100)) })
+            .await
+            .unwrap();
+        assert_eq!(value, 7);
+        let failed: Result<(), _> = report
+            .record("load", "load a", async {
+                Err(tonic::Status::internal("load failed"))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert!(!report.ok());
+        assert_eq!(report.bytes(), 100);
+        assert_eq!(report.steps[1].error.as_deref(), Some("load failed"));
+        assert!(
+            report
+                .to_json()
+                .starts_with("{\"operation\":\"deploy\",\"ok\":false,")
+        );
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let step = |name: &str, start: u64, duration: u64| StepRecord {
+            name: name.to_string(),
+            detail: String::new(),
+            start: Duration::from_millis(start),
+            duration: Duration::from_millis(duration),
+            result: true,
+            bytes: 0,
+            error: None,
+        };
+        let mut report = OperationReport::new("calls");
+        // completion order: the second upload overlaps the first
+        report.steps = vec![
+            step("upload_firmware", 0, 30),
+            step("upload_firmware", 10, 40),
+            step("load", 50, 5),
+        ];
+        let trace = report.to_chrome_trace();
+        assert!(trace.starts_with("{\"displayTimeUnit\":\"ms\",\"traceEvents\":["));
+        assert!(trace.contains("\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":0,\"dur\":30000,"));
+        assert!(trace.contains("\"tid\":2,\"ts\":10000,"));
+        assert!(trace.contains(
+            "\"name\":\"load\",\"cat\":\"calls\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":50000,"
+        ));
+    }
+}
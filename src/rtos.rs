@@ -0,0 +1,402 @@
+//! jelly RTOS control interface (`jelly2_rtos`)
+//!
+//! The jelly RTOS core schedules tasks in hardware and exposes its
+//! services as registers: the register index is `opcode << id_width | id`,
+//! a read returns the state of object `id` and a write issues the service
+//! call with the written value as its parameter. [`JellyRtos`] uses that
+//! interface from the host to inspect tasks, semaphores and event flags of
+//! firmware running in the PL and to wake tasks, signal semaphores or set
+//! flags, e.g. to find out why a task hangs.
+//!
+//! Opcodes and information ids can differ between RTL versions;
+//! [`RtosLayout::jelly2`] holds the defaults and every field can be
+//! changed to match the parameters of the build. Task states use the
+//! μITRON `TTS_*` encoding. Object ids start at 1.
+
+use std::fmt;
+
+use crate::accessor::Accessor;
+use crate::addr::AccessSize;
+
+/// Task state `TTS_RUN`
+pub const TTS_RUN: u64 = 0x01;
+/// Task state `TTS_RDY`
+pub const TTS_RDY: u64 = 0x02;
+/// Task state `TTS_WAI`
+pub const TTS_WAI: u64 = 0x04;
+/// Task state `TTS_SUS`
+pub const TTS_SUS: u64 = 0x08;
+/// Task state `TTS_WAS`
+pub const TTS_WAS: u64 = 0x0c;
+/// Task state `TTS_DMT`
+pub const TTS_DMT: u64 = 0x10;
+
+/// Register layout of the RTOS core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RtosLayout {
+    /// Bits of the object id in the register index
+    pub id_width: u32,
+    /// Opcode of the core information registers (the id selects the item)
+    pub ref_inf: u64,
+    /// Information id: core ID
+    pub inf_core_id: u64,
+    /// Information id: core version
+    pub inf_version: u64,
+    /// Information id: highest task id
+    pub inf_tmax_tskid: u64,
+    /// Information id: highest semaphore id
+    pub inf_tmax_semid: u64,
+    /// Information id: highest event flag id
+    pub inf_tmax_flgid: u64,
+    /// Information id: running task id (0 when idle)
+    pub inf_run_tskid: u64,
+    /// Opcode reading a task state (`TTS_*`)
+    pub ref_tsk_sts: u64,
+    /// Opcode reading a task priority
+    pub ref_tsk_pri: u64,
+    /// Opcode reading a semaphore count
+    pub ref_sem_cnt: u64,
+    /// Opcode reading an event flag pattern
+    pub ref_flg_ptn: u64,
+    /// Opcode `wup_tsk`
+    pub wup_tsk: u64,
+    /// Opcode `rel_wai`
+    pub rel_wai: u64,
+    /// Opcode `chg_pri` (the value is the new priority)
+    pub chg_pri: u64,
+    /// Opcode `sig_sem`
+    pub sig_sem: u64,
+    /// Opcode `set_flg` (the value is ORed into the pattern)
+    pub set_flg: u64,
+    /// Opcode `clr_flg` (the value is ANDed with the pattern)
+    pub clr_flg: u64,
+}
+
+impl RtosLayout {
+    /// Defaults of `jelly2_rtos`
+    pub fn jelly2() -> Self {
+        RtosLayout {
+            id_width: 8,
+            ref_inf: 0x00,
+            inf_core_id: 0x00,
+            inf_version: 0x01,
+            inf_tmax_tskid: 0x10,
+            inf_tmax_semid: 0x11,
+            inf_tmax_flgid: 0x12,
+            inf_run_tskid: 0x20,
+            ref_tsk_sts: 0x80,
+            ref_tsk_pri: 0x81,
+            ref_sem_cnt: 0x88,
+            ref_flg_ptn: 0x90,
+            wup_tsk: 0x10,
+            rel_wai: 0x12,
+            chg_pri: 0x13,
+            sig_sem: 0x21,
+            set_flg: 0x31,
+            clr_flg: 0x32,
+        }
+    }
+
+    /// Register index of `opcode` for object `id`
+    pub fn reg(&self, opcode: u64, id: u64) -> Result<u64, tonic::Status> {
+        if self.id_width < 64 && id >> self.id_width != 0 {
+            return Err(tonic::Status::invalid_argument(format!(
+                "RTOS object id {} exceeds {} bits",
+                id, self.id_width
+            )));
+        }
+        Ok((opcode << self.id_width) | id)
+    }
+}
+
+impl Default for RtosLayout {
+    fn default() -> Self {
+        RtosLayout::jelly2()
+    }
+}
+
+/// Task state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TaskState {
+    /// Running
+    Running,
+    /// Ready to run
+    Ready,
+    /// Waiting (sleep, semaphore, flag or delay)
+    Waiting,
+    /// Suspended
+    Suspended,
+    /// Waiting and suspended
+    WaitingSuspended,
+    /// Not started
+    Dormant,
+    /// Value outside the `TTS_*` encoding
+    Unknown(u64),
+}
+
+impl TaskState {
+    /// Decode a `TTS_*` value
+    pub fn from_raw(raw: u64) -> Self {
+        match raw {
+            TTS_RUN => TaskState::Running,
+            TTS_RDY => TaskState::Ready,
+            TTS_WAI => TaskState::Waiting,
+            TTS_SUS => TaskState::Suspended,
+            TTS_WAS => TaskState::WaitingSuspended,
+            TTS_DMT => TaskState::Dormant,
+            raw => TaskState::Unknown(raw),
+        }
+    }
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskState::Running => write!(f, "running"),
+            TaskState::Ready => write!(f, "ready"),
+            TaskState::Waiting => write!(f, "waiting"),
+            TaskState::Suspended => write!(f, "suspended"),
+            TaskState::WaitingSuspended => write!(f, "waiting-suspended"),
+            TaskState::Dormant => write!(f, "dormant"),
+            TaskState::Unknown(raw) => write!(f, "unknown(0x{:x})", raw),
+        }
+    }
+}
+
+/// State of one task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaskInfo {
+    /// Task id
+    pub id: u64,
+    /// State
+    pub state: TaskState,
+    /// Current priority
+    pub priority: u64,
+}
+
+/// State of all RTOS objects at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RtosSnapshot {
+    /// Running task, `None` when idle
+    pub running: Option<u64>,
+    /// Tasks by id
+    pub tasks: Vec<TaskInfo>,
+    /// Semaphore (id, count)
+    pub semaphores: Vec<(u64, u64)>,
+    /// Event flag (id, pattern)
+    pub flags: Vec<(u64, u64)>,
+}
+
+impl fmt::Display for RtosSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.running {
+            Some(id) => writeln!(f, "running task {}", id)?,
+            None => writeln!(f, "idle")?,
+        }
+        for t in &self.tasks {
+            writeln!(f, "task {:3} pri {:3} {}", t.id, t.priority, t.state)?;
+        }
+        for (id, count) in &self.semaphores {
+            writeln!(f, "sem  {:3} count {}", id, count)?;
+        }
+        for (id, pattern) in &self.flags {
+            writeln!(f, "flg  {:3} 0x{:x}", id, pattern)?;
+        }
+        Ok(())
+    }
+}
+
+/// jelly RTOS core
+pub struct JellyRtos {
+    regs: Accessor,
+    layout: RtosLayout,
+    reg_size: AccessSize,
+}
+
+impl JellyRtos {
+    /// Driver for the core at `regs` (register units, 32-bit registers)
+    pub fn new(regs: Accessor, layout: RtosLayout) -> Self {
+        JellyRtos {
+            regs,
+            layout,
+            reg_size: AccessSize::U32,
+        }
+    }
+
+    /// Set register access size (default `U32`)
+    pub fn set_reg_size(&mut self, size: AccessSize) {
+        self.reg_size = size;
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Register layout
+    pub fn layout(&self) -> &RtosLayout {
+        &self.layout
+    }
+
+    async fn read(&mut self, opcode: u64, id: u64) -> Result<u64, tonic::Status> {
+        let reg = self.layout.reg(opcode, id)?;
+        self.regs.read_reg_u(reg, self.reg_size).await
+    }
+
+    async fn write(&mut self, opcode: u64, id: u64, value: u64) -> Result<(), tonic::Status> {
+        let reg = self.layout.reg(opcode, id)?;
+        self.regs.write_reg_u(reg, value, self.reg_size).await
+    }
+
+    /// Read core information item `inf`
+    pub async fn info(&mut self, inf: u64) -> Result<u64, tonic::Status> {
+        self.read(self.layout.ref_inf, inf).await
+    }
+
+    /// Read core ID
+    pub async fn core_id(&mut self) -> Result<u64, tonic::Status> {
+        self.info(self.layout.inf_core_id).await
+    }
+
+    /// Read core version
+    pub async fn core_version(&mut self) -> Result<u64, tonic::Status> {
+        self.info(self.layout.inf_version).await
+    }
+
+    /// Highest task id
+    pub async fn max_task_id(&mut self) -> Result<u64, tonic::Status> {
+        self.info(self.layout.inf_tmax_tskid).await
+    }
+
+    /// Highest semaphore id
+    pub async fn max_semaphore_id(&mut self) -> Result<u64, tonic::Status> {
+        self.info(self.layout.inf_tmax_semid).await
+    }
+
+    /// Highest event flag id
+    pub async fn max_flag_id(&mut self) -> Result<u64, tonic::Status> {
+        self.info(self.layout.inf_tmax_flgid).await
+    }
+
+    /// Running task, `None` when idle
+    pub async fn running_task(&mut self) -> Result<Option<u64>, tonic::Status> {
+        let id = self.info(self.layout.inf_run_tskid).await?;
+        Ok((id != 0).then_some(id))
+    }
+
+    /// State and priority of task `id`
+    pub async fn task(&mut self, id: u64) -> Result<TaskInfo, tonic::Status> {
+        let state = TaskState::from_raw(self.read(self.layout.ref_tsk_sts, id).await?);
+        let priority = self.read(self.layout.ref_tsk_pri, id).await?;
+        Ok(TaskInfo {
+            id,
+            state,
+            priority,
+        })
+    }
+
+    /// All tasks
+    pub async fn tasks(&mut self) -> Result<Vec<TaskInfo>, tonic::Status> {
+        let mut tasks = Vec::new();
+        for id in 1..=self.max_task_id().await? {
+            tasks.push(self.task(id).await?);
+        }
+        Ok(tasks)
+    }
+
+    /// Count of semaphore `id`
+    pub async fn semaphore_count(&mut self, id: u64) -> Result<u64, tonic::Status> {
+        self.read(self.layout.ref_sem_cnt, id).await
+    }
+
+    /// Pattern of event flag `id`
+    pub async fn flag_pattern(&mut self, id: u64) -> Result<u64, tonic::Status> {
+        self.read(self.layout.ref_flg_ptn, id).await
+    }
+
+    /// Read every task, semaphore and event flag
+    ///
+    /// The registers are read one after another while the RTOS runs, so
+    /// the snapshot is not atomic.
+    pub async fn snapshot(&mut self) -> Result<RtosSnapshot, tonic::Status> {
+        let running = self.running_task().await?;
+        let tasks = self.tasks().await?;
+        let mut semaphores = Vec::new();
+        for id in 1..=self.max_semaphore_id().await? {
+            semaphores.push((id, self.semaphore_count(id).await?));
+        }
+        let mut flags = Vec::new();
+        for id in 1..=self.max_flag_id().await? {
+            flags.push((id, self.flag_pattern(id).await?));
+        }
+        Ok(RtosSnapshot {
+            running,
+            tasks,
+            semaphores,
+            flags,
+        })
+    }
+
+    /// Wake up task `id` (`wup_tsk`)
+    pub async fn wakeup_task(&mut self, id: u64) -> Result<(), tonic::Status> {
+        self.write(self.layout.wup_tsk, id, 0).await
+    }
+
+    /// Release task `id` from waiting (`rel_wai`)
+    pub async fn release_wait(&mut self, id: u64) -> Result<(), tonic::Status> {
+        self.write(self.layout.rel_wai, id, 0).await
+    }
+
+    /// Change the priority of task `id` (`chg_pri`)
+    pub async fn change_priority(&mut self, id: u64, priority: u64) -> Result<(), tonic::Status> {
+        self.write(self.layout.chg_pri, id, priority).await
+    }
+
+    /// Signal semaphore `id` (`sig_sem`)
+    pub async fn signal_semaphore(&mut self, id: u64) -> Result<(), tonic::Status> {
+        self.write(self.layout.sig_sem, id, 0).await
+    }
+
+    /// Set `bits` of event flag `id` (`set_flg`)
+    pub async fn set_flag(&mut self, id: u64, bits: u64) -> Result<(), tonic::Status> {
+        self.write(self.layout.set_flg, id, bits).await
+    }
+
+    /// Clear `bits` of event flag `id` (`clr_flg` with the inverted bits)
+    pub async fn clear_flag(&mut self, id: u64, bits: u64) -> Result<(), tonic::Status> {
+        let keep = !bits & self.reg_size.mask();
+        self.write(self.layout.clr_flg, id, keep).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtos_layout() {
+        let layout = RtosLayout::jelly2();
+        assert_eq!(layout.reg(layout.sig_sem, 3).unwrap(), 0x2103);
+        assert!(layout.reg(layout.sig_sem, 0x100).is_err());
+        assert_eq!(TaskState::from_raw(TTS_WAI), TaskState::Waiting);
+        assert_eq!(TaskState::from_raw(0x40), TaskState::Unknown(0x40));
+        let snapshot = RtosSnapshot {
+            running: Some(1),
+            tasks: vec![TaskInfo {
+                id: 1,
+                state: TaskState::Running,
+                priority: 2,
+            }],
+            semaphores: vec![(1, 0)],
+            flags: vec![(1, 0x5)],
+        };
+        assert_eq!(
+            snapshot.to_string(),
+            "running task 1\ntask   1 pri   2 running\nsem    1 count 0\nflg    1 0x5\n"
+        );
+    }
+}
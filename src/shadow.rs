@@ -0,0 +1,107 @@
+//! Shadow registers
+//!
+//! Cache behind [`Accessor::set_shadow`](crate::Accessor::set_shadow):
+//! register reads of [`RegAccess::WriteOnly`] registers return the last
+//! written value and those of [`RegAccess::Static`] registers are read from
+//! the hardware once; [`RegAccess::Volatile`] registers and registers not
+//! in the map always hit the hardware. Values are kept in host order per
+//! register index and access size.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::regmap::{RegAccess, RegisterMap};
+
+pub(crate) struct Shadow {
+    access: BTreeMap<u64, RegAccess>,
+    values: Mutex<BTreeMap<(u64, u64), u64>>,
+}
+
+impl Shadow {
+    pub(crate) fn new(map: &RegisterMap) -> Self {
+        Shadow {
+            access: map
+                .registers
+                .iter()
+                .filter(|r| r.access != RegAccess::Volatile)
+                .map(|r| (r.reg, r.access))
+                .collect(),
+            values: Mutex::default(),
+        }
+    }
+
+    /// Cached value of a read, `None` if the hardware must be read
+    ///
+    /// Fails for a write-only register that was not written yet.
+    pub(crate) fn read(&self, reg: u64, size: u64) -> Result<Option<u64>, tonic::Status> {
+        let Some(&access) = self.access.get(&reg) else {
+            return Ok(None);
+        };
+        let cached = self.values.lock().unwrap().get(&(reg, size)).copied();
+        match (access, cached) {
+            (RegAccess::WriteOnly, None) => Err(tonic::Status::failed_precondition(format!(
+                "write-only register 0x{:x} has not been written",
+                reg
+            ))),
+            _ => Ok(cached),
+        }
+    }
+
+    /// Record a value read from the hardware
+    pub(crate) fn fetched(&self, reg: u64, size: u64, value: u64) {
+        if self.access.get(&reg) == Some(&RegAccess::Static) {
+            self.values.lock().unwrap().insert((reg, size), value);
+        }
+    }
+
+    /// Record a value written to the hardware
+    pub(crate) fn written(&self, reg: u64, size: u64, value: u64) {
+        if self.access.contains_key(&reg) {
+            let mut values = self.values.lock().unwrap();
+            values.retain(|&(r, _), _| r != reg);
+            values.insert((reg, size), value);
+        }
+    }
+
+    pub(crate) fn get(&self, reg: u64) -> Option<u64> {
+        let values = self.values.lock().unwrap();
+        values
+            .iter()
+            .find(|((r, _), _)| *r == reg)
+            .map(|(_, value)| *value)
+    }
+
+    pub(crate) fn invalidate(&self) {
+        self.values.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow() {
+        let map = RegisterMap::new()
+            .register("CTRL", 0, 4)
+            .register("DIV", 1, 4)
+            .access(RegAccess::WriteOnly)
+            .register("GAIN", 2, 4)
+            .access(RegAccess::Static);
+        let shadow = Shadow::new(&map);
+        shadow.written(0, 4, 5);
+        assert_eq!(shadow.read(0, 4).unwrap(), None);
+        assert!(shadow.read(1, 4).is_err());
+        shadow.written(1, 4, 7);
+        assert_eq!(shadow.read(1, 4).unwrap(), Some(7));
+        assert_eq!(shadow.read(2, 4).unwrap(), None);
+        shadow.fetched(2, 4, 9);
+        assert_eq!(shadow.read(2, 4).unwrap(), Some(9));
+        assert_eq!(shadow.read(2, 2).unwrap(), None);
+        shadow.written(2, 2, 3);
+        assert_eq!(shadow.read(2, 4).unwrap(), None);
+        assert_eq!(shadow.get(2), Some(3));
+        shadow.invalidate();
+        assert!(shadow.read(1, 4).is_err());
+    }
+}
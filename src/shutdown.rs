@@ -0,0 +1,100 @@
+//! Graceful shutdown
+//!
+//! [`JellyFpgaClient::shutdown`] undoes what the client set up on the board
+//! and stops its background tasks (lease keepalive, event health checks),
+//! so a long-running service can exit without leaving handles, the board
+//! lock or a session behind.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::task::AbortHandle;
+
+use crate::JellyFpgaClient;
+use crate::report::OperationReport;
+
+/// Background tasks of a client and its clones
+#[derive(Default)]
+pub(crate) struct Tasks {
+    handles: Mutex<Vec<AbortHandle>>,
+}
+
+impl Tasks {
+    /// Stop `task` on shutdown
+    pub(crate) fn register(&self, task: AbortHandle) {
+        let mut handles = self.handles.lock().unwrap();
+        handles.retain(|h| !h.is_finished());
+        handles.push(task);
+    }
+
+    fn abort_all(&self) {
+        for task in self.handles.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl JellyFpgaClient {
+    /// Stop background tasks, close tracked handles and release the lock and lease
+    ///
+    /// Steps:
+    /// 1. abort the lease keepalive and event health check; event streams end
+    /// 2. close every handle in [`list_open_handles`](Self::list_open_handles)
+    /// 3. release the board lock if held
+    /// 4. send a keepalive with a zero TTL so a lease-aware server releases
+    ///    the session at once
+    ///
+    /// Every step runs even if an earlier one failed; the report lists them
+    /// and the first failure is returned after the last step. Operations
+    /// still running on clones are not waited for, so await them first. The
+    /// connection closes once the last clone is dropped.
+    pub async fn shutdown(mut self) -> Result<OperationReport, tonic::Status> {
+        let mut report = OperationReport::new("shutdown");
+        let mut first_error = None;
+        self.tasks.abort_all();
+        self.events.close();
+        // a non-strict clone, so handles closed by the server already are not errors
+        let mut client = self.clone();
+        client.set_strict(false);
+        for id in self.handles.list().into_iter().rev() {
+            let closed = report
+                .record("close", &format!("close id {}", id), async {
+                    client.close(id).await.map(|result| ((), result, 0))
+                })
+                .await;
+            if let Err(e) = closed {
+                first_error.get_or_insert(e);
+            }
+        }
+        if self.holds_lock() {
+            let released = report
+                .record("release_lock", "release the board lock", async {
+                    self.release_lock().await.map(|_| ((), true, 0))
+                })
+                .await;
+            if let Err(e) = released {
+                first_error.get_or_insert(e);
+            }
+        }
+        if let Some(session) = self.session.clone() {
+            let released = report
+                .record(
+                    "release_lease",
+                    &format!("release session {}", session),
+                    async {
+                        self.lease_keepalive(Duration::ZERO)
+                            .await
+                            .map(|acknowledged| ((), acknowledged, 0))
+                    },
+                )
+                .await;
+            if let Err(e) = released {
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(report),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Memory soak test
+//!
+//! [`JellyFpgaClient::soak_test`] writes and verifies test [`Pattern`]s over
+//! a region for a fixed time while a [`Recorder`] samples telemetry (e.g.
+//! the die temperature of an XADC/SYSMON block) alongside, for burn-in and
+//! signal-integrity checks of new boards. Verify errors are counted per
+//! pass in the [`SoakReport`]; only failed RPCs end the test early.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::JellyFpgaClient;
+use crate::pattern::{Pattern, PatternCheck};
+use crate::recorder::{Recorder, Recording};
+
+/// What to stress and for how long
+pub struct SoakConfig {
+    id: u32,
+    offset: u64,
+    len: u64,
+    duration: Duration,
+    patterns: Vec<Pattern>,
+    telemetry: Option<(Recorder, Duration)>,
+}
+
+impl SoakConfig {
+    /// Soak `len` bytes from byte `offset` of `id` for one minute
+    ///
+    /// The default patterns are a ramp, PRBS and the two checkerboards.
+    pub fn new(id: u32, offset: u64, len: u64) -> Self {
+        SoakConfig {
+            id,
+            offset,
+            len,
+            duration: Duration::from_secs(60),
+            patterns: vec![
+                Pattern::Ramp,
+                Pattern::Prbs(1),
+                Pattern::Const(0x5555_5555),
+                Pattern::Const(0xaaaa_aaaa),
+            ],
+            telemetry: None,
+        }
+    }
+
+    /// Stop starting new passes after `duration`
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Cycle through `patterns`, one per pass
+    ///
+    /// `Prbs(seed)` is reseeded with `seed + pass` so consecutive PRBS
+    /// passes write different data.
+    pub fn with_patterns(mut self, patterns: Vec<Pattern>) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Sample the channels of `recorder` every `interval` during the test
+    pub fn with_telemetry(mut self, recorder: Recorder, interval: Duration) -> Self {
+        self.telemetry = Some((recorder, interval));
+        self
+    }
+}
+
+/// One write/verify pass over the region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoakPass {
+    /// Pattern written
+    pub pattern: Pattern,
+    /// Start of the pass relative to the start of the test
+    pub start: Duration,
+    /// Time spent writing
+    pub write_time: Duration,
+    /// Time spent reading back and comparing
+    pub check_time: Duration,
+    /// Verify result
+    pub check: PatternCheck,
+}
+
+/// Result of [`soak_test`](JellyFpgaClient::soak_test)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SoakReport {
+    /// Bytes per pass
+    pub len: u64,
+    /// Wall time of the test
+    pub duration: Duration,
+    /// Passes in order
+    pub passes: Vec<SoakPass>,
+    /// Telemetry samples, if a recorder was configured
+    pub telemetry: Option<Recording>,
+}
+
+impl SoakReport {
+    /// Mismatching words over all passes
+    pub fn errors(&self) -> u64 {
+        self.passes.iter().map(|p| p.check.errors).sum()
+    }
+
+    /// Whether every pass verified without errors
+    pub fn is_ok(&self) -> bool {
+        self.errors() == 0
+    }
+
+    /// Average write and read throughput in bytes per second
+    pub fn throughput(&self) -> (f64, f64) {
+        let rate = |time: Duration| {
+            let secs = time.as_secs_f64();
+            if secs > 0.0 {
+                (self.len * self.passes.len() as u64) as f64 / secs
+            } else {
+                0.0
+            }
+        };
+        (
+            rate(self.passes.iter().map(|p| p.write_time).sum()),
+            rate(self.passes.iter().map(|p| p.check_time).sum()),
+        )
+    }
+}
+
+impl fmt::Display for SoakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (write, read) = self.throughput();
+        writeln!(
+            f,
+            "soak: {} passes of {} bytes in {:.1} s, {} errors, write {:.1} MB/s, read {:.1} MB/s",
+            self.passes.len(),
+            self.len,
+            self.duration.as_secs_f64(),
+            self.errors(),
+            write / 1e6,
+            read / 1e6
+        )?;
+        for (i, pass) in self.passes.iter().enumerate() {
+            if !pass.check.is_ok() {
+                writeln!(f, "  pass {} {:?}: {}", i, pass.pattern, pass.check)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl JellyFpgaClient {
+    /// Write and verify the configured patterns until the duration is over
+    ///
+    /// At least one pass runs. The telemetry recorder, if any, runs
+    /// concurrently for the same duration.
+    pub async fn soak_test(&mut self, config: SoakConfig) -> Result<SoakReport, tonic::Status> {
+        if config.patterns.is_empty() {
+            return Err(tonic::Status::invalid_argument(
+                "soak test without patterns",
+            ));
+        }
+        let SoakConfig {
+            id,
+            offset,
+            len,
+            duration,
+            patterns,
+            telemetry,
+        } = config;
+        let begin = Instant::now();
+        let stress = async {
+            let mut passes = Vec::new();
+            while passes.is_empty() || begin.elapsed() < duration {
+                let pattern = match patterns[passes.len() % patterns.len()] {
+                    Pattern::Prbs(seed) => Pattern::Prbs(seed.wrapping_add(passes.len() as u32)),
+                    pattern => pattern,
+                };
+                let start = begin.elapsed();
+                self.mem_write_pattern(id, offset, len, pattern).await?;
+                let written = begin.elapsed();
+                let check = self.mem_check_pattern(id, offset, len, pattern).await?;
+                passes.push(SoakPass {
+                    pattern,
+                    start,
+                    write_time: written - start,
+                    check_time: begin.elapsed() - written,
+                    check,
+                });
+            }
+            Ok(passes)
+        };
+        let (passes, telemetry) = match telemetry {
+            Some((mut recorder, interval)) => {
+                let (passes, recording) =
+                    tokio::try_join!(stress, recorder.record(interval, duration))?;
+                (passes, Some(recording))
+            }
+            None => (stress.await?, None),
+        };
+        Ok(SoakReport {
+            len,
+            duration: begin.elapsed(),
+            passes,
+            telemetry,
+        })
+    }
+}
@@ -0,0 +1,237 @@
+//! Soft-core CPU (MicroBlaze / RISC-V) program loader
+//!
+//! The CPU is held in reset through a control register, its instruction
+//! memory is written with `mem_copy_to` and the reset is released.
+
+use crate::accessor::Accessor;
+use crate::addr::AccessSize;
+use crate::framebuffer::DEFAULT_CHUNK_SIZE;
+
+const PT_LOAD: u32 = 1;
+
+/// Loadable segment of an ELF file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElfSegment {
+    /// Physical load address
+    pub addr: u64,
+    /// File contents
+    pub data: Vec<u8>,
+    /// Size in memory (the tail beyond `data` is zero filled)
+    pub mem_size: u64,
+}
+
+/// Loadable contents of an ELF file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElfImage {
+    /// Entry point
+    pub entry: u64,
+    /// PT_LOAD segments
+    pub segments: Vec<ElfSegment>,
+}
+
+/// Parse the PT_LOAD segments of an ELF32/ELF64 file (either endianness)
+pub fn parse_elf(elf: &[u8]) -> Result<ElfImage, String> {
+    if elf.len() < 0x34 || &elf[0..4] != b"\x7fELF" {
+        return Err("not an ELF file".to_string());
+    }
+    let is64 = match elf[4] {
+        1 => false,
+        2 => true,
+        c => return Err(format!("unknown ELF class {}", c)),
+    };
+    let be = match elf[5] {
+        1 => false,
+        2 => true,
+        d => return Err(format!("unknown ELF data encoding {}", d)),
+    };
+    let rd = |off: usize, len: usize| -> Result<u64, String> {
+        let bytes = elf
+            .get(off..off + len)
+            .ok_or_else(|| "truncated ELF file".to_string())?;
+        let mut v = 0u64;
+        for i in 0..len {
+            let b = if be { bytes[i] } else { bytes[len - 1 - i] };
+            v = (v << 8) | b as u64;
+        }
+        Ok(v)
+    };
+
+    let (entry, phoff, phentsize, phnum) = if is64 {
+        (rd(0x18, 8)?, rd(0x20, 8)?, rd(0x36, 2)?, rd(0x38, 2)?)
+    } else {
+        (rd(0x18, 4)?, rd(0x1c, 4)?, rd(0x2a, 2)?, rd(0x2c, 2)?)
+    };
+
+    let mut segments = Vec::new();
+    for i in 0..phnum {
+        let ph = (phoff + i * phentsize) as usize;
+        let (p_type, offset, paddr, filesz, memsz) = if is64 {
+            (
+                rd(ph, 4)?,
+                rd(ph + 0x08, 8)?,
+                rd(ph + 0x18, 8)?,
+                rd(ph + 0x20, 8)?,
+                rd(ph + 0x28, 8)?,
+            )
+        } else {
+            (
+                rd(ph, 4)?,
+                rd(ph + 0x04, 4)?,
+                rd(ph + 0x0c, 4)?,
+                rd(ph + 0x10, 4)?,
+                rd(ph + 0x14, 4)?,
+            )
+        };
+        if p_type != PT_LOAD as u64 || memsz == 0 {
+            continue;
+        }
+        let data = elf
+            .get(offset as usize..(offset + filesz) as usize)
+            .ok_or_else(|| format!("segment {} exceeds file size", i))?
+            .to_vec();
+        segments.push(ElfSegment {
+            addr: paddr,
+            data,
+            mem_size: memsz,
+        });
+    }
+    Ok(ElfImage { entry, segments })
+}
+
+/// Soft-core CPU with a reset control register and instruction memory
+pub struct SoftCore {
+    ctrl: Accessor,
+    ctrl_reg: u64,
+    reset_value: u64,
+    run_value: u64,
+    mem: Accessor,
+    mem_base: u64,
+}
+
+impl SoftCore {
+    /// Create loader
+    ///
+    /// `ctrl_reg` is written with `reset_value` to hold the core and with
+    /// `run_value` to release it. `mem_base` is the CPU address that maps to
+    /// offset 0 of `mem`.
+    pub fn new(
+        ctrl: Accessor,
+        ctrl_reg: u64,
+        reset_value: u64,
+        run_value: u64,
+        mem: Accessor,
+        mem_base: u64,
+    ) -> Self {
+        SoftCore {
+            ctrl,
+            ctrl_reg,
+            reset_value,
+            run_value,
+            mem,
+            mem_base,
+        }
+    }
+
+    /// Hold the CPU in reset
+    pub async fn halt(&mut self) -> Result<(), tonic::Status> {
+        self.ctrl
+            .write_reg_u(self.ctrl_reg, self.reset_value, AccessSize::U32)
+            .await
+    }
+
+    /// Release the CPU from reset
+    pub async fn run(&mut self) -> Result<(), tonic::Status> {
+        self.ctrl
+            .write_reg_u(self.ctrl_reg, self.run_value, AccessSize::U32)
+            .await
+    }
+
+    /// Pulse reset
+    pub async fn reset(&mut self) -> Result<(), tonic::Status> {
+        self.halt().await?;
+        self.run().await
+    }
+
+    /// Write a raw binary at CPU address `addr`
+    pub async fn load_binary(&mut self, addr: u64, data: &[u8]) -> Result<(), tonic::Status> {
+        let offset = addr.checked_sub(self.mem_base).ok_or_else(|| {
+            tonic::Status::out_of_range(format!(
+                "address 0x{:x} below memory base 0x{:x}",
+                addr, self.mem_base
+            ))
+        })?;
+        self.mem
+            .write_bytes_chunked(offset, data, DEFAULT_CHUNK_SIZE)
+            .await
+    }
+
+    /// Write all loadable segments of an ELF image and return the entry point
+    pub async fn load_elf(&mut self, elf: &[u8]) -> Result<u64, tonic::Status> {
+        let image = parse_elf(elf).map_err(tonic::Status::invalid_argument)?;
+        for seg in &image.segments {
+            let mut data = seg.data.clone();
+            data.resize(seg.mem_size as usize, 0);
+            self.load_binary(seg.addr, &data).await?;
+        }
+        Ok(image.entry)
+    }
+
+    /// Halt, load an ELF file, and release the CPU
+    pub async fn boot_elf_file(&mut self, path: &str) -> Result<u64, tonic::Status> {
+        let elf = crate::fs::read(path).await?;
+        self.halt().await?;
+        let entry = self.load_elf(&elf).await?;
+        self.run().await?;
+        Ok(entry)
+    }
+
+    /// Halt, load a raw binary at `addr`, and release the CPU
+    pub async fn boot_binary(&mut self, addr: u64, data: &[u8]) -> Result<(), tonic::Status> {
+        self.halt().await?;
+        self.load_binary(addr, data).await?;
+        self.run().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_elf32_le() {
+        // ELF32 header + one program header + 4 bytes payload
+        let mut elf = vec![0u8; 0x34 + 0x20 + 4];
+        elf[0..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 1; // ELFCLASS32
+        elf[5] = 1; // little endian
+        elf[0x18..0x1c].copy_from_slice(&0x100u32.to_le_bytes()); // e_entry
+        elf[0x1c..0x20].copy_from_slice(&0x34u32.to_le_bytes()); // e_phoff
+        elf[0x2a..0x2c].copy_from_slice(&0x20u16.to_le_bytes()); // e_phentsize
+        elf[0x2c..0x2e].copy_from_slice(&1u16.to_le_bytes()); // e_phnum
+        let ph = 0x34;
+        elf[ph..ph + 4].copy_from_slice(&PT_LOAD.to_le_bytes());
+        elf[ph + 0x04..ph + 0x08].copy_from_slice(&0x54u32.to_le_bytes()); // p_offset
+        elf[ph + 0x0c..ph + 0x10].copy_from_slice(&0x100u32.to_le_bytes()); // p_paddr
+        elf[ph + 0x10..ph + 0x14].copy_from_slice(&4u32.to_le_bytes()); // p_filesz
+        elf[ph + 0x14..ph + 0x18].copy_from_slice(&8u32.to_le_bytes()); // p_memsz
+        elf[0x54..0x58].copy_from_slice(&[1, 2, 3, 4]);
+
+        let image = parse_elf(&elf).unwrap();
+        assert_eq!(image.entry, 0x100);
+        assert_eq!(
+            image.segments,
+            vec![ElfSegment {
+                addr: 0x100,
+                data: vec![1, 2, 3, 4],
+                mem_size: 8,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_elf_rejects_garbage() {
+        assert!(parse_elf(&[0u8; 64]).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Xilinx AXI Quad SPI driver (standard mode, controller)
+
+use std::time::Duration;
+
+use crate::accessor::Accessor;
+
+/// Software reset
+pub const REG_SRR: u64 = 0x40;
+/// Control
+pub const REG_SPICR: u64 = 0x60;
+/// Status
+pub const REG_SPISR: u64 = 0x64;
+/// Transmit data
+pub const REG_SPI_DTR: u64 = 0x68;
+/// Receive data
+pub const REG_SPI_DRR: u64 = 0x6c;
+/// Slave select
+pub const REG_SPISSR: u64 = 0x70;
+
+/// SRR reset key
+pub const SRR_KEY: u32 = 0x0a;
+/// SPICR: enable
+pub const SPICR_SPE: u32 = 1 << 1;
+/// SPICR: controller mode
+pub const SPICR_MASTER: u32 = 1 << 2;
+/// SPICR: clock polarity
+pub const SPICR_CPOL: u32 = 1 << 3;
+/// SPICR: clock phase
+pub const SPICR_CPHA: u32 = 1 << 4;
+/// SPICR: transmit FIFO reset
+pub const SPICR_TX_FIFO_RESET: u32 = 1 << 5;
+/// SPICR: receive FIFO reset
+pub const SPICR_RX_FIFO_RESET: u32 = 1 << 6;
+/// SPICR: manual slave select
+pub const SPICR_MANUAL_SS: u32 = 1 << 7;
+/// SPICR: inhibit transfers
+pub const SPICR_MASTER_INHIBIT: u32 = 1 << 8;
+/// SPICR: LSB first
+pub const SPICR_LSB_FIRST: u32 = 1 << 9;
+/// SPISR: receive FIFO empty
+pub const SPISR_RX_EMPTY: u32 = 1 << 0;
+/// SPISR: transmit FIFO empty
+pub const SPISR_TX_EMPTY: u32 = 1 << 2;
+
+/// AXI Quad SPI controller with manual chip select
+#[derive(Clone)]
+pub struct AxiQuadSpi {
+    regs: Accessor,
+    cs: u32,
+    mode: u32,
+    timeout: Duration,
+}
+
+impl AxiQuadSpi {
+    /// Create driver for chip select `cs`
+    pub fn new(regs: Accessor, cs: u32) -> Self {
+        AxiQuadSpi {
+            regs,
+            cs,
+            mode: 0,
+            timeout: Duration::from_millis(100),
+        }
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Set clock polarity/phase; applied by `init`
+    pub fn set_mode(&mut self, cpol: bool, cpha: bool) {
+        self.mode = if cpol { SPICR_CPOL } else { 0 } | if cpha { SPICR_CPHA } else { 0 };
+    }
+
+    fn control(&self) -> u32 {
+        SPICR_SPE | SPICR_MASTER | SPICR_MANUAL_SS | self.mode
+    }
+
+    /// Reset and configure the controller
+    pub async fn init(&mut self) -> Result<(), tonic::Status> {
+        self.regs.write_mem_u32(REG_SRR, SRR_KEY).await?;
+        self.regs.write_mem_u32(REG_SPISSR, !0).await?;
+        let cr = self.control() | SPICR_MASTER_INHIBIT | SPICR_TX_FIFO_RESET | SPICR_RX_FIFO_RESET;
+        self.regs.write_mem_u32(REG_SPICR, cr).await
+    }
+
+    /// Assert chip select
+    pub async fn select(&mut self) -> Result<(), tonic::Status> {
+        self.regs.write_mem_u32(REG_SPISSR, !(1 << self.cs)).await
+    }
+
+    /// Deassert chip select
+    pub async fn deselect(&mut self) -> Result<(), tonic::Status> {
+        self.regs.write_mem_u32(REG_SPISSR, !0).await
+    }
+
+    /// Exchange one byte
+    pub async fn transfer_byte(&mut self, byte: u8) -> Result<u8, tonic::Status> {
+        self.regs.write_mem_u32(REG_SPI_DTR, byte as u32).await?;
+        self.regs.write_mem_u32(REG_SPICR, self.control()).await?;
+        self.regs
+            .wait_mem_u32(
+                REG_SPISR,
+                SPISR_RX_EMPTY,
+                0,
+                Duration::from_micros(10),
+                self.timeout,
+            )
+            .await?;
+        let data = self.regs.read_mem_u32(REG_SPI_DRR).await? as u8;
+        self.regs
+            .write_mem_u32(REG_SPICR, self.control() | SPICR_MASTER_INHIBIT)
+            .await?;
+        Ok(data)
+    }
+
+    /// Full duplex transfer in place (chip select must already be asserted)
+    pub async fn transfer_in_place(&mut self, buf: &mut [u8]) -> Result<(), tonic::Status> {
+        for b in buf.iter_mut() {
+            *b = self.transfer_byte(*b).await?;
+        }
+        Ok(())
+    }
+
+    /// Select, transfer `buf` in place, deselect
+    pub async fn transfer(&mut self, buf: &mut [u8]) -> Result<(), tonic::Status> {
+        self.select().await?;
+        let result = self.transfer_in_place(buf).await;
+        self.deselect().await?;
+        result
+    }
+}
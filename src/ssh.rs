@@ -0,0 +1,239 @@
+//! gRPC connection through an SSH jump host
+//!
+//! Lab boards are often only reachable from a gateway machine, which
+//! otherwise means setting up `ssh -L` by hand before connecting.
+//! [`JellyFpgaClient::connect_via_ssh`] logs in to the jump host and carries
+//! the gRPC connection over an SSH `direct-tcpip` channel to the board
+//! address as seen from the jump host; when the connection drops, the next
+//! RPC opens a new channel on the same SSH session. Authentication is by
+//! private key file, and the jump host key must be listed in
+//! `~/.ssh/known_hosts` unless [`SshTunnel::accept_unknown_host`] is set
+//! (`ssh-tunnel` feature).
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use russh::client;
+use russh::keys::{PrivateKeyWithHashAlg, ssh_key};
+
+use crate::{JellyFpgaClient, raw};
+
+/// Default SSH port
+pub const DEFAULT_SSH_PORT: u16 = 22;
+
+/// Jump host login
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshTunnel {
+    /// Jump host name or address
+    pub host: String,
+    /// SSH port
+    pub port: u16,
+    /// User name
+    pub user: String,
+    /// Private key file (`~/` is expanded)
+    pub key: PathBuf,
+    /// Passphrase of an encrypted key
+    pub passphrase: Option<String>,
+    /// Accept a host key missing from `~/.ssh/known_hosts` (a changed key is always rejected)
+    pub accept_unknown_host: bool,
+}
+
+impl SshTunnel {
+    /// Jump host `[user@]host[:port]` with the private key file `key`
+    ///
+    /// The user defaults to `$USER`.
+    pub fn new(jump: &str, key: impl Into<PathBuf>) -> Self {
+        let (user, host) = match jump.split_once('@') {
+            Some((user, host)) => (user.to_string(), host),
+            None => (std::env::var("USER").unwrap_or_default(), jump),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            // an IPv6 address needs brackets to carry a port
+            Some((name, port)) if !name.contains(':') || name.ends_with(']') => {
+                match port.parse() {
+                    Ok(port) => (name, port),
+                    Err(_) => (host, DEFAULT_SSH_PORT),
+                }
+            }
+            _ => (host, DEFAULT_SSH_PORT),
+        };
+        SshTunnel {
+            host: host.trim_matches(['[', ']']).to_string(),
+            port,
+            user,
+            key: key.into(),
+            passphrase: None,
+            accept_unknown_host: false,
+        }
+    }
+
+    /// Set the key passphrase
+    pub fn with_passphrase(mut self, passphrase: &str) -> Self {
+        self.passphrase = Some(passphrase.to_string());
+        self
+    }
+
+    /// Accept a jump host key that is not in `~/.ssh/known_hosts`
+    pub fn with_accept_unknown_host(mut self, accept: bool) -> Self {
+        self.accept_unknown_host = accept;
+        self
+    }
+
+    /// Log in to the jump host
+    async fn login(&self) -> Result<client::Handle<HostCheck>, tonic::Status> {
+        let key = russh::keys::load_secret_key(expand_home(&self.key), self.passphrase.as_deref())
+            .map_err(|e| {
+                tonic::Status::failed_precondition(format!(
+                    "cannot load ssh key {}: {}",
+                    self.key.display(),
+                    e
+                ))
+            })?;
+        let check = HostCheck {
+            host: self.host.clone(),
+            port: self.port,
+            accept_unknown: self.accept_unknown_host,
+        };
+        let config = Arc::new(client::Config::default());
+        let mut session = client::connect(config, (self.host.as_str(), self.port), check)
+            .await
+            .map_err(|e| match e {
+                russh::Error::UnknownKey => tonic::Status::unavailable(format!(
+                    "ssh {}:{}: host key unknown or changed (see ~/.ssh/known_hosts)",
+                    self.host, self.port
+                )),
+                e => tonic::Status::unavailable(format!("ssh {}:{}: {}", self.host, self.port, e)),
+            })?;
+        let hash = session
+            .best_supported_rsa_hash()
+            .await
+            .map_err(|e| tonic::Status::unavailable(format!("ssh {}: {}", self.host, e)))?
+            .flatten();
+        let auth = session
+            .authenticate_publickey(
+                self.user.as_str(),
+                PrivateKeyWithHashAlg::new(Arc::new(key), hash),
+            )
+            .await
+            .map_err(|e| tonic::Status::unavailable(format!("ssh {}: {}", self.host, e)))?;
+        if !auth.success() {
+            return Err(tonic::Status::unauthenticated(format!(
+                "ssh key {} not accepted for {}@{}",
+                self.key.display(),
+                self.user,
+                self.host
+            )));
+        }
+        Ok(session)
+    }
+}
+
+/// `path` with a leading `~/` replaced by `$HOME`
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Host key verification against `~/.ssh/known_hosts`
+struct HostCheck {
+    host: String,
+    port: u16,
+    accept_unknown: bool,
+}
+
+impl client::Handler for HostCheck {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, key: &ssh_key::PublicKey) -> Result<bool, Self::Error> {
+        // Ok(false) = unknown host, Err = key changed (or unreadable file)
+        match russh::keys::check_known_hosts(&self.host, self.port, key) {
+            Ok(known) => Ok(known || self.accept_unknown),
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+impl JellyFpgaClient {
+    /// Connect to `dst` (as reachable from the jump host) through an SSH tunnel
+    ///
+    /// TLS and HTTP/2 settings of `dst` apply end to end as with
+    /// [`connect`](Self::connect). The SSH session lasts as long as the
+    /// client or any of its clones. Fails with `unavailable` if the jump
+    /// host or the board cannot be reached and `unauthenticated` if the key
+    /// is refused.
+    pub async fn connect_via_ssh<D>(dst: D, tunnel: &SshTunnel) -> Result<Self, tonic::Status>
+    where
+        D: std::convert::TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let endpoint = tonic::transport::Endpoint::new(dst)
+            .map_err(|e| tonic::Status::invalid_argument(format!("invalid address: {}", e)))?;
+        let uri = endpoint.uri();
+        let host = uri
+            .host()
+            .unwrap_or_default()
+            .trim_matches(['[', ']'])
+            .to_string();
+        let port = uri
+            .port_u16()
+            .unwrap_or(if uri.scheme_str() == Some("https") {
+                443
+            } else {
+                80
+            });
+        let session = Arc::new(tunnel.login().await?);
+
+        let connector = tower::service_fn(move |_: tonic::transport::Uri| {
+            let session = session.clone();
+            let host = host.clone();
+            async move {
+                let channel = session
+                    .channel_open_direct_tcpip(host, u32::from(port), "127.0.0.1", 0)
+                    .await?;
+                Ok::<_, russh::Error>(hyper_util::rt::TokioIo::new(channel.into_stream()))
+            }
+        });
+        let channel = endpoint
+            .connect_with_connector(connector)
+            .await
+            .map_err(|e| {
+                tonic::Status::unavailable(format!(
+                    "{} via ssh {}: {}",
+                    endpoint.uri(),
+                    tunnel.host,
+                    e
+                ))
+            })?;
+        let mut client = Self::from_raw(raw::JellyFpgaControlClient::new(channel));
+        let _ = client.capabilities().await;
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssh_tunnel_new() {
+        let tunnel = SshTunnel::new("lab@jump.example.com:2222", "~/.ssh/id_ed25519");
+        assert_eq!(
+            (tunnel.user.as_str(), tunnel.host.as_str(), tunnel.port),
+            ("lab", "jump.example.com", 2222)
+        );
+        let tunnel = SshTunnel::new("lab@[fe80::1]", "key");
+        assert_eq!(
+            (tunnel.host.as_str(), tunnel.port),
+            ("fe80::1", DEFAULT_SSH_PORT)
+        );
+        let tunnel = SshTunnel::new("lab@[fe80::1]:2200", "key");
+        assert_eq!((tunnel.host.as_str(), tunnel.port), ("fe80::1", 2200));
+        let tunnel = SshTunnel::new("lab@2001:db8::1:2", "key");
+        assert_eq!(
+            (tunnel.host.as_str(), tunnel.port),
+            ("2001:db8::1:2", DEFAULT_SSH_PORT)
+        );
+    }
+}
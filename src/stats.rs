@@ -0,0 +1,206 @@
+//! Statistics over sampled register data
+//!
+//! Summaries (min/max/mean/standard deviation/percentiles), histograms and
+//! rates of change of a series of samples, such as a column of a
+//! [`Recording`](crate::recorder::Recording), so latency counters or FIFO
+//! occupancy can go into a test report without exporting the data first.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Percentile `p` (0..=100) of `sorted` values, interpolated between neighbours
+///
+/// `sorted` must be in ascending order; an empty slice gives NaN.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = p.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+/// Summary statistics of a series
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Summary {
+    /// Number of values
+    pub count: usize,
+    /// Minimum
+    pub min: f64,
+    /// Maximum
+    pub max: f64,
+    /// Arithmetic mean
+    pub mean: f64,
+    /// Population standard deviation
+    pub std_dev: f64,
+    /// Median
+    pub p50: f64,
+    /// 90th percentile
+    pub p90: f64,
+    /// 99th percentile
+    pub p99: f64,
+}
+
+impl Summary {
+    /// Summary of `values`, `None` if empty
+    pub fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+        Some(Summary {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean,
+            std_dev: variance.sqrt(),
+            p50: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            p99: percentile(&sorted, 99.0),
+        })
+    }
+
+    /// Summary of raw register values
+    pub fn of_u64(values: &[u64]) -> Option<Self> {
+        let values: Vec<f64> = values.iter().map(|&v| v as f64).collect();
+        Summary::of(&values)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} min={} mean={:.3} max={} sd={:.3} p50={} p90={} p99={}",
+            self.count, self.min, self.mean, self.max, self.std_dev, self.p50, self.p90, self.p99
+        )
+    }
+}
+
+/// Equal-width histogram
+///
+/// Prints one line per bin with its range, count and a bar of up to 40 `#`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Histogram {
+    /// Lower edge of the first bin
+    pub start: f64,
+    /// Bin width
+    pub bin_width: f64,
+    /// Values per bin
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// `bins` bins spanning the minimum to the maximum of `values`
+    pub fn new(values: &[f64], bins: usize) -> Self {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if min > max {
+            return Histogram::with_range(values, 0.0, 1.0, bins);
+        }
+        Histogram::with_range(values, min, max, bins)
+    }
+
+    /// `bins` bins spanning `[start, end]`; values outside go to the first or last bin
+    pub fn with_range(values: &[f64], start: f64, end: f64, bins: usize) -> Self {
+        let bins = bins.max(1);
+        let bin_width = if end > start {
+            (end - start) / bins as f64
+        } else {
+            1.0
+        };
+        let mut counts = vec![0; bins];
+        for &v in values {
+            let bin = ((v - start) / bin_width).floor().max(0.0) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+        Histogram {
+            start,
+            bin_width,
+            counts,
+        }
+    }
+
+    /// Lower edge of bin `i`
+    pub fn bin_start(&self, i: usize) -> f64 {
+        self.start + self.bin_width * i as f64
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let peak = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        for (i, &count) in self.counts.iter().enumerate() {
+            let bar = "#".repeat((count * 40).div_ceil(peak) as usize);
+            writeln!(
+                f,
+                "[{:>10.3}, {:>10.3}) {:>8} {}",
+                self.bin_start(i),
+                self.bin_start(i + 1),
+                count,
+                bar
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-second rate of change between consecutive samples (e.g. FIFO fill rate)
+///
+/// Intervals of zero duration are skipped.
+pub fn rates(times: &[Duration], values: &[f64]) -> Vec<f64> {
+    times
+        .windows(2)
+        .zip(values.windows(2))
+        .filter_map(|(t, v)| {
+            let dt = t[1].saturating_sub(t[0]).as_secs_f64();
+            (dt > 0.0).then(|| (v[1] - v[0]) / dt)
+        })
+        .collect()
+}
+
+/// [`rates`] of a free-running `bits` wide counter, allowing for wrap-around
+pub fn counter_rates(times: &[Duration], values: &[u64], bits: u32) -> Vec<f64> {
+    let mask = if bits >= 64 { !0 } else { (1u64 << bits) - 1 };
+    times
+        .windows(2)
+        .zip(values.windows(2))
+        .filter_map(|(t, v)| {
+            let dt = t[1].saturating_sub(t[0]).as_secs_f64();
+            (dt > 0.0).then(|| (v[1].wrapping_sub(v[0]) & mask) as f64 / dt)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&values, 50.0), 5.5);
+        assert_eq!(percentile(&values, 100.0), 10.0);
+        let summary = Summary::of(&values).unwrap();
+        assert_eq!((summary.min, summary.max, summary.mean), (1.0, 10.0, 5.5));
+        assert!((summary.p90 - 9.1).abs() < 1e-9);
+        assert!(Summary::of(&[]).is_none());
+
+        let histogram = Histogram::new(&values, 3);
+        assert_eq!(histogram.counts, vec![3, 3, 4]);
+        assert_eq!(histogram.bin_start(1), 4.0);
+
+        let times: Vec<Duration> = (0..3).map(|i| Duration::from_millis(500 * i)).collect();
+        assert_eq!(rates(&times, &[10.0, 12.0, 9.0]), vec![4.0, -6.0]);
+        assert_eq!(
+            counter_rates(&times, &[0xfe, 0x02, 0x04], 8),
+            vec![8.0, 4.0]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Operation timing
+//!
+//! [`JellyFpgaClient::timed`] measures one block of client calls;
+//! [`with_call_timing`](JellyFpgaClient::with_call_timing) records every
+//! RPC (name, start, duration, error) into an [`OperationReport`] named
+//! `calls`, so scripts can report how long loads, uploads and transfers
+//! took without their own `Instant` bookkeeping.
+//! [`OperationReport::by_name`] sums the records per RPC, and
+//! [`OperationReport::to_chrome_trace`] turns them into a timeline for
+//! Perfetto that shows which calls ran one after another and which
+//! overlapped.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::JellyFpgaClient;
+use crate::report::OperationReport;
+
+/// Call records shared by a client and its clones
+pub(crate) struct CallLog {
+    report: Mutex<OperationReport>,
+}
+
+impl CallLog {
+    /// Record the RPC `rpc` started at `start` that just ended with `output`
+    ///
+    /// Bytes are not known at this level and recorded as 0; `result` is
+    /// whether a response arrived.
+    pub(crate) fn record<T>(&self, rpc: &str, start: Instant, output: &Result<T, tonic::Status>) {
+        let output = match output {
+            Ok(_) => Ok(((), true, 0)),
+            Err(e) => Err(e.clone()),
+        };
+        let _ = self.report.lock().unwrap().finish(rpc, "", start, output);
+    }
+}
+
+impl JellyFpgaClient {
+    /// Run `f` with this client and return its output with the elapsed time
+    ///
+    /// E.g. `client.timed(async |c| c.load("design").await).await`.
+    pub async fn timed<T, F>(&mut self, f: F) -> (T, Duration)
+    where
+        F: AsyncFnOnce(&mut JellyFpgaClient) -> T,
+    {
+        let start = Instant::now();
+        let output = f(self).await;
+        (output, start.elapsed())
+    }
+
+    /// Record every RPC of this client and of clones made afterwards
+    ///
+    /// Calls through [`raw_mut`](Self::raw_mut) are not recorded. The log
+    /// grows with every call; [`take_call_report`](Self::take_call_report)
+    /// empties it.
+    pub fn with_call_timing(mut self) -> Self {
+        Arc::make_mut(&mut self.limiter).calls = Some(Arc::new(CallLog {
+            report: Mutex::new(OperationReport::new("calls")),
+        }));
+        self
+    }
+
+    /// Calls recorded so far, if call timing is enabled
+    pub fn call_report(&self) -> Option<OperationReport> {
+        let calls = self.limiter.calls.as_ref()?;
+        Some(calls.report.lock().unwrap().clone())
+    }
+
+    /// Return the recorded calls and start a new log
+    pub fn take_call_report(&self) -> Option<OperationReport> {
+        let calls = self.limiter.calls.as_ref()?;
+        Some(std::mem::replace(
+            &mut *calls.report.lock().unwrap(),
+            OperationReport::new("calls"),
+        ))
+    }
+}
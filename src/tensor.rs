@@ -0,0 +1,101 @@
+//! `ndarray` interop for device buffers (feature `ndarray`)
+//!
+//! Elements are stored in host byte order. On the device an array is laid
+//! out row-major unless explicit element strides are given.
+
+use bytemuck::Pod;
+use ndarray::{ArrayBase, ArrayD, ArrayView, Data, Dimension, IxDyn, ShapeBuilder};
+
+use crate::accessor::Accessor;
+use crate::addr::RegOffset;
+use crate::framebuffer::DEFAULT_CHUNK_SIZE;
+
+fn shape_error(e: ndarray::ShapeError) -> tonic::Status {
+    tonic::Status::invalid_argument(format!("invalid array shape: {}", e))
+}
+
+fn from_bytes<T: Pod>(data: &[u8]) -> Vec<T> {
+    // the buffer from the RPC is not necessarily aligned for T
+    data.chunks_exact(std::mem::size_of::<T>())
+        .map(bytemuck::pod_read_unaligned)
+        .collect()
+}
+
+impl Accessor {
+    /// Write `array` at `offset` in row-major order
+    ///
+    /// Views with any memory layout (transposed, sliced) are accepted; they
+    /// are gathered into row-major order first.
+    pub async fn write_array<T, S, D>(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        array: &ArrayBase<S, D>,
+    ) -> Result<(), tonic::Status>
+    where
+        T: Pod,
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        let gathered;
+        let elements = match array.as_slice() {
+            Some(slice) => slice,
+            None => {
+                gathered = array.iter().copied().collect::<Vec<T>>();
+                &gathered
+            }
+        };
+        self.write_bytes_chunked(offset, bytemuck::cast_slice(elements), DEFAULT_CHUNK_SIZE)
+            .await
+    }
+
+    /// Read a row-major array of `shape` from `offset`
+    pub async fn read_array<T: Pod>(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        shape: &[usize],
+    ) -> Result<ArrayD<T>, tonic::Status> {
+        let len: usize = shape.iter().product();
+        let size = (len * std::mem::size_of::<T>()) as u64;
+        let data = self
+            .read_bytes_chunked(offset, size, DEFAULT_CHUNK_SIZE)
+            .await?;
+        ArrayD::from_shape_vec(IxDyn(shape), from_bytes(&data)).map_err(shape_error)
+    }
+
+    /// Read an array of `shape` laid out with element `strides` on the device
+    ///
+    /// Useful for padded rows or a sub-region of a larger buffer: only the
+    /// span covering the addressed elements is read, and the result is an
+    /// owned array in standard layout.
+    pub async fn read_array_strided<T: Pod>(
+        &mut self,
+        offset: impl Into<RegOffset>,
+        shape: &[usize],
+        strides: &[usize],
+    ) -> Result<ArrayD<T>, tonic::Status> {
+        if shape.len() != strides.len() {
+            return Err(tonic::Status::invalid_argument(format!(
+                "shape has {} dimensions but strides have {}",
+                shape.len(),
+                strides.len()
+            )));
+        }
+        let span = if shape.contains(&0) {
+            0
+        } else {
+            1 + shape
+                .iter()
+                .zip(strides)
+                .map(|(n, s)| (n - 1) * s)
+                .sum::<usize>()
+        };
+        let size = (span * std::mem::size_of::<T>()) as u64;
+        let data = self
+            .read_bytes_chunked(offset, size, DEFAULT_CHUNK_SIZE)
+            .await?;
+        let elements = from_bytes::<T>(&data);
+        let view = ArrayView::from_shape(IxDyn(shape).strides(IxDyn(strides)), &elements)
+            .map_err(shape_error)?;
+        Ok(view.to_owned())
+    }
+}
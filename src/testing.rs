@@ -0,0 +1,184 @@
+//! Harness for board-attached integration tests
+//!
+//! [`TestSetup`] connects to the board named by the `JELLY_FPGA_TARGET`
+//! environment variable, optionally deploys a [`DeployManifest`] and opens
+//! its regions; [`TestSetup::run`] hands the resulting [`TestContext`] to
+//! the test body and cleans up afterwards even if the body fails or
+//! panics:
+//!
+//! 1. remove the deployment's firmware (`cleanup_deployment`), if any
+//! 2. then load the default firmware again (unless `without_restore`)
+//! 3. [`shutdown`](JellyFpgaClient::shutdown) the client, closing handles
+//!
+//! Without `JELLY_FPGA_TARGET` the test is skipped (`run` returns `Ok`
+//! without calling the body), so hardware tests can live next to unit
+//! tests and only run where a board is attached.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+
+use crate::JellyFpgaClient;
+use crate::accessor::Accessor;
+use crate::deploy::DeployManifest;
+use crate::report::OperationReport;
+
+/// Environment variable holding the server address
+pub const TARGET_ENV: &str = "JELLY_FPGA_TARGET";
+
+/// How to prepare the board for a test
+#[derive(Debug, Clone, Default)]
+pub struct TestSetup {
+    target: Option<String>,
+    manifest: Option<DeployManifest>,
+    no_restore: bool,
+}
+
+impl TestSetup {
+    /// Setup connecting to `$JELLY_FPGA_TARGET`
+    pub fn new() -> Self {
+        TestSetup {
+            target: std::env::var(TARGET_ENV).ok().filter(|t| !t.is_empty()),
+            ..Default::default()
+        }
+    }
+
+    /// Connect to `target` regardless of the environment
+    pub fn with_target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// Deploy `manifest` before the test and remove it afterwards
+    pub fn with_manifest(mut self, manifest: DeployManifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Leave the board without firmware after removing the deployment
+    pub fn without_restore(mut self) -> Self {
+        self.no_restore = true;
+        self
+    }
+
+    /// Connect and deploy; `None` if no target is configured
+    ///
+    /// The caller is responsible for [`TestContext::finish`]; prefer
+    /// [`run`](Self::run), which also cleans up after a panic.
+    pub async fn start(self) -> Result<Option<TestContext>, tonic::Status> {
+        let Some(target) = self.target else {
+            return Ok(None);
+        };
+        let mut client = JellyFpgaClient::connect(target.clone())
+            .await
+            .map_err(|e| tonic::Status::unavailable(format!("connect {}: {}", target, e)))?;
+        client.set_strict(true);
+        let mut context = TestContext {
+            client,
+            accessors: BTreeMap::new(),
+            manifest: self.manifest,
+            restore: !self.no_restore,
+        };
+        if let Err(e) = context.prepare().await {
+            // best effort; the deploy error is the one worth reporting
+            let _ = context.finish().await;
+            return Err(e);
+        }
+        Ok(Some(context))
+    }
+
+    /// Run `test` on a prepared board and clean up afterwards
+    ///
+    /// The body runs as its own task on a clone of the context, so a panic
+    /// is caught, the board cleaned up, and the panic resumed. Otherwise
+    /// the body's error, or else the first cleanup error, is returned.
+    pub async fn run<F, Fut>(self, test: F) -> Result<(), tonic::Status>
+    where
+        F: FnOnce(TestContext) -> Fut,
+        Fut: Future<Output = Result<(), tonic::Status>> + Send + 'static,
+    {
+        let Some(context) = self.start().await? else {
+            eprintln!("{} not set, skipping hardware test", TARGET_ENV);
+            return Ok(());
+        };
+        let outcome = tokio::spawn(test(context.clone())).await;
+        let cleanup = context.finish().await;
+        match outcome {
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => Err(tonic::Status::aborted(format!("test task: {}", e))),
+            Ok(Err(e)) => Err(e),
+            Ok(Ok(())) => cleanup.map(|_| ()),
+        }
+    }
+}
+
+/// Connected client with the deployment's regions opened
+#[derive(Clone)]
+pub struct TestContext {
+    client: JellyFpgaClient,
+    accessors: BTreeMap<String, Accessor>,
+    manifest: Option<DeployManifest>,
+    restore: bool,
+}
+
+impl TestContext {
+    async fn prepare(&mut self) -> Result<(), tonic::Status> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(());
+        };
+        self.client.deploy(manifest).await?;
+        for region in &manifest.regions {
+            let accessor = self.client.open_region(&region.name).await?;
+            self.accessors.insert(region.name.clone(), accessor);
+        }
+        Ok(())
+    }
+
+    /// Client (strict mode, shared with the accessors)
+    pub fn client(&mut self) -> &mut JellyFpgaClient {
+        &mut self.client
+    }
+
+    /// Accessor of the manifest region `name`
+    ///
+    /// Panics with the known names if there is no such region, as a test
+    /// assertion would.
+    pub fn accessor(&self, name: &str) -> Accessor {
+        match self.accessors.get(name) {
+            Some(accessor) => accessor.clone(),
+            None => panic!(
+                "no region {:?} in the test manifest (have {:?})",
+                name,
+                self.accessors.keys().collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    /// Names of the opened regions
+    pub fn regions(&self) -> Vec<String> {
+        self.accessors.keys().cloned().collect()
+    }
+
+    /// Remove the deployment, restore the default firmware and shut the client down
+    ///
+    /// Every step runs; the first error is returned.
+    pub async fn finish(self) -> Result<OperationReport, tonic::Status> {
+        let mut client = self.client;
+        let mut first_error = None;
+        if let Some(manifest) = &self.manifest {
+            if let Err(e) = client.cleanup_deployment(manifest.deployment()).await {
+                first_error.get_or_insert(e);
+            }
+            if self.restore
+                && let Err(e) = client.restore_default(None).await
+            {
+                first_error.get_or_insert(e);
+            }
+        }
+        drop(self.accessors);
+        let report = client.shutdown().await;
+        match (first_error, report) {
+            (Some(e), _) | (None, Err(e)) => Err(e),
+            (None, Ok(report)) => Ok(report),
+        }
+    }
+}
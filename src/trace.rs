@@ -0,0 +1,371 @@
+//! Trace buffer capture (ILA-style, without JTAG)
+//!
+//! For designs with a simple capture core: a ring buffer in BRAM that
+//! records one sample per clock once armed, stops a configurable number of
+//! samples after the trigger condition `(probe & mask) == value` and
+//! reports where in the ring the trigger sample is. [`TraceBuffer`] arms
+//! the trigger through the control registers ([`TraceRegs`]), waits for
+//! completion by polling the status register (or an interrupt status
+//! register, cleared by writing the mask back), reads the ring with
+//! `mem_copy_from` and decodes the samples with a [`TraceLayout`] into a
+//! [`TraceCapture`], which can be written as a VCD or CSV file.
+
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::accessor::Accessor;
+use crate::addr::AccessSize;
+use crate::framebuffer::DEFAULT_CHUNK_SIZE;
+use crate::recorder::csv_field;
+use crate::vcd::VcdWriter;
+
+/// One signal in a sample
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceSignal {
+    /// Name shown in the waveform viewer
+    pub name: String,
+    /// Lowest bit within the sample
+    pub lsb: u32,
+    /// Width in bits (1..=64)
+    pub width: u32,
+}
+
+/// Bit layout of the samples in the buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceLayout {
+    /// Bytes per sample in memory (little-endian)
+    pub sample_bytes: usize,
+    /// Signals
+    pub signals: Vec<TraceSignal>,
+}
+
+impl TraceLayout {
+    /// Layout of `sample_bytes` byte samples without signals
+    pub fn new(sample_bytes: usize) -> Self {
+        TraceLayout {
+            sample_bytes,
+            signals: Vec::new(),
+        }
+    }
+
+    /// Add signal `name` at bits `[lsb, lsb + width)`
+    pub fn signal(mut self, name: &str, lsb: u32, width: u32) -> Self {
+        self.signals.push(TraceSignal {
+            name: name.to_string(),
+            lsb,
+            width,
+        });
+        self
+    }
+
+    /// Signal values of one sample
+    pub fn decode_sample(&self, sample: &[u8]) -> Vec<u64> {
+        self.signals
+            .iter()
+            .map(|s| {
+                let mut value = 0u64;
+                for bit in 0..s.width.min(64) {
+                    let pos = (s.lsb + bit) as usize;
+                    if sample
+                        .get(pos / 8)
+                        .is_some_and(|byte| (byte >> (pos % 8)) & 1 != 0)
+                    {
+                        value |= 1 << bit;
+                    }
+                }
+                value
+            })
+            .collect()
+    }
+
+    /// Decode consecutive samples, starting at sample `start` of the ring `data`
+    pub fn decode(&self, data: &[u8], start: usize) -> Vec<Vec<u64>> {
+        let samples: Vec<&[u8]> = data.chunks_exact(self.sample_bytes.max(1)).collect();
+        (0..samples.len())
+            .map(|i| self.decode_sample(samples[(start + i) % samples.len()]))
+            .collect()
+    }
+}
+
+/// Control registers of the capture core (register indices)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceRegs {
+    /// Control register
+    pub control: u64,
+    /// Control value that arms the trigger (clears the done status)
+    pub arm: u64,
+    /// Status register
+    pub status: u64,
+    /// Status bit set when the capture completed
+    pub done: u64,
+    /// Trigger compare value
+    pub trigger_value: u64,
+    /// Trigger compare mask
+    pub trigger_mask: u64,
+    /// Samples kept before the trigger
+    pub pre_trigger: u64,
+    /// Ring index of the trigger sample, valid when done
+    pub trigger_index: u64,
+}
+
+impl Default for TraceRegs {
+    fn default() -> Self {
+        TraceRegs {
+            control: 0x00,
+            arm: 0x1,
+            status: 0x01,
+            done: 0x1,
+            trigger_value: 0x02,
+            trigger_mask: 0x03,
+            pre_trigger: 0x04,
+            trigger_index: 0x05,
+        }
+    }
+}
+
+/// Decoded samples of one capture
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceCapture {
+    /// Signals in column order
+    pub signals: Vec<TraceSignal>,
+    /// Signal values per sample, oldest first
+    pub samples: Vec<Vec<u64>>,
+    /// Index of the trigger sample in `samples`
+    pub trigger: usize,
+}
+
+impl TraceCapture {
+    /// Signal values of `name` over time
+    pub fn column(&self, name: &str) -> Option<Vec<u64>> {
+        let i = self.signals.iter().position(|s| s.name == name)?;
+        Some(self.samples.iter().map(|s| s[i]).collect())
+    }
+
+    /// CSV with one row per sample (`sample` relative to the trigger, then the signals)
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("sample");
+        for signal in &self.signals {
+            s.push(',');
+            s.push_str(&csv_field(&signal.name));
+        }
+        s.push('\n');
+        for (i, values) in self.samples.iter().enumerate() {
+            let _ = write!(s, "{}", i as i64 - self.trigger as i64);
+            for v in values {
+                let _ = write!(s, ",{}", v);
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Write [`to_csv`](Self::to_csv) to a file
+    pub async fn save_csv(&self, path: &str) -> Result<(), tonic::Status> {
+        crate::fs::write(path, self.to_csv().into_bytes()).await
+    }
+
+    /// VCD with samples `sample_period_ps` picoseconds apart (10000 for 100 MHz)
+    ///
+    /// The trigger sample is marked with a comment.
+    pub fn to_vcd(&self, sample_period_ps: u64) -> String {
+        let signals: Vec<(&str, u32)> = self
+            .signals
+            .iter()
+            .map(|s| (s.name.as_str(), s.width))
+            .collect();
+        let mut vcd = VcdWriter::new("1ps", "trace", &signals);
+        for (i, values) in self.samples.iter().enumerate() {
+            if i == self.trigger {
+                vcd.comment(&format!("trigger at sample {}", i));
+            }
+            vcd.sample(i as u64 * sample_period_ps, values);
+        }
+        vcd.finish(self.samples.len() as u64 * sample_period_ps)
+    }
+
+    /// Write [`to_vcd`](Self::to_vcd) to a file
+    pub async fn save_vcd(&self, path: &str, sample_period_ps: u64) -> Result<(), tonic::Status> {
+        crate::fs::write(path, self.to_vcd(sample_period_ps).into_bytes()).await
+    }
+}
+
+/// Capture core with its sample buffer
+pub struct TraceBuffer {
+    regs: Accessor,
+    mem: Accessor,
+    reg_layout: TraceRegs,
+    layout: TraceLayout,
+    depth: usize,
+    reg_size: AccessSize,
+    irq: Option<(u64, u64)>,
+    poll_interval: Duration,
+}
+
+impl TraceBuffer {
+    /// Capture core with control registers at `regs` and a ring of `depth` samples at `mem`
+    pub fn new(
+        regs: Accessor,
+        mem: Accessor,
+        reg_layout: TraceRegs,
+        layout: TraceLayout,
+        depth: usize,
+    ) -> Self {
+        TraceBuffer {
+            regs,
+            mem,
+            reg_layout,
+            layout,
+            depth,
+            reg_size: AccessSize::U32,
+            irq: None,
+            poll_interval: Duration::from_millis(1),
+        }
+    }
+
+    /// Wait for `mask` in interrupt status register `reg` instead of polling the status
+    pub fn with_irq_status(mut self, reg: u64, mask: u64) -> Self {
+        self.irq = Some((reg, mask));
+        self
+    }
+
+    /// Set register access size (default `U32`)
+    pub fn set_reg_size(&mut self, size: AccessSize) {
+        self.reg_size = size;
+    }
+
+    /// Set polling interval
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Sample layout
+    pub fn layout(&self) -> &TraceLayout {
+        &self.layout
+    }
+
+    /// Arm the trigger `(probe & mask) == value`, keeping `pre_trigger` samples before it
+    pub async fn arm(
+        &mut self,
+        value: u64,
+        mask: u64,
+        pre_trigger: usize,
+    ) -> Result<(), tonic::Status> {
+        if pre_trigger >= self.depth {
+            return Err(tonic::Status::invalid_argument(format!(
+                "pre-trigger {} must be less than the depth {}",
+                pre_trigger, self.depth
+            )));
+        }
+        let r = self.reg_layout;
+        self.regs
+            .write_reg_u(r.trigger_value, value, self.reg_size)
+            .await?;
+        self.regs
+            .write_reg_u(r.trigger_mask, mask, self.reg_size)
+            .await?;
+        self.regs
+            .write_reg_u(r.pre_trigger, pre_trigger as u64, self.reg_size)
+            .await?;
+        self.regs.write_reg_u(r.control, r.arm, self.reg_size).await
+    }
+
+    /// Capture completed
+    pub async fn is_done(&mut self) -> Result<bool, tonic::Status> {
+        let r = self.reg_layout;
+        Ok(self.regs.read_reg_u(r.status, self.reg_size).await? & r.done != 0)
+    }
+
+    /// Wait up to `timeout` for the capture to complete
+    pub async fn wait(&mut self, timeout: Duration) -> Result<(), tonic::Status> {
+        let (reg, mask) = self
+            .irq
+            .unwrap_or((self.reg_layout.status, self.reg_layout.done));
+        self.regs
+            .wait_reg_u(reg, self.reg_size, mask, mask, self.poll_interval, timeout)
+            .await
+            .map_err(|e| {
+                tonic::Status::with_metadata(
+                    e.code(),
+                    format!("trace buffer: {}", e.message()),
+                    e.metadata().clone(),
+                )
+            })?;
+        if self.irq.is_some() {
+            self.regs.write_reg_u(reg, mask, self.reg_size).await?;
+        }
+        Ok(())
+    }
+
+    /// Read and decode the completed capture
+    pub async fn read_capture(&mut self) -> Result<TraceCapture, tonic::Status> {
+        let r = self.reg_layout;
+        let pre_trigger = self.regs.read_reg_u(r.pre_trigger, self.reg_size).await? as usize;
+        let trigger_index = self.regs.read_reg_u(r.trigger_index, self.reg_size).await? as usize;
+        let size = (self.depth * self.layout.sample_bytes) as u64;
+        let data = self
+            .mem
+            .read_bytes_chunked(0, size, DEFAULT_CHUNK_SIZE)
+            .await?;
+        let depth = self.depth.max(1);
+        let pre_trigger = pre_trigger.min(depth - 1);
+        let start = (trigger_index % depth + depth - pre_trigger) % depth;
+        Ok(TraceCapture {
+            signals: self.layout.signals.clone(),
+            samples: self.layout.decode(&data, start),
+            trigger: pre_trigger,
+        })
+    }
+
+    /// Arm, wait for completion and read the capture
+    pub async fn capture(
+        &mut self,
+        value: u64,
+        mask: u64,
+        pre_trigger: usize,
+        timeout: Duration,
+    ) -> Result<TraceCapture, tonic::Status> {
+        self.arm(value, mask, pre_trigger).await?;
+        self.wait(timeout).await?;
+        self.read_capture().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_decode() {
+        let layout = TraceLayout::new(2)
+            .signal("valid", 0, 1)
+            .signal("data", 4, 8);
+        assert_eq!(layout.decode_sample(&[0x31, 0x0a]), vec![1, 0xa3]);
+        let ring = [0x10, 0x00, 0x21, 0x00, 0x30, 0x00];
+        assert_eq!(
+            layout.decode(&ring, 1),
+            vec![vec![1, 2], vec![0, 3], vec![0, 1]]
+        );
+        let capture = TraceCapture {
+            signals: layout.signals.clone(),
+            samples: layout.decode(&ring, 1),
+            trigger: 1,
+        };
+        assert_eq!(capture.column("data"), Some(vec![2, 3, 1]));
+        let vcd = capture.to_vcd(10_000);
+        assert!(vcd.contains("$comment trigger at sample 1 $end\n#10000\n0!\nb11 \"\n"));
+        assert!(vcd.ends_with("#30000\n"));
+        assert_eq!(
+            capture.to_csv(),
+            "sample,valid,data\n-1,1,2\n0,0,3\n1,0,1\n"
+        );
+    }
+}
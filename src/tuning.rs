@@ -0,0 +1,156 @@
+//! HTTP/2 flow control and message size tuning
+//!
+//! The client defaults (hyper's 2 MiB stream and 5 MiB connection windows,
+//! tonic's 4 MiB largest received message) suit a LAN. Over a link with a
+//! high bandwidth-delay product a transfer stalls every window's worth of
+//! data waiting for the peer's WINDOW_UPDATE: at 100 ms round trip a 2 MiB
+//! window caps a stream at about 170 Mbit/s whatever the link speed.
+//! [`Http2Tuning::bulk_transfer`] raises the windows to 16 MiB per stream
+//! (1 Gbit/s up to about 130 ms) and the message limit to 64 MiB;
+//! `jelly-fpga --bulk-tuning bench dma` against plain `bench dma` measures
+//! the difference on a given link.
+//!
+//! The windows limit what the peer may send before being acknowledged, so
+//! the client's settings speed up `mem_copy_from` and other downloads;
+//! upload speed depends on the server's windows (1 MiB by default for a
+//! hyper server). HTTP/2 frame size is not configurable through tonic's
+//! `Endpoint`; the client controls the size of each upload message instead
+//! ([`with_upload_chunk_size`](Http2Tuning::with_upload_chunk_size)).
+
+use crate::JellyFpgaClient;
+
+/// Default bytes per `upload_firmware` message
+pub const DEFAULT_UPLOAD_CHUNK_SIZE: usize = 2 * 1024 * 1024;
+
+/// Connection and message settings applied by [`JellyFpgaClient::connect_tuned`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Http2Tuning {
+    /// Initial HTTP/2 stream window in bytes (`None` = 2 MiB)
+    pub stream_window: Option<u32>,
+    /// Initial HTTP/2 connection window in bytes (`None` = 5 MiB)
+    pub connection_window: Option<u32>,
+    /// Let the windows grow with the measured bandwidth-delay product (overrides the sizes)
+    pub adaptive_window: bool,
+    /// Largest response message in bytes (`None` = tonic default, 4 MiB)
+    pub max_message_size: Option<usize>,
+    /// Bytes per `upload_firmware` message
+    pub upload_chunk_size: usize,
+}
+
+impl Default for Http2Tuning {
+    fn default() -> Self {
+        Http2Tuning {
+            stream_window: None,
+            connection_window: None,
+            adaptive_window: false,
+            max_message_size: None,
+            upload_chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+        }
+    }
+}
+
+impl Http2Tuning {
+    /// Preset for large transfers over high-latency links
+    ///
+    /// 16 MiB stream and 64 MiB connection windows and a 64 MiB message
+    /// limit, so `mem_copy_from` chunks up to that size are accepted. The
+    /// upload chunk size stays at the default, as servers with tonic's
+    /// default limits reject messages over 4 MiB.
+    pub fn bulk_transfer() -> Self {
+        Http2Tuning {
+            stream_window: Some(16 * 1024 * 1024),
+            connection_window: Some(64 * 1024 * 1024),
+            adaptive_window: false,
+            max_message_size: Some(64 * 1024 * 1024),
+            upload_chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+        }
+    }
+
+    /// Set the initial stream window
+    pub fn with_stream_window(mut self, bytes: u32) -> Self {
+        self.stream_window = Some(bytes);
+        self
+    }
+
+    /// Set the initial connection window
+    pub fn with_connection_window(mut self, bytes: u32) -> Self {
+        self.connection_window = Some(bytes);
+        self
+    }
+
+    /// Enable or disable adaptive windows
+    pub fn with_adaptive_window(mut self, enabled: bool) -> Self {
+        self.adaptive_window = enabled;
+        self
+    }
+
+    /// Set the largest accepted response message
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
+
+    /// Set the bytes per upload message (at least 1)
+    pub fn with_upload_chunk_size(mut self, bytes: usize) -> Self {
+        self.upload_chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Apply the HTTP/2 settings to `endpoint`
+    #[cfg(not(feature = "wasm"))]
+    pub fn apply(&self, endpoint: tonic::transport::Endpoint) -> tonic::transport::Endpoint {
+        endpoint
+            .initial_stream_window_size(self.stream_window)
+            .initial_connection_window_size(self.connection_window)
+            .http2_adaptive_window(self.adaptive_window)
+    }
+}
+
+impl JellyFpgaClient {
+    /// Connect to `endpoint` with `tuning` applied
+    ///
+    /// Combine with [`with_bulk_connection`](Self::with_bulk_connection)
+    /// (passing `tuning.apply(endpoint)`) to tune only the bulk connection.
+    #[cfg(not(feature = "wasm"))]
+    pub async fn connect_tuned(
+        endpoint: tonic::transport::Endpoint,
+        tuning: &Http2Tuning,
+    ) -> Result<Self, tonic::transport::Error> {
+        Ok(Self::connect(tuning.apply(endpoint))
+            .await?
+            .with_tuning(tuning))
+    }
+
+    /// Apply the message size settings of `tuning`
+    ///
+    /// Covers the connections opened so far and any bulk or priority
+    /// connection opened afterwards; the window sizes only take effect
+    /// through [`Http2Tuning::apply`] when connecting.
+    pub fn with_tuning(mut self, tuning: &Http2Tuning) -> Self {
+        self.max_message_size = tuning.max_message_size;
+        self.upload_chunk_size = tuning.upload_chunk_size;
+        self.client = self.limit_messages(self.client.clone());
+        if let Some(bulk) = self.bulk.take() {
+            self.bulk = Some(std::sync::Arc::new(self.limit_messages((*bulk).clone())));
+        }
+        if let Some(priority) = self.priority.take() {
+            self.priority = Some(std::sync::Arc::new(
+                self.limit_messages((*priority).clone()),
+            ));
+        }
+        self
+    }
+
+    /// Bytes per `upload_firmware` message
+    pub fn upload_chunk_size(&self) -> usize {
+        self.upload_chunk_size
+    }
+
+    /// Apply the configured message size limit to a generated client
+    pub(crate) fn limit_messages(&self, client: crate::raw::RawClient) -> crate::raw::RawClient {
+        match self.max_message_size {
+            Some(limit) => client.max_decoding_message_size(limit),
+            None => client,
+        }
+    }
+}
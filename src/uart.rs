@@ -0,0 +1,264 @@
+//! Xilinx AXI UART Lite driver with `AsyncRead`/`AsyncWrite` bridge
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::accessor::Accessor;
+
+/// Receive FIFO
+pub const REG_RX_FIFO: u64 = 0x00;
+/// Transmit FIFO
+pub const REG_TX_FIFO: u64 = 0x04;
+/// Status
+pub const REG_STAT: u64 = 0x08;
+/// Control
+pub const REG_CTRL: u64 = 0x0c;
+
+/// STAT: receive FIFO has data
+pub const STAT_RX_VALID: u32 = 1 << 0;
+/// STAT: transmit FIFO empty
+pub const STAT_TX_EMPTY: u32 = 1 << 2;
+/// STAT: transmit FIFO full
+pub const STAT_TX_FULL: u32 = 1 << 3;
+/// STAT: overrun error
+pub const STAT_OVERRUN: u32 = 1 << 5;
+/// STAT: frame error
+pub const STAT_FRAME: u32 = 1 << 6;
+/// STAT: parity error
+pub const STAT_PARITY: u32 = 1 << 7;
+/// CTRL: reset transmit FIFO
+pub const CTRL_RST_TX: u32 = 1 << 0;
+/// CTRL: reset receive FIFO
+pub const CTRL_RST_RX: u32 = 1 << 1;
+
+/// AXI UART Lite
+#[derive(Clone)]
+pub struct UartLite {
+    regs: Accessor,
+    poll_interval: Duration,
+}
+
+impl UartLite {
+    /// Create driver
+    pub fn new(regs: Accessor) -> Self {
+        UartLite {
+            regs,
+            poll_interval: Duration::from_millis(5),
+        }
+    }
+
+    /// Set status polling interval
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Register accessor
+    pub fn regs(&mut self) -> &mut Accessor {
+        &mut self.regs
+    }
+
+    /// Read status register
+    pub async fn status(&mut self) -> Result<u32, tonic::Status> {
+        self.regs.read_mem_u32(REG_STAT).await
+    }
+
+    /// Clear both FIFOs
+    pub async fn reset_fifos(&mut self) -> Result<(), tonic::Status> {
+        self.regs
+            .write_mem_u32(REG_CTRL, CTRL_RST_TX | CTRL_RST_RX)
+            .await
+    }
+
+    /// Read up to `max` bytes currently in the receive FIFO (may return empty)
+    pub async fn read_available(&mut self, max: usize) -> Result<Vec<u8>, tonic::Status> {
+        let mut data = Vec::new();
+        while data.len() < max && self.status().await? & STAT_RX_VALID != 0 {
+            data.push(self.regs.read_mem_u32(REG_RX_FIFO).await? as u8);
+        }
+        Ok(data)
+    }
+
+    /// Wait until at least one byte is received, then read up to `max` bytes
+    pub async fn read_some(&mut self, max: usize) -> Result<Vec<u8>, tonic::Status> {
+        loop {
+            let data = self.read_available(max).await?;
+            if !data.is_empty() || max == 0 {
+                return Ok(data);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Send bytes, waiting while the transmit FIFO is full
+    pub async fn write_all(&mut self, data: &[u8]) -> Result<(), tonic::Status> {
+        self.write_counted(data, &mut 0).await
+    }
+
+    /// [`write_all`](Self::write_all), counting the bytes pushed in `sent` (also on error)
+    async fn write_counted(&mut self, data: &[u8], sent: &mut usize) -> Result<(), tonic::Status> {
+        for &b in data {
+            while self.status().await? & STAT_TX_FULL != 0 {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+            self.regs.write_mem_u32(REG_TX_FIFO, b as u32).await?;
+            *sent += 1;
+        }
+        Ok(())
+    }
+
+    /// Wait until the transmit FIFO drained
+    pub async fn flush(&mut self) -> Result<(), tonic::Status> {
+        while self.status().await? & STAT_TX_EMPTY == 0 {
+            tokio::time::sleep(self.poll_interval).await;
+        }
+        Ok(())
+    }
+
+    /// Turn into an `AsyncRead + AsyncWrite` stream
+    pub fn into_stream(self) -> UartStream {
+        UartStream {
+            reader: Some(self.clone()),
+            read_fut: None,
+            read_buf: Vec::new(),
+            writer: Some(self),
+            write_fut: None,
+            write_error: None,
+            flush_fut: None,
+        }
+    }
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = (UartLite, Result<T, tonic::Status>)> + Send>>;
+
+/// Write in progress: resolves to the bytes pushed and how the write ended
+type WriteFuture =
+    Pin<Box<dyn Future<Output = (UartLite, usize, Result<(), tonic::Status>)> + Send>>;
+
+fn to_io(status: tonic::Status) -> io::Error {
+    io::Error::other(status)
+}
+
+/// `AsyncRead + AsyncWrite` view of a [`UartLite`]
+///
+/// Reads poll the receive FIFO; writes complete once all bytes have been
+/// pushed into the transmit FIFO. Flush (and shutdown) additionally waits
+/// until the transmit FIFO has drained. A write failing after some bytes
+/// were pushed returns their count, and the error on the next write or
+/// flush.
+pub struct UartStream {
+    reader: Option<UartLite>,
+    read_fut: Option<BoxFuture<Vec<u8>>>,
+    read_buf: Vec<u8>,
+    writer: Option<UartLite>,
+    write_fut: Option<WriteFuture>,
+    /// Error of a partial write, reported by the next call
+    write_error: Option<io::Error>,
+    flush_fut: Option<BoxFuture<()>>,
+}
+
+impl UartStream {
+    fn poll_write_fut(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let Some(fut) = self.write_fut.as_mut() else {
+            return Poll::Ready(Ok(0));
+        };
+        let (uart, sent, result) = std::task::ready!(fut.as_mut().poll(cx));
+        self.write_fut = None;
+        self.writer = Some(uart);
+        Poll::Ready(match result {
+            Ok(()) => Ok(sent),
+            Err(e) if sent == 0 => Err(to_io(e)),
+            Err(e) => {
+                self.write_error = Some(to_io(e));
+                Ok(sent)
+            }
+        })
+    }
+
+    fn poll_flush_fut(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let Some(fut) = self.flush_fut.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        let (uart, result) = std::task::ready!(fut.as_mut().poll(cx));
+        self.flush_fut = None;
+        self.writer = Some(uart);
+        Poll::Ready(result.map_err(to_io))
+    }
+}
+
+impl AsyncRead for UartStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.read_buf.is_empty() {
+            if this.read_fut.is_none() {
+                let mut uart = this.reader.take().expect("reader missing");
+                let max = buf.remaining();
+                this.read_fut = Some(Box::pin(async move {
+                    let result = uart.read_some(max).await;
+                    (uart, result)
+                }));
+            }
+            let fut = this.read_fut.as_mut().unwrap();
+            let (uart, result) = std::task::ready!(fut.as_mut().poll(cx));
+            this.read_fut = None;
+            this.reader = Some(uart);
+            this.read_buf = result.map_err(to_io)?;
+        }
+        let n = std::cmp::min(buf.remaining(), this.read_buf.len());
+        buf.put_slice(&this.read_buf[..n]);
+        this.read_buf.drain(..n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for UartStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        std::task::ready!(this.poll_flush_fut(cx))?;
+        if let Some(e) = this.write_error.take() {
+            return Poll::Ready(Err(e));
+        }
+        if this.write_fut.is_none() {
+            let mut uart = this.writer.take().expect("writer missing");
+            let data = data.to_vec();
+            this.write_fut = Some(Box::pin(async move {
+                let mut sent = 0;
+                let result = uart.write_counted(&data, &mut sent).await;
+                (uart, sent, result)
+            }));
+        }
+        this.poll_write_fut(cx)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        std::task::ready!(this.poll_write_fut(cx))?;
+        if let Some(e) = this.write_error.take() {
+            return Poll::Ready(Err(e));
+        }
+        if this.flush_fut.is_none() {
+            let mut uart = this.writer.take().expect("writer missing");
+            this.flush_fut = Some(Box::pin(async move {
+                let result = uart.flush().await;
+                (uart, result)
+            }));
+        }
+        this.poll_flush_fut(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
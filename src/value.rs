@@ -0,0 +1,324 @@
+//! Dynamically typed memory access
+//!
+//! [`read_value`](JellyFpgaClient::read_value) and
+//! [`write_value`](JellyFpgaClient::write_value) take the element type as
+//! data, so scripting frontends map a type name such as `"u16"` to a
+//! [`ValueType`] once instead of choosing among the typed methods.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::JellyFpgaClient;
+use crate::accessor::check;
+
+/// Type of a [`Value`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValueType {
+    /// `u8`
+    U8,
+    /// `u16`
+    U16,
+    /// `u32`
+    U32,
+    /// `u64`
+    U64,
+    /// `i8`
+    I8,
+    /// `i16`
+    I16,
+    /// `i32`
+    I32,
+    /// `i64`
+    I64,
+    /// `f32`
+    F32,
+    /// `f64`
+    F64,
+    /// Byte string of the given length (`bytes:N`)
+    Bytes(u64),
+}
+
+impl ValueType {
+    /// Size in bytes
+    pub fn size(self) -> u64 {
+        match self {
+            ValueType::U8 | ValueType::I8 => 1,
+            ValueType::U16 | ValueType::I16 => 2,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => 4,
+            ValueType::U64 | ValueType::I64 | ValueType::F64 => 8,
+            ValueType::Bytes(len) => len,
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::U8 => write!(f, "u8"),
+            ValueType::U16 => write!(f, "u16"),
+            ValueType::U32 => write!(f, "u32"),
+            ValueType::U64 => write!(f, "u64"),
+            ValueType::I8 => write!(f, "i8"),
+            ValueType::I16 => write!(f, "i16"),
+            ValueType::I32 => write!(f, "i32"),
+            ValueType::I64 => write!(f, "i64"),
+            ValueType::F32 => write!(f, "f32"),
+            ValueType::F64 => write!(f, "f64"),
+            ValueType::Bytes(len) => write!(f, "bytes:{}", len),
+        }
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = String;
+
+    /// `u8` .. `u64`, `i8` .. `i64`, `f32`, `f64` or `bytes:N`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "u8" => ValueType::U8,
+            "u16" => ValueType::U16,
+            "u32" => ValueType::U32,
+            "u64" => ValueType::U64,
+            "i8" => ValueType::I8,
+            "i16" => ValueType::I16,
+            "i32" => ValueType::I32,
+            "i64" => ValueType::I64,
+            "f32" => ValueType::F32,
+            "f64" => ValueType::F64,
+            _ => {
+                let len = s
+                    .strip_prefix("bytes:")
+                    .and_then(|len| len.parse().ok())
+                    .ok_or_else(|| format!("unknown value type {:?}", s))?;
+                ValueType::Bytes(len)
+            }
+        })
+    }
+}
+
+/// Value of any supported type
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    /// `u8`
+    U8(u8),
+    /// `u16`
+    U16(u16),
+    /// `u32`
+    U32(u32),
+    /// `u64`
+    U64(u64),
+    /// `i8`
+    I8(i8),
+    /// `i16`
+    I16(i16),
+    /// `i32`
+    I32(i32),
+    /// `i64`
+    I64(i64),
+    /// `f32`
+    F32(f32),
+    /// `f64`
+    F64(f64),
+    /// Byte string
+    Bytes(Vec<u8>),
+}
+
+/// Parse an integer, decimal or `0x` hexadecimal
+fn parse_int<T: TryFrom<i128>>(s: &str) -> Option<T> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(&hex.replace('_', ""), 16).ok()?,
+        None => digits.replace('_', "").parse::<i128>().ok()?,
+    };
+    T::try_from(if negative { -value } else { value }).ok()
+}
+
+/// Parse hex digits, two per byte (whitespace and an `0x` prefix allowed)
+fn parse_hex_bytes(s: &str) -> Option<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    let digits: Vec<u8> = s.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+impl Value {
+    /// Type of the value
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::U8(_) => ValueType::U8,
+            Value::U16(_) => ValueType::U16,
+            Value::U32(_) => ValueType::U32,
+            Value::U64(_) => ValueType::U64,
+            Value::I8(_) => ValueType::I8,
+            Value::I16(_) => ValueType::I16,
+            Value::I32(_) => ValueType::I32,
+            Value::I64(_) => ValueType::I64,
+            Value::F32(_) => ValueType::F32,
+            Value::F64(_) => ValueType::F64,
+            Value::Bytes(data) => ValueType::Bytes(data.len() as u64),
+        }
+    }
+
+    /// Parse `s` as a value of type `ty`
+    ///
+    /// Integers may be decimal or `0x` hexadecimal, bytes are hex digits;
+    /// for `bytes:N` the length must match.
+    pub fn parse(ty: ValueType, s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let value = match ty {
+            ValueType::U8 => parse_int(s).map(Value::U8),
+            ValueType::U16 => parse_int(s).map(Value::U16),
+            ValueType::U32 => parse_int(s).map(Value::U32),
+            ValueType::U64 => parse_int(s).map(Value::U64),
+            ValueType::I8 => parse_int(s).map(Value::I8),
+            ValueType::I16 => parse_int(s).map(Value::I16),
+            ValueType::I32 => parse_int(s).map(Value::I32),
+            ValueType::I64 => parse_int(s).map(Value::I64),
+            ValueType::F32 => s.parse().ok().map(Value::F32),
+            ValueType::F64 => s.parse().ok().map(Value::F64),
+            ValueType::Bytes(len) => parse_hex_bytes(s)
+                .filter(|data| data.len() as u64 == len)
+                .map(Value::Bytes),
+        };
+        value.ok_or_else(|| format!("{:?} is not a valid {}", s, ty))
+    }
+
+    /// Integer value widened to `i128` (`None` for floats and bytes)
+    pub fn as_i128(&self) -> Option<i128> {
+        Some(match *self {
+            Value::U8(v) => v.into(),
+            Value::U16(v) => v.into(),
+            Value::U32(v) => v.into(),
+            Value::U64(v) => v.into(),
+            Value::I8(v) => v.into(),
+            Value::I16(v) => v.into(),
+            Value::I32(v) => v.into(),
+            Value::I64(v) => v.into(),
+            _ => return None,
+        })
+    }
+
+    /// Numeric value as `f64` (`None` for bytes)
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::F32(v) => Some(v.into()),
+            Value::F64(v) => Some(v),
+            _ => self.as_i128().map(|v| v as f64),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::F32(v) => write!(f, "{}", v),
+            Value::F64(v) => write!(f, "{}", v),
+            Value::Bytes(data) => {
+                for byte in data {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+            _ => write!(f, "{}", self.as_i128().unwrap()),
+        }
+    }
+}
+
+impl JellyFpgaClient {
+    /// Read a value of type `ty` at byte `offset`
+    ///
+    /// `result=false` is an error regardless of strict mode.
+    pub async fn read_value(
+        &mut self,
+        id: u32,
+        offset: u64,
+        ty: ValueType,
+    ) -> Result<Value, tonic::Status> {
+        let (result, value) = match ty {
+            ValueType::U8 => fmap(self.read_mem_u8(id, offset).await?, Value::U8),
+            ValueType::U16 => fmap(self.read_mem_u16(id, offset).await?, Value::U16),
+            ValueType::U32 => fmap(self.read_mem_u32(id, offset).await?, Value::U32),
+            ValueType::U64 => fmap(self.read_mem_u64(id, offset).await?, Value::U64),
+            ValueType::I8 => fmap(self.read_mem_i8(id, offset).await?, Value::I8),
+            ValueType::I16 => fmap(self.read_mem_i16(id, offset).await?, Value::I16),
+            ValueType::I32 => fmap(self.read_mem_i32(id, offset).await?, Value::I32),
+            ValueType::I64 => fmap(self.read_mem_i64(id, offset).await?, Value::I64),
+            ValueType::F32 => fmap(self.read_mem_f32(id, offset).await?, Value::F32),
+            ValueType::F64 => fmap(self.read_mem_f64(id, offset).await?, Value::F64),
+            ValueType::Bytes(len) => fmap(self.mem_copy_from(id, offset, len).await?, Value::Bytes),
+        };
+        check(result, &format!("read_value {}", ty))?;
+        Ok(value)
+    }
+
+    /// Write `value` at byte `offset`
+    ///
+    /// `result=false` is an error regardless of strict mode.
+    pub async fn write_value(
+        &mut self,
+        id: u32,
+        offset: u64,
+        value: Value,
+    ) -> Result<(), tonic::Status> {
+        let ty = value.value_type();
+        let result = match value {
+            Value::U8(v) => self.write_mem_u8(id, offset, v).await?,
+            Value::U16(v) => self.write_mem_u16(id, offset, v).await?,
+            Value::U32(v) => self.write_mem_u32(id, offset, v).await?,
+            Value::U64(v) => self.write_mem_u64(id, offset, v).await?,
+            Value::I8(v) => self.write_mem_i8(id, offset, v).await?,
+            Value::I16(v) => self.write_mem_i16(id, offset, v).await?,
+            Value::I32(v) => self.write_mem_i32(id, offset, v).await?,
+            Value::I64(v) => self.write_mem_i64(id, offset, v).await?,
+            Value::F32(v) => self.write_mem_f32(id, offset, v).await?,
+            Value::F64(v) => self.write_mem_f64(id, offset, v).await?,
+            Value::Bytes(data) => self.mem_copy_to(id, offset, data).await?,
+        };
+        check(result, &format!("write_value {}", ty))
+    }
+}
+
+/// Wrap the data of a `(result, data)` pair
+fn fmap<T>((result, data): (bool, T), f: impl FnOnce(T) -> Value) -> (bool, Value) {
+    (result, f(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("u16".parse(), Ok(ValueType::U16));
+        assert_eq!("bytes:4".parse(), Ok(ValueType::Bytes(4)));
+        assert!("u128".parse::<ValueType>().is_err());
+        assert_eq!(
+            Value::parse(ValueType::U32, "0x1234"),
+            Ok(Value::U32(0x1234))
+        );
+        assert_eq!(Value::parse(ValueType::I8, "-128"), Ok(Value::I8(-128)));
+        assert!(Value::parse(ValueType::U8, "256").is_err());
+        assert_eq!(Value::parse(ValueType::F32, "1.5"), Ok(Value::F32(1.5)));
+        assert_eq!(
+            Value::parse(ValueType::Bytes(2), "0xbeef"),
+            Ok(Value::Bytes(vec![0xbe, 0xef]))
+        );
+        assert!(Value::parse(ValueType::Bytes(3), "beef").is_err());
+        assert_eq!(Value::I16(-2).to_string(), "-2");
+        assert_eq!(Value::Bytes(vec![1, 0xab]).to_string(), "01ab");
+        assert_eq!(ValueType::Bytes(8).to_string(), "bytes:8");
+    }
+}
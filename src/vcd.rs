@@ -0,0 +1,134 @@
+//! Value Change Dump (VCD) writer
+//!
+//! Minimal IEEE 1364 VCD output for viewing sampled signals in GTKWave or
+//! other waveform viewers: one scope, `wire` variables of up to 64 bits,
+//! and a value change section that only lists values that changed since
+//! the previous sample.
+
+use std::fmt::Write as _;
+
+/// Identifier code of variable `index` (printable ASCII `!`..`~`)
+fn code(mut index: usize) -> String {
+    let mut code = String::new();
+    loop {
+        code.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return code;
+        }
+        index -= 1;
+    }
+}
+
+/// Incremental VCD writer
+#[derive(Debug, Clone)]
+pub struct VcdWriter {
+    out: String,
+    widths: Vec<u32>,
+    last: Vec<Option<u64>>,
+    time: Option<u64>,
+}
+
+impl VcdWriter {
+    /// Write the header declaring `signals` (name, width in bits) in `scope`
+    ///
+    /// `timescale` is a VCD time unit such as `"1ns"` or `"1us"`; sample
+    /// times are multiples of it. Whitespace in names is replaced by `_`.
+    pub fn new(timescale: &str, scope: &str, signals: &[(&str, u32)]) -> Self {
+        let mut out = String::new();
+        let _ = writeln!(out, "$version jelly-fpga-client $end");
+        let _ = writeln!(out, "$timescale {} $end", timescale);
+        let _ = writeln!(out, "$scope module {} $end", scope.replace(' ', "_"));
+        for (i, (name, width)) in signals.iter().enumerate() {
+            let name: String = name
+                .chars()
+                .map(|c| if c.is_whitespace() { '_' } else { c })
+                .collect();
+            let _ = writeln!(out, "$var wire {} {} {} $end", width, code(i), name);
+        }
+        let _ = writeln!(out, "$upscope $end");
+        let _ = writeln!(out, "$enddefinitions $end");
+        VcdWriter {
+            out,
+            widths: signals
+                .iter()
+                .map(|&(_, width)| width.clamp(1, 64))
+                .collect(),
+            last: vec![None; signals.len()],
+            time: None,
+        }
+    }
+
+    /// Add a comment (e.g. a trigger marker)
+    pub fn comment(&mut self, text: &str) {
+        let _ = writeln!(self.out, "$comment {} $end", text);
+    }
+
+    /// Record the signal values at `time` (in `timescale` units, increasing)
+    ///
+    /// Values are masked to their width; missing trailing values are left
+    /// unchanged.
+    pub fn sample(&mut self, time: u64, values: &[u64]) {
+        for (i, &value) in values.iter().enumerate().take(self.widths.len()) {
+            let width = self.widths[i];
+            let value = if width >= 64 {
+                value
+            } else {
+                value & ((1u64 << width) - 1)
+            };
+            if self.last[i] == Some(value) {
+                continue;
+            }
+            if self.time != Some(time) {
+                let _ = writeln!(self.out, "#{}", time);
+                self.time = Some(time);
+            }
+            if width == 1 {
+                let _ = writeln!(self.out, "{}{}", value, code(i));
+            } else {
+                let _ = writeln!(self.out, "b{:b} {}", value, code(i));
+            }
+            self.last[i] = Some(value);
+        }
+    }
+
+    /// Finish at `end_time` and return the file contents
+    pub fn finish(mut self, end_time: u64) -> String {
+        if self.time.is_none_or(|time| time < end_time) {
+            let _ = writeln!(self.out, "#{}", end_time);
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vcd_writer() {
+        assert_eq!(code(0), "!");
+        assert_eq!(code(93), "~");
+        assert_eq!(code(94), "!!");
+        let mut vcd = VcdWriter::new("1ns", "trace", &[("valid", 1), ("data bus", 8)]);
+        vcd.sample(0, &[0, 0x1ff]);
+        vcd.comment("trigger");
+        vcd.sample(10, &[1, 0xff]);
+        vcd.sample(20, &[1, 0x12]);
+        assert_eq!(
+            vcd.finish(30),
+            "$version jelly-fpga-client $end\n\
+             $timescale 1ns $end\n\
+             $scope module trace $end\n\
+             $var wire 1 ! valid $end\n\
+             $var wire 8 \" data_bus $end\n\
+             $upscope $end\n\
+             $enddefinitions $end\n\
+             #0\n0!\nb11111111 \"\n\
+             $comment trigger $end\n\
+             #10\n1!\n\
+             #20\nb10010 \"\n\
+             #30\n"
+        );
+    }
+}
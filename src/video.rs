@@ -0,0 +1,373 @@
+//! Jelly video DMA drivers (`jelly2_dma_video_write` / `jelly2_dma_video_read`)
+//!
+//! Both cores share the same register layout, addressed in register units
+//! (open the UIO/mmap with the bus width as `unit`, e.g. 8 on ZynqMP).
+//! Size parameters are programmed as `N - 1` as the RTL expects; the driver
+//! takes natural counts.
+
+use std::time::Duration;
+
+use crate::accessor::Accessor;
+use crate::addr::AccessSize;
+
+/// Core ID
+pub const REG_VDMA_CORE_ID: u64 = 0x00;
+/// Core version
+pub const REG_VDMA_CORE_VERSION: u64 = 0x01;
+/// Core configuration
+pub const REG_VDMA_CORE_CONFIG: u64 = 0x03;
+/// Control
+pub const REG_VDMA_CTL_CONTROL: u64 = 0x04;
+/// Status
+pub const REG_VDMA_CTL_STATUS: u64 = 0x05;
+/// Frame index (incremented on every completed frame)
+pub const REG_VDMA_CTL_INDEX: u64 = 0x07;
+/// IRQ enable
+pub const REG_VDMA_IRQ_ENABLE: u64 = 0x08;
+/// IRQ status
+pub const REG_VDMA_IRQ_STATUS: u64 = 0x09;
+/// IRQ clear
+pub const REG_VDMA_IRQ_CLR: u64 = 0x0a;
+/// IRQ set
+pub const REG_VDMA_IRQ_SET: u64 = 0x0b;
+/// Frame buffer address
+pub const REG_VDMA_PARAM_ADDR: u64 = 0x10;
+/// Max AXI burst length (AWLEN for write, ARLEN for read)
+pub const REG_VDMA_PARAM_LEN_MAX: u64 = 0x11;
+/// Pixels per line - 1
+pub const REG_VDMA_PARAM_H_SIZE: u64 = 0x20;
+/// Number of lines - 1
+pub const REG_VDMA_PARAM_V_SIZE: u64 = 0x24;
+/// Line stride in bytes
+pub const REG_VDMA_PARAM_LINE_STEP: u64 = 0x25;
+/// Number of frames - 1
+pub const REG_VDMA_PARAM_F_SIZE: u64 = 0x28;
+/// Frame stride in bytes
+pub const REG_VDMA_PARAM_FRAME_STEP: u64 = 0x29;
+/// Skip enable (write DMA only)
+pub const REG_VDMA_SKIP_EN: u64 = 0x70;
+/// Detect first (write DMA only)
+pub const REG_VDMA_DETECT_FIRST: u64 = 0x72;
+/// Detect last (write DMA only)
+pub const REG_VDMA_DETECT_LAST: u64 = 0x73;
+/// Padding enable (write DMA only)
+pub const REG_VDMA_PADDING_EN: u64 = 0x74;
+/// Padding data (write DMA only)
+pub const REG_VDMA_PADDING_DATA: u64 = 0x75;
+/// Padding strobe (write DMA only)
+pub const REG_VDMA_PADDING_STRB: u64 = 0x76;
+/// Shadow of the frame buffer address currently in use
+pub const REG_VDMA_SHADOW_ADDR: u64 = 0x90;
+
+/// CTL_CONTROL: enable
+pub const VDMA_CONTROL_ENABLE: u64 = 1 << 0;
+/// CTL_CONTROL: latch new parameters
+pub const VDMA_CONTROL_UPDATE: u64 = 1 << 1;
+/// CTL_CONTROL: stop after one frame sequence
+pub const VDMA_CONTROL_ONESHOT: u64 = 1 << 2;
+
+/// CTL_STATUS: busy
+pub const VDMA_STATUS_BUSY: u64 = 1 << 0;
+
+/// Frame geometry for a video DMA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VideoDmaParams {
+    /// Physical address of the first frame
+    pub addr: u64,
+    /// Pixels per line
+    pub width: u64,
+    /// Lines per frame
+    pub height: u64,
+    /// Line stride in bytes
+    pub line_step: u64,
+    /// Number of frames in the ring
+    pub frames: u64,
+    /// Frame stride in bytes
+    pub frame_step: u64,
+}
+
+impl VideoDmaParams {
+    /// Packed frames of `width * height` pixels of `pixel_bytes` bytes
+    pub fn packed(addr: u64, width: u64, height: u64, pixel_bytes: u64, frames: u64) -> Self {
+        let line_step = width * pixel_bytes;
+        VideoDmaParams {
+            addr,
+            width,
+            height,
+            line_step,
+            frames,
+            frame_step: line_step * height,
+        }
+    }
+
+    /// Bytes needed to hold all frames
+    pub fn total_bytes(&self) -> u64 {
+        self.frame_step * self.frames.saturating_sub(1) + self.line_step * self.height
+    }
+}
+
+/// Register level implementation shared by the write and read cores
+struct VideoDma {
+    regs: Accessor,
+    reg_size: AccessSize,
+    /// Width of the frame index counter, `None` for the register size
+    index_width: Option<u32>,
+    poll_interval: Duration,
+}
+
+impl VideoDma {
+    fn new(regs: Accessor) -> Self {
+        VideoDma {
+            regs,
+            reg_size: AccessSize::U64,
+            index_width: None,
+            poll_interval: Duration::from_millis(1),
+        }
+    }
+
+    /// Mask of the frame index counter
+    fn index_mask(&self) -> u64 {
+        match self.index_width {
+            Some(bits) if bits < 64 => (1 << bits) - 1,
+            Some(_) => !0,
+            None => self.reg_size.mask(),
+        }
+    }
+
+    async fn write(&mut self, reg: u64, data: u64) -> Result<(), tonic::Status> {
+        self.regs.write_reg_u(reg, data, self.reg_size).await
+    }
+
+    async fn read(&mut self, reg: u64) -> Result<u64, tonic::Status> {
+        self.regs.read_reg_u(reg, self.reg_size).await
+    }
+
+    async fn set_params(&mut self, params: &VideoDmaParams) -> Result<(), tonic::Status> {
+        if params.width == 0 || params.height == 0 || params.frames == 0 {
+            return Err(tonic::Status::invalid_argument(
+                "video DMA sizes must not be zero",
+            ));
+        }
+        self.write(REG_VDMA_PARAM_ADDR, params.addr).await?;
+        self.write(REG_VDMA_PARAM_H_SIZE, params.width - 1).await?;
+        self.write(REG_VDMA_PARAM_V_SIZE, params.height - 1).await?;
+        self.write(REG_VDMA_PARAM_LINE_STEP, params.line_step)
+            .await?;
+        self.write(REG_VDMA_PARAM_F_SIZE, params.frames - 1).await?;
+        self.write(REG_VDMA_PARAM_FRAME_STEP, params.frame_step)
+            .await
+    }
+
+    async fn start(&mut self, oneshot: bool) -> Result<(), tonic::Status> {
+        let mut control = VDMA_CONTROL_ENABLE | VDMA_CONTROL_UPDATE;
+        if oneshot {
+            control |= VDMA_CONTROL_ONESHOT;
+        }
+        self.write(REG_VDMA_CTL_CONTROL, control).await
+    }
+
+    async fn stop(&mut self) -> Result<(), tonic::Status> {
+        self.write(REG_VDMA_CTL_CONTROL, 0).await
+    }
+
+    async fn is_busy(&mut self) -> Result<bool, tonic::Status> {
+        Ok(self.read(REG_VDMA_CTL_STATUS).await? & VDMA_STATUS_BUSY != 0)
+    }
+
+    async fn wait_idle(&mut self, timeout: Duration) -> Result<(), tonic::Status> {
+        self.regs
+            .wait_reg_u(
+                REG_VDMA_CTL_STATUS,
+                self.reg_size,
+                VDMA_STATUS_BUSY,
+                0,
+                self.poll_interval,
+                timeout,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Wait until `deadline` for the core to take a start: busy, or a frame completed since `index`
+    async fn wait_started(
+        &mut self,
+        index: u64,
+        deadline: tokio::time::Instant,
+    ) -> Result<(), tonic::Status> {
+        loop {
+            // a short capture may be done before the first poll
+            if self.is_busy().await? || self.read(REG_VDMA_CTL_INDEX).await? != index {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded(
+                    "timeout waiting for video DMA to start",
+                ));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn wait_frame(&mut self, timeout: Duration) -> Result<u64, tonic::Status> {
+        let start = self.read(REG_VDMA_CTL_INDEX).await?;
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let index = self.read(REG_VDMA_CTL_INDEX).await?;
+            if index != start {
+                return Ok(index);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(tonic::Status::deadline_exceeded(
+                    "timeout waiting for video DMA frame",
+                ));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+macro_rules! video_dma_common {
+    () => {
+        /// Set register access size (bus width, default `U64`)
+        pub fn set_reg_size(&mut self, size: AccessSize) {
+            self.dma.reg_size = size;
+        }
+
+        /// Set the width of the frame index counter in bits (default: the register access size)
+        ///
+        /// Frame counts from [`frames_since`](Self::frames_since) are taken
+        /// modulo `2^bits`, so they stay right across a counter wrap.
+        pub fn set_index_width(&mut self, bits: u32) {
+            self.dma.index_width = Some(bits);
+        }
+
+        /// Set status polling interval
+        pub fn set_poll_interval(&mut self, interval: Duration) {
+            self.dma.poll_interval = interval;
+        }
+
+        /// Register accessor
+        pub fn regs(&mut self) -> &mut Accessor {
+            &mut self.dma.regs
+        }
+
+        /// Read core ID
+        pub async fn core_id(&mut self) -> Result<u64, tonic::Status> {
+            self.dma.read(REG_VDMA_CORE_ID).await
+        }
+
+        /// Read core version
+        pub async fn core_version(&mut self) -> Result<u64, tonic::Status> {
+            self.dma.read(REG_VDMA_CORE_VERSION).await
+        }
+
+        /// Program frame geometry (takes effect on next `start`)
+        pub async fn set_params(&mut self, params: &VideoDmaParams) -> Result<(), tonic::Status> {
+            self.dma.set_params(params).await
+        }
+
+        /// Set max AXI burst length
+        pub async fn set_burst_len(&mut self, len: u64) -> Result<(), tonic::Status> {
+            self.dma.write(REG_VDMA_PARAM_LEN_MAX, len).await
+        }
+
+        /// Start; `oneshot` stops after the configured number of frames
+        pub async fn start(&mut self, oneshot: bool) -> Result<(), tonic::Status> {
+            self.dma.start(oneshot).await
+        }
+
+        /// Request stop (in-flight frame completes)
+        pub async fn stop(&mut self) -> Result<(), tonic::Status> {
+            self.dma.stop().await
+        }
+
+        /// Core is busy
+        pub async fn is_busy(&mut self) -> Result<bool, tonic::Status> {
+            self.dma.is_busy().await
+        }
+
+        /// Completed frame index
+        pub async fn frame_index(&mut self) -> Result<u64, tonic::Status> {
+            self.dma.read(REG_VDMA_CTL_INDEX).await
+        }
+
+        /// Frames completed since [`frame_index`](Self::frame_index) returned `start`
+        pub async fn frames_since(&mut self, start: u64) -> Result<u64, tonic::Status> {
+            let index = self.frame_index().await?;
+            Ok(index.wrapping_sub(start) & self.dma.index_mask())
+        }
+
+        /// Wait until the core is idle
+        pub async fn wait_idle(&mut self, timeout: Duration) -> Result<(), tonic::Status> {
+            self.dma.wait_idle(timeout).await
+        }
+
+        /// Wait for the next completed frame and return the new frame index
+        pub async fn wait_frame(&mut self, timeout: Duration) -> Result<u64, tonic::Status> {
+            self.dma.wait_frame(timeout).await
+        }
+    };
+}
+
+/// Jelly video write-DMA (AXI4-Stream video to memory)
+pub struct VideoWriteDma {
+    dma: VideoDma,
+}
+
+impl VideoWriteDma {
+    /// Create driver
+    pub fn new(regs: Accessor) -> Self {
+        VideoWriteDma {
+            dma: VideoDma::new(regs),
+        }
+    }
+
+    video_dma_common!();
+
+    /// Enable skipping of frames while the core is busy
+    pub async fn set_skip(&mut self, enable: bool) -> Result<(), tonic::Status> {
+        self.dma.write(REG_VDMA_SKIP_EN, enable as u64).await
+    }
+
+    /// Enable padding of short lines/frames with `data`
+    pub async fn set_padding(&mut self, enable: bool, data: u64) -> Result<(), tonic::Status> {
+        self.dma.write(REG_VDMA_PADDING_DATA, data).await?;
+        self.dma.write(REG_VDMA_PADDING_EN, enable as u64).await
+    }
+
+    /// Capture `params.frames` frames once and wait for completion
+    ///
+    /// The status may still read idle right after the start, so this first
+    /// waits for the core to turn busy (or complete a frame), then for idle;
+    /// `timeout` covers both.
+    pub async fn capture(
+        &mut self,
+        params: &VideoDmaParams,
+        timeout: Duration,
+    ) -> Result<(), tonic::Status> {
+        self.set_params(params).await?;
+        let index = self.frame_index().await?;
+        let deadline = tokio::time::Instant::now() + timeout;
+        self.start(true).await?;
+        self.dma.wait_started(index, deadline).await?;
+        self.wait_idle(deadline.saturating_duration_since(tokio::time::Instant::now()))
+            .await
+    }
+}
+
+/// Jelly video read-DMA (memory to AXI4-Stream video)
+pub struct VideoReadDma {
+    dma: VideoDma,
+}
+
+impl VideoReadDma {
+    /// Create driver
+    pub fn new(regs: Accessor) -> Self {
+        VideoReadDma {
+            dma: VideoDma::new(regs),
+        }
+    }
+
+    video_dma_common!();
+}
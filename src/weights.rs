@@ -0,0 +1,503 @@
+//! Model weight loading from `.npy` and `.safetensors` files
+//!
+//! Only the file header is parsed on the host; the tensor payload is
+//! streamed from the file into device memory in chunks, so large weight
+//! files are never held in memory as a whole.
+
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::JellyFpgaClient;
+use crate::accessor::check;
+use crate::framebuffer::DEFAULT_CHUNK_SIZE;
+
+/// Element type of a tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DType {
+    /// `bool` (1 byte)
+    Bool,
+    /// `u8`
+    U8,
+    /// `i8`
+    I8,
+    /// `u16`
+    U16,
+    /// `i16`
+    I16,
+    /// IEEE half precision
+    F16,
+    /// bfloat16
+    BF16,
+    /// `u32`
+    U32,
+    /// `i32`
+    I32,
+    /// `f32`
+    F32,
+    /// `u64`
+    U64,
+    /// `i64`
+    I64,
+    /// `f64`
+    F64,
+}
+
+impl DType {
+    /// Element size in bytes
+    pub fn size(self) -> usize {
+        match self {
+            DType::Bool | DType::U8 | DType::I8 => 1,
+            DType::U16 | DType::I16 | DType::F16 | DType::BF16 => 2,
+            DType::U32 | DType::I32 | DType::F32 => 4,
+            DType::U64 | DType::I64 | DType::F64 => 8,
+        }
+    }
+
+    fn from_safetensors(s: &str) -> Option<Self> {
+        Some(match s {
+            "BOOL" => DType::Bool,
+            "U8" => DType::U8,
+            "I8" => DType::I8,
+            "U16" => DType::U16,
+            "I16" => DType::I16,
+            "F16" => DType::F16,
+            "BF16" => DType::BF16,
+            "U32" => DType::U32,
+            "I32" => DType::I32,
+            "F32" => DType::F32,
+            "U64" => DType::U64,
+            "I64" => DType::I64,
+            "F64" => DType::F64,
+            _ => return None,
+        })
+    }
+
+    /// numpy `descr` such as `<f4`; big-endian types are not supported
+    fn from_npy(descr: &str) -> Option<Self> {
+        let ty = descr.strip_prefix(['<', '|', '='])?;
+        Some(match ty {
+            "b1" | "?" => DType::Bool,
+            "u1" => DType::U8,
+            "i1" => DType::I8,
+            "u2" => DType::U16,
+            "i2" => DType::I16,
+            "f2" => DType::F16,
+            "u4" => DType::U32,
+            "i4" => DType::I32,
+            "f4" => DType::F32,
+            "u8" => DType::U64,
+            "i8" => DType::I64,
+            "f8" => DType::F64,
+            _ => return None,
+        })
+    }
+}
+
+/// Tensor written by [`JellyFpgaClient::load_weights`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TensorInfo {
+    /// Tensor name (the file stem for `.npy`)
+    pub name: String,
+    /// Element type
+    pub dtype: DType,
+    /// Shape (row-major)
+    pub shape: Vec<usize>,
+    /// Device offset of the first element
+    pub offset: u64,
+    /// Size in bytes
+    pub size: u64,
+}
+
+fn invalid(path: &str, msg: &str) -> tonic::Status {
+    tonic::Status::invalid_argument(format!("{}: {}", path, msg))
+}
+
+/// Parsed header: payload position and size in the file, and the tensors
+/// with offsets relative to the payload
+struct Header {
+    payload_start: u64,
+    payload_size: u64,
+    tensors: Vec<TensorInfo>,
+}
+
+/// Parse the dict literal of a `.npy` header
+fn parse_npy_dict(name: &str, dict: &str) -> Option<TensorInfo> {
+    let value = |key: &str| {
+        let start = dict.find(&format!("'{}'", key))? + key.len() + 2;
+        Some(dict[start..].trim_start().strip_prefix(':')?.trim_start())
+    };
+    let descr = value("descr")?.strip_prefix('\'')?;
+    let dtype = DType::from_npy(&descr[..descr.find('\'')?])?;
+    if !value("fortran_order")?.starts_with("False") {
+        return None;
+    }
+    let shape = value("shape")?.strip_prefix('(')?;
+    let shape = shape[..shape.find(')')?]
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().ok())
+        .collect::<Option<Vec<usize>>>()?;
+    let size = (shape.iter().product::<usize>() * dtype.size()) as u64;
+    Some(TensorInfo {
+        name: name.to_string(),
+        dtype,
+        shape,
+        offset: 0,
+        size,
+    })
+}
+
+/// Minimal JSON value for the safetensors header
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Num(String),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+struct JsonParser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_ws(&mut self) {
+        while self.pos < self.s.len() && self.s[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> Option<()> {
+        self.skip_ws();
+        (self.s.get(self.pos) == Some(&c)).then(|| self.pos += 1)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.eat(b'"')?;
+        let mut out = Vec::new();
+        loop {
+            let c = *self.s.get(self.pos)?;
+            self.pos += 1;
+            match c {
+                b'"' => return String::from_utf8(out).ok(),
+                b'\\' => {
+                    let e = *self.s.get(self.pos)?;
+                    self.pos += 1;
+                    match e {
+                        b'n' => out.push(b'\n'),
+                        b't' => out.push(b'\t'),
+                        b'r' => out.push(b'\r'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'u' => {
+                            let hex =
+                                std::str::from_utf8(self.s.get(self.pos..self.pos + 4)?).ok()?;
+                            self.pos += 4;
+                            let c = char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+                                .unwrap_or(char::REPLACEMENT_CHARACTER);
+                            out.extend_from_slice(c.to_string().as_bytes());
+                        }
+                        e => out.push(e),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.skip_ws();
+        match *self.s.get(self.pos)? {
+            b'"' => self.string().map(Json::Str),
+            b'{' => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                if self.eat(b'}').is_some() {
+                    return Some(Json::Obj(members));
+                }
+                loop {
+                    let key = self.string()?;
+                    self.eat(b':')?;
+                    members.push((key, self.value()?));
+                    if self.eat(b',').is_none() {
+                        self.eat(b'}')?;
+                        return Some(Json::Obj(members));
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if self.eat(b']').is_some() {
+                    return Some(Json::Arr(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    if self.eat(b',').is_none() {
+                        self.eat(b']')?;
+                        return Some(Json::Arr(items));
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while self.pos < self.s.len()
+                    && !matches!(self.s[self.pos], b',' | b'}' | b']')
+                    && !self.s[self.pos].is_ascii_whitespace()
+                {
+                    self.pos += 1;
+                }
+                match &self.s[start..self.pos] {
+                    b"null" => Some(Json::Null),
+                    b"true" => Some(Json::Bool(true)),
+                    b"false" => Some(Json::Bool(false)),
+                    b"" => None,
+                    n => Some(Json::Num(String::from_utf8(n.to_vec()).ok()?)),
+                }
+            }
+        }
+    }
+}
+
+fn as_usizes(json: &Json) -> Option<Vec<usize>> {
+    match json {
+        Json::Arr(items) => items
+            .iter()
+            .map(|v| match v {
+                Json::Num(n) => n.parse().ok(),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Parse the JSON header of a `.safetensors` file
+fn parse_safetensors_json(header: &[u8]) -> Option<Vec<TensorInfo>> {
+    let mut parser = JsonParser { s: header, pos: 0 };
+    let Json::Obj(entries) = parser.value()? else {
+        return None;
+    };
+    let mut tensors = Vec::new();
+    for (name, entry) in entries {
+        if name == "__metadata__" {
+            continue;
+        }
+        let Json::Obj(fields) = entry else {
+            return None;
+        };
+        let field = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+        let Json::Str(dtype) = field("dtype")? else {
+            return None;
+        };
+        let dtype = DType::from_safetensors(dtype)?;
+        let shape = as_usizes(field("shape")?)?;
+        let [begin, end] = as_usizes(field("data_offsets")?)?[..] else {
+            return None;
+        };
+        let size = shape.iter().product::<usize>() * dtype.size();
+        if end < begin || end - begin != size {
+            return None;
+        }
+        tensors.push(TensorInfo {
+            name,
+            dtype,
+            shape,
+            offset: begin as u64,
+            size: size as u64,
+        });
+    }
+    tensors.sort_by_key(|t| t.offset);
+    Some(tensors)
+}
+
+async fn read_header(file: &mut tokio::fs::File, path: &str) -> Result<Header, tonic::Status> {
+    let io =
+        |e: std::io::Error| tonic::Status::internal(format!("Failed to read file {}: {}", path, e));
+    let file_size = file.metadata().await.map_err(io)?.len();
+    if path.ends_with(".npy") {
+        let mut prefix = [0u8; 10];
+        file.read_exact(&mut prefix).await.map_err(io)?;
+        if &prefix[..6] != b"\x93NUMPY" {
+            return Err(invalid(path, "not a .npy file"));
+        }
+        let (header_len, payload_start) = if prefix[6] == 1 {
+            let len = u16::from_le_bytes([prefix[8], prefix[9]]) as u64;
+            (len, 10 + len)
+        } else {
+            let mut rest = [0u8; 2];
+            file.read_exact(&mut rest).await.map_err(io)?;
+            let len = u32::from_le_bytes([prefix[8], prefix[9], rest[0], rest[1]]) as u64;
+            (len, 12 + len)
+        };
+        let mut dict = vec![0u8; header_len as usize];
+        file.read_exact(&mut dict).await.map_err(io)?;
+        let name = std::path::Path::new(path)
+            .file_stem()
+            .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+        let tensor = parse_npy_dict(&name, &String::from_utf8_lossy(&dict)).ok_or_else(|| {
+            invalid(
+                path,
+                "unsupported .npy header (need a little-endian numeric dtype in C order)",
+            )
+        })?;
+        Ok(Header {
+            payload_start,
+            payload_size: file_size.saturating_sub(payload_start),
+            tensors: vec![tensor],
+        })
+    } else if path.ends_with(".safetensors") {
+        let mut len = [0u8; 8];
+        file.read_exact(&mut len).await.map_err(io)?;
+        let header_len = u64::from_le_bytes(len);
+        if header_len > file_size {
+            return Err(invalid(path, "not a .safetensors file"));
+        }
+        let mut json = vec![0u8; header_len as usize];
+        file.read_exact(&mut json).await.map_err(io)?;
+        let tensors = parse_safetensors_json(&json)
+            .ok_or_else(|| invalid(path, "invalid or unsupported .safetensors header"))?;
+        let payload_start = 8 + header_len;
+        Ok(Header {
+            payload_start,
+            payload_size: file_size.saturating_sub(payload_start),
+            tensors,
+        })
+    } else {
+        Err(invalid(path, "expected a .npy or .safetensors file"))
+    }
+}
+
+impl JellyFpgaClient {
+    /// Stream the tensors of a `.npy` or `.safetensors` file into memory `id` at `offset`
+    ///
+    /// The payload keeps the file layout: tensors are placed at `offset`
+    /// plus their offset in the file's data section. Returns the tensors
+    /// with their device offsets. The header (dtype, shape, sizes) is
+    /// validated before anything is written; a failed write is an error
+    /// regardless of strict mode.
+    pub async fn load_weights(
+        &mut self,
+        id: u32,
+        offset: u64,
+        path: &str,
+    ) -> Result<Vec<TensorInfo>, tonic::Status> {
+        self.load_weights_checked(id, offset, path, None).await
+    }
+
+    /// [`load_weights`](Self::load_weights) that fails unless every tensor has element type `dtype`
+    pub async fn load_weights_as(
+        &mut self,
+        id: u32,
+        offset: u64,
+        path: &str,
+        dtype: DType,
+    ) -> Result<Vec<TensorInfo>, tonic::Status> {
+        self.load_weights_checked(id, offset, path, Some(dtype))
+            .await
+    }
+
+    async fn load_weights_checked(
+        &mut self,
+        id: u32,
+        offset: u64,
+        path: &str,
+        dtype: Option<DType>,
+    ) -> Result<Vec<TensorInfo>, tonic::Status> {
+        let io = |e: std::io::Error| {
+            tonic::Status::internal(format!("Failed to read file {}: {}", path, e))
+        };
+        let mut file = tokio::fs::File::open(path).await.map_err(io)?;
+        let header = read_header(&mut file, path).await?;
+
+        if let Some(dtype) = dtype
+            && let Some(t) = header.tensors.iter().find(|t| t.dtype != dtype)
+        {
+            return Err(invalid(
+                path,
+                &format!("tensor {} is {:?}, expected {:?}", t.name, t.dtype, dtype),
+            ));
+        }
+        let needed = header
+            .tensors
+            .iter()
+            .map(|t| t.offset + t.size)
+            .max()
+            .unwrap_or(0);
+        if needed > header.payload_size {
+            return Err(invalid(path, "file is shorter than its header describes"));
+        }
+
+        file.seek(std::io::SeekFrom::Start(header.payload_start))
+            .await
+            .map_err(io)?;
+        let mut written = 0;
+        while written < needed {
+            let len = std::cmp::min(DEFAULT_CHUNK_SIZE as u64, needed - written) as usize;
+            let mut chunk = vec![0u8; len];
+            file.read_exact(&mut chunk).await.map_err(io)?;
+            check(
+                self.mem_copy_to(id, offset + written, chunk).await?,
+                "mem_copy_to",
+            )?;
+            written += len as u64;
+        }
+
+        Ok(header
+            .tensors
+            .into_iter()
+            .map(|t| TensorInfo {
+                offset: offset + t.offset,
+                ..t
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_headers() {
+        let npy = parse_npy_dict(
+            "w",
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 4), }",
+        )
+        .unwrap();
+        assert_eq!(npy.dtype, DType::F32);
+        assert_eq!(npy.shape, vec![3, 4]);
+        assert_eq!(npy.size, 48);
+        assert!(
+            parse_npy_dict(
+                "w",
+                "{'descr': '>f4', 'fortran_order': False, 'shape': (3,), }"
+            )
+            .is_none()
+        );
+        assert!(
+            parse_npy_dict(
+                "w",
+                "{'descr': '<f4', 'fortran_order': True, 'shape': (3,), }"
+            )
+            .is_none()
+        );
+
+        let tensors = parse_safetensors_json(
+            br#"{"__metadata__":{"format":"pt"},"b":{"dtype":"I8","shape":[2],"data_offsets":[16,18]},"a":{"dtype":"F32","shape":[2,2],"data_offsets":[0,16]}}"#,
+        )
+        .unwrap();
+        assert_eq!(tensors[0].name, "a");
+        assert_eq!(tensors[1].offset, 16);
+        assert_eq!(tensors[1].dtype, DType::I8);
+        assert!(
+            parse_safetensors_json(br#"{"a":{"dtype":"F32","shape":[3],"data_offsets":[0,8]}}"#)
+                .is_none()
+        );
+    }
+}